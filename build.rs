@@ -0,0 +1,42 @@
+use std::{env, fs, path::PathBuf};
+
+/// Record the versions of tree-sitter and its grammar crates from Cargo.lock, so that findings
+/// cached on disk are dropped when a grammar is upgraded without the scanner's version changing
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let manifest_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by cargo"));
+
+    // Built as a dependency, the lockfile is the workspace's, found above the target directory
+    let lockfile = manifest_dir.ancestors().chain(out_dir.ancestors())
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|path| path.is_file());
+    let versions = lockfile.and_then(|path| {
+        println!("cargo:rerun-if-changed={}", path.display());
+        grammar_versions(&fs::read_to_string(path).ok()?)
+    });
+
+    let constant = format!(
+        "/// Versions of tree-sitter and its grammar crates in this build, one \"name version\" per\n\
+         /// line, or `None` if Cargo.lock couldn't be found\n\
+         pub const GRAMMAR_VERSIONS: Option<&str> = {:?};\n",
+        versions,
+    );
+    fs::write(out_dir.join("grammar_versions.rs"), constant).expect("failed to write grammar versions");
+}
+
+/// "name version" of every tree-sitter package in a lockfile, one per line
+fn grammar_versions(lockfile: &str) -> Option<String> {
+    let mut versions = Vec::new();
+    let mut name = None;
+    for line in lockfile.lines() {
+        if let Some(value) = line.strip_prefix("name = ") {
+            name = Some(value.trim_matches('"'));
+        } else if let (Some(value), Some(package)) = (line.strip_prefix("version = "), name.take())
+            && package.starts_with("tree-sitter")
+        {
+            versions.push(format!("{} {}", package, value.trim_matches('"')));
+        }
+    }
+    (!versions.is_empty()).then(|| versions.join("\n"))
+}
@@ -0,0 +1,46 @@
+#ifndef MAGMA_SCANNER_H
+#define MAGMA_SCANNER_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A single finding. Strings are NUL-terminated UTF-8 owned by the result set. */
+typedef struct MagmaFinding {
+    const char *file;
+    const char *question_id;
+    const char *text;
+    const char *severity;
+    size_t line;
+    size_t column;
+} MagmaFinding;
+
+/* Opaque result set, freed with magma_results_free. */
+typedef struct MagmaResults MagmaResults;
+
+/*
+ * Scan a file or every supported file under a directory. queries_json is a JSON
+ * array of queries, or an object with a "TreeSitterQueries" array.
+ * Return NULL on error, including a path that is missing or unreadable; call
+ * magma_last_error for the reason.
+ */
+MagmaResults *magma_scan_file(const char *path, const char *queries_json);
+MagmaResults *magma_scan_dir(const char *dir, const char *queries_json);
+
+/* Iterate over findings. Pointers stay valid until the result set is freed. */
+size_t magma_results_count(const MagmaResults *results);
+const MagmaFinding *magma_results_get(const MagmaResults *results, size_t index);
+
+/* Free a result set. NULL is ignored. */
+void magma_results_free(MagmaResults *results);
+
+/* Last error on the calling thread, or NULL. Valid until the next failing call. */
+const char *magma_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* MAGMA_SCANNER_H */
@@ -0,0 +1,142 @@
+use crate::types::MatchResult;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::{BTreeMap, HashSet}, error::Error, fs, path::Path, time::{SystemTime, UNIX_EPOCH}};
+
+/// Current baseline file format version
+const BASELINE_VERSION: u32 = 1;
+
+/// A snapshot of known findings that later scans can suppress
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Baseline {
+    pub version: u32,
+    /// Seconds since the Unix epoch when the baseline was last written
+    pub updated_at: u64,
+    pub findings: Vec<BaselineEntry>,
+}
+
+/// A single suppressed finding
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub fingerprint: String,
+    pub question_id: String,
+    pub file: String,
+    pub line: usize,
+    pub text: String,
+}
+
+/// Summary counts for a baseline
+#[derive(Debug, Default)]
+pub struct BaselineStats {
+    pub total: usize,
+    pub by_rule: BTreeMap<String, usize>,
+    pub by_file: BTreeMap<String, usize>,
+}
+
+/// Compute a stable fingerprint for a finding.
+///
+/// The line and column are deliberately left out so that findings survive
+/// unrelated edits above them; the path is taken relative to `root`.
+pub fn fingerprint(result: &MatchResult, root: &Path) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(result.question_id.as_bytes());
+    hasher.update([0u8]);
+    hasher.update(relative_path(&result.file, root).as_bytes());
+    hasher.update([0u8]);
+    hasher.update(result.text.trim().as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Path of `file` relative to `root` with forward slashes, or `file` itself if it lies outside `root`
+fn relative_path(file: &str, root: &Path) -> String {
+    let path = Path::new(file);
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+impl Baseline {
+    /// Build a baseline from the findings of a scan
+    pub fn from_results(results: &[MatchResult], root: &Path) -> Self {
+        let mut seen = HashSet::new();
+        let findings = results.iter()
+            .filter_map(|r| {
+                let fingerprint = fingerprint(r, root);
+                seen.insert(fingerprint.clone()).then(|| BaselineEntry {
+                    fingerprint,
+                    question_id: r.question_id.clone(),
+                    file: relative_path(&r.file, root),
+                    line: r.line,
+                    text: r.text.clone(),
+                })
+            })
+            .collect();
+
+        Self {
+            version: BASELINE_VERSION,
+            updated_at: now_secs(),
+            findings,
+        }
+    }
+
+    /// Load a baseline from a JSON file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read baseline {}: {}", path.display(), e))?;
+        let baseline: Baseline = serde_json::from_str(&content)?;
+
+        if baseline.version > BASELINE_VERSION {
+            return Err(format!("Unsupported baseline version {} in {}", baseline.version, path.display()).into());
+        }
+
+        Ok(baseline)
+    }
+
+    /// Write the baseline as pretty-printed JSON
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Drop entries whose findings no longer appear in `current`, returning how many were removed
+    pub fn prune(&mut self, current: &[MatchResult], root: &Path) -> usize {
+        let live: HashSet<String> = current.iter().map(|r| fingerprint(r, root)).collect();
+        let before = self.findings.len();
+
+        self.findings.retain(|entry| live.contains(&entry.fingerprint));
+        self.updated_at = now_secs();
+
+        before - self.findings.len()
+    }
+
+    /// Drop the findings this baseline already knows about, keeping only new ones
+    pub fn new_findings(&self, results: Vec<MatchResult>, root: &Path) -> Vec<MatchResult> {
+        let known: HashSet<&str> = self.findings.iter().map(|entry| entry.fingerprint.as_str()).collect();
+        results.into_iter()
+            .filter(|r| !known.contains(fingerprint(r, root).as_str()))
+            .collect()
+    }
+
+    /// Count entries overall, per rule, and per file
+    pub fn stats(&self) -> BaselineStats {
+        let mut stats = BaselineStats {
+            total: self.findings.len(),
+            ..Default::default()
+        };
+
+        for entry in &self.findings {
+            *stats.by_rule.entry(entry.question_id.clone()).or_default() += 1;
+            *stats.by_file.entry(entry.file.clone()).or_default() += 1;
+        }
+
+        stats
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,209 @@
+use crate::discovery::{discover_commit, file_extensions, find_files};
+use crate::git;
+use crate::redact;
+use crate::scanner::{Scanner, SharedResources};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{error::Error, fs, path::{Path, PathBuf}, sync::Arc};
+use tokio::sync::Semaphore;
+
+/// A manifest listing the repositories to scan in one batch run
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchManifest {
+    /// Maximum number of repositories scanned at once; overrides the CLI setting
+    #[serde(default)]
+    pub parallelism: Option<usize>,
+    pub repos: Vec<RepoEntry>,
+}
+
+/// A single repository in a batch manifest
+#[derive(Debug, Clone, Deserialize)]
+pub struct RepoEntry {
+    /// Display name, also the start of the checkout directory name for cloned repos
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Remote URL to clone or update before scanning
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Local checkout to scan in place, or the clone destination when `url` is set
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Bare repository to scan in place from its object database, at `branch` or `HEAD`
+    #[serde(default)]
+    pub bare: Option<String>,
+    #[serde(default)]
+    pub branch: Option<String>,
+    #[serde(default)]
+    pub organization_id: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub report_id: Option<String>,
+}
+
+impl RepoEntry {
+    /// Name used in logs and to start the checkout directory's name
+    pub fn display_name(&self) -> String {
+        self.name.clone()
+            .or_else(|| self.url.as_deref().map(repo_name_from_url))
+            .or_else(|| self.path.clone())
+            .or_else(|| self.bare.as_deref().map(repo_name_from_url))
+            .unwrap_or_else(|| "unnamed".to_string())
+    }
+    /// Where a cloned entry is checked out under `workdir` when it has no `path`. The display
+    /// name is followed by a hash of the URL, so `org-a/api` and `org-b/api` don't share one.
+    pub fn checkout_dir(&self, workdir: &Path) -> PathBuf {
+        let name = self.display_name().replace(['/', '\\'], "-");
+        let hash = format!("{:x}", Sha256::digest(self.url.as_deref().unwrap_or_default()));
+        workdir.join(format!("{}-{}", name, &hash[..12]))
+    }
+}
+
+fn repo_name_from_url(url: &str) -> String {
+    let last = url.trim_end_matches('/').rsplit(['/', ':']).next().unwrap_or(url);
+    last.trim_end_matches(".git").to_string()
+}
+
+/// Settings shared by every repository in a batch, used where an entry doesn't override them
+#[derive(Debug, Clone)]
+pub struct BatchSettings {
+    pub api_key: Option<String>,
+    pub organization_id: Option<String>,
+    /// API base URL; the `API_BASE_URL` environment variable's value if unset
+    pub api_url: Option<String>,
+    /// Directory that cloned repositories are checked out under
+    pub workdir: PathBuf,
+    pub poll_interval_secs: u64,
+    pub max_polls: usize,
+    /// Maximum number of repositories scanned at once
+    pub parallelism: usize,
+    /// Maximum number of concurrent evidence uploads for each repository
+    pub upload_concurrency: usize,
+    /// Maximum number of concurrent evidence uploads across all repositories
+    pub global_upload_concurrency: usize,
+}
+
+/// The result of scanning one repository: its report ID, or the error that stopped it
+#[derive(Debug)]
+pub struct RepoOutcome {
+    pub name: String,
+    pub result: Result<String, String>,
+}
+
+/// Load a batch manifest from a JSON file
+pub fn load_manifest(path: &Path) -> Result<BatchManifest, Box<dyn Error>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read manifest {}: {}", path.display(), e))?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Scan every repository in the manifest, at most `parallelism` at a time.
+///
+/// All repositories share one AST cache, compiled query cache, and upload cap,
+/// but each gets its own report. A repository's ASTs are dropped from the cache
+/// when its scan ends, and bare repositories, whose paths are relative to their
+/// root, keep their ASTs apart. A failing repository doesn't stop the others.
+pub async fn run_batch(manifest: BatchManifest, settings: BatchSettings) -> Vec<RepoOutcome> {
+    let parallelism = manifest.parallelism.unwrap_or(settings.parallelism).max(1);
+    let semaphore = Arc::new(Semaphore::new(parallelism));
+    let shared = SharedResources::new(settings.global_upload_concurrency);
+    let settings = Arc::new(settings);
+
+    let mut handles = Vec::new();
+    for entry in manifest.repos {
+        let semaphore = semaphore.clone();
+        let settings = settings.clone();
+        let shared = shared.clone();
+
+        handles.push(tokio::spawn(async move {
+            let _permit = semaphore.acquire_owned().await.expect("semaphore closed");
+            let name = entry.display_name();
+            println!("📦 Starting scan of {}", name);
+
+            let result = scan_repo(&entry, &settings, &shared).await.map_err(|e| redact::redact(&e.to_string()));
+            RepoOutcome { name, result }
+        }));
+    }
+
+    let mut outcomes = Vec::new();
+    for handle in handles {
+        match handle.await {
+            Ok(outcome) => outcomes.push(outcome),
+            Err(e) => eprintln!("Batch scan task failed: {}", e),
+        }
+    }
+
+    outcomes
+}
+
+/// Check out, initialize a report for, and scan a single repository
+async fn scan_repo(entry: &RepoEntry, settings: &BatchSettings, shared: &SharedResources) -> Result<String, Box<dyn Error>> {
+    let api_key = entry.api_key.clone()
+        .or_else(|| settings.api_key.clone())
+        .ok_or("No API key configured")?;
+    redact::register(&api_key);
+    let organization_id = entry.organization_id.clone()
+        .or_else(|| settings.organization_id.clone())
+        .ok_or("No organization ID configured")?;
+
+    // Bare repositories are read from the object database instead of a checkout
+    let (dir, commit) = match &entry.bare {
+        Some(git_dir) => {
+            let refname = entry.branch.as_deref().unwrap_or("HEAD");
+            let commit = discover_commit(Path::new(git_dir), refname)?;
+            (PathBuf::from(git_dir), Some(commit))
+        }
+        None => (prepare_checkout(entry, &settings.workdir)?, None),
+    };
+
+    let commit_hash = match &commit {
+        Some(commit) => commit.commit.clone(),
+        None => git::get_git_commit_hash(&dir).unwrap_or_else(|_| "unknown".to_string()),
+    };
+    let branch_name = entry.branch.clone()
+        .or_else(|| git::get_git_branch_name(&dir).ok())
+        .unwrap_or_else(|| "unknown".to_string());
+    let repo_url = entry.url.clone()
+        .or_else(|| git::get_git_repo_url(&dir).ok())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut scanner = Scanner::new(api_key, organization_id, commit_hash.clone(), entry.report_id.clone())
+        .with_shared_resources(shared)
+        .with_upload_concurrency(settings.upload_concurrency);
+    let files = match commit {
+        Some(commit) => {
+            scanner = scanner.with_sources(commit.sources);
+            commit.files
+        }
+        None => find_files(&dir.to_string_lossy())?,
+    };
+    if let Some(url) = &settings.api_url {
+        scanner = scanner.with_api_base_url(url);
+    }
+    let report_id = scanner.initialize_code_scan(file_extensions(&files), &commit_hash, &branch_name, &repo_url).await?;
+    let scanned = scanner.start_continuous_scan(files.clone(), settings.poll_interval_secs, settings.max_polls).await;
+    // The AST cache outlives this repository's scan, so its trees are dropped for the next ones
+    scanner.evict_files(&files);
+    scanned?;
+
+    Ok(report_id)
+}
+
+/// Clone or update a remote entry under `workdir`, or resolve a local entry's path
+fn prepare_checkout(entry: &RepoEntry, workdir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    match (&entry.url, &entry.path) {
+        (Some(url), path) => {
+            let dest = match path {
+                Some(path) => PathBuf::from(path),
+                None => {
+                    fs::create_dir_all(workdir)?;
+                    entry.checkout_dir(workdir)
+                }
+            };
+            git::clone_or_update(url, entry.branch.as_deref(), &dest)?;
+            Ok(dest)
+        }
+        (None, Some(path)) => Ok(PathBuf::from(path)),
+        (None, None) => Err("Manifest entry needs a url or a path".into()),
+    }
+}
@@ -0,0 +1,110 @@
+use crate::types::TreeSitterQuery;
+use crate::validate::check_query;
+use serde::Serialize;
+use std::{collections::BTreeMap, fs, time::Duration};
+
+/// Queries run when benchmarking without a queries file, each compiled for every language
+/// whose grammar has the node kinds it uses
+const STANDARD_QUERIES: [(&str, &str); 4] = [
+    ("bench/identifiers", "(identifier) @identifier"),
+    ("bench/comments", "(comment) @comment"),
+    ("bench/strings", "(string) @string"),
+    ("bench/calls", "(call_expression) @call"),
+];
+
+/// The standard query set for the given languages, by canonical name
+pub fn standard_queries(languages: &[&'static str]) -> Vec<TreeSitterQuery> {
+    languages.iter()
+        .flat_map(|&language| STANDARD_QUERIES.iter().map(move |&(question_id, query)| (language, question_id, query)))
+        .filter(|(language, _, query)| check_query(language, query).is_ok_and(|check| check.error.is_none()))
+        .map(|(language, question_id, query)| TreeSitterQuery {
+            question_id: question_id.to_string(),
+            file_type: language.to_string(),
+            query: query.to_string(),
+            ..Default::default()
+        })
+        .collect()
+}
+
+/// Parse throughput of one language
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LanguageBench {
+    pub files: usize,
+    pub bytes: u64,
+    pub parse_ms: f64,
+}
+
+impl LanguageBench {
+    /// Megabytes parsed per second
+    pub fn throughput(&self) -> f64 {
+        if self.parse_ms == 0.0 {
+            return 0.0;
+        }
+        self.bytes as f64 / 1_000_000.0 / (self.parse_ms / 1000.0)
+    }
+}
+
+/// Execution time of one query, over every file it ran on
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueryBench {
+    pub runs: usize,
+    pub matches: usize,
+    /// Including compiling the query the first time it runs for a language
+    pub total_ms: f64,
+}
+
+/// Timings of a benchmark run, for sizing machines that scan large repositories
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BenchReport {
+    pub languages: BTreeMap<String, LanguageBench>,
+    pub queries: BTreeMap<String, QueryBench>,
+    pub wall_ms: f64,
+    /// Peak resident memory of the process, where the platform reports it
+    pub peak_memory_bytes: Option<u64>,
+}
+
+impl BenchReport {
+    pub(crate) fn record_parse(&mut self, language: &str, bytes: u64, elapsed: Duration) {
+        let entry = self.languages.entry(language.to_string()).or_default();
+        entry.files += 1;
+        entry.bytes += bytes;
+        entry.parse_ms += elapsed.as_secs_f64() * 1000.0;
+    }
+
+    pub(crate) fn record_query(&mut self, question_id: &str, matches: usize, elapsed: Duration) {
+        let entry = self.queries.entry(question_id.to_string()).or_default();
+        entry.runs += 1;
+        entry.matches += matches;
+        entry.total_ms += elapsed.as_secs_f64() * 1000.0;
+    }
+
+    /// Print a table of parse throughput per language, then one of query times, slowest first
+    pub fn print(&self) {
+        println!("⏱️ {:<12} {:>7} {:>12} {:>11} {:>9}", "Language", "Files", "Bytes", "Parse ms", "MB/s");
+        for (language, bench) in &self.languages {
+            println!("   {:<12} {:>7} {:>12} {:>11.1} {:>9.2}", language, bench.files, bench.bytes, bench.parse_ms, bench.throughput());
+        }
+
+        let mut queries: Vec<(&String, &QueryBench)> = self.queries.iter().collect();
+        queries.sort_by(|a, b| b.1.total_ms.total_cmp(&a.1.total_ms));
+        println!("\n🔎 {:<32} {:>7} {:>9} {:>11} {:>9}", "Query", "Runs", "Matches", "Total ms", "Avg ms");
+        for (question_id, bench) in queries {
+            let average = bench.total_ms / bench.runs.max(1) as f64;
+            println!("   {:<32} {:>7} {:>9} {:>11.1} {:>9.3}", question_id, bench.runs, bench.matches, bench.total_ms, average);
+        }
+
+        let memory = match self.peak_memory_bytes {
+            Some(bytes) => format!("{:.1} MB", bytes as f64 / 1_000_000.0),
+            None => "unavailable".to_string(),
+        };
+        println!("\n📊 Finished in {:.1} ms; peak memory {}", self.wall_ms, memory);
+    }
+}
+
+/// Peak resident memory of this process, read from `/proc` on Linux
+pub fn peak_memory_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kilobytes: u64 = line.trim_start_matches("VmHWM:").trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
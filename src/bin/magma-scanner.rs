@@ -1,9 +1,39 @@
-use magma_scanner::scanner::Scanner;
-use std::{path::Path, process::Command, env, ffi::OsStr};
+use magma_scanner::engine::{Engine, ScanProgress};
+use magma_scanner::scanner::{ApiFailure, CacheMode, Scanner};
+use magma_scanner::gate::{FailOn, FindingsAboveThreshold};
+use magma_scanner::types::{ApiKeyInfo, MatchResult, Severity, TreeSitterQuery};
+use magma_scanner::baseline::Baseline;
+use magma_scanner::config::{delete_keychain, find_keychain, read_keychain, read_secret_file, store_keychain, Config, Connection};
+use magma_scanner::project::ProjectConfig;
+use magma_scanner::plan::ScanPlan;
+use magma_scanner::coverage::Coverage;
+use magma_scanner::manifest::ScanManifest;
+use magma_scanner::bundle::{self, Bundle, BundleSummary};
+use magma_scanner::results::{ScanResults, RESULTS_FORMAT_VERSION};
+use magma_scanner::reporter::{self, ReportFormat, ReportOutput};
+use magma_scanner::disk_cache::DiskCache;
+use magma_scanner::encryption::EvidenceEncryptor;
+use magma_scanner::scripting::ResultTransform;
+use magma_scanner::batch::{self, BatchSettings};
+use magma_scanner::discovery::{discover_commit_matching, discover_files, discover_files_matching, file_extensions, find_files, find_targets_matching, parse_file_list, retain_changed, PathFilter};
+use magma_scanner::git::{get_git_branch_name, get_git_commit_hash, get_git_repo_url};
+use magma_scanner::shutdown::{ScanInterrupted, ShutdownSignal};
+use magma_scanner::timeout::{self, ScanClock, ScanPhase, ScanTimedOut, Timeouts};
+use magma_scanner::store::ResultsStore;
+use magma_scanner::trends::{TrendFormat, Trends};
+use magma_scanner::playground::Playground;
+use magma_scanner::color::{self, Color, ColorChoice};
+use magma_scanner::logging::{self, LogFormat};
+use magma_scanner::progress::ScanProgressBar;
+use magma_scanner::watch::{self, WatchSession};
+use magma_scanner::serve::{self, ScanDaemon};
+use magma_scanner::{bench, dead_code, duplication, exit_codes, hook, language_loader, redact, rpc, rules, sbom, tui, validate};
+use std::{fs, path::{Path, PathBuf}, env, sync::Arc, time::{Duration, SystemTime, UNIX_EPOCH}};
 use std::error::Error;
-use clap::{Parser, Subcommand};
+use std::io::Read;
+use clap::{ArgAction, Parser, Subcommand};
 use dotenv::dotenv;
-use walkdir::WalkDir;
+use tracing::{error, info, warn};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -11,14 +41,59 @@ struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
 
-    /// Target directory to scan
-    #[arg(short, long, default_value = ".")]
-    target: String,
+    /// Directory or file to scan, the current directory by default; can be repeated. The first
+    /// target's directory is the one settings, git information and baselines are read from.
+    #[arg(short, long = "target", value_name = "PATH")]
+    targets: Vec<String>,
 
-    /// API key for authentication
+    /// Also scan the files listed in this newline-delimited file, or stdin for `-`
+    #[arg(long, value_name = "FILE", conflicts_with = "bare")]
+    files_from: Option<PathBuf>,
+
+    /// Bare repository to scan in place, reading files from its object database instead of
+    /// a working tree
+    #[arg(long, value_name = "GIT_DIR")]
+    bare: Option<PathBuf>,
+
+    /// Only scan files matching this glob, relative to the target, e.g. "src/**"; can be repeated
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files matching this glob, relative to the target, e.g. "**/generated/**"; can be
+    /// repeated and wins over --include
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// List the files that would be scanned, the queries that apply to each file type and the
+    /// API requests that would be sent, without parsing or uploading anything
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Follow symbolic links while looking for files; they are skipped by default
+    #[arg(long)]
+    follow_symlinks: bool,
+
+    /// Only scan files modified or staged in the git working tree, e.g. before committing
+    #[arg(long, conflicts_with = "bare")]
+    changed_only: bool,
+
+    /// Ref of the bare repository to scan
+    #[arg(long = "ref", value_name = "REF", default_value = "HEAD", requires = "bare")]
+    git_ref: String,
+
+    /// API key for authentication; prefer --api-key-file or --api-key-keychain, since flags
+    /// show up in process listings and shell history
     #[arg(short, long)]
     api_key: Option<String>,
 
+    /// File holding the API key; defaults to the API_KEY_FILE environment variable
+    #[arg(long, value_name = "PATH")]
+    api_key_file: Option<PathBuf>,
+
+    /// Read the API key stored in the OS keychain under this account
+    #[arg(long, value_name = "ACCOUNT")]
+    api_key_keychain: Option<String>,
+
     /// Organization ID
     #[arg(short, long)]
     organization_id: Option<String>,
@@ -27,6 +102,44 @@ struct Cli {
     #[arg(short, long)]
     report_id: Option<String>,
 
+    /// Named profile from the config file supplying the API URL, organization ID and API key;
+    /// defaults to the MAGMA_PROFILE environment variable
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Load environment variables from this dotenv file, e.g. a mounted CI secret; can be
+    /// repeated, with earlier files taking precedence. `.env` in the working directory is
+    /// still loaded after them.
+    #[arg(long, value_name = "PATH")]
+    env_file: Vec<PathBuf>,
+
+    /// Config file with connection profiles; defaults to MAGMA_CONFIG or ~/.magma/config.json
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// JSON or YAML file of queries to scan with offline, without contacting the Magma API;
+    /// results are written to --results-file instead of being uploaded
+    #[arg(long, value_name = "PATH")]
+    queries_file: Option<PathBuf>,
+
+    /// File the findings of an offline scan are written to as JSON
+    #[arg(long, value_name = "PATH", default_value = "magma-results.json", requires = "queries_file")]
+    results_file: PathBuf,
+
+    /// How offline results are printed: "console" (findings and a summary, also writing
+    /// --results-file) or "json" (the results document alone on stdout)
+    #[arg(long, value_name = "FORMAT", default_value = "console", requires = "queries_file")]
+    format: ReportFormat,
+
+    /// Shorthand for --format json
+    #[arg(long, requires = "queries_file", conflicts_with = "format")]
+    json: bool,
+
+    /// Write the report of an offline scan to this file instead of stdout, replacing it only
+    /// once the whole report is written; "-" is stdout
+    #[arg(long, value_name = "PATH", requires = "queries_file")]
+    output: Option<ReportOutput>,
+
     /// Polling interval in seconds
     #[arg(short, long, default_value_t = 5)]
     poll_interval: u64,
@@ -34,6 +147,156 @@ struct Cli {
     /// Maximum number of polling iterations
     #[arg(short, long, default_value_t = 20)]
     max_polls: usize,
+
+    /// Only report findings missing from this baseline file, e.g. one written by --write-baseline
+    #[arg(long, value_name = "PATH")]
+    baseline: Option<PathBuf>,
+
+    /// Snapshot every finding of this scan into a baseline file, to suppress them in later runs
+    #[arg(long, value_name = "PATH", conflicts_with = "baseline")]
+    write_baseline: Option<PathBuf>,
+
+    /// Abort as soon as a finding at or above this severity is produced
+    #[arg(long, value_name = "SEVERITY", num_args = 0..=1, default_missing_value = "high")]
+    fail_fast: Option<Severity>,
+
+    /// Exit with code 1 when the findings reach a threshold: a severity ("high"), a number of
+    /// findings ("10") or both ("high:3", three findings at or above high)
+    #[arg(long, value_name = "THRESHOLD")]
+    fail_on: Option<FailOn>,
+
+    /// Stop the scan after this long, e.g. "90s", "15m" or "1h", reporting what finished and
+    /// exiting with code 4
+    #[arg(long, value_name = "DURATION", value_parser = timeout::parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Limit one phase of the scan (discovery, parse, query or upload), e.g. "parse=10m";
+    /// can be repeated
+    #[arg(long, value_name = "PHASE=DURATION", value_parser = timeout::parse_phase_timeout)]
+    phase_timeout: Vec<(ScanPhase, Duration)>,
+
+    /// Keep the findings of files in a cache on disk, so later offline scans reuse them for
+    /// files whose contents, language and queries are unchanged instead of parsing them again
+    #[arg(long)]
+    disk_cache: bool,
+
+    /// Directory of the disk cache; implies --disk-cache [default: ~/.cache/magma-scanner]
+    #[arg(long, value_name = "DIR")]
+    disk_cache_dir: Option<PathBuf>,
+
+    /// Drop disk cache entries written longer ago than this, e.g. "12h" or "7d"
+    #[arg(long, value_name = "DURATION", default_value = "7d", value_parser = timeout::parse_duration)]
+    disk_cache_ttl: Duration,
+
+    /// Prune the disk cache back to this many megabytes, oldest entries first, at most hourly
+    #[arg(long, value_name = "MB", default_value_t = 1024)]
+    disk_cache_max_size: u64,
+
+    /// Parse all files into the AST cache while the first queries are being fetched
+    #[arg(long)]
+    warm_up: bool,
+
+    /// Disable the AST cache, re-parsing files on every poll
+    #[arg(long, conflicts_with = "cache_scope")]
+    no_cache: bool,
+
+    /// How long parsed ASTs are cached: "run" (one poll) or "persistent" (all polls)
+    #[arg(long, value_name = "SCOPE", default_value = "persistent")]
+    cache_scope: CacheMode,
+
+    /// Number of files parsed and queried at once; defaults to the number of cores
+    #[arg(long, value_name = "N")]
+    threads: Option<usize>,
+
+    /// Skip files larger than this many megabytes, logging a warning; 0 scans files of any size
+    #[arg(long, value_name = "MB", default_value_t = 4)]
+    max_file_size: u64,
+
+    /// Keep at most N matches per file for queries that don't set their own sample
+    #[arg(long, value_name = "N")]
+    sample: Option<usize>,
+
+    /// Also report email addresses, SSNs, card numbers and phone numbers in string literals
+    #[arg(long)]
+    detect_pii: bool,
+
+    /// Parse files matching a glob with the given language, e.g. "include/legacy/*.h=c";
+    /// can be repeated, and the first matching pattern wins
+    #[arg(long, value_name = "PATTERN=LANG")]
+    language_override: Vec<String>,
+
+    /// Also run queries on embedded code: scripts and styles in HTML, shell in YAML `run:` steps,
+    /// and SQL in string literals
+    #[arg(long)]
+    injections: bool,
+
+    /// Report functions whose cyclomatic complexity exceeds N, and summarize complexity per scan
+    #[arg(long, value_name = "N")]
+    max_complexity: Option<usize>,
+
+    /// lcov or Cobertura coverage report; findings record whether tests cover their line
+    #[arg(long, value_name = "PATH")]
+    coverage: Option<PathBuf>,
+
+    /// Rhai script defining `fn transform(finding)`, run on every finding before it is output
+    /// or uploaded; it returns the finding, possibly changed, or () to drop it
+    #[arg(long, value_name = "PATH")]
+    transform: Option<PathBuf>,
+
+    /// Directory of tree-sitter grammar shared libraries to load at startup, in addition to the
+    /// built-in languages; defaults to MAGMA_GRAMMAR_DIR
+    #[arg(long, value_name = "DIR")]
+    grammar_dir: Option<PathBuf>,
+
+    /// Record every poll's findings in this SQLite database
+    #[arg(long, value_name = "PATH")]
+    store: Option<PathBuf>,
+
+    /// PEM RSA public key of the organization; evidence is encrypted with it before upload
+    #[arg(long, value_name = "PATH")]
+    evidence_public_key: Option<PathBuf>,
+
+    /// Write a manifest of every scanned file with its SHA-256, language and size, and upload
+    /// it when the scan is finalized
+    #[arg(long)]
+    manifest: bool,
+
+    /// File holding the organization key the manifest is signed with; implies --manifest
+    #[arg(long, value_name = "PATH")]
+    manifest_key: Option<PathBuf>,
+
+    /// JSONL file that evidence uploads failing every retry are written to
+    #[arg(long, default_value = ".magma/dead-letter.jsonl")]
+    dead_letter_file: PathBuf,
+
+    /// When to color output: "auto" (terminals only, honoring NO_COLOR), "always" or "never"
+    #[arg(long, value_name = "WHEN", default_value = "auto")]
+    color: ColorChoice,
+
+    /// Log more detail to stderr: -v for per-file diagnostics, -vv for per-query ones
+    #[arg(short, long, action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Only log warnings and errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Format of log lines on stderr: "text" or "json", one object per line with the
+    /// enclosing file and query spans
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    log_format: LogFormat,
+
+    /// Don't draw a progress bar while scanning; it is only drawn when stdout is a terminal
+    #[arg(long)]
+    no_progress: bool,
+
+    /// Settings from the repository's magma.toml, for those without a flag
+    #[arg(skip)]
+    project: ProjectConfig,
+
+    /// Serve JSON-RPC 2.0 requests on stdin, writing responses and notifications to stdout
+    #[arg(long)]
+    rpc: bool,
 }
 
 #[derive(Subcommand)]
@@ -43,195 +306,1757 @@ enum Commands {
         /// Target directory to scan
         #[arg(short, long, default_value = ".")]
         target: String,
+
+        /// Fetch the queries once, scan, upload and finalize the report, then exit, instead of
+        /// polling for new queries; a report that can't be finalized fails the run
+        #[arg(long)]
+        once: bool,
+    },
+    /// Run as a server-side pre-receive hook, rejecting pushes that violate blocking policies
+    PreReceive {
+        /// JSON file containing the queries to enforce
+        #[arg(short, long)]
+        queries: PathBuf,
+
+        /// Minimum severity that rejects the push
+        #[arg(short, long, default_value = "high")]
+        block_on: Severity,
+    },
+    /// Clone or update and scan every repository listed in a manifest
+    Batch {
+        /// JSON manifest listing the repositories to scan
+        manifest: PathBuf,
+
+        /// Directory that remote repositories are cloned into
+        #[arg(short, long, default_value = ".magma-repos")]
+        workdir: PathBuf,
+
+        /// Maximum number of repositories scanned concurrently
+        #[arg(long, default_value_t = 1)]
+        parallelism: usize,
+
+        /// Maximum number of concurrent evidence uploads per repository
+        #[arg(long, default_value_t = 4)]
+        upload_concurrency: usize,
+
+        /// Maximum number of concurrent evidence uploads across all repositories
+        #[arg(long, default_value_t = 8)]
+        global_upload_concurrency: usize,
+    },
+    /// Resubmit evidence uploads from the dead-letter file
+    Flush,
+    /// Check, store and remove the API key used to reach the Magma API
+    Auth {
+        #[command(subcommand)]
+        action: AuthCommand,
+    },
+    /// Run queries locally over files, directories or a source read from stdin and print the
+    /// matches, e.g. `query --lang rust --query '(function_item name: (identifier) @f)' src`
+    Query {
+        /// Files or directories to scan, or `-` to read the source from stdin
+        #[arg(value_name = "PATH", required_unless_present = "stdin", conflicts_with = "stdin")]
+        targets: Vec<PathBuf>,
+
+        /// Read the source to scan from stdin
+        #[arg(long)]
+        stdin: bool,
+
+        /// Language of the source or of --query; defaults to the language implied by --path
+        #[arg(long)]
+        lang: Option<String>,
+
+        /// Path the source read from stdin is reported under, also used to select queries;
+        /// `stdin` if unset. Only valid with --stdin or `-`
+        #[arg(long)]
+        path: Option<String>,
+
+        /// JSON or YAML file containing the queries to run
+        #[arg(short, long, required_unless_present = "query", conflicts_with = "query")]
+        queries: Option<PathBuf>,
+
+        /// A single query to run, for prototyping rules; needs --lang
+        #[arg(long, requires = "lang")]
+        query: Option<String>,
+
+        /// Print the matches as JSON; always the case with --stdin
+        #[arg(long)]
+        json: bool,
+    },
+    /// Compile a query for a language and report exactly where and why it is broken
+    ValidateQuery {
+        /// Language of the query, by name or file extension
+        #[arg(long)]
+        lang: String,
+
+        /// Query text
+        #[arg(long, required_unless_present = "file", conflicts_with = "file")]
+        query: Option<String>,
+
+        /// File holding the query text
+        #[arg(long)]
+        file: Option<PathBuf>,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Scan the target locally in an interactive terminal UI for triaging findings
+    Tui {
+        /// JSON file containing the queries to run
+        #[arg(short, long)]
+        queries: PathBuf,
+    },
+    /// Scan the target with local queries, then rescan files as they change until interrupted
+    Watch {
+        /// JSON or YAML file containing the queries to run
+        #[arg(short, long)]
+        queries: PathBuf,
+
+        /// How long to wait for more changes before rescanning, in milliseconds
+        #[arg(long, value_name = "MS", default_value_t = 200)]
+        debounce: u64,
+    },
+    /// Keep the target's ASTs parsed and scan paths or diffs on request over local HTTP
+    Serve {
+        /// JSON or YAML file containing the queries to run
+        #[arg(short, long)]
+        queries: PathBuf,
+
+        /// Address to listen on; only the local machine can connect by default
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:7878")]
+        listen: String,
+    },
+    /// Load a file and interactively run queries against it, to write and test new rules
+    Playground {
+        /// Source file to run queries against
+        file: String,
+    },
+    /// Time parsing per language and each query over a repository, and report the peak
+    /// memory use, for capacity planning
+    Bench {
+        /// Directory to benchmark; defaults to --target
+        target: Option<String>,
+
+        /// Queries to run instead of the built-in standard set
+        #[arg(short, long, value_name = "PATH")]
+        queries: Option<PathBuf>,
+
+        /// Print the timings as JSON instead of tables
+        #[arg(long)]
+        json: bool,
+    },
+    /// Count files and code, comment and blank lines per language in the target
+    Stats {
+        /// Print the counts as JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+    /// Report code duplicated across files in the target
+    Duplicates {
+        /// Minimum length of a reported clone, in tokens
+        #[arg(long, default_value_t = duplication::DEFAULT_MIN_TOKENS)]
+        min_tokens: usize,
+
+        /// Print the clone groups as JSON instead of a list
+        #[arg(long)]
+        json: bool,
+    },
+    /// Report exported functions and classes that no other file in the target refers to
+    Unused {
+        /// Name pattern of entry points to skip, e.g. "handle_*"; can be repeated.
+        /// Common entry points such as main and test functions are always skipped.
+        #[arg(long, value_name = "PATTERN")]
+        allow: Vec<String>,
+
+        /// Print the findings as JSON instead of a list
+        #[arg(long)]
+        json: bool,
+    },
+    /// Fetch a completed report's findings from the API and render them, e.g. to regenerate
+    /// deliverables without rescanning
+    Report {
+        /// ID of the report
+        report_id: String,
+
+        /// Output format: "console" or "json"
+        #[arg(long, value_name = "FORMAT", default_value = "console")]
+        format: ReportFormat,
+
+        /// Write the rendered report to this file instead of stdout; "-" is stdout
+        #[arg(long, short, value_name = "PATH")]
+        output: Option<ReportOutput>,
+    },
+    /// Show finding counts per rule across the organization's recent reports
+    Trends {
+        /// Number of most recent reports to include
+        #[arg(long, default_value_t = 10)]
+        last: usize,
+
+        /// Output format: "table", "csv" or "json"
+        #[arg(long, default_value = "table")]
+        format: TrendFormat,
+    },
+    /// Scan the target with local queries and write a signed bundle for upload from another machine
+    ExportBundle {
+        /// JSON file containing the queries to run
+        #[arg(short, long)]
+        queries: PathBuf,
+
+        /// Bundle file to write
+        #[arg(long, default_value = "magma-bundle.json")]
+        output: PathBuf,
+
+        /// File holding the key shared with the importing machine
+        #[arg(long)]
+        signing_key: PathBuf,
+    },
+    /// Check a scan manifest's signature and that the files it lists are unchanged
+    VerifyManifest {
+        /// Manifest file written by a scan with --manifest
+        manifest: PathBuf,
+
+        /// File holding the key the manifest was signed with
+        #[arg(long)]
+        signing_key: Option<PathBuf>,
+    },
+    /// Verify a bundle written by export-bundle and upload its findings as a new report
+    Import {
+        /// Bundle file to import
+        bundle: PathBuf,
+
+        /// File holding the key the bundle was signed with
+        #[arg(long)]
+        signing_key: PathBuf,
+    },
+    /// Inspect the local results store written with --store
+    Db {
+        /// SQLite database to read
+        #[arg(long, default_value = ".magma/results.db")]
+        path: PathBuf,
+
+        #[command(subcommand)]
+        action: DbCommand,
+    },
+    /// Inspect, prune or clear the disk cache in --disk-cache-dir or its default directory
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommand,
+    },
+    /// List, show, test and lint rules in local rule files and downloaded rule packs
+    Rules {
+        #[command(subcommand)]
+        action: RulesCommand,
+    },
+    /// Create and maintain the suppression baseline for the target directory
+    Baseline {
+        #[command(subcommand)]
+        action: BaselineCommand,
     },
 }
 
+#[derive(Subcommand)]
+enum CacheCommand {
+    /// Show how many entries the cache holds, their size and the hit rate of scans using it
+    Stats {
+        /// Print the statistics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove expired entries, then the oldest ones until the cache fits --disk-cache-max-size
+    Prune,
+    /// Remove every entry
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum DbCommand {
+    /// List recorded scans, most recent first
+    Scans,
+    /// Show finding counts per rule for one recorded scan
+    Summary {
+        /// ID of the scan, as listed by `db scans`
+        scan_id: i64,
+    },
+    /// Run a SQL query against the store and print the rows tab-separated
+    Query {
+        /// SQL to run, e.g. "SELECT question_id, COUNT(*) FROM findings GROUP BY question_id"
+        sql: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthCommand {
+    /// Check an API key against the API and store it in the OS keychain under the
+    /// organization ID, where later runs for the organization find it without a flag or
+    /// environment variable
+    Login {
+        /// Store the key without checking it against the API, e.g. while offline
+        #[arg(long)]
+        no_verify: bool,
+    },
+    /// Check the API key a scan would use and print the organizations and permissions it grants
+    Status {
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Remove the organization's API key from the OS keychain
+    Logout,
+}
+
+#[derive(Subcommand)]
+enum RulesCommand {
+    /// List the rules with their file types and severities
+    List {
+        /// Rule files, or directories searched for JSON and YAML rule files
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Print the rules as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print one rule in full
+    Show {
+        /// Question ID of the rule
+        question_id: String,
+
+        /// Rule files, or directories searched for JSON and YAML rule files
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Run the rules over annotated fixtures and fail if any `ruleid:` line goes unmatched or
+    /// a rule matches another line
+    Test {
+        /// Rule files, or directories searched for JSON and YAML rule files
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Fixture files, or directories of them, annotated with `ruleid:` and `ok:` comments
+        #[arg(long, required = true)]
+        fixtures: Vec<PathBuf>,
+    },
+    /// Check that every rule compiles, captures something and has a unique question ID
+    Lint {
+        /// Rule files, or directories searched for JSON and YAML rule files
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+
+        /// Print the problems as JSON
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum BaselineCommand {
+    /// Scan the target and write all current findings to a baseline file
+    Write {
+        /// Baseline file to write
+        output: PathBuf,
+
+        /// JSON file containing the queries to run
+        #[arg(short, long)]
+        queries: PathBuf,
+    },
+    /// Rescan the target and drop baseline entries that no longer match anything
+    Prune {
+        /// Baseline file to prune in place
+        baseline: PathBuf,
+
+        /// JSON file containing the queries to run
+        #[arg(short, long)]
+        queries: PathBuf,
+    },
+    /// Print counts of baseline entries per rule and per file
+    Stats {
+        /// Baseline file to summarize
+        baseline: PathBuf,
+    },
+}
+
+impl Cli {
+    /// Fill in what the flags and environment leave unset from the repository's magma.toml.
+    /// Its exclude patterns are added to those of `--exclude`.
+    fn apply_project(&mut self, project: ProjectConfig) {
+        let scan = &project.scan;
+        if self.include.is_empty() {
+            self.include = scan.include.clone();
+        }
+        self.exclude.extend(scan.exclude.iter().cloned());
+        self.fail_on = self.fail_on.or(scan.fail_on);
+        self.fail_fast = self.fail_fast.or(scan.fail_fast);
+        self.max_complexity = self.max_complexity.or(scan.max_complexity);
+        self.detect_pii |= scan.detect_pii;
+        self.injections |= scan.injections;
+        if self.profile.is_none() && env::var_os("MAGMA_PROFILE").is_none() {
+            self.profile = project.api.profile.clone();
+        }
+        self.project = project;
+    }
+
+    /// Organization ID from the flag, the environment or magma.toml
+    fn organization_id(&self) -> Option<String> {
+        self.organization_id.clone()
+            .or_else(|| env::var("ORGANIZATION_ID").ok())
+            .or_else(|| self.project.api.organization_id.clone())
+    }
+
+    /// Directory of the first target, or the current directory without any
+    fn target(&self) -> String {
+        let Some(target) = self.targets.first() else {
+            return ".".to_string();
+        };
+        let path = Path::new(target);
+        if !path.is_file() {
+            return target.clone();
+        }
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy().to_string(),
+            _ => ".".to_string(),
+        }
+    }
+
+    /// Supported files of every target and those listed by `--files-from`. Without either,
+    /// the current directory is scanned.
+    fn find_files(&self, filter: &PathFilter, verbose: bool) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut targets = self.targets.clone();
+        match &self.files_from {
+            Some(list) if list.as_os_str() == "-" => {
+                let mut content = String::new();
+                std::io::stdin().read_to_string(&mut content)?;
+                targets.extend(parse_file_list(&content));
+            }
+            Some(list) => {
+                let content = fs::read_to_string(list)
+                    .map_err(|e| format!("Failed to read file list {}: {}", list.display(), e))?;
+                targets.extend(parse_file_list(&content));
+            }
+            None if targets.is_empty() => targets.push(".".to_string()),
+            None => {}
+        }
+        narrow_to_changed(find_targets_matching(&targets, filter, verbose)?, self)
+    }
+
+    /// The `--include` and `--exclude` globs and symlink setting that discovery goes by
+    fn path_filter(&self) -> Result<PathFilter, Box<dyn Error>> {
+        Ok(PathFilter::new(&self.include, &self.exclude)?.with_follow_symlinks(self.follow_symlinks))
+    }
+
+    /// Size limit of scanned files in bytes, if any
+    fn max_file_size(&self) -> Option<u64> {
+        (self.max_file_size > 0).then(|| self.max_file_size * 1024 * 1024)
+    }
+
+    /// Limits of `--timeout` and `--phase-timeout`, falling back on magma.toml's
+    fn timeouts(&self) -> Timeouts {
+        let mut timeouts = Timeouts { total: self.timeout, ..Timeouts::default() };
+        for (phase, limit) in &self.phase_timeout {
+            timeouts.set_phase(*phase, *limit);
+        }
+        timeouts.or(self.project.scan.timeouts)
+    }
+
+    /// The disk cache selected by `--disk-cache` or `--disk-cache-dir`, if any
+    fn disk_cache(&self) -> Result<Option<DiskCache>, Box<dyn Error>> {
+        if !self.disk_cache && self.disk_cache_dir.is_none() {
+            return Ok(None);
+        }
+        self.open_disk_cache().map(Some)
+    }
+
+    /// The disk cache in --disk-cache-dir or the default directory, whether or not scans use it
+    fn open_disk_cache(&self) -> Result<DiskCache, Box<dyn Error>> {
+        let dir = self.disk_cache_dir.clone().or_else(DiskCache::default_dir)
+            .ok_or("No home directory for the disk cache; set --disk-cache-dir")?;
+        let cache = DiskCache::new(dir)
+            .with_ttl(self.disk_cache_ttl)
+            .with_max_bytes(self.disk_cache_max_size * 1024 * 1024);
+        Ok(cache)
+    }
+
+    /// Load queries from a file, applying magma.toml's rule overrides
+    fn load_queries(&self, path: &Path) -> Result<Vec<TreeSitterQuery>, Box<dyn Error>> {
+        Ok(self.project.rules.apply(rules::load_queries_file(path)?))
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    // Load environment variables from .env file if it exists
+async fn main() {
+    if let Err(e) = run().await {
+        // Errors can quote requests and responses, so strip credentials before they are printed
+        error!("Error: {}", redact::redact(&e.to_string()));
+        std::process::exit(exit_code(e.as_ref()));
+    }
+}
+
+/// Exit code telling CI why a run failed: findings over the threshold, the API, or the scan itself
+fn exit_code(error: &(dyn Error + 'static)) -> i32 {
+    if error.is::<FindingsAboveThreshold>() {
+        exit_codes::FINDINGS
+    } else if error.is::<ApiFailure>() || error.is::<reqwest::Error>() {
+        exit_codes::API_FAILURE
+    } else if error.is::<ScanInterrupted>() || error.is::<ScanTimedOut>() {
+        exit_codes::INCOMPLETE
+    } else {
+        exit_codes::SCAN_ERROR
+    }
+}
+
+async fn run() -> Result<(), Box<dyn Error>> {
+    let mut cli = Cli::parse();
+
+    // Variables already set win, then those of the --env-file files in order, then `.env`
+    for path in &cli.env_file {
+        dotenv::from_path(path).map_err(|e| format!("Failed to load env file {}: {}", path.display(), e))?;
+    }
     dotenv().ok();
 
-    let cli = Cli::parse();
+    color::init(cli.color);
+    logging::init(logging::level(cli.verbose, cli.quiet), cli.log_format);
+    if let Some((path, project)) = ProjectConfig::find(Path::new(&cli.target()))? {
+        info!("⚙️ Using settings from {}", path.display());
+        cli.apply_project(project);
+    }
 
-    let target_dir = cli.target;
+    if let Some(dir) = cli.grammar_dir.clone().or_else(|| env::var_os("MAGMA_GRAMMAR_DIR").map(PathBuf::from)) {
+        // Logged to stderr, since stdout may belong to the RPC protocol
+        for name in language_loader::load_grammar_dir(&dir)? {
+            info!("🧩 Loaded grammar {} from {}", name, dir.display());
+        }
+    }
 
-    // Use command line args if provided, otherwise fall back to environment variables
-    let api_key = cli.api_key
-        .or_else(|| env::var("API_KEY").ok())
-        .expect("API key must be provided via --api-key argument or API_KEY environment variable");
+    // Stdout belongs to the protocol, so nothing else may print before this
+    if cli.rpc {
+        return rpc::serve().await;
+    }
 
-    let organization_id = cli.organization_id
-        .or_else(|| env::var("ORGANIZATION_ID").ok())
-        .expect("Organization ID must be provided via --organization-id argument or ORGANIZATION_ID environment variable");
+    if let Some(Commands::PreReceive { queries, block_on }) = &cli.command {
+        return run_pre_receive(queries, *block_on, cli.organization_id.clone());
+    }
+
+    if let Some(Commands::Batch { manifest, workdir, parallelism, upload_concurrency, global_upload_concurrency }) = &cli.command {
+        let connection = resolve_connection(&cli)?;
+        let settings = BatchSettings {
+            api_key: connection.api_key,
+            organization_id: connection.organization_id,
+            api_url: connection.api_url,
+            workdir: workdir.clone(),
+            poll_interval_secs: cli.poll_interval,
+            max_polls: cli.max_polls,
+            parallelism: *parallelism,
+            upload_concurrency: *upload_concurrency,
+            global_upload_concurrency: *global_upload_concurrency,
+        };
+        return run_batch(manifest, settings).await;
+    }
+
+    if let Some(Commands::Auth { action }) = &cli.command {
+        return run_auth(action, &cli).await;
+    }
+
+    if let Some(Commands::Rules { action }) = &cli.command {
+        return run_rules(action);
+    }
 
-    let report_id = cli.report_id
+    if let Some(Commands::Baseline { action }) = &cli.command {
+        return run_baseline(action, &cli.target()).await;
+    }
+
+    if let Some(Commands::ExportBundle { queries, output, signing_key }) = &cli.command {
+        return run_export_bundle(&cli.target(), queries, output, signing_key, &cli);
+    }
+
+    if let Some(Commands::Playground { file }) = &cli.command {
+        let mut playground = Playground::open(file)?;
+        playground.repl(std::io::stdin().lock(), std::io::stdout())?;
+        return Ok(());
+    }
+
+    if let Some(Commands::Bench { target, queries, json }) = &cli.command {
+        let target = target.clone().unwrap_or_else(|| cli.target());
+        return run_bench(&target, queries.as_deref(), *json, &cli);
+    }
+
+    if let Some(Commands::Stats { json }) = &cli.command {
+        return run_stats(&cli.target(), *json);
+    }
+
+    if let Some(Commands::Duplicates { min_tokens, json }) = &cli.command {
+        return run_duplicates(&cli.target(), *min_tokens, *json);
+    }
+
+    if let Some(Commands::Unused { allow, json }) = &cli.command {
+        return run_unused(&cli.target(), allow, *json);
+    }
+
+    if let Some(Commands::VerifyManifest { manifest, signing_key }) = &cli.command {
+        return run_verify_manifest(manifest, signing_key.as_deref());
+    }
+
+    if let Some(Commands::Db { path, action }) = &cli.command {
+        return run_db(path, action);
+    }
+
+    if let Some(Commands::Cache { action }) = &cli.command {
+        return run_cache(action, &cli);
+    }
+
+    if let Some(Commands::Query { targets, stdin, lang, path, queries, query, json }) = &cli.command {
+        let queries = match (queries, query) {
+            (Some(queries), _) => cli.load_queries(queries)?,
+            (None, Some(query)) => vec![ad_hoc_query(lang.as_deref().unwrap_or_default(), query)?],
+            (None, None) => unreachable!("clap requires --queries or --query"),
+        };
+        let dash = targets.iter().any(|target| target.as_os_str() == "-");
+        if dash && targets.len() > 1 {
+            return Err("`-` reads the source from stdin and can't be combined with other paths".into());
+        }
+        if *stdin || dash {
+            return run_query(lang.as_deref(), path.as_deref().unwrap_or("stdin"), &queries, &cli);
+        }
+        if path.is_some() {
+            return Err("--path names the source read from stdin and needs --stdin or `-`".into());
+        }
+        return run_query_on_files(targets, &queries, *json, &cli);
+    }
+
+    if let Some(Commands::ValidateQuery { lang, query, file, json }) = &cli.command {
+        let query = match (query, file) {
+            (Some(query), _) => query.clone(),
+            (None, Some(path)) => fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read query file {}: {}", path.display(), e))?,
+            (None, None) => unreachable!("clap requires --query or --file"),
+        };
+        return run_validate_query(lang, &query, *json);
+    }
+
+    if let Some(Commands::Tui { queries }) = &cli.command {
+        return run_tui(&cli.target(), queries, cli.detect_pii).await;
+    }
+
+    if let Some(Commands::Watch { queries, debounce }) = &cli.command {
+        return run_watch(queries, Duration::from_millis(*debounce), &cli);
+    }
+
+    if let Some(Commands::Serve { queries, listen }) = &cli.command {
+        return run_serve(queries, listen, &cli).await;
+    }
+
+    if let Some(queries) = &cli.queries_file {
+        return run_offline(queries, &cli);
+    }
+
+    // Use command line args if provided, otherwise fall back to the selected profile and environment variables
+    let connection = resolve_connection(&cli)?;
+    let target_dir = cli.target();
+
+    let api_key = connection.api_key
+        .expect("API key must be provided via --api-key argument, a --profile, or API_KEY environment variable");
+
+    let organization_id = connection.organization_id
+        .expect("Organization ID must be provided via --organization-id argument, a --profile, or ORGANIZATION_ID environment variable");
+
+    // Create a scanner talking to the resolved API for the given code base version and report
+    let connect = |code_base_version: String, report_id: Option<String>| {
+        let scanner = Scanner::new(api_key.clone(), organization_id.clone(), code_base_version, report_id);
+        match &connection.api_url {
+            Some(url) => scanner.with_api_base_url(url),
+            None => scanner,
+        }
+    };
+
+    if let Some(Commands::Flush) = &cli.command {
+        let scanner = connect(String::new(), None);
+        let (delivered, remaining) = scanner.flush_dead_letters(&cli.dead_letter_file).await?;
+        println!("✅ Resubmitted {} uploads, {} still failing", delivered, remaining);
+        if remaining > 0 {
+            return Err(format!("{} uploads remain in {}", remaining, cli.dead_letter_file.display()).into());
+        }
+        return Ok(());
+    }
+
+    // Encrypt evidence before it leaves the machine if the organization provided a key
+    let evidence_key = cli.evidence_public_key.clone()
+        .or_else(|| env::var("EVIDENCE_PUBLIC_KEY").ok().map(PathBuf::from));
+    let encryptor = match &evidence_key {
+        Some(path) => Some(EvidenceEncryptor::from_pem_file(path)?),
+        None => None,
+    };
+
+    if let Some(Commands::Import { bundle, signing_key }) = &cli.command {
+        let mut scanner = connect(String::new(), None)
+            .with_dead_letter_file(&cli.dead_letter_file);
+        if let Some(encryptor) = encryptor {
+            scanner = scanner.with_evidence_encryption(encryptor);
+        }
+        return run_import(scanner, bundle, signing_key).await;
+    }
+
+    if let Some(Commands::Report { report_id, format, output }) = &cli.command {
+        let scanner = connect(String::new(), None);
+        let report = scanner.fetch_report(report_id).await.map_err(ApiFailure)?;
+        let results = ScanResults::from_report(&organization_id, report);
+        let output = output.clone().unwrap_or(ReportOutput::Stdout);
+        output.write(*format, &results)?;
+        if let ReportOutput::File(path) = &output {
+            info!("✅ Wrote {} findings of report {} to {}", results.findings.len(), report_id, path.display());
+        }
+        return Ok(());
+    }
+
+    if let Some(Commands::Trends { last, format }) = &cli.command {
+        let scanner = connect(String::new(), None);
+        let reports = scanner.fetch_report_summaries(*last).await.map_err(ApiFailure)?;
+        if reports.is_empty() {
+            eprintln!("No reports found");
+            return Ok(());
+        }
+        print!("{}", Trends::new(reports).render(*format)?);
+        return Ok(());
+    }
+
+    let report_id = cli.report_id.clone()
         .or_else(|| env::var("REPORT_ID").ok());
 
     let poll_interval = cli.poll_interval;
     let max_polls = cli.max_polls;
 
-    println!("Target Directory: {}", target_dir);
-    println!("API Key: {}", api_key);
-    println!("Organization ID: {}", organization_id);
+    match &cli.bare {
+        Some(git_dir) => info!("Bare Repository: {} at {}", git_dir.display(), cli.git_ref),
+        None => info!("Target Directory: {}", target_dir),
+    }
+    info!("API Key: {}", redact::mask(&api_key));
+    info!("Organization ID: {}", organization_id);
     if let Some(report_id) = &report_id {
-        println!("Report ID: {}", report_id);
+        info!("Report ID: {}", report_id);
     }
 
-    // Get git information
-    let commit_hash = get_git_commit_hash().unwrap_or_else(|_| "unknown".to_string());
-    let branch_name = get_git_branch_name().unwrap_or_else(|_| "unknown".to_string());
-    let repo_url = get_git_repo_url().unwrap_or_else(|_| "unknown".to_string());
+    // Get git information, reading a bare repository's files at the requested ref
+    let clock = ScanClock::start(cli.timeouts());
+    clock.begin(ScanPhase::Discovery);
+    let filter = cli.path_filter()?.with_clock(clock.clone());
+    let commit = match &cli.bare {
+        Some(git_dir) => Some(discover_commit_matching(git_dir, &cli.git_ref, &filter)?),
+        None => None,
+    };
+    let git_dir = cli.bare.clone().unwrap_or_else(|| PathBuf::from("."));
+    let commit_hash = match &commit {
+        Some(commit) => commit.commit.clone(),
+        None => get_git_commit_hash(&git_dir).unwrap_or_else(|_| "unknown".to_string()),
+    };
+    let branch_name = match &commit {
+        Some(_) if cli.git_ref != "HEAD" => cli.git_ref.clone(),
+        _ => get_git_branch_name(&git_dir).unwrap_or_else(|_| "unknown".to_string()),
+    };
+    let repo_url = get_git_repo_url(&git_dir).unwrap_or_else(|_| "unknown".to_string());
 
-    println!("\n📦 Repository: {}", repo_url);
-    println!("🔗 Commit Hash: {}", commit_hash);
-    println!("🌿 Branch URL: {}", branch_name);
+    info!("📦 Repository: {}", repo_url);
+    info!("🔗 Commit Hash: {}", commit_hash);
+    info!("🌿 Branch URL: {}", branch_name);
 
     // Find all supported files
-    let files = find_files(&target_dir)?;
-    println!("\n🔍 Scanning {} files", files.len());
+    let (files, sources) = match commit {
+        Some(commit) => (commit.files, Some(commit.sources)),
+        None => (cli.find_files(&filter, true)?, None),
+    };
+    info!(files = files.len(), "🔍 Scanning {} files", files.len());
 
     // Get distinct file extensions for API
-    let file_extensions: Vec<String> = files.iter()
-        .filter_map(|file| {
-            Path::new(file)
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .map(|ext| ext.to_string())
-        })
-        .collect::<std::collections::HashSet<_>>()
-        .into_iter()
-        .collect();
+    let file_extensions = file_extensions(&files);
 
     // Create scanner
-    let mut scanner = Scanner::new(
-        api_key,
-        organization_id,
-        commit_hash.clone(),
-        report_id,
-    );
+    let has_report = report_id.is_some();
+    let mut scanner = connect(commit_hash.clone(), report_id);
+    if let Some(sources) = sources {
+        scanner = scanner.with_sources(sources);
+    }
+    if let Some(severity) = cli.fail_fast {
+        scanner = scanner.with_fail_fast(severity);
+    }
+    if let Some(limit) = cli.sample {
+        scanner = scanner.with_sample(limit);
+    }
+    if let Some(threads) = cli.threads {
+        scanner = scanner.with_threads(threads);
+    }
+    scanner = scanner.with_max_file_size(cli.max_file_size());
+    if let Some(progress) = progress_bar(&cli) {
+        scanner = scanner.with_progress(progress);
+    }
+    scanner = scanner.with_pii_detection(cli.detect_pii).with_injections(cli.injections)
+        .with_disabled_languages(cli.project.disabled_languages()?)
+        .with_rule_overrides(cli.project.rules.clone());
+    if !cli.language_override.is_empty() {
+        scanner = scanner.with_language_override(language_loader::language_overrides(&cli.language_override)?);
+    }
+    if let Some(threshold) = cli.max_complexity {
+        scanner = scanner.with_complexity_threshold(threshold);
+    }
+    if let Some(path) = &cli.coverage {
+        let coverage = Coverage::load(path)?;
+        info!("🧪 Loaded coverage for {} files from {}", coverage.files(), path.display());
+        scanner = scanner.with_coverage(coverage);
+    }
+    if let Some(path) = &cli.transform {
+        scanner = scanner.with_transform(ResultTransform::from_file(path)?);
+    }
+    scanner = scanner.with_warm_up(cli.warm_up);
+    scanner = scanner.with_cache_mode(if cli.no_cache { CacheMode::Disabled } else { cli.cache_scope });
+    scanner = scanner.with_dead_letter_file(&cli.dead_letter_file);
+    if let Some(path) = &cli.manifest_key {
+        scanner = scanner.with_scan_manifest(Some(bundle::load_signing_key(path)?));
+    } else if cli.manifest {
+        scanner = scanner.with_scan_manifest(None);
+    }
+    if let Some(path) = &cli.store {
+        scanner = scanner.with_results_store(path, &target_dir);
+    }
+    if let Some(encryptor) = encryptor {
+        info!("🔐 Encrypting evidence with key {}", encryptor.key_id());
+        scanner = scanner.with_evidence_encryption(encryptor);
+    }
+
+    if cli.dry_run {
+        // Queries can only be read ahead of the scan for an existing report
+        let queries = match has_report {
+            true => Some(scanner.fetch_available_queries().await.map_err(ApiFailure)?),
+            false => None,
+        };
+        let requests = scanner.planned_requests(&file_extensions, &commit_hash, &branch_name, &repo_url, queries.as_ref().map(Vec::len));
+        ScanPlan::new(scanner.engine(), &files, queries.as_deref()).with_requests(requests).print();
+        return Ok(());
+    }
+
+    // Flush evidence and record a checkpoint instead of dying mid-upload on SIGINT/SIGTERM
+    let shutdown = ShutdownSignal::new();
+    shutdown.listen_for_signals();
+    if let Some(remaining) = clock.remaining(None) {
+        // Wind the scan down like an interrupted one once the whole scan runs out of time
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(remaining).await;
+            shutdown.trigger();
+        });
+    }
+    scanner = scanner.with_shutdown(shutdown).with_clock(clock);
 
     // Initialize scan if needed
-    let report_id = scanner.initialize_code_scan(file_extensions, &commit_hash, &branch_name, &repo_url).await?;
-    println!("Using report ID: {}", report_id);
+    let report_id = scanner.initialize_code_scan(file_extensions, &commit_hash, &branch_name, &repo_url).await
+        .map_err(ApiFailure)?;
+    info!(report_id = %report_id, "Using report ID: {}", report_id);
 
     // Start continuous scanning
-    scanner.start_continuous_scan(files, poll_interval, max_polls).await?;
+    let once = matches!(cli.command, Some(Commands::Scan { once: true, .. }));
+    let scanned = if once {
+        scanner.scan_once(files).await
+    } else {
+        scanner.start_continuous_scan(files, poll_interval, max_polls).await
+    };
+    let findings = match scanned {
+        Ok(findings) => findings,
+        Err(e) => {
+            if e.is::<ScanInterrupted>() || e.is::<ScanTimedOut>() {
+                warn!("⚠️ {}", e);
+                std::process::exit(exit_codes::INCOMPLETE);
+            }
+            return Err(e);
+        }
+    };
+
+    let findings = apply_baseline(findings, &cli, Path::new(&target_dir))?;
+    if let Some(fail_on) = cli.fail_on {
+        fail_on.check(&findings)?;
+    }
+    Ok(())
+}
+
+/// Write every finding to `--write-baseline`, then drop the findings known to `--baseline`
+fn apply_baseline(findings: Vec<MatchResult>, cli: &Cli, root: &Path) -> Result<Vec<MatchResult>, Box<dyn Error>> {
+    if let Some(path) = &cli.write_baseline {
+        let baseline = Baseline::from_results(&findings, root);
+        baseline.save(path)?;
+        info!("✅ Wrote {} findings to baseline {}", baseline.findings.len(), path.display());
+    }
+
+    let Some(path) = &cli.baseline else {
+        return Ok(findings);
+    };
+    let total = findings.len();
+    let findings = Baseline::load(path)?.new_findings(findings, root);
+    info!("🧊 Suppressed {} findings known to baseline {}", total - findings.len(), path.display());
+    Ok(findings)
+}
+
+/// Scan the blobs of a push read from pre-receive stdin and exit non-zero if it must be rejected
+fn run_pre_receive(queries_path: &Path, block_on: Severity, organization_id: Option<String>) -> Result<(), Box<dyn Error>> {
+    let queries = rules::load_queries_file(queries_path)?;
+
+    let mut input = String::new();
+    std::io::stdin().read_to_string(&mut input)?;
+    let updates = hook::parse_pre_receive_input(&input);
+
+    let organization_id = organization_id
+        .or_else(|| env::var("ORGANIZATION_ID").ok())
+        .unwrap_or_default();
+    let code_base_version = updates.last().map(|u| u.new.clone()).unwrap_or_default();
+    let engine = Engine::new(organization_id, code_base_version);
+
+    let blocking = hook::check_push(&engine, &updates, &queries, block_on)?;
+    if blocking.is_empty() {
+        println!("✅ {}", color::paint("No blocking findings", Color::Green));
+        return Ok(());
+    }
+
+    eprintln!("❌ {}", color::paint(format!("Push rejected: {} findings at or above {} severity", blocking.len(), block_on), Color::Red));
+    for finding in &blocking {
+        eprintln!("  [{}] {}:{}:{} {} — \"{}\"", color::severity(finding.severity), finding.file, finding.line, finding.column, finding.question_id, finding.text);
+    }
+    std::process::exit(exit_codes::FINDINGS);
+}
+
+/// Resolve the API connection from the flags, the config file's profiles and the environment
+fn resolve_connection(cli: &Cli) -> Result<Connection, Box<dyn Error>> {
+    let config = Config::load_or_default(cli.config.as_deref())?;
+    let profile = cli.profile.clone().or_else(|| env::var("MAGMA_PROFILE").ok());
+    let api_key_file = cli.api_key_file.clone().or_else(|| env::var_os("API_KEY_FILE").map(PathBuf::from));
+
+    let api_key = match (&cli.api_key, &api_key_file, &cli.api_key_keychain) {
+        (Some(key), _, _) => Some(key.clone()),
+        (None, Some(path), _) => Some(read_secret_file(path)?),
+        (None, None, Some(account)) => Some(read_keychain(account)?),
+        (None, None, None) => None,
+    };
+
+    let mut connection = config.connection(profile.as_deref(), api_key, cli.organization_id.clone())?;
+    connection.organization_id = connection.organization_id.or_else(|| cli.project.api.organization_id.clone());
+    connection.api_url = connection.api_url.or_else(|| cli.project.api.url.clone());
+    // Fall back to the key `auth login` stored for the organization
+    if connection.api_key.is_none() {
+        connection.api_key = connection.organization_id.as_deref().and_then(find_keychain);
+    }
+    if let Some(key) = &connection.api_key {
+        redact::register(key);
+    }
+    Ok(connection)
+}
+
+/// Log in, out, or check the API key against the API
+async fn run_auth(action: &AuthCommand, cli: &Cli) -> Result<(), Box<dyn Error>> {
+    match action {
+        AuthCommand::Login { no_verify } => {
+            // Stored under the organization ID, which is where scans look for it
+            let organization_id = cli.organization_id()
+                .ok_or("Pass --organization-id to name the organization the key is for")?;
+            let api_key = prompt_api_key(&organization_id)?;
+            redact::register(&api_key);
+
+            if !*no_verify {
+                let info = check_api_key(&api_key, &organization_id, resolve_connection(cli)?.api_url.as_deref()).await?;
+                print_api_key_info(&organization_id, &api_key, &info);
+            }
+
+            store_keychain(&organization_id, &api_key)?;
+            println!("✅ Stored the API key for {} in the OS keychain", organization_id);
+        }
+        AuthCommand::Status { json } => {
+            let connection = resolve_connection(cli)?;
+            let organization_id = connection.organization_id.clone()
+                .ok_or("No organization ID; pass --organization-id, set ORGANIZATION_ID or select a --profile")?;
+            let api_key = connection.api_key.clone()
+                .ok_or_else(|| format!("No API key for {}; run `magma-scanner auth login` or set API_KEY", organization_id))?;
+
+            let info = check_api_key(&api_key, &organization_id, connection.api_url.as_deref()).await?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+                    "organizationId": organization_id,
+                    "apiKey": redact::mask(&api_key),
+                    "organizations": info.organizations,
+                    "permissions": info.permissions,
+                    "expiresAt": info.expires_at,
+                }))?);
+            } else {
+                print_api_key_info(&organization_id, &api_key, &info);
+            }
+        }
+        AuthCommand::Logout => {
+            let organization_id = cli.organization_id()
+                .ok_or("Pass --organization-id to name the organization whose key to remove")?;
+            delete_keychain(&organization_id)?;
+            println!("✅ Removed the API key for {} from the OS keychain", organization_id);
+        }
+    }
+
+    Ok(())
+}
+
+/// An API key typed at a hidden prompt, or piped to stdin
+fn prompt_api_key(account: &str) -> Result<String, Box<dyn Error>> {
+    use std::io::IsTerminal;
+
+    let api_key = if std::io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("API key for {}: ", account))?
+    } else {
+        let mut input = String::new();
+        std::io::stdin().read_to_string(&mut input)?;
+        input
+    };
+    let api_key = api_key.trim();
+    if api_key.is_empty() {
+        return Err("No API key given".into());
+    }
+    Ok(api_key.to_string())
+}
+
+/// Ask the API what a key grants for the organization
+async fn check_api_key(api_key: &str, organization_id: &str, api_url: Option<&str>) -> Result<ApiKeyInfo, Box<dyn Error>> {
+    let scanner = Scanner::new(api_key.to_string(), organization_id.to_string(), String::new(), None);
+    let scanner = match api_url {
+        Some(url) => scanner.with_api_base_url(url),
+        None => scanner,
+    };
+    scanner.verify_api_key().await
+}
+
+fn print_api_key_info(organization_id: &str, api_key: &str, info: &ApiKeyInfo) {
+    println!("🔑 API key {} is valid for {}", redact::mask(api_key), organization_id);
+    if !info.organizations.is_empty() {
+        println!("   Organizations: {}", info.organizations.join(", "));
+    }
+    let permissions = if info.permissions.is_empty() { "none".to_string() } else { info.permissions.join(", ") };
+    println!("   Permissions: {}", permissions);
+    if let Some(expires_at) = &info.expires_at {
+        println!("   Expires: {}", expires_at);
+    }
+}
+
+/// Compile a query and print its captures, or where it fails; broken queries fail the run
+fn run_validate_query(lang: &str, query: &str, json: bool) -> Result<(), Box<dyn Error>> {
+    let check = validate::check_query(lang, query)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&check)?);
+    }
+
+    if let Some(problem) = &check.error {
+        if !json {
+            println!("❌ {}", color::paint(problem.render(query), Color::Red));
+        }
+        return Err(format!("Query does not compile for {}", check.language).into());
+    }
+    if check.captures.is_empty() {
+        return Err("Query has no captures, so it can never produce findings".into());
+    }
 
+    if !json {
+        let captures: Vec<String> = check.captures.iter().map(|name| format!("@{}", name)).collect();
+        println!("✅ {} for {}, capturing {}", color::paint("Query compiles", Color::Green), check.language, captures.join(", "));
+    }
     Ok(())
 }
 
-/// Find all supported files in the target directory and all subdirectories
-fn find_files(target_dir: &str) -> Result<Vec<String>, Box<dyn Error>> {
-    // Extensions for supported languages
-    let extensions = [
-        "rs", "js", "py", "go", "ts", "java", "cpp", "h", "hpp", "cc", "rb", "php"
-    ];
+/// Scan source read from stdin as if it were a file at `path` and print the findings as JSON
+fn run_query(lang: Option<&str>, path: &str, queries: &[TreeSitterQuery], cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let mut content = String::new();
+    std::io::stdin().read_to_string(&mut content)?;
 
-    // Directories to ignore
-    let ignore_dirs = ["node_modules", "target", "dist", "build"];
+    let organization_id = env::var("ORGANIZATION_ID").unwrap_or_default();
+    let engine = local_engine(cli, organization_id, String::new())?;
+    let lang = match lang {
+        Some(lang) => lang,
+        None => engine.get_language_for_file(path)
+            .ok_or_else(|| format!("Cannot tell the language of {}; pass --lang", path))?,
+    };
 
-    println!("Searching for files in directory and subdirectories: {}", target_dir);
+    let results = engine.scan_source_as(path, content, lang, queries)?;
+    println!("{}", serde_json::to_string_pretty(&results)?);
+    Ok(())
+}
 
+/// Scan files, and the supported files of directories, with the queries and print each match
+fn run_query_on_files(targets: &[PathBuf], queries: &[TreeSitterQuery], json: bool, cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let filter = cli.path_filter()?;
     let mut files = Vec::new();
+    for target in targets {
+        let target = target.to_string_lossy().to_string();
+        if Path::new(&target).is_dir() {
+            files.extend(discover_files_matching(&target, &filter)?.files);
+        } else {
+            files.push(target);
+        }
+    }
+
+    let organization_id = cli.organization_id().unwrap_or_default();
+    let results = local_engine(cli, organization_id, String::new())?.scan_files(&files, queries);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&results)?);
+        return Ok(());
+    }
+
+    for result in &results {
+        println!("{}:{}:{} {} — \"{}\"", result.file, result.line, result.column, result.question_id, result.text);
+    }
+    println!("📊 {} matches in {} files", results.len(), files.len());
+    Ok(())
+}
 
-    // Ensure the target directory exists
-    let target_path = Path::new(target_dir);
-    if !target_path.exists() {
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            format!("Target directory not found: {}", target_dir)
-        )));
-    }
-
-    // Use WalkDir to recursively walk the directory tree
-    // This will automatically walk through all subdirectories
-    let walker = WalkDir::new(target_dir)
-        .follow_links(true)  // Follow symbolic links
-        .into_iter();
-
-    // Process each entry
-    for entry_result in walker {
-        // Handle any errors during directory traversal
-        let entry = match entry_result {
-            Ok(entry) => entry,
+/// A query given on the command line, compiled up front so mistakes are reported precisely
+/// instead of as an empty result
+fn ad_hoc_query(lang: &str, query: &str) -> Result<TreeSitterQuery, Box<dyn Error>> {
+    let check = validate::check_query(lang, query)?;
+    if let Some(problem) = &check.error {
+        return Err(format!("Invalid query: {}", problem.render(query)).into());
+    }
+
+    Ok(TreeSitterQuery {
+        question_id: "query".to_string(),
+        file_type: check.language.to_string(),
+        query: query.to_string(),
+        ..Default::default()
+    })
+}
+
+/// An engine for scanning without the API, configured from the global analysis flags; its
+/// per-file diagnostics are only logged with -v
+fn local_engine(cli: &Cli, organization_id: String, code_base_version: String) -> Result<Engine, Box<dyn Error>> {
+    let mut engine = Engine::new(organization_id, code_base_version)
+        .with_quiet(cli.verbose == 0)
+        .with_pii_detection(cli.detect_pii)
+        .with_injections(cli.injections)
+        .with_disabled_languages(cli.project.disabled_languages()?)
+        .with_max_file_size(cli.max_file_size());
+    if !cli.language_override.is_empty() {
+        engine = engine.with_language_override(language_loader::language_overrides(&cli.language_override)?);
+    }
+    if let Some(limit) = cli.sample {
+        engine = engine.with_sample(limit);
+    }
+    if let Some(threads) = cli.threads {
+        engine = engine.with_threads(threads);
+    }
+    if let Some(threshold) = cli.max_complexity {
+        engine = engine.with_complexity_threshold(threshold);
+    }
+    if let Some(path) = &cli.coverage {
+        engine = engine.with_coverage(Coverage::load(path)?);
+    }
+    if let Some(path) = &cli.transform {
+        engine = engine.with_transform(ResultTransform::from_file(path)?);
+    }
+    if let Some(cache) = cli.disk_cache()? {
+        info!("💾 Using the disk cache in {}", cache.dir().display());
+        engine = engine.with_disk_cache(cache);
+    }
+    Ok(engine)
+}
+
+/// Scan every repository in a manifest and fail if any of them could not be scanned
+async fn run_batch(manifest_path: &Path, settings: BatchSettings) -> Result<(), Box<dyn Error>> {
+    let manifest = batch::load_manifest(manifest_path)?;
+    info!("📋 Batch scanning {} repositories", manifest.repos.len());
+
+    let outcomes = batch::run_batch(manifest, settings).await;
+
+    let mut failed = 0;
+    for outcome in &outcomes {
+        match &outcome.result {
+            Ok(report_id) => info!(repo = %outcome.name, "✅ {}: report {}", outcome.name, report_id),
             Err(e) => {
-                eprintln!("Error accessing path: {}", e);
-                continue;
+                failed += 1;
+                error!(repo = %outcome.name, "❌ {}: {}", outcome.name, e);
             }
-        };
+        }
+    }
 
-        let path = entry.path();
-        let path_str = path.to_string_lossy().to_string();
-
-        // Skip directories we want to ignore
-        if path.is_dir() {
-            let dir_name = path.file_name().unwrap_or_default().to_string_lossy();
-            if ignore_dirs.iter().any(|&ignore| dir_name == ignore) {
-                println!("Skipping directory: {}", path.display());
-                // This will skip the directory and all its contents
-                continue;
-            }
-        }
-        // Only process files
-        else if path.is_file() {
-            // Check if the file has one of our supported extensions
-            if let Some(ext) = path.extension().and_then(OsStr::to_str) {
-                if extensions.contains(&ext) {
-                    println!("Found file: {}", path_str);
-                    files.push(path_str);
-                }
-            }
+    if failed > 0 {
+        return Err(format!("{} of {} repositories failed to scan", failed, outcomes.len()).into());
+    }
+
+    Ok(())
+}
+
+/// Run the queries in a local file against the target without contacting the API
+async fn scan_locally(target_dir: &str, queries_path: &Path) -> Result<Vec<MatchResult>, Box<dyn Error>> {
+    let queries = rules::load_queries_file(queries_path)?;
+    let files = find_files(target_dir)?;
+
+    let commit_hash = get_git_commit_hash(Path::new(target_dir)).unwrap_or_else(|_| "unknown".to_string());
+    let organization_id = env::var("ORGANIZATION_ID").unwrap_or_default();
+    let scanner = Scanner::new(String::new(), organization_id, commit_hash, None);
+
+    Ok(scanner.scan_files(files, queries).await)
+}
+
+/// Scan the target, or a bare repository's ref, with local queries without contacting the API,
+/// and report the findings in the selected format. The console format also writes the findings
+/// and scan metadata to the results file; other formats own stdout, so nothing else is printed.
+fn run_offline(queries_path: &Path, cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let started_at = unix_now();
+    let format = if cli.json { ReportFormat::Json } else { cli.format };
+    let console = format == ReportFormat::Console;
+    let queries = cli.load_queries(queries_path)?;
+    info!("📋 Loaded {} queries from {}", queries.len(), queries_path.display());
+
+    let clock = ScanClock::start(cli.timeouts());
+    clock.begin(ScanPhase::Discovery);
+    let filter = cli.path_filter()?.with_clock(clock.clone());
+    let commit = match &cli.bare {
+        Some(git_dir) => Some(discover_commit_matching(git_dir, &cli.git_ref, &filter)?),
+        None => None,
+    };
+    let git_dir = cli.bare.clone().unwrap_or_else(|| PathBuf::from(&cli.target()));
+    let commit_hash = match &commit {
+        Some(commit) => commit.commit.clone(),
+        None => get_git_commit_hash(&git_dir).unwrap_or_else(|_| "unknown".to_string()),
+    };
+    let branch_name = match &commit {
+        Some(_) if cli.git_ref != "HEAD" => cli.git_ref.clone(),
+        _ => get_git_branch_name(&git_dir).unwrap_or_else(|_| "unknown".to_string()),
+    };
+    let organization_id = cli.organization_id().unwrap_or_default();
+
+    let mut engine = local_engine(cli, organization_id.clone(), commit_hash.clone())?.with_clock(clock.clone());
+    let files = match commit {
+        Some(commit) => {
+            engine = engine.with_sources(commit.sources);
+            commit.files
+        }
+        None => cli.find_files(&filter, console)?,
+    };
+    if console
+        && let Some(progress) = progress_bar(cli)
+    {
+        engine = engine.with_progress(progress);
+    }
+    if cli.dry_run {
+        let plan = ScanPlan::new(&engine, &files, Some(&queries));
+        match format {
+            ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&plan)?),
+            _ => plan.print(),
+        }
+        return Ok(());
+    }
+    info!(files = files.len(), "🔍 Scanning {} files offline", files.len());
+
+    let findings = apply_baseline(engine.scan_files(&files, &queries), cli, Path::new(&cli.target()))?;
+    let mut summary = BundleSummary::new(files.len(), &findings);
+    summary.complexity = engine.take_complexity_summary();
+    if console {
+        if let Some(coverage) = engine.coverage() {
+            coverage.print_untested(&findings);
+        }
+        if let Some(complexity) = &summary.complexity {
+            complexity.print();
         }
     }
 
-    println!("Found {} files", files.len());
+    let results = ScanResults {
+        format_version: RESULTS_FORMAT_VERSION,
+        scanner_version: env!("CARGO_PKG_VERSION").to_string(),
+        organization_id,
+        code_base_version: commit_hash,
+        branch_name,
+        repo_url: get_git_repo_url(&git_dir).unwrap_or_else(|_| "unknown".to_string()),
+        target: git_dir.display().to_string(),
+        started_at,
+        finished_at: unix_now(),
+        queries: queries.into_iter().map(|query| query.question_id).collect(),
+        summary,
+        findings,
+    };
 
-    // If no files were found, print a warning
-    if files.is_empty() {
-        println!("Warning: No files with supported extensions found in {}", target_dir);
+    let output = cli.output.clone().unwrap_or(ReportOutput::Stdout);
+    output.write(format, &results)
+        .map_err(|e| format!("Failed to write report: {}", e))?;
+    if let ReportOutput::File(path) = &output {
+        info!("✅ Wrote {} findings to {}", results.findings.len(), path.display());
     }
 
-    Ok(files)
+    if console {
+        write_results_file(&results, &cli.results_file)?;
+    }
+    if let Some(fail_on) = cli.fail_on {
+        fail_on.check(&results.findings)?;
+    }
+    if let Some(timed_out) = clock.expired() {
+        return Err(Box::new(timed_out));
+    }
+    Ok(())
 }
 
-/// Get the current git commit hash
-fn get_git_commit_hash() -> Result<String, Box<dyn Error>> {
-    let output = Command::new("git")
-        .args(["rev-parse", "HEAD"])
-        .output()?;
+/// Scan the target with local queries and keep rescanning the files that change, printing
+/// their findings, so rules can be checked while editing
+fn run_watch(queries_path: &Path, debounce: Duration, cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let queries = cli.load_queries(queries_path)?;
+    let filter = cli.path_filter()?;
+    let files = discover_files_matching(&cli.target(), &filter)?.files;
+    let organization_id = cli.organization_id().unwrap_or_default();
+    let commit_hash = get_git_commit_hash(Path::new(&cli.target())).unwrap_or_else(|_| "unknown".to_string());
 
-    if output.status.success() {
-        let hash = String::from_utf8(output.stdout)?;
-        Ok(hash.trim().to_string())
-    } else {
-        Err("Failed to get git commit hash".into())
+    // Rescans rely on unchanged files staying parsed
+    let engine = local_engine(cli, organization_id, commit_hash)?.with_cache_mode(CacheMode::Persistent);
+    let mut session = WatchSession::new(engine, queries);
+    let findings = session.scan_all(&files);
+    print_findings(&findings);
+    println!("👀 {} findings in {} files; watching {} for changes", findings.len(), session.files(), cli.target());
+
+    watch::watch(&cli.target(), &filter, &mut session, debounce, |changed, findings, session| {
+        println!("\n🔄 Rescanned {} changed files", changed.len());
+        print_findings(findings);
+        println!("📊 {} findings in changed files, {} in total", findings.len(), session.findings().count());
+    })
+}
+
+/// Parse the target once, then serve scans that only reparse the files changed since
+async fn run_serve(queries_path: &Path, listen: &str, cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let queries = cli.load_queries(queries_path)?;
+    let organization_id = cli.organization_id().unwrap_or_default();
+    let engine = local_engine(cli, organization_id, String::new())?;
+    let daemon = ScanDaemon::new(engine, &cli.target(), cli.path_filter()?, queries);
+
+    let started = std::time::Instant::now();
+    let files = daemon.warm()?;
+    info!("🔥 Parsed {} files in {:.1}s", files, started.elapsed().as_secs_f64());
+    serve::serve(daemon, listen).await
+}
+
+fn print_findings(findings: &[MatchResult]) {
+    for finding in findings {
+        println!("  [{}] {}:{}:{} {} — \"{}\"", color::severity(finding.severity), finding.file, finding.line, finding.column, finding.question_id, finding.text);
+    }
+}
+
+/// With `--changed-only`, keep just the files modified or staged in the target's git working tree
+fn narrow_to_changed(files: Vec<String>, cli: &Cli) -> Result<Vec<String>, Box<dyn Error>> {
+    if !cli.changed_only {
+        return Ok(files);
+    }
+    let changed = retain_changed(files, &cli.target())?;
+    info!("✏️ {} of the files are modified or staged", changed.len());
+    Ok(changed)
+}
+
+/// A progress bar for scans, unless it was turned off or stdout isn't a terminal
+fn progress_bar(cli: &Cli) -> Option<Arc<dyn ScanProgress>> {
+    if cli.no_progress || cli.quiet {
+        return None;
+    }
+    Some(Arc::new(ScanProgressBar::for_terminal()?))
+}
+
+fn write_results_file(results: &ScanResults, path: &Path) -> Result<(), Box<dyn Error>> {
+    let json = results.to_json()?;
+    reporter::write_atomic(path, |out| out.write_all(json.as_bytes()))
+        .map_err(|e| format!("Failed to write results file {}: {}", path.display(), e))?;
+    info!("✅ Wrote {} findings to {}", results.findings.len(), path.display());
+
+    Ok(())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+/// Scan the target with local queries inside the terminal UI
+async fn run_tui(target_dir: &str, queries_path: &Path, detect_pii: bool) -> Result<(), Box<dyn Error>> {
+    let queries = rules::load_queries_file(queries_path)?;
+    let files = find_files(target_dir)?;
+
+    let commit_hash = get_git_commit_hash(Path::new(target_dir)).unwrap_or_else(|_| "unknown".to_string());
+    let organization_id = env::var("ORGANIZATION_ID").unwrap_or_default();
+    let scanner = Scanner::new(String::new(), organization_id, commit_hash, None)
+        .with_pii_detection(detect_pii);
+
+    tui::run(scanner, files, queries).await
+}
+
+/// Scan the target with local queries and write everything a connected machine needs to upload it
+fn run_export_bundle(
+    target_dir: &str,
+    queries_path: &Path,
+    output: &Path,
+    key_path: &Path,
+    cli: &Cli,
+) -> Result<(), Box<dyn Error>> {
+    let key = bundle::load_signing_key(key_path)?;
+    let queries = cli.load_queries(queries_path)?;
+    let discovered = discover_files_matching(target_dir, &cli.path_filter()?)?;
+
+    let root = Path::new(target_dir);
+    let commit_hash = get_git_commit_hash(root).unwrap_or_else(|_| "unknown".to_string());
+    let organization_id = cli.organization_id().unwrap_or_default();
+
+    let engine = local_engine(cli, organization_id.clone(), commit_hash.clone())?;
+    let findings = engine.scan_files(&discovered.files, &queries);
+    if let Some(coverage) = engine.coverage() {
+        coverage.print_untested(&findings);
     }
+
+    let mut summary = BundleSummary::new(discovered.files.len(), &findings);
+    summary.complexity = engine.take_complexity_summary();
+
+    let bundle = Bundle {
+        created_at: unix_now(),
+        organization_id,
+        code_base_version: commit_hash,
+        branch_name: get_git_branch_name(root).unwrap_or_else(|_| "unknown".to_string()),
+        repo_url: get_git_repo_url(root).unwrap_or_else(|_| "unknown".to_string()),
+        file_types: file_extensions(&discovered.files),
+        config_hash: bundle::config_hash(&queries, cli.sample),
+        summary,
+        sbom: sbom::collect_components(root),
+        skipped_files: discovered.skipped,
+        queries,
+        findings,
+    };
+    bundle.write_signed(output, &key)?;
+
+    println!(
+        "📦 Exported {} findings from {} files ({} skipped, {} components) to {}",
+        bundle.summary.findings,
+        bundle.summary.files_scanned,
+        bundle.skipped_files.len(),
+        bundle.sbom.len(),
+        output.display()
+    );
+    Ok(())
 }
 
-/// Get the current git branch name
-fn get_git_branch_name() -> Result<String, Box<dyn Error>> {
-    let output = Command::new("git")
-        .args(["rev-parse", "--abbrev-ref", "HEAD"])
-        .output()?;
+/// Verify a bundle and upload its findings to a new report
+async fn run_import(mut scanner: Scanner, path: &Path, key_path: &Path) -> Result<(), Box<dyn Error>> {
+    let key = bundle::load_signing_key(key_path)?;
+    let bundle = Bundle::read_signed(path, &key)?;
+    println!("🔏 Verified bundle {} (config {})", path.display(), bundle.config_hash);
+
+    let report_id = scanner.initialize_code_scan(
+        bundle.file_types.clone(),
+        &bundle.code_base_version,
+        &bundle.branch_name,
+        &bundle.repo_url,
+    ).await?;
+    scanner.post_query_results(&bundle.queries, &bundle.findings).await?;
 
-    if output.status.success() {
-        let branch = String::from_utf8(output.stdout)?;
-        Ok(branch.trim().to_string())
+    println!("✅ Imported {} findings into report {}", bundle.findings.len(), report_id);
+    Ok(())
+}
+
+/// Parse the supported files in the target and run queries over them, printing the timings
+fn run_bench(target_dir: &str, queries_path: Option<&Path>, json: bool, cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let files = discover_files_matching(target_dir, &cli.path_filter()?)?.files;
+    let engine = local_engine(cli, String::new(), String::new())?
+        .with_quiet(true)
+        .with_cache_mode(CacheMode::Disabled);
+
+    let queries = match queries_path {
+        Some(path) => cli.load_queries(path)?,
+        None => {
+            let mut languages: Vec<&'static str> = files.iter().filter_map(|file| engine.get_language_for_file(file)).collect();
+            languages.sort_unstable();
+            languages.dedup();
+            bench::standard_queries(&languages)
+        }
+    };
+    info!("⏱️ Benchmarking {} files with {} queries", files.len(), queries.len());
+
+    let report = engine.benchmark(&files, &queries);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
     } else {
-        Err("Failed to get git branch name".into())
+        report.print();
     }
+    Ok(())
 }
 
-/// Get the git repository URL
-fn get_git_repo_url() -> Result<String, Box<dyn Error>> {
-    let output = Command::new("git")
-        .args(["config", "--get", "remote.origin.url"])
-        .output()?;
+/// Print line counts per language for the supported files in the target
+fn run_stats(target_dir: &str, json: bool) -> Result<(), Box<dyn Error>> {
+    let discovered = discover_files(target_dir)?;
+    let engine = Engine::new(String::new(), String::new())
+        .with_quiet(true)
+        .with_cache_mode(CacheMode::Disabled);
 
-    if output.status.success() {
-            let url = String::from_utf8(output.stdout)?;
-            Ok(url.trim().to_string())
+    let code_stats = engine.collect_stats(&discovered.files);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&code_stats)?);
     } else {
-        Err("Failed to get git repository URL".into())
+        code_stats.print();
+    }
+    Ok(())
+}
+
+/// Print groups of code duplicated across the supported files in the target
+fn run_duplicates(target_dir: &str, min_tokens: usize, json: bool) -> Result<(), Box<dyn Error>> {
+    let discovered = discover_files(target_dir)?;
+    let engine = Engine::new(String::new(), String::new())
+        .with_quiet(true)
+        .with_cache_mode(CacheMode::Disabled);
+
+    let groups = engine.find_duplicates(&discovered.files, min_tokens);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&groups)?);
+        return Ok(());
+    }
+
+    if groups.is_empty() {
+        println!("✅ {}", color::paint(format!("No clones of {} or more tokens", min_tokens), Color::Green));
+        return Ok(());
+    }
+    println!("🧬 Found {} clone groups", groups.len());
+    for group in &groups {
+        println!("  {} tokens of {} in {} places:", group.tokens, group.language, group.locations.len());
+        for location in &group.locations {
+            println!("    {}:{}-{}", location.file, location.start_line, location.end_line);
+        }
+    }
+    Ok(())
+}
+
+/// Print exported symbols in the target that are never referenced from another file
+fn run_unused(target_dir: &str, allow: &[String], json: bool) -> Result<(), Box<dyn Error>> {
+    let discovered = discover_files(target_dir)?;
+    let commit_hash = get_git_commit_hash(Path::new(target_dir)).unwrap_or_else(|_| "unknown".to_string());
+    let organization_id = env::var("ORGANIZATION_ID").unwrap_or_default();
+    let engine = Engine::new(organization_id, commit_hash)
+        .with_quiet(true)
+        .with_cache_mode(CacheMode::Disabled);
+
+    let mut patterns: Vec<String> = dead_code::DEFAULT_ENTRY_POINTS.iter().map(|p| p.to_string()).collect();
+    patterns.extend(allow.iter().cloned());
+
+    let findings = engine.find_unused_symbols(&discovered.files, &patterns);
+    if json {
+        println!("{}", serde_json::to_string_pretty(&findings)?);
+        return Ok(());
+    }
+
+    if findings.is_empty() {
+        println!("✅ {}", color::paint("No unused exported symbols", Color::Green));
+        return Ok(());
+    }
+    println!("🪦 Found {} unused exported symbols", findings.len());
+    for finding in &findings {
+        println!("  {}:{}:{} {}", finding.file, finding.line, finding.column, finding.text);
+    }
+    Ok(())
+}
+
+/// Check a scan manifest's signature, if a key is given, and re-hash the files it lists
+fn run_verify_manifest(path: &Path, key_path: Option<&Path>) -> Result<(), Box<dyn Error>> {
+    let manifest = ScanManifest::read(path)?;
+    if let Some(key_path) = key_path {
+        manifest.verify(&bundle::load_signing_key(key_path)?)?;
+        println!("🔏 Signature of {} verified", path.display());
+    }
+
+    let changed = manifest.changed_files();
+    if changed.is_empty() {
+        println!("✅ All {} files match the manifest", manifest.files.len());
+        return Ok(());
+    }
+    for file in &changed {
+        println!("  {}", file);
+    }
+    Err(format!("{} of {} files changed or missing since the scan", changed.len(), manifest.files.len()).into())
+}
+
+/// Handle the `db` subcommands
+fn run_db(path: &Path, action: &DbCommand) -> Result<(), Box<dyn Error>> {
+    if !path.exists() {
+        return Err(format!("No results store at {}; record one with --store", path.display()).into());
+    }
+    let store = ResultsStore::open(path)?;
+
+    match action {
+        DbCommand::Scans => {
+            for scan in store.scans()? {
+                println!(
+                    "{:>5}  {}  {:>6} findings  {}  {}",
+                    scan.id,
+                    scan.recorded_at,
+                    scan.findings,
+                    scan.report_id.as_deref().unwrap_or("-"),
+                    scan.target
+                );
+            }
+        }
+        DbCommand::Summary { scan_id } => {
+            for (rule, count) in store.summary(*scan_id)? {
+                println!("  {:>6}  {}", count, rule);
+            }
+        }
+        DbCommand::Query { sql } => {
+            let result = store.query(sql)?;
+            println!("{}", result.columns.join("\t"));
+            for row in result.rows {
+                println!("{}", row.join("\t"));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Show, prune or clear the disk cache
+fn run_cache(action: &CacheCommand, cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let cache = cli.open_disk_cache()?;
+    let dir = cache.dir().display();
+
+    match action {
+        CacheCommand::Stats { json } => {
+            let stats = cache.stats();
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&serde_json::json!({
+                    "dir": cache.dir(),
+                    "entries": stats.entries,
+                    "bytes": stats.bytes,
+                    "hits": stats.hits,
+                    "misses": stats.misses,
+                    "hitRate": stats.hit_rate(),
+                }))?);
+            } else {
+                println!("💾 Disk cache in {}", dir);
+                println!("  Entries:  {}", stats.entries);
+                println!("  Size:     {:.1} MB", stats.bytes as f64 / (1024.0 * 1024.0));
+                match stats.hit_rate() {
+                    Some(rate) => println!("  Hit rate: {:.1}% ({} hits, {} misses)", rate * 100.0, stats.hits, stats.misses),
+                    None => println!("  Hit rate: no scans recorded yet"),
+                }
+            }
+        }
+        CacheCommand::Prune => {
+            let removed = cache.prune();
+            println!("✅ Removed {} entries from {}", removed, dir);
+        }
+        CacheCommand::Clear => {
+            cache.clear()?;
+            println!("✅ Cleared the disk cache in {}", dir);
+        }
     }
+
+    Ok(())
+}
+
+/// Handle the `baseline` subcommands
+/// Manage rule files the way a scan would load them, without contacting the API
+fn run_rules(action: &RulesCommand) -> Result<(), Box<dyn Error>> {
+    match action {
+        RulesCommand::List { paths, json } => {
+            let rules = rules::load_rules(paths)?;
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&rules)?);
+                return Ok(());
+            }
+            for rule in &rules {
+                let query = &rule.query;
+                println!("{:<40} {:<16} {:<9} {}", query.question_id, query.file_type, query.severity.to_string(), rule.source.display());
+            }
+            println!("📋 {} rules", rules.len());
+        }
+        RulesCommand::Show { question_id, paths } => {
+            let rules = rules::load_rules(paths)?;
+            let rule = rules.iter()
+                .find(|rule| &rule.query.question_id == question_id)
+                .ok_or_else(|| format!("No rule with question ID {}", question_id))?;
+            println!("# {}", rule.source.display());
+            print!("{}", serde_yaml::to_string(&rule.query)?);
+        }
+        RulesCommand::Test { paths, fixtures } => {
+            let queries: Vec<TreeSitterQuery> = rules::load_rules(paths)?.into_iter().map(|rule| rule.query).collect();
+            let engine = Engine::new(String::new(), String::new())
+                .with_quiet(true)
+                .with_cache_mode(CacheMode::Disabled);
+
+            let mut files = Vec::new();
+            for fixture in fixtures {
+                let fixture = fixture.to_string_lossy().to_string();
+                if Path::new(&fixture).is_dir() {
+                    files.extend(discover_files(&fixture)?.files);
+                } else {
+                    files.push(fixture);
+                }
+            }
+
+            let (mut tested, mut failed) = (0, 0);
+            for file in &files {
+                let expectations = rules::FixtureExpectations::parse(&fs::read_to_string(file)?);
+                if expectations.question_ids().is_empty() {
+                    continue;
+                }
+                tested += 1;
+
+                let failures = expectations.check(&engine.scan_files(std::slice::from_ref(file), &queries));
+                if failures.is_empty() {
+                    println!("✅ {}", file);
+                    continue;
+                }
+                failed += 1;
+                println!("❌ {}", color::paint(file, Color::Red));
+                for failure in failures {
+                    println!("   {}", failure);
+                }
+            }
+
+            println!("📊 {} of {} fixtures passed", tested - failed, tested);
+            if tested == 0 {
+                return Err("No fixture has `ruleid:` or `ok:` annotations".into());
+            }
+            if failed > 0 {
+                return Err(format!("{} fixtures failed", failed).into());
+            }
+        }
+        RulesCommand::Lint { paths, json } => {
+            let rules = rules::load_rules(paths)?;
+            let problems = rules::lint_rules(&rules);
+            if *json {
+                println!("{}", serde_json::to_string_pretty(&problems)?);
+            } else {
+                for problem in &problems {
+                    println!("❌ {}", problem);
+                }
+            }
+            if !problems.is_empty() {
+                return Err(format!("{} problems in {} rules", problems.len(), rules.len()).into());
+            }
+            if !*json {
+                println!("✅ {} rules are valid", rules.len());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_baseline(action: &BaselineCommand, target_dir: &str) -> Result<(), Box<dyn Error>> {
+    let root = Path::new(target_dir);
+
+    match action {
+        BaselineCommand::Write { output, queries } => {
+            let results = scan_locally(target_dir, queries).await?;
+            let baseline = Baseline::from_results(&results, root);
+            baseline.save(output)?;
+            println!("✅ Wrote {} findings to baseline {}", baseline.findings.len(), output.display());
+        }
+        BaselineCommand::Prune { baseline: path, queries } => {
+            let mut baseline = Baseline::load(path)?;
+            let results = scan_locally(target_dir, queries).await?;
+            let removed = baseline.prune(&results, root);
+            baseline.save(path)?;
+            println!("✅ Pruned {} stale entries, {} remain in {}", removed, baseline.findings.len(), path.display());
+        }
+        BaselineCommand::Stats { baseline: path } => {
+            let stats = Baseline::load(path)?.stats();
+            println!("Baseline: {}", path.display());
+            println!("Total findings: {}", stats.total);
+
+            println!("\nBy rule:");
+            for (rule, count) in &stats.by_rule {
+                println!("  {:>6}  {}", count, rule);
+            }
+
+            println!("\nBy file:");
+            for (file, count) in &stats.by_file {
+                println!("  {:>6}  {}", count, file);
+            }
+        }
+    }
+
+    Ok(())
 }
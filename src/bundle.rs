@@ -0,0 +1,153 @@
+use crate::complexity::ComplexitySummary;
+use crate::sbom::Component;
+use crate::types::{MatchResult, TreeSitterQuery};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+
+/// Identifies bundle files in their header line
+const BUNDLE_FORMAT: &str = "magma-bundle";
+/// Current bundle format version
+const BUNDLE_VERSION: u32 = 1;
+
+/// Everything needed to upload a scan made on a machine without API access
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bundle {
+    /// Seconds since the Unix epoch when the bundle was exported
+    pub created_at: u64,
+    pub organization_id: String,
+    pub code_base_version: String,
+    pub branch_name: String,
+    pub repo_url: String,
+    /// Extensions of the scanned files, as sent when a report is initialized
+    pub file_types: Vec<String>,
+    /// SHA-256 of the queries and settings the scan ran with
+    pub config_hash: String,
+    pub queries: Vec<TreeSitterQuery>,
+    pub findings: Vec<MatchResult>,
+    pub summary: BundleSummary,
+    /// Dependencies pinned in the scanned repository's lockfiles
+    pub sbom: Vec<Component>,
+    /// Files that were not scanned because their language is unsupported
+    pub skipped_files: Vec<String>,
+}
+
+/// Counts describing a bundled scan
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BundleSummary {
+    pub files_scanned: usize,
+    pub findings: usize,
+    pub by_rule: BTreeMap<String, usize>,
+    /// Function complexity metrics, when the scan measured them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub complexity: Option<ComplexitySummary>,
+}
+
+impl BundleSummary {
+    /// Summarize the findings of a scan of `files_scanned` files
+    pub fn new(files_scanned: usize, findings: &[MatchResult]) -> Self {
+        let mut by_rule = BTreeMap::new();
+        for finding in findings {
+            *by_rule.entry(finding.question_id.clone()).or_default() += 1;
+        }
+
+        Self { files_scanned, findings: findings.len(), by_rule, complexity: None }
+    }
+}
+
+/// First line of a bundle file; the signature covers every byte after it
+#[derive(Debug, Serialize, Deserialize)]
+struct BundleHeader {
+    format: String,
+    version: u32,
+    /// Hex HMAC-SHA256 of the bundle body
+    signature: String,
+}
+
+/// Hash of the queries and settings a scan ran with, so imports can tell which configuration produced them
+pub fn config_hash(queries: &[TreeSitterQuery], sample: Option<usize>) -> String {
+    let mut hasher = Sha256::new();
+    for query in queries {
+        for field in [&query.question_id, &query.file_type, &query.query, &query.object_id] {
+            hasher.update(field.as_bytes());
+            hasher.update([0u8]);
+        }
+        hasher.update(query.severity.to_string().as_bytes());
+        hasher.update(format!("{:?}", query.sample).as_bytes());
+        hasher.update([0u8]);
+    }
+    hasher.update(format!("sample={:?}", sample).as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+impl Bundle {
+    /// Write the bundle to `path`, signed with `key`
+    pub fn write_signed(&self, path: &Path, key: &[u8]) -> Result<(), Box<dyn Error>> {
+        let body = serde_json::to_vec(self)?;
+        let header = BundleHeader {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            signature: to_hex(&sign(key, &body)?),
+        };
+
+        let mut content = serde_json::to_vec(&header)?;
+        content.push(b'\n');
+        content.extend(body);
+        fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Read a bundle from `path`, failing unless its signature verifies with `key`
+    pub fn read_signed(path: &Path, key: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read(path).map_err(|e| format!("Failed to read bundle {}: {}", path.display(), e))?;
+        let split = content.iter().position(|&b| b == b'\n').ok_or("Not a bundle: missing header")?;
+        let (header, body) = (&content[..split], &content[split + 1..]);
+
+        let header: BundleHeader = serde_json::from_slice(header).map_err(|_| "Not a bundle: invalid header")?;
+        if header.format != BUNDLE_FORMAT {
+            return Err(format!("Not a bundle: unknown format {}", header.format).into());
+        }
+        if header.version > BUNDLE_VERSION {
+            return Err(format!("Unsupported bundle version {} in {}", header.version, path.display()).into());
+        }
+
+        let signature = from_hex(&header.signature).ok_or("Malformed bundle signature")?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(|_| "Invalid signing key")?;
+        mac.update(body);
+        mac.verify_slice(&signature)
+            .map_err(|_| format!("Signature of {} does not match; the bundle was modified or signed with another key", path.display()))?;
+
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
+/// Read the shared signing key from a file; surrounding whitespace is ignored
+pub fn load_signing_key(path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    let content = fs::read(path).map_err(|e| format!("Failed to read signing key {}: {}", path.display(), e))?;
+    let key = content.trim_ascii();
+    if key.is_empty() {
+        return Err(format!("Signing key {} is empty", path.display()).into());
+    }
+    Ok(key.to_vec())
+}
+
+pub(crate) fn sign(key: &[u8], body: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(|_| "Invalid signing key")?;
+    mac.update(body);
+    Ok(mac.finalize().into_bytes().to_vec())
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
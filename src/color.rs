@@ -0,0 +1,102 @@
+use crate::types::Severity;
+use std::{
+    env,
+    fmt::Display,
+    io::{self, IsTerminal},
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Whether console output is colored; off until `init` is called
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// When to color console output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color only when writing to a terminal and `NO_COLOR` isn't set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorChoice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            other => Err(format!("unknown color choice: {} (expected auto, always or never)", other)),
+        }
+    }
+}
+
+/// ANSI colors used in console output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Dim,
+}
+
+impl Color {
+    fn code(self) -> &'static str {
+        match self {
+            Color::Red => "31",
+            Color::Green => "32",
+            Color::Yellow => "33",
+            Color::Blue => "34",
+            Color::Magenta => "35",
+            Color::Dim => "2",
+        }
+    }
+}
+
+/// Decide whether to color output. `Auto` follows the NO_COLOR convention
+/// (any non-empty value disables color) and only colors terminals other than `TERM=dumb`.
+pub fn init(choice: ColorChoice) {
+    ENABLED.store(should_color(choice), Ordering::Relaxed);
+}
+
+fn should_color(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => {
+            let no_color = env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+            let dumb = env::var("TERM").is_ok_and(|term| term == "dumb");
+            !no_color && !dumb && io::stdout().is_terminal()
+        }
+    }
+}
+
+/// Whether console output is currently colored
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Wrap text in the escape codes for `color`, or return it unchanged when color is off
+pub fn paint(text: impl Display, color: Color) -> String {
+    if enabled() {
+        format!("\x1b[{}m{}\x1b[0m", color.code(), text)
+    } else {
+        text.to_string()
+    }
+}
+
+/// A severity label colored by how serious it is
+pub fn severity(severity: Severity) -> String {
+    let color = match severity {
+        Severity::Critical => Color::Magenta,
+        Severity::High => Color::Red,
+        Severity::Medium => Color::Yellow,
+        Severity::Low => Color::Blue,
+        Severity::Info => Color::Dim,
+    };
+    paint(severity, color)
+}
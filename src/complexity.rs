@@ -0,0 +1,178 @@
+use crate::types::Severity;
+use serde::{Deserialize, Serialize};
+use tree_sitter::{Node, Tree};
+
+/// Category recorded on complexity findings
+pub const COMPLEXITY_CATEGORY: &str = "complexity";
+/// Rule ID of functions over the complexity threshold
+pub const COMPLEXITY_QUESTION_ID: &str = "complexity/cyclomatic";
+
+/// Node kinds that define functions and that add a path through them, per language
+struct LanguageSpec {
+    functions: &'static [&'static str],
+    decisions: &'static [&'static str],
+}
+
+fn spec(language: &str) -> Option<LanguageSpec> {
+    let spec = match language {
+        "rust" => LanguageSpec {
+            functions: &["function_item", "closure_expression"],
+            decisions: &["if_expression", "while_expression", "for_expression", "match_arm", "try_expression"],
+        },
+        "javascript" | "typescript" | "tsx" | "svelte" => LanguageSpec {
+            functions: &["function_declaration", "function", "function_expression", "arrow_function", "method_definition", "generator_function_declaration"],
+            decisions: &["if_statement", "for_statement", "for_in_statement", "while_statement", "do_statement", "switch_case", "catch_clause", "ternary_expression"],
+        },
+        "python" => LanguageSpec {
+            functions: &["function_definition", "lambda"],
+            decisions: &["if_statement", "elif_clause", "for_statement", "while_statement", "except_clause", "conditional_expression", "boolean_operator", "if_clause"],
+        },
+        "go" => LanguageSpec {
+            functions: &["function_declaration", "method_declaration", "func_literal"],
+            decisions: &["if_statement", "for_statement", "expression_case", "type_case", "communication_case"],
+        },
+        "java" => LanguageSpec {
+            functions: &["method_declaration", "constructor_declaration", "lambda_expression"],
+            decisions: &["if_statement", "for_statement", "enhanced_for_statement", "while_statement", "do_statement", "switch_label", "catch_clause", "ternary_expression"],
+        },
+        "cpp" => LanguageSpec {
+            functions: &["function_definition", "lambda_expression"],
+            decisions: &["if_statement", "for_statement", "for_range_loop", "while_statement", "do_statement", "case_statement", "catch_clause", "conditional_expression"],
+        },
+        "c" => LanguageSpec {
+            functions: &["function_definition"],
+            decisions: &["if_statement", "for_statement", "while_statement", "do_statement", "case_statement", "conditional_expression"],
+        },
+        "ruby" => LanguageSpec {
+            functions: &["method", "singleton_method", "lambda"],
+            decisions: &["if", "elsif", "unless", "while", "until", "for", "when", "rescue", "conditional", "if_modifier", "unless_modifier"],
+        },
+        "php" => LanguageSpec {
+            functions: &["function_definition", "method_declaration", "anonymous_function_creation_expression", "arrow_function"],
+            decisions: &["if_statement", "else_if_clause", "for_statement", "foreach_statement", "while_statement", "do_statement", "case_statement", "catch_clause", "conditional_expression"],
+        },
+        "csharp" => LanguageSpec {
+            functions: &["method_declaration", "constructor_declaration", "local_function_statement", "lambda_expression", "anonymous_method_expression"],
+            decisions: &["if_statement", "for_statement", "for_each_statement", "while_statement", "do_statement", "switch_section", "switch_expression_arm", "catch_clause", "conditional_expression"],
+        },
+        "kotlin" => LanguageSpec {
+            functions: &["function_declaration", "anonymous_function", "lambda_literal"],
+            decisions: &["if_expression", "for_statement", "while_statement", "do_while_statement", "when_entry", "catch_block", "elvis_expression"],
+        },
+        "swift" => LanguageSpec {
+            functions: &["function_declaration", "init_declaration", "lambda_literal"],
+            decisions: &["if_statement", "guard_statement", "for_statement", "while_statement", "repeat_while_statement", "switch_entry", "catch_block", "ternary_expression", "nil_coalescing_expression"],
+        },
+        "scala" => LanguageSpec {
+            functions: &["function_definition", "lambda_expression"],
+            decisions: &["if_expression", "for_expression", "while_expression", "do_while_expression", "case_clause", "catch_clause"],
+        },
+        "bash" => LanguageSpec {
+            functions: &["function_definition"],
+            decisions: &["if_statement", "elif_clause", "for_statement", "c_style_for_statement", "while_statement", "case_item"],
+        },
+        _ => return None,
+    };
+    Some(spec)
+}
+
+/// Short-circuit operators, each of which adds a path
+const LOGICAL_OPERATORS: [&str; 5] = ["&&", "||", "??", "and", "or"];
+
+/// Cyclomatic complexity of one function
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionComplexity {
+    /// Function name, or `<anonymous>` for closures and lambdas
+    pub name: String,
+    pub line: usize,
+    pub column: usize,
+    pub complexity: usize,
+}
+
+/// Compute the cyclomatic complexity of every function in a parsed file: one plus the
+/// number of branches, loops, cases, handlers and short-circuit operators in its body.
+/// Nested functions are measured on their own and don't add to the enclosing function.
+pub fn analyze(tree: &Tree, source: &str, language: &str) -> Vec<FunctionComplexity> {
+    let Some(spec) = spec(language) else {
+        return Vec::new();
+    };
+
+    let mut functions: Vec<FunctionComplexity> = Vec::new();
+    // Nodes still to visit, with the function (index into `functions`) that encloses them
+    let mut stack: Vec<(Node, Option<usize>)> = vec![(tree.root_node(), None)];
+
+    while let Some((node, enclosing)) = stack.pop() {
+        let mut current = enclosing;
+
+        if spec.functions.contains(&node.kind()) {
+            let start = node.start_position();
+            functions.push(FunctionComplexity {
+                name: node.child_by_field_name("name")
+                    .and_then(|name| name.utf8_text(source.as_bytes()).ok())
+                    .unwrap_or("<anonymous>")
+                    .to_string(),
+                line: start.row + 1,
+                column: start.column + 1,
+                complexity: 1,
+            });
+            current = Some(functions.len() - 1);
+        } else if let Some(index) = current
+            && (spec.decisions.contains(&node.kind()) || is_logical_operation(node))
+        {
+            functions[index].complexity += 1;
+        }
+
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev().map(|child| (child, current)));
+    }
+
+    functions.sort_by_key(|f| (f.line, f.column));
+    functions
+}
+
+fn is_logical_operation(node: Node) -> bool {
+    matches!(node.kind(), "binary_expression" | "binary")
+        && node.child_by_field_name("operator").is_some_and(|op| LOGICAL_OPERATORS.contains(&op.kind()))
+}
+
+/// Severity of a function over the threshold: medium once it is more than twice the threshold
+pub fn severity(complexity: usize, threshold: usize) -> Severity {
+    if complexity > threshold * 2 { Severity::Medium } else { Severity::Low }
+}
+
+/// Aggregate complexity metrics over the functions of a scan
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ComplexitySummary {
+    pub functions: usize,
+    /// Sum of the complexity of all functions
+    pub total: usize,
+    pub max: usize,
+    /// Functions over the threshold, each reported as a finding
+    pub over_threshold: usize,
+}
+
+impl ComplexitySummary {
+    pub fn record(&mut self, function: &FunctionComplexity, threshold: usize) {
+        self.functions += 1;
+        self.total += function.complexity;
+        self.max = self.max.max(function.complexity);
+        if function.complexity > threshold {
+            self.over_threshold += 1;
+        }
+    }
+
+    pub fn average(&self) -> f64 {
+        if self.functions == 0 { 0.0 } else { self.total as f64 / self.functions as f64 }
+    }
+
+    pub fn print(&self) {
+        println!(
+            "📐 Complexity: {} functions, average {:.1}, max {}, {} over threshold",
+            self.functions,
+            self.average(),
+            self.max,
+            self.over_threshold
+        );
+    }
+}
@@ -0,0 +1,189 @@
+use serde::Deserialize;
+use std::{collections::BTreeMap, env, error::Error, fs, path::{Path, PathBuf}};
+
+/// Scanner configuration file, holding named connection profiles:
+///
+/// ```json
+/// {
+///   "default_profile": "acme",
+///   "profiles": {
+///     "acme": { "api_url": "https://api.example.com/v1", "organization_id": "org_1", "api_key_env": "ACME_API_KEY" },
+///     "globex": { "organization_id": "org_2", "api_key_file": "/run/secrets/globex" }
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Config {
+    /// Profile used when none is selected
+    #[serde(default)]
+    pub default_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// How to reach the API for one organization. The API key itself is never stored in the
+/// config file, only where to find it.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Profile {
+    #[serde(default)]
+    pub api_url: Option<String>,
+    #[serde(default)]
+    pub organization_id: Option<String>,
+    /// Environment variable holding the API key
+    #[serde(default)]
+    pub api_key_env: Option<String>,
+    /// File holding the API key
+    #[serde(default)]
+    pub api_key_file: Option<PathBuf>,
+    /// Account under which the API key is stored in the OS keychain
+    #[serde(default)]
+    pub api_key_keychain: Option<String>,
+}
+
+impl Profile {
+    /// Read the API key this profile refers to, if it refers to one
+    pub fn api_key(&self) -> Result<Option<String>, Box<dyn Error>> {
+        if let Some(var) = &self.api_key_env {
+            let key = env::var(var).map_err(|_| format!("Environment variable {} is not set", var))?;
+            return Ok(Some(key));
+        }
+        if let Some(path) = &self.api_key_file {
+            return read_secret_file(path).map(Some);
+        }
+        match &self.api_key_keychain {
+            Some(account) => read_keychain(account).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Settings for connecting to the API, resolved from flags, profiles and environment variables
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Connection {
+    pub api_key: Option<String>,
+    pub organization_id: Option<String>,
+    pub api_url: Option<String>,
+}
+
+impl Config {
+    /// Load a config file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file {}: {}", path.display(), e))?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid config file {}: {}", path.display(), e).into())
+    }
+
+    /// The config file named by `MAGMA_CONFIG`, or `~/.magma/config.json`
+    pub fn default_path() -> Option<PathBuf> {
+        if let Ok(path) = env::var("MAGMA_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))
+            .map(|home| PathBuf::from(home).join(".magma").join("config.json"))
+    }
+
+    /// Load the config file at `path`, or at the default path if it exists. A missing
+    /// default config file is an empty config.
+    pub fn load_or_default(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        match path {
+            Some(path) => Self::load(path),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => Self::load(&path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+
+    /// Resolve the connection settings. Each comes from the first of: the flag, the profile
+    /// if one was selected by name, the environment (`API_KEY`, `ORGANIZATION_ID`,
+    /// `API_BASE_URL`), and the default profile.
+    pub fn connection(&self, profile: Option<&str>, api_key: Option<String>, organization_id: Option<String>) -> Result<Connection, Box<dyn Error>> {
+        let selected = match profile.or(self.default_profile.as_deref()) {
+            Some(name) => Some(self.profiles.get(name).ok_or_else(|| {
+                let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+                format!("Unknown profile {} (configured: {})", name, known.join(", "))
+            })?),
+            None => None,
+        };
+        let explicit = profile.is_some();
+
+        let pick = |flag: Option<String>, from_profile: Option<String>, var: &str| -> Option<String> {
+            if flag.is_some() {
+                return flag;
+            }
+            if explicit && from_profile.is_some() {
+                return from_profile;
+            }
+            env::var(var).ok().or(from_profile)
+        };
+
+        // Only read the profile's key when no flag provides one
+        let profile_key = match (&api_key, selected) {
+            (None, Some(profile)) => profile.api_key()?,
+            _ => None,
+        };
+
+        Ok(Connection {
+            api_key: pick(api_key, profile_key, "API_KEY"),
+            organization_id: pick(organization_id, selected.and_then(|p| p.organization_id.clone()), "ORGANIZATION_ID"),
+            api_url: pick(None, selected.and_then(|p| p.api_url.clone()), "API_BASE_URL"),
+        })
+    }
+}
+
+/// Service name under which API keys are stored in the OS keychain
+pub const KEYCHAIN_SERVICE: &str = "magma-scanner";
+
+/// Read an API key stored in the OS keychain (macOS Keychain, Windows Credential Manager or
+/// the Secret Service on Linux) under `KEYCHAIN_SERVICE` and the given account
+#[cfg(feature = "keychain")]
+pub fn read_keychain(account: &str) -> Result<String, Box<dyn Error>> {
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, account)?;
+    entry.get_password()
+        .map_err(|e| format!("Failed to read API key for {} from the keychain: {}", account, e).into())
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn read_keychain(account: &str) -> Result<String, Box<dyn Error>> {
+    Err(format!("Cannot read API key for {} from the keychain: built without the keychain feature", account).into())
+}
+
+/// Like `read_keychain`, but `None` if no key is stored for the account or the keychain
+/// can't be reached, e.g. on a CI runner without a Secret Service
+#[cfg(feature = "keychain")]
+pub fn find_keychain(account: &str) -> Option<String> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, account).ok()?.get_password().ok()
+}
+
+#[cfg(not(feature = "keychain"))]
+pub fn find_keychain(_account: &str) -> Option<String> {
+    None
+}
+
+/// Store an API key in the OS keychain under `KEYCHAIN_SERVICE` and the given account,
+/// replacing any key stored there before
+#[cfg(feature = "keychain")]
+pub fn store_keychain(account: &str, api_key: &str) -> Result<(), Box<dyn Error>> {
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, account)?;
+    entry.set_password(api_key)
+        .map_err(|e| format!("Failed to store API key for {} in the keychain: {}", account, e).into())
+}
+
+/// Remove the API key stored in the OS keychain under `KEYCHAIN_SERVICE` and the given account
+#[cfg(feature = "keychain")]
+pub fn delete_keychain(account: &str) -> Result<(), Box<dyn Error>> {
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, account)?;
+    entry.delete_password()
+        .map_err(|e| format!("Failed to remove API key for {} from the keychain: {}", account, e).into())
+}
+
+/// Read a secret from a file, dropping surrounding whitespace such as a trailing newline
+pub fn read_secret_file(path: &Path) -> Result<String, Box<dyn Error>> {
+    let secret = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read secret file {}: {}", path.display(), e))?;
+    let secret = secret.trim();
+    if secret.is_empty() {
+        return Err(format!("Secret file {} is empty", path.display()).into());
+    }
+    Ok(secret.to_string())
+}
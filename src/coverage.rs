@@ -0,0 +1,173 @@
+use crate::discovery::normalize_path;
+use crate::types::{MatchResult, Severity};
+use regex::Regex;
+use std::{collections::{BTreeMap, HashMap}, error::Error, fs, path::Path, sync::OnceLock};
+
+/// Line hit counts from a test coverage report, by file
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    files: HashMap<String, BTreeMap<usize, u64>>,
+}
+
+/// A file with findings that no test executes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UntestedFile {
+    pub file: String,
+    /// Findings in the file at or above the requested severity
+    pub findings: usize,
+}
+
+impl Coverage {
+    /// Load an lcov tracefile or a Cobertura XML report
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read coverage report {}: {}", path.display(), e))?;
+
+        let coverage = if content.trim_start().starts_with('<') {
+            Self::parse_cobertura(&content)
+        } else {
+            Self::parse_lcov(&content)
+        };
+        if coverage.files.is_empty() {
+            return Err(format!("No coverage data found in {}", path.display()).into());
+        }
+        Ok(coverage)
+    }
+
+    /// Parse an lcov tracefile: `SF:` starts a file's record and `DA:<line>,<hits>` gives a line's hits
+    pub fn parse_lcov(content: &str) -> Self {
+        let mut coverage = Self::default();
+        let mut current: Option<String> = None;
+
+        for line in content.lines().map(str::trim) {
+            if let Some(file) = line.strip_prefix("SF:") {
+                current = Some(normalize_path(file));
+            } else if let Some(data) = line.strip_prefix("DA:") {
+                let mut fields = data.split(',');
+                let number = fields.next().and_then(|n| n.parse().ok());
+                let hits = fields.next().and_then(|h| h.parse().ok());
+                if let (Some(file), Some(number), Some(hits)) = (&current, number, hits) {
+                    coverage.record(file, number, hits);
+                }
+            } else if line == "end_of_record" {
+                current = None;
+            }
+        }
+
+        coverage
+    }
+
+    /// Parse a Cobertura XML report, reading the `<line>` elements of each `<class>`
+    pub fn parse_cobertura(content: &str) -> Self {
+        static ELEMENT: OnceLock<Regex> = OnceLock::new();
+        static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+        let element = ELEMENT.get_or_init(|| Regex::new(r"<(class|line)\b([^>]*)>").unwrap());
+        let attribute = ATTRIBUTE.get_or_init(|| Regex::new(r#"([\w-]+)="([^"]*)""#).unwrap());
+
+        let mut coverage = Self::default();
+        let mut current: Option<String> = None;
+
+        for tag in element.captures_iter(content) {
+            let attributes: HashMap<&str, &str> = attribute.captures_iter(&tag[2])
+                .map(|a| (a.get(1).unwrap().as_str(), a.get(2).unwrap().as_str()))
+                .collect();
+
+            if &tag[1] == "class" {
+                current = attributes.get("filename").map(|f| normalize_path(f));
+                continue;
+            }
+
+            let number = attributes.get("number").and_then(|n| n.parse().ok());
+            let hits = attributes.get("hits").and_then(|h| h.parse().ok());
+            if let (Some(file), Some(number), Some(hits)) = (&current, number, hits) {
+                coverage.record(file, number, hits);
+            }
+        }
+
+        coverage
+    }
+
+    fn record(&mut self, file: &str, line: usize, hits: u64) {
+        *self.files.entry(file.to_string()).or_default().entry(line).or_default() += hits;
+    }
+
+    /// Number of files in the report
+    pub fn files(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Line hits of a file. Reports often use paths rooted somewhere other than the scan, so
+    /// a file otherwise matches the report path sharing the most trailing path components.
+    fn lines_for(&self, file: &str) -> Option<&BTreeMap<usize, u64>> {
+        let file = normalize_path(file);
+        let file = file.trim_start_matches("./");
+        if let Some(lines) = self.files.get(file) {
+            return Some(lines);
+        }
+
+        self.files.iter()
+            .map(|(path, lines)| (common_suffix_len(file, path), lines))
+            .filter(|(common, _)| *common > 0)
+            .max_by_key(|(common, _)| *common)
+            .map(|(_, lines)| lines)
+    }
+
+    /// Whether tests execute a line: `None` if the report has no data for the line, such as
+    /// for comments and declarations. Files missing from the report count as not covered.
+    pub fn is_covered(&self, file: &str, line: usize) -> Option<bool> {
+        match self.lines_for(file) {
+            Some(lines) => lines.get(&line).map(|hits| *hits > 0),
+            None => Some(false),
+        }
+    }
+
+    /// Whether tests execute any line of a file
+    pub fn is_file_tested(&self, file: &str) -> bool {
+        self.lines_for(file).is_some_and(|lines| lines.values().any(|hits| *hits > 0))
+    }
+
+    /// Record on each result whether its line is covered
+    pub fn annotate(&self, results: &mut [MatchResult]) {
+        for result in results {
+            result.covered = self.is_covered(&result.file, result.line);
+        }
+    }
+
+    /// Files that no test executes but which hold findings at or above `min_severity`,
+    /// most findings first
+    pub fn untested_files(&self, results: &[MatchResult], min_severity: Severity) -> Vec<UntestedFile> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for result in results.iter().filter(|r| r.severity >= min_severity) {
+            *counts.entry(&result.file).or_default() += 1;
+        }
+
+        let mut untested: Vec<UntestedFile> = counts.into_iter()
+            .filter(|(file, _)| !self.is_file_tested(file))
+            .map(|(file, findings)| UntestedFile { file: file.to_string(), findings })
+            .collect();
+        untested.sort_by_key(|file| std::cmp::Reverse(file.findings));
+        untested
+    }
+
+    /// Print the untested files holding critical findings, if any
+    pub fn print_untested(&self, results: &[MatchResult]) {
+        let untested = self.untested_files(results, Severity::Critical);
+        if untested.is_empty() {
+            return;
+        }
+
+        println!("🧪 {} files with critical findings are not covered by tests:", untested.len());
+        for file in untested {
+            println!("  {} ({} critical)", file.file, file.findings);
+        }
+    }
+}
+
+/// The number of trailing path components two paths share
+fn common_suffix_len(a: &str, b: &str) -> usize {
+    a.rsplit('/')
+        .zip(b.rsplit('/'))
+        .take_while(|(a, b)| a == b && !a.is_empty() && *a != ".")
+        .count()
+}
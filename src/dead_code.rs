@@ -0,0 +1,166 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tree_sitter::{Node, Tree};
+
+/// Category recorded on unused symbol findings
+pub const DEAD_CODE_CATEGORY: &str = "dead-code";
+/// Rule ID of exported symbols that nothing else references
+pub const UNUSED_EXPORT_QUESTION_ID: &str = "dead-code/unused-export";
+
+/// Name patterns of entry points that are called by a runtime or framework rather than by
+/// code in the repository, and so are never reported
+pub const DEFAULT_ENTRY_POINTS: [&str; 9] = [
+    "main", "init", "handler", "lambda_handler", "test*", "Test*", "Benchmark*", "setUp", "tearDown",
+];
+
+/// An exported function, class or type that no other file refers to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnusedSymbol {
+    pub file: String,
+    pub language: String,
+    pub name: String,
+    /// "function", "method", "class" or "type"
+    pub kind: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Exported symbol definitions and referenced names across every file of a repository
+#[derive(Default)]
+pub struct SymbolIndex {
+    /// Exported definitions and the index of the file defining them
+    definitions: Vec<(usize, UnusedSymbol)>,
+    /// Files each identifier appears in, excluding the names of definitions themselves
+    references: HashMap<String, HashSet<usize>>,
+    files: usize,
+}
+
+impl SymbolIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index the exported definitions of a parsed file and every identifier it uses
+    pub fn add_file(&mut self, path: &str, language: &'static str, tree: &Tree, source: &str) {
+        let file = self.files;
+        self.files += 1;
+
+        let mut definition_names = HashSet::new();
+        let mut stack = vec![tree.root_node()];
+        while let Some(node) = stack.pop() {
+            if let Some((kind, name)) = exported_definition(node, language, source) {
+                definition_names.insert(name.start_byte());
+                let start = name.start_position();
+                self.definitions.push((file, UnusedSymbol {
+                    file: path.to_string(),
+                    language: language.to_string(),
+                    name: text(name, source).to_string(),
+                    kind: kind.to_string(),
+                    line: start.row + 1,
+                    column: start.column + 1,
+                }));
+            }
+
+            if node.child_count() == 0 && node.kind().ends_with("identifier") && !definition_names.contains(&node.start_byte()) {
+                self.references.entry(text(node, source).to_string()).or_default().insert(file);
+            }
+
+            let mut cursor = node.walk();
+            let children: Vec<Node> = node.children(&mut cursor).collect();
+            stack.extend(children.into_iter().rev());
+        }
+    }
+
+    /// Exported symbols whose name appears in no other file, skipping names matching any
+    /// of the `allow` patterns. `*` in a pattern matches any run of characters.
+    pub fn unused(&self, allow: &[String]) -> Vec<UnusedSymbol> {
+        self.definitions.iter()
+            .filter(|(_, symbol)| !allow.iter().any(|pattern| matches_pattern(pattern, &symbol.name)))
+            .filter(|(file, symbol)| {
+                self.references.get(&symbol.name).is_none_or(|files| files.iter().all(|f| f == file))
+            })
+            .map(|(_, symbol)| symbol.clone())
+            .collect()
+    }
+}
+
+/// If `node` defines an exported symbol, its kind and name node
+fn exported_definition<'a>(node: Node<'a>, language: &str, source: &str) -> Option<(&'static str, Node<'a>)> {
+    let kind = match (language, node.kind()) {
+        ("rust", "function_item") => "function",
+        ("rust", "struct_item" | "enum_item" | "trait_item") => "type",
+        ("javascript" | "typescript" | "tsx", "function_declaration") => "function",
+        ("javascript" | "typescript" | "tsx", "class_declaration") => "class",
+        ("python", "function_definition") => "function",
+        ("python", "class_definition") => "class",
+        ("go", "function_declaration") => "function",
+        ("go", "method_declaration") => "method",
+        ("go", "type_spec") => "type",
+        ("java", "method_declaration") => "method",
+        ("java", "class_declaration" | "interface_declaration") => "class",
+        ("csharp", "method_declaration") => "method",
+        ("csharp", "class_declaration" | "interface_declaration" | "struct_declaration" | "record_declaration") => "class",
+        _ => return None,
+    };
+    let name = node.child_by_field_name("name")?;
+
+    let exported = match language {
+        // `pub` items
+        "rust" => has_child(node, |child| child.kind() == "visibility_modifier"),
+        // Named exports; default exports are imported under any name
+        "javascript" | "typescript" | "tsx" => node.parent().is_some_and(|parent| {
+            parent.kind() == "export_statement" && !has_child(parent, |child| child.kind() == "default")
+        }),
+        // Module-level definitions without a leading underscore
+        "python" => {
+            let parent = node.parent().filter(|p| p.kind() == "decorated_definition").and_then(|p| p.parent()).or(node.parent());
+            parent.is_some_and(|p| p.kind() == "module") && !text(name, source).starts_with('_')
+        }
+        // Capitalized identifiers
+        "go" => text(name, source).starts_with(|c: char| c.is_uppercase()),
+        // Public members, except overrides which are called through their supertype
+        "java" => has_child(node, |child| {
+            let modifiers: Vec<&str> = text(child, source).split_whitespace().collect();
+            child.kind() == "modifiers" && modifiers.contains(&"public") && !modifiers.contains(&"@Override")
+        }),
+        // Public members, except overrides
+        "csharp" => {
+            has_child(node, |child| child.kind() == "modifier" && text(child, source) == "public")
+                && !has_child(node, |child| child.kind() == "modifier" && text(child, source) == "override")
+        }
+        _ => false,
+    };
+
+    exported.then_some((kind, name))
+}
+
+fn has_child(node: Node, predicate: impl Fn(Node) -> bool) -> bool {
+    let mut cursor = node.walk();
+    node.children(&mut cursor).any(predicate)
+}
+
+fn text<'a>(node: Node, source: &'a str) -> &'a str {
+    &source[node.start_byte()..node.end_byte()]
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters
+pub fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard: the whole name must match
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
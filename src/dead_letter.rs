@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Serializes appends from concurrent upload tasks so lines never interleave
+static APPEND_LOCK: Mutex<()> = Mutex::new(());
+
+/// An evidence upload that kept failing after all retries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// The exact request body that failed to upload
+    pub payload: serde_json::Value,
+    /// The error from the last attempt
+    pub error: String,
+    pub attempts: u32,
+    /// Seconds since the Unix epoch of the last attempt
+    pub failed_at: u64,
+}
+
+impl DeadLetter {
+    pub fn new(payload: serde_json::Value, error: String, attempts: u32) -> Self {
+        Self {
+            payload,
+            error,
+            attempts,
+            failed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Append a failed upload to the dead-letter JSONL file, creating it if needed
+pub fn append(path: &Path, letter: &DeadLetter) -> Result<(), Box<dyn Error>> {
+    let mut line = serde_json::to_string(letter)?;
+    line.push('\n');
+
+    let _guard = APPEND_LOCK.lock().unwrap();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Read every entry of a dead-letter file; a missing file has no entries
+pub fn read_all(path: &Path) -> Result<Vec<DeadLetter>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(|e| e.into()))
+        .collect()
+}
+
+/// Replace the dead-letter file with `letters`, removing it when none are left
+pub fn rewrite(path: &Path, letters: &[DeadLetter]) -> Result<(), Box<dyn Error>> {
+    let _guard = APPEND_LOCK.lock().unwrap();
+
+    if letters.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+
+    let mut content = String::new();
+    for letter in letters {
+        content.push_str(&serde_json::to_string(letter)?);
+        content.push('\n');
+    }
+    fs::write(path, content)?;
+    Ok(())
+}
@@ -0,0 +1,413 @@
+use crate::git::{self, CommitBlobs};
+use crate::timeout::{ScanClock, ScanPhase};
+use crate::language_loader::{get_language, language_for_file_name, language_for_shebang, registered_language};
+use glob::{MatchOptions, Pattern};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::{collections::HashSet, error::Error, ffi::OsStr, fs::File, io::{BufRead, BufReader, Read}, path::{Path, PathBuf}};
+use tracing::warn;
+use walkdir::WalkDir;
+
+/// Directories to ignore
+const IGNORE_DIRS: [&str; 4] = ["node_modules", "target", "dist", "build"];
+
+/// Extensions for supported languages
+const EXTENSIONS: [&str; 42] = [
+    "rs", "js", "py", "go", "ts", "java", "cpp", "h", "hpp", "cc", "c", "rb", "php", "cs", "kt", "kts", "swift", "scala", "sc",
+    "tf", "tfvars", "hcl", "yml", "yaml", "sql", "html", "htm", "css", "scss", "tsx", "jsx", "svelte", "sol", "lua", "dart", "zig", "ps1", "psm1", "groovy", "gradle", "sh", "bash"
+];
+
+/// `--include` and `--exclude` globs scoping discovery to part of the target. Patterns are
+/// matched against paths relative to the target directory, anywhere in the tree unless they
+/// start with `/`, which anchors them to the target directory itself.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    /// Walk into symlinked directories and scan symlinked files, which can lead discovery
+    /// onto other mounts or into loops
+    follow_symlinks: bool,
+    /// Deadlines after which the walk stops with the files found so far
+    clock: Option<ScanClock>,
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self, Box<dyn Error>> {
+        Ok(Self { include: compile_patterns(include)?, exclude: compile_patterns(exclude)?, follow_symlinks: false, clock: None })
+    }
+
+    /// Follow symbolic links during discovery instead of skipping them
+    pub fn with_follow_symlinks(mut self, follow: bool) -> Self {
+        self.follow_symlinks = follow;
+        self
+    }
+
+    /// Stop discovery with the files found so far once the scan or its discovery phase, timed
+    /// from `ScanClock::begin`, runs out of time
+    pub fn with_clock(mut self, clock: ScanClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Whether a file, given relative to the target directory, is scanned: it matches an include
+    /// pattern, if there are any, and no exclude pattern
+    pub fn allows(&self, relative: &str) -> bool {
+        let path = relative.replace('\\', "/");
+        (self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches_with(&path, MATCH_OPTIONS)))
+            && !self.exclude.iter().any(|pattern| pattern.matches_with(&path, MATCH_OPTIONS))
+    }
+
+    /// Whether everything under a directory is excluded, by a pattern like `**/generated/**`,
+    /// so the walk can skip it
+    fn excludes_dir(&self, relative: &str) -> bool {
+        let path = relative.replace('\\', "/");
+        self.exclude.iter().any(|pattern| {
+            pattern.as_str().strip_suffix("/**")
+                .and_then(|dir| Pattern::new(dir).ok())
+                .is_some_and(|dir| dir.matches_with(&path, MATCH_OPTIONS))
+        })
+    }
+}
+
+/// `*` stays within one directory; `**` crosses directories
+const MATCH_OPTIONS: MatchOptions = MatchOptions { case_sensitive: true, require_literal_separator: true, require_literal_leading_dot: false };
+
+fn compile_patterns(patterns: &[String]) -> Result<Vec<Pattern>, Box<dyn Error>> {
+    patterns.iter()
+        .map(|pattern| {
+            let glob = pattern.trim().replace('\\', "/");
+            let glob = match glob.strip_prefix('/') {
+                Some(anchored) => anchored.to_string(),
+                None if glob.starts_with("**") => glob,
+                None => format!("**/{}", glob),
+            };
+            Pattern::new(&glob).map_err(|e| format!("Invalid pattern {:?}: {}", pattern, e).into())
+        })
+        .collect()
+}
+
+/// Repository-level file listing paths to leave out of scans, in gitignore syntax
+pub const IGNORE_FILE: &str = ".magmaignore";
+
+/// The patterns of a `.magmaignore` file, which discovery honors like git honors `.gitignore`
+/// while leaving `.gitignore` itself alone
+#[derive(Debug, Clone)]
+pub struct MagmaIgnore(Gitignore);
+
+impl MagmaIgnore {
+    /// Parse the contents of a `.magmaignore` file
+    pub fn parse(content: &str) -> Result<Self, Box<dyn Error>> {
+        let mut builder = GitignoreBuilder::new("");
+        for line in content.lines() {
+            builder.add_line(None, line)?;
+        }
+        Ok(Self(builder.build()?))
+    }
+
+    /// Load the `.magmaignore` file at the root of `dir`, if there is one
+    pub fn load(dir: &Path) -> Result<Option<Self>, Box<dyn Error>> {
+        let path = dir.join(IGNORE_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&content).map(Some)
+    }
+
+    /// Whether a path relative to the repository root is ignored, by its own pattern or one of
+    /// its parent directories'
+    pub fn is_ignored(&self, relative: &str, is_dir: bool) -> bool {
+        let path = relative.replace('\\', "/");
+        self.0.matched_path_or_any_parents(&path, is_dir).is_ignore()
+    }
+}
+
+/// Files found by discovery
+#[derive(Debug, Default)]
+pub struct Discovered {
+    /// Files in supported languages
+    pub files: Vec<String>,
+    /// Files outside ignored directories that were left out because their language is unsupported
+    pub skipped: Vec<String>,
+}
+
+/// Find all supported files in the target directory and all subdirectories
+pub fn find_files(target_dir: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    find_files_matching(target_dir, &PathFilter::default())
+}
+
+/// Like `find_files`, keeping only the files the filter allows
+pub fn find_files_matching(target_dir: &str, filter: &PathFilter) -> Result<Vec<String>, Box<dyn Error>> {
+    Ok(discover(target_dir, filter, true)?.files)
+}
+
+/// Like `find_files`, but without printing progress, for callers that own stdout
+pub fn find_files_quietly(target_dir: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    Ok(discover(target_dir, &PathFilter::default(), false)?.files)
+}
+
+/// Find supported files like `find_files_quietly`, also listing the files that were skipped
+pub fn discover_files(target_dir: &str) -> Result<Discovered, Box<dyn Error>> {
+    discover_files_matching(target_dir, &PathFilter::default())
+}
+
+/// Like `discover_files`, keeping only the files the filter allows. Filtered-out files are
+/// neither found nor skipped.
+pub fn discover_files_matching(target_dir: &str, filter: &PathFilter) -> Result<Discovered, Box<dyn Error>> {
+    discover(target_dir, filter, false)
+}
+
+/// Find supported files across several targets. Directories are walked like
+/// `find_files_matching`, while files named directly are scanned as given. A file reached
+/// through more than one target is listed once.
+pub fn find_targets_matching(targets: &[String], filter: &PathFilter, verbose: bool) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut seen = HashSet::new();
+    let mut files = Vec::new();
+    for target in targets {
+        let path = Path::new(target);
+        let found = if path.is_dir() {
+            discover(target, filter, verbose)?.files
+        } else if path.is_file() {
+            vec![target.clone()]
+        } else {
+            return Err(format!("Target not found: {}", target).into());
+        };
+
+        for file in found {
+            let key = Path::new(&file).canonicalize().unwrap_or_else(|_| PathBuf::from(&file));
+            if seen.insert(key) {
+                files.push(file);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Parse a newline-delimited list of files, as passed to `--files-from`, skipping blank lines
+pub fn parse_file_list(content: &str) -> Vec<String> {
+    content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Supported files of one commit, read from a repository's object database
+#[derive(Debug, Default)]
+pub struct CommitSources {
+    /// Commit hash the ref resolved to
+    pub commit: String,
+    /// Paths of the supported files, relative to the repository root
+    pub files: Vec<String>,
+    /// Contents of those files by path, read when they are scanned
+    pub sources: CommitBlobs,
+}
+
+/// Read the supported files at `refname` of the repository at `git_dir`, which may be bare,
+/// without checking it out. Ignored directories are skipped as in `find_files`.
+pub fn discover_commit(git_dir: &Path, refname: &str) -> Result<CommitSources, Box<dyn Error>> {
+    discover_commit_matching(git_dir, refname, &PathFilter::default())
+}
+
+/// Like `discover_commit`, keeping only the files the filter allows
+pub fn discover_commit_matching(git_dir: &Path, refname: &str, filter: &PathFilter) -> Result<CommitSources, Box<dyn Error>> {
+    let commit = git::resolve_commit(git_dir, refname)?;
+    let ignore = match git::read_commit_file(git_dir, &commit, IGNORE_FILE)? {
+        Some(content) => Some(MagmaIgnore::parse(&content)?),
+        None => None,
+    };
+    let sources = git::commit_blobs(git_dir, &commit, |path| {
+        let mut components = path.rsplit('/');
+        let name = components.next().unwrap_or(path);
+        has_supported_extension(Path::new(name))
+            && !components.any(is_ignored_dir)
+            && filter.allows(path)
+            && !ignore.as_ref().is_some_and(|ignore| ignore.is_ignored(path, false))
+    })?;
+
+    let mut files: Vec<String> = sources.paths().map(str::to_string).collect();
+    files.sort();
+    Ok(CommitSources { commit, files, sources })
+}
+
+fn has_supported_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .is_some_and(|ext| {
+            (EXTENSIONS.contains(&ext.to_lowercase().as_str()) || registered_language(ext).is_some())
+                && get_language(ext).is_some()
+        })
+        || path.file_name().and_then(OsStr::to_str).and_then(language_for_file_name).is_some()
+}
+
+/// Maximum number of bytes read looking for a shebang line
+const MAX_SHEBANG_LEN: u64 = 256;
+
+/// The first line of a file if it is a shebang line (`#!...`)
+pub fn shebang_line(path: &Path) -> Option<String> {
+    let mut line = String::new();
+    BufReader::new(File::open(path).ok()?.take(MAX_SHEBANG_LEN)).read_line(&mut line).ok()?;
+    line.starts_with("#!").then(|| line.trim_end().to_string())
+}
+
+/// Whether an extensionless file is a script in a supported language, going by its shebang
+fn is_supported_script(path: &Path) -> bool {
+    path.extension().is_none() && shebang_line(path).and_then(|line| language_for_shebang(&line)).is_some()
+}
+
+fn discover(target_dir: &str, filter: &PathFilter, verbose: bool) -> Result<Discovered, Box<dyn Error>> {
+    if verbose {
+        println!("Searching for files in directory and subdirectories: {}", target_dir);
+    }
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+
+    // Ensure the target directory exists
+    let target_path = Path::new(target_dir);
+    if !target_path.exists() {
+        return Err(Box::new(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("Target directory not found: {}", target_dir)
+        )));
+    }
+    let ignore = MagmaIgnore::load(target_path)?;
+    let is_ignored = |path: &Path, is_dir: bool| {
+        ignore.as_ref().is_some_and(|ignore| ignore.is_ignored(&relative_path(path, target_path), is_dir))
+    };
+
+    // Use WalkDir to recursively walk the directory tree
+    // This will automatically walk through all subdirectories
+    let walker = WalkDir::new(target_dir)
+        .follow_links(filter.follow_symlinks)
+        .into_iter()
+        .filter_entry(|entry| {
+            // Skip directories we want to ignore or that are excluded, along with all their contents
+            let ignored = entry.file_type().is_dir() && entry.depth() > 0
+                && (is_ignored_dir(&entry.file_name().to_string_lossy())
+                    || filter.excludes_dir(&relative_path(entry.path(), target_path))
+                    || is_ignored(entry.path(), true));
+            if ignored && verbose {
+                println!("Skipping directory: {}", entry.path().display());
+            }
+            !ignored
+        });
+
+    // Process each entry
+    for entry_result in walker {
+        if filter.clock.as_ref().is_some_and(|clock| clock.check(ScanPhase::Discovery)) {
+            warn!("⏰ Discovery stopped early with {} files found in {}", files.len(), target_dir);
+            break;
+        }
+
+        // Handle any errors during directory traversal
+        let entry = match entry_result {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("Error accessing path: {}", e);
+                continue;
+            }
+        };
+
+        let path = entry.path();
+
+        // Only process files the filter allows; unless links are followed, symlinks are skipped
+        if entry.file_type().is_file() && filter.allows(&relative_path(path, target_path)) && !is_ignored(path, false) {
+            // Check if the file has one of our supported extensions
+            let path_str = strip_verbatim_prefix(&path.to_string_lossy());
+            if has_supported_extension(path) || is_supported_script(path) {
+                if verbose {
+                    println!("Found file: {}", path_str);
+                }
+                files.push(path_str);
+            } else {
+                skipped.push(path_str);
+            }
+        }
+    }
+
+    if verbose {
+        println!("Found {} files", files.len());
+
+        // If no files were found, print a warning
+        if files.is_empty() {
+            println!("Warning: No files with supported extensions found in {}", target_dir);
+        }
+    }
+
+    Ok(Discovered { files, skipped })
+}
+
+/// Whether discovery would scan the file at `path`, given `relative` to the target directory,
+/// so that files changing after discovery ran can be checked the same way
+pub fn is_scannable(path: &Path, relative: &str, filter: &PathFilter, ignore: Option<&MagmaIgnore>) -> bool {
+    let in_ignored_dir = Path::new(relative).parent()
+        .is_some_and(|dir| dir.iter().any(|name| is_ignored_dir(&name.to_string_lossy())));
+
+    !in_ignored_dir
+        && filter.allows(relative)
+        && !ignore.is_some_and(|ignore| ignore.is_ignored(relative, false))
+        && (has_supported_extension(path) || is_supported_script(path))
+}
+
+/// Keep only the files modified or staged in the git working tree containing `target_dir`,
+/// so that just the work about to be committed is scanned
+pub fn retain_changed(files: Vec<String>, target_dir: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let changed: HashSet<PathBuf> = git::changed_files(Path::new(target_dir))?.into_iter()
+        .filter_map(|path| path.canonicalize().ok())
+        .collect();
+
+    Ok(files.into_iter()
+        .filter(|file| Path::new(file).canonicalize().is_ok_and(|path| changed.contains(&path)))
+        .collect())
+}
+
+/// A path found by the walk relative to the target directory
+fn relative_path(path: &Path, target: &Path) -> String {
+    path.strip_prefix(target).unwrap_or(path).to_string_lossy().to_string()
+}
+
+/// Whether a directory should be skipped during discovery.
+/// Windows file systems are case-insensitive, so names are compared ignoring case there.
+fn is_ignored_dir(name: &str) -> bool {
+    IGNORE_DIRS.iter().any(|&ignore| {
+        if cfg!(windows) {
+            name.eq_ignore_ascii_case(ignore)
+        } else {
+            name == ignore
+        }
+    })
+}
+
+/// Remove the `\\?\` prefix of Windows verbatim paths, turning `\\?\UNC\server\share`
+/// back into `\\server\share`. Such prefixes come from canonicalized or long paths and
+/// would otherwise leak into reports; the standard library re-adds them when opening long paths.
+fn strip_verbatim_prefix(path: &str) -> String {
+    if let Some(unc) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{}", unc)
+    } else if let Some(local) = path.strip_prefix(r"\\?\") {
+        local.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Normalize a path for reporting: verbatim prefixes are dropped and backslashes become
+/// forward slashes, so scans on Windows and Linux produce the same file paths.
+pub fn normalize_path(path: &str) -> String {
+    strip_verbatim_prefix(path).replace('\\', "/")
+}
+
+/// Get the distinct extensions of the given files, as sent to the API when initializing a scan
+pub fn file_extensions(files: &[String]) -> Vec<String> {
+    files.iter()
+        .filter_map(|file| {
+            Path::new(file)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map(|ext| ext.to_string())
+        })
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect()
+}
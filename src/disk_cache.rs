@@ -0,0 +1,291 @@
+use crate::reporter::write_atomic;
+use crate::types::MatchResult;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::{atomic::{AtomicU64, Ordering}, Arc},
+    time::{Duration, SystemTime},
+};
+use tracing::debug;
+
+/// Bumped whenever entries are laid out differently, so older entries are never read
+const FORMAT_VERSION: &str = "1";
+
+/// Entries older than this are dropped unless the cache is given its own TTL
+pub const DEFAULT_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Size the cache is pruned back to unless it is given its own cap
+pub const DEFAULT_MAX_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// `prune_if_due` walks the cache at most this often, however many scans use it
+pub const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Written on every prune, so scans in other processes can tell when the cache was last pruned
+const PRUNE_MARKER: &str = "last-prune";
+
+/// Hits and misses of every scan that used the cache, added up by `record_lookups`
+const LOOKUPS_FILE: &str = "lookups.json";
+
+include!(concat!(env!("OUT_DIR"), "/grammar_versions.rs"));
+
+/// Findings of files kept on disk between runs, so that scans of mostly unchanged repositories
+/// skip parsing the files they have already seen. Entries are keyed by `CacheKey`: the file's
+/// path and contents, its language and the scanner version that bundles its grammar, and the
+/// queries and settings it was scanned with.
+///
+/// Entries are files named by their key, written atomically, so several scans can share a
+/// cache directory. Unreadable or corrupt entries are misses, never errors.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: u64,
+    /// Lookups since they were last recorded, shared by clones of the cache
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+/// What a cache holds and how often scans found what they looked for in it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub entries: usize,
+    pub bytes: u64,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Share of lookups that were hits, or `None` before any lookup was recorded
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+/// Hits and misses as stored in `LOOKUPS_FILE`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Lookups {
+    hits: u64,
+    misses: u64,
+}
+
+impl DiskCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl: DEFAULT_TTL,
+            max_bytes: DEFAULT_MAX_BYTES,
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Treat entries written longer ago than `ttl` as missing
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Drop the oldest entries once the cache holds more than `max_bytes`
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// `$XDG_CACHE_HOME/magma-scanner`, or `~/.cache/magma-scanner`
+    pub fn default_dir() -> Option<PathBuf> {
+        env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()).map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")).map(|home| PathBuf::from(home).join(".cache")))
+            .map(|dir| dir.join("magma-scanner"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Findings stored under `key`, unless there are none or they have expired
+    pub fn get(&self, key: &CacheKey) -> Option<Vec<MatchResult>> {
+        let results = self.lookup(key);
+        let counter = if results.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        results
+    }
+
+    fn lookup(&self, key: &CacheKey) -> Option<Vec<MatchResult>> {
+        let path = self.entry_path(key);
+        let written = fs::metadata(&path).ok()?.modified().ok()?;
+        if written.elapsed().unwrap_or_default() > self.ttl {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        match serde_json::from_slice(&fs::read(&path).ok()?) {
+            Ok(results) => Some(results),
+            Err(e) => {
+                debug!("Ignoring corrupt cache entry {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Store findings under `key`. Failures are logged and otherwise ignored, since a scan
+    /// never depends on its cache.
+    pub fn put(&self, key: &CacheKey, results: &[MatchResult]) {
+        let path = self.entry_path(key);
+        let written = serde_json::to_vec(results)
+            .map_err(std::io::Error::from)
+            .and_then(|json| write_atomic(&path, |out| out.write_all(&json)));
+        if let Err(e) = written {
+            debug!("Failed to write cache entry {}: {}", path.display(), e);
+        }
+    }
+
+    /// Remove expired entries, then the oldest ones until the cache fits its size cap.
+    /// Returns the number of entries removed.
+    pub fn prune(&self) -> usize {
+        let mut entries: Vec<(SystemTime, u64, PathBuf)> = Vec::new();
+        let mut removed = 0;
+        for path in self.entry_paths() {
+            let Some((written, len)) = fs::metadata(&path).ok()
+                .and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())))
+            else {
+                continue;
+            };
+            if written.elapsed().unwrap_or_default() > self.ttl {
+                removed += usize::from(fs::remove_file(&path).is_ok());
+            } else {
+                entries.push((written, len, path));
+            }
+        }
+
+        let mut total: u64 = entries.iter().map(|(_, len, _)| len).sum();
+        entries.sort();
+        for (_, len, path) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if fs::remove_file(&path).is_ok() {
+                total -= len;
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            debug!("Pruned {} entries from the cache in {}", removed, self.dir.display());
+        }
+        removed
+    }
+
+    /// Prune the cache unless it was pruned, by any process, within the last `PRUNE_INTERVAL`,
+    /// so that frequent scans don't each walk a large cache. Returns the number of entries removed.
+    pub fn prune_if_due(&self) -> usize {
+        let marker = self.dir.join(FORMAT_VERSION).join(PRUNE_MARKER);
+        let pruned = fs::metadata(&marker).and_then(|metadata| metadata.modified()).ok();
+        if pruned.is_some_and(|pruned| pruned.elapsed().unwrap_or_default() < PRUNE_INTERVAL) {
+            return 0;
+        }
+        if let Err(e) = write_atomic(&marker, |_| Ok(())) {
+            debug!("Failed to write {}: {}", marker.display(), e);
+        }
+        self.prune()
+    }
+
+    /// Add the hits and misses of `get` since the last call to those recorded in the cache
+    /// directory, where `stats` reads them. Concurrent scans may lose some of each other's
+    /// counts, which is fine for a hit rate.
+    pub fn record_lookups(&self) {
+        let (hits, misses) = (self.hits.swap(0, Ordering::Relaxed), self.misses.swap(0, Ordering::Relaxed));
+        if hits + misses == 0 {
+            return;
+        }
+        let path = self.dir.join(FORMAT_VERSION).join(LOOKUPS_FILE);
+        let mut lookups = self.recorded_lookups();
+        lookups.hits += hits;
+        lookups.misses += misses;
+        let written = serde_json::to_vec(&lookups)
+            .map_err(std::io::Error::from)
+            .and_then(|json| write_atomic(&path, |out| out.write_all(&json)));
+        if let Err(e) = written {
+            debug!("Failed to write {}: {}", path.display(), e);
+        }
+    }
+
+    fn recorded_lookups(&self) -> Lookups {
+        fs::read(self.dir.join(FORMAT_VERSION).join(LOOKUPS_FILE)).ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    /// Number and total size of the entries, and the hits and misses recorded so far
+    pub fn stats(&self) -> CacheStats {
+        let lookups = self.recorded_lookups();
+        let mut stats = CacheStats { hits: lookups.hits, misses: lookups.misses, ..CacheStats::default() };
+        for path in self.entry_paths() {
+            if let Ok(metadata) = fs::metadata(&path) {
+                stats.entries += 1;
+                stats.bytes += metadata.len();
+            }
+        }
+        stats
+    }
+
+    /// Number of entries in the cache
+    pub fn len(&self) -> usize {
+        self.entry_paths().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove every entry, along with the recorded hits and misses
+    pub fn clear(&self) -> std::io::Result<()> {
+        match fs::remove_dir_all(self.dir.join(FORMAT_VERSION)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Entries are spread over subdirectories by the first two hex digits of their key
+    fn entry_path(&self, key: &CacheKey) -> PathBuf {
+        self.dir.join(FORMAT_VERSION).join(&key.0[..2]).join(format!("{}.json", key.0))
+    }
+
+    fn entry_paths(&self) -> impl Iterator<Item = PathBuf> {
+        fs::read_dir(self.dir.join(FORMAT_VERSION)).into_iter().flatten()
+            .filter_map(|shard| fs::read_dir(shard.ok()?.path()).ok())
+            .flatten()
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "json"))
+    }
+}
+
+/// SHA-256 of everything a file's findings depend on, built up one part at a time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey(String);
+
+impl CacheKey {
+    pub fn builder() -> CacheKeyBuilder {
+        CacheKeyBuilder(Sha256::new())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+pub struct CacheKeyBuilder(Sha256);
+
+impl CacheKeyBuilder {
+    /// Add a part; parts are length-prefixed, so ("ab", "c") and ("a", "bc") differ
+    pub fn part(mut self, part: impl AsRef<[u8]>) -> Self {
+        let part = part.as_ref();
+        self.0.update((part.len() as u64).to_le_bytes());
+        self.0.update(part);
+        self
+    }
+
+    pub fn finish(self) -> CacheKey {
+        CacheKey(format!("{:x}", self.0.finalize()))
+    }
+}
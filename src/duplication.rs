@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::{hash_map::DefaultHasher, HashMap}, hash::{Hash, Hasher}};
+use tree_sitter::Tree;
+
+/// Default number of tokens a clone must span to be reported
+pub const DEFAULT_MIN_TOKENS: usize = 50;
+
+/// A normalized token: the kind of a leaf node, so identifiers and literals compare equal
+/// whatever their text, while keywords and punctuation keep their own kinds
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    kind: &'static str,
+    line: usize,
+}
+
+/// Where one copy of a clone sits
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct CloneLocation {
+    pub file: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Two or more places holding the same normalized token sequence
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CloneGroup {
+    pub language: String,
+    /// Length of the duplicated sequence in tokens
+    pub tokens: usize,
+    pub locations: Vec<CloneLocation>,
+}
+
+/// Finds duplicated code by fingerprinting every window of `min_tokens` normalized tokens.
+/// Windows shared by the same files at consecutive offsets are merged into one clone, so
+/// each clone is reported once at its full length.
+pub struct DuplicateDetector {
+    min_tokens: usize,
+    files: Vec<(String, &'static str, Vec<Token>)>,
+}
+
+impl DuplicateDetector {
+    pub fn new(min_tokens: usize) -> Self {
+        Self { min_tokens: min_tokens.max(1), files: Vec::new() }
+    }
+
+    /// Add a parsed file. Clones are only looked for between files of the same language.
+    pub fn add_file(&mut self, path: &str, language: &'static str, tree: &Tree) {
+        self.files.push((path.to_string(), language, tokenize(tree)));
+    }
+
+    /// Clone groups spanning at least `min_tokens` tokens, largest first
+    pub fn find_clones(&self) -> Vec<CloneGroup> {
+        let k = self.min_tokens;
+
+        // Fingerprint of every window, per file
+        let fingerprints: Vec<Vec<u64>> = self.files.iter()
+            .map(|(_, language, tokens)| {
+                if tokens.len() < k {
+                    return Vec::new();
+                }
+                (0..=tokens.len() - k).map(|start| fingerprint(language, &tokens[start..start + k])).collect()
+            })
+            .collect();
+
+        let mut occurrences: HashMap<u64, Vec<(usize, usize)>> = HashMap::new();
+        for (file, hashes) in fingerprints.iter().enumerate() {
+            for (position, hash) in hashes.iter().enumerate() {
+                occurrences.entry(*hash).or_default().push((file, position));
+            }
+        }
+
+        // Whether every location has a window at `offset` from it and those windows are
+        // shared by exactly these locations
+        let same_group = |locations: &[(usize, usize)], offset: isize| -> bool {
+            let mut hashes = locations.iter().map(|(file, position)| {
+                position.checked_add_signed(offset).and_then(|p| fingerprints[*file].get(p))
+            });
+            let Some(Some(first)) = hashes.next() else {
+                return false;
+            };
+            hashes.all(|hash| hash == Some(first)) && without_overlaps(&occurrences[first], k).len() == locations.len()
+        };
+
+        let mut groups = Vec::new();
+        for locations in occurrences.values() {
+            let locations = without_overlaps(locations, k);
+            if locations.len() < 2 || same_group(&locations, -1) {
+                continue;
+            }
+
+            // Extend the clone while the following windows stay shared by the same locations
+            let mut length = k;
+            while same_group(&locations, (length - k + 1) as isize) {
+                length += 1;
+            }
+
+            let (_, language, _) = &self.files[locations[0].0];
+            groups.push(CloneGroup {
+                language: language.to_string(),
+                tokens: length,
+                locations: locations.iter()
+                    .map(|(file, start)| {
+                        let (path, _, tokens) = &self.files[*file];
+                        CloneLocation {
+                            file: path.clone(),
+                            start_line: tokens[*start].line,
+                            end_line: tokens[start + length - 1].line,
+                        }
+                    })
+                    .collect(),
+            });
+        }
+
+        groups.sort_by(|a, b| b.tokens.cmp(&a.tokens).then_with(|| a.locations.cmp(&b.locations)));
+        groups
+    }
+}
+
+/// Drop locations overlapping an earlier one in the same file, as in runs of repeated tokens
+fn without_overlaps(locations: &[(usize, usize)], window: usize) -> Vec<(usize, usize)> {
+    let mut sorted = locations.to_vec();
+    sorted.sort_unstable();
+
+    let mut kept: Vec<(usize, usize)> = Vec::new();
+    for (file, position) in sorted {
+        if kept.last().is_none_or(|(last_file, last)| *last_file != file || position >= last + window) {
+            kept.push((file, position));
+        }
+    }
+    kept
+}
+
+fn fingerprint(language: &str, tokens: &[Token]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    language.hash(&mut hasher);
+    for token in tokens {
+        token.kind.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// The leaf nodes of a tree in source order, skipping comments
+fn tokenize(tree: &Tree) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut stack = vec![tree.root_node()];
+
+    while let Some(node) = stack.pop() {
+        if node.kind().contains("comment") {
+            continue;
+        }
+        if node.child_count() == 0 {
+            if node.end_byte() > node.start_byte() {
+                tokens.push(Token { kind: node.kind(), line: node.start_position().row + 1 });
+            }
+            continue;
+        }
+
+        let mut cursor = node.walk();
+        let children: Vec<_> = node.children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev());
+    }
+
+    tokens
+}
@@ -0,0 +1,101 @@
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use rsa::{
+    pkcs1::DecodeRsaPublicKey,
+    pkcs8::{DecodePublicKey, EncodePublicKey},
+    Oaep, RsaPublicKey,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{error::Error, fs, path::Path};
+
+/// Algorithms used for envelopes: the data key is wrapped with RSA-OAEP (SHA-256)
+/// and the evidence encrypted with AES-256-GCM
+pub const ENVELOPE_ALGORITHM: &str = "RSA-OAEP-256+A256GCM";
+
+/// Fields of an evidence request body that are encrypted; everything else stays readable
+/// so the API can still route the upload
+const SEALED_FIELDS: [&str; 2] = ["evidence", "evidence_context"];
+
+/// Encrypted data together with the wrapped key needed to decrypt it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    pub algorithm: String,
+    /// SHA-256 of the DER-encoded public key, so the right private key can be found
+    pub key_id: String,
+    /// The random AES-256 data key, encrypted with the public key (base64)
+    pub encrypted_key: String,
+    /// AES-GCM nonce (base64)
+    pub nonce: String,
+    /// Encrypted data including the GCM tag (base64)
+    pub ciphertext: String,
+}
+
+/// Encrypts evidence with an organization's RSA public key before it is uploaded,
+/// so code excerpts can only be read by whoever holds the private key
+pub struct EvidenceEncryptor {
+    key: RsaPublicKey,
+    key_id: String,
+}
+
+impl EvidenceEncryptor {
+    /// Load a PEM public key, in SPKI (`PUBLIC KEY`) or PKCS#1 (`RSA PUBLIC KEY`) form
+    pub fn from_pem(pem: &str) -> Result<Self, Box<dyn Error>> {
+        let key = RsaPublicKey::from_public_key_pem(pem)
+            .or_else(|_| RsaPublicKey::from_pkcs1_pem(pem))
+            .map_err(|e| format!("Invalid RSA public key: {}", e))?;
+
+        let der = key.to_public_key_der()?;
+        let key_id = format!("{:x}", Sha256::digest(der.as_bytes()));
+        Ok(Self { key, key_id })
+    }
+
+    /// Load a PEM public key from a file
+    pub fn from_pem_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let pem = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read public key {}: {}", path.display(), e))?;
+        Self::from_pem(&pem)
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Encrypt `plaintext` under a fresh data key
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Envelope, Box<dyn Error>> {
+        let data_key = Aes256Gcm::generate_key(&mut OsRng);
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(&data_key)
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| "Failed to encrypt evidence")?;
+        let encrypted_key = self.key.encrypt(&mut OsRng, Oaep::new::<Sha256>(), data_key.as_slice())?;
+
+        Ok(Envelope {
+            algorithm: ENVELOPE_ALGORITHM.to_string(),
+            key_id: self.key_id.clone(),
+            encrypted_key: STANDARD.encode(encrypted_key),
+            nonce: STANDARD.encode(nonce),
+            ciphertext: STANDARD.encode(ciphertext),
+        })
+    }
+
+    /// Replace the evidence and its context in an evidence request body with an
+    /// `encrypted_evidence` envelope holding them as JSON
+    pub fn seal_evidence(&self, mut request_body: serde_json::Value) -> Result<serde_json::Value, Box<dyn Error>> {
+        let body = request_body.as_object_mut().ok_or("Evidence request body is not an object")?;
+
+        let mut sealed = serde_json::Map::new();
+        for field in SEALED_FIELDS {
+            if let Some(value) = body.remove(field) {
+                sealed.insert(field.to_string(), value);
+            }
+        }
+
+        let envelope = self.encrypt(&serde_json::to_vec(&sealed)?)?;
+        body.insert("encrypted_evidence".to_string(), serde_json::to_value(envelope)?);
+        Ok(request_body)
+    }
+}
@@ -0,0 +1,1398 @@
+use crate::bench::{self, BenchReport};
+use crate::complexity::{self, ComplexitySummary};
+use crate::coverage::Coverage;
+use crate::dead_code::{self, SymbolIndex};
+use crate::discovery::normalize_path;
+use crate::duplication::{CloneGroup, DuplicateDetector};
+use crate::language_loader::{canonical_language, get_language, is_ambiguous_extension, language_for_content, language_for_file_name, language_for_shebang, registered_language};
+use crate::pii;
+#[cfg(feature = "scripting")]
+use crate::scripting::ResultTransform;
+use crate::stats::{self, CodeStats};
+use crate::svelte;
+use crate::timeout::{ScanClock, ScanPhase};
+use crate::disk_cache::{CacheKey, DiskCache, GRAMMAR_VERSIONS};
+use crate::incremental::{self, LineSpan};
+use crate::injection::{self, InjectedTree};
+use crate::types::{CaptureResult, FileTarget, MatchResult, Severity, TreeSitterQuery};
+use rayon::{prelude::*, ThreadPoolBuilder};
+use sha2::{Digest, Sha256};
+use std::{collections::{hash_map::DefaultHasher, HashMap}, error::Error, fs::{self, File}, hash::{Hash, Hasher}, io::Read, ops::Range, path::PathBuf, sync::{atomic::{AtomicBool, Ordering}, Arc, Mutex}, time::{Duration, Instant, SystemTime}};
+use tracing::{debug, debug_span, trace, trace_span, warn};
+use tree_sitter::{Language, Parser, Point, Query, QueryCursor, Tree};
+
+/// A parsed tree together with the source it was parsed from, shared cheaply between
+/// the AST cache and the scans using it
+pub type ParsedFile = Arc<(Tree, String)>;
+
+/// Resolves the language of a file by its path, overriding the usual detection
+pub type LanguageOverride = Arc<dyn Fn(&str) -> Option<&'static str> + Send + Sync>;
+
+/// Notified as files are scanned, e.g. to drive a progress bar
+pub trait ScanProgress: Send + Sync {
+    /// A scan of `files` files is starting
+    fn started(&self, files: usize);
+    /// A file was scanned, producing `matches` findings
+    fn file_scanned(&self, matches: usize);
+    /// The scan finished or stopped early
+    fn finished(&self);
+}
+
+/// File contents read instead of the filesystem, e.g. the blobs of a commit in a bare repository
+pub trait SourceFiles: Send + Sync {
+    /// Contents of the file at `path`, or `None` if there is no such file or it isn't text
+    fn read(&self, path: &str) -> Option<String>;
+    /// Size in bytes of the file at `path`, without reading it
+    fn size(&self, path: &str) -> Option<u64>;
+}
+
+impl SourceFiles for HashMap<String, String> {
+    fn read(&self, path: &str) -> Option<String> {
+        self.get(path).cloned()
+    }
+
+    fn size(&self, path: &str) -> Option<u64> {
+        self.get(path).map(|source| source.len() as u64)
+    }
+}
+
+/// Bytes of a file read to tell what kind of file it is
+const FILE_HEAD_LEN: usize = 4096;
+
+/// Files larger than this many bytes are skipped unless the limit is changed, since generated
+/// files of hundreds of megabytes would otherwise be read into memory whole
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// Cache of parsed ASTs and their sources by file path
+pub(crate) type AstCache = Arc<Mutex<HashMap<String, CachedTree>>>;
+
+/// Files modified this close to when they were read may have changed again within the same
+/// tick of a coarse filesystem clock, so their modification time alone can't be trusted
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// Modification time and size of a file on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+}
+
+impl FileStamp {
+    pub(crate) fn from_metadata(metadata: &fs::Metadata) -> Option<Self> {
+        Some(Self { modified: metadata.modified().ok()?, len: metadata.len() })
+    }
+}
+
+/// A parsed file in the AST cache, with what the file looked like when it was read
+#[derive(Clone)]
+pub(crate) struct CachedTree {
+    parsed: ParsedFile,
+    /// `None` for in-memory sources and files whose modification time is unknown
+    stamp: Option<FileStamp>,
+    /// When the stamp was taken, just before the file was read
+    read_at: SystemTime,
+    hash: u64,
+}
+
+impl CachedTree {
+    pub(crate) fn new(parsed: ParsedFile, stamp: Option<FileStamp>, read_at: SystemTime) -> Self {
+        let hash = content_hash(&parsed.1);
+        Self { parsed, stamp, read_at, hash }
+    }
+
+    #[cfg(feature = "client")]
+    pub(crate) fn parsed(&self) -> &ParsedFile {
+        &self.parsed
+    }
+
+    /// Whether a file with this stamp certainly still holds the cached source: it is unchanged
+    /// and was last modified well before it was read
+    fn matches_stamp(&self, stamp: Option<FileStamp>) -> bool {
+        match (self.stamp, stamp) {
+            (Some(cached), Some(current)) => {
+                cached == current && current.modified + RACY_WINDOW <= self.read_at
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the file's current contents are those the cached tree was parsed from
+    pub(crate) fn matches_source(&self, source: &str) -> bool {
+        content_hash(source) == self.hash
+    }
+}
+
+/// How `refresh_tree` came by a file's tree
+enum TreeChange {
+    /// The cached tree, parsed from the file as it is now
+    Unchanged,
+    /// The cached tree edited to the file's new contents and re-parsed incrementally
+    Edited { span: LineSpan, bytes: Range<usize> },
+    /// A tree parsed from scratch
+    Parsed,
+}
+
+/// Findings of a file scanned again by `Engine::rescan_file`
+#[derive(Debug)]
+pub enum Rescan {
+    /// The file is as it was, and so are its findings
+    Unchanged,
+    /// Only the lines in `span` changed; `results` are the findings within them, to be merged
+    /// into the previous ones with `LineSpan::merge`
+    Partial { span: LineSpan, results: Vec<MatchResult> },
+    /// All findings of the file
+    Full(Vec<MatchResult>),
+}
+
+impl Rescan {
+    /// The findings of the file, given those of its previous scan
+    pub fn apply(self, previous: Vec<MatchResult>) -> Vec<MatchResult> {
+        match self {
+            Rescan::Unchanged => previous,
+            Rescan::Partial { span, results } => span.merge(previous, results),
+            Rescan::Full(results) => results,
+        }
+    }
+}
+
+/// What the AST cache holds for a file
+pub(crate) enum CacheLookup {
+    /// A tree parsed from the file as it is now
+    Hit(ParsedFile),
+    /// A tree that is current only if the file's contents still hash the same, e.g. after the
+    /// file was touched or modified right around when it was read
+    Unsure(CachedTree),
+    Miss,
+}
+/// Cache of compiled queries by (language, query text)
+pub(crate) type QueryCache = Arc<Mutex<HashMap<(String, String), Arc<Query>>>>;
+
+/// How long parsed ASTs are kept in the scanner's cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Never cache; every scan re-reads and re-parses its files
+    Disabled,
+    /// Cache for the duration of a single scan run, then drop everything
+    Run,
+    /// Keep cached ASTs for the lifetime of the scanner, across polls
+    #[default]
+    Persistent,
+}
+
+impl std::str::FromStr for CacheMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" | "disabled" => Ok(CacheMode::Disabled),
+            "run" => Ok(CacheMode::Run),
+            "persistent" => Ok(CacheMode::Persistent),
+            other => Err(format!("unknown cache scope: {}", other)),
+        }
+    }
+}
+
+/// The parsing and query engine: parses files into cached ASTs and runs queries against them.
+/// Needs no network access or async runtime, so it can be embedded for purely local scanning.
+#[derive(Clone)]
+pub struct Engine {
+    /// Cache of parsed ASTs by file path
+    ast_cache: AstCache,
+    /// Cache of compiled queries
+    query_cache: QueryCache,
+    /// Organization ID recorded on results
+    organization_id: String,
+    /// Code base version (commit hash) recorded on results
+    code_base_version: String,
+    /// Stop scanning as soon as a finding at or above this severity is produced
+    fail_fast: Option<Severity>,
+    /// How long parsed ASTs stay in the cache
+    cache_mode: CacheMode,
+    /// Suppress per-file console output, e.g. while a terminal UI owns the screen
+    quiet: bool,
+    /// Per-file match limit for queries that don't set their own `sample`
+    sample: Option<usize>,
+    /// Look for PII in string literals alongside the queries
+    detect_pii: bool,
+    /// Report functions whose cyclomatic complexity exceeds this
+    complexity_threshold: Option<usize>,
+    /// Complexity metrics accumulated over the functions scanned so far
+    complexity_summary: Arc<Mutex<ComplexitySummary>>,
+    /// Test coverage that findings are annotated with
+    coverage: Option<Arc<Coverage>>,
+    /// Script run on every finding before it is returned
+    #[cfg(feature = "scripting")]
+    transform: Option<Arc<ResultTransform>>,
+    /// File contents by path, read instead of the filesystem, e.g. when scanning a bare repository
+    sources: Option<Arc<dyn SourceFiles>>,
+    /// Also run queries on code embedded in other languages, like SQL in string literals
+    injections: bool,
+    /// Hook deciding the language of files before their extension is looked at
+    language_override: Option<LanguageOverride>,
+    /// Number of files scanned at once; one per core if unset
+    threads: Option<usize>,
+    /// Notified as files are scanned
+    progress: Option<Arc<dyn ScanProgress>>,
+    /// Canonical names of languages whose files are never scanned
+    disabled_languages: Vec<&'static str>,
+    /// Files larger than this many bytes are skipped
+    max_file_size: Option<u64>,
+    /// Deadlines after which files are no longer parsed or queried
+    clock: Option<ScanClock>,
+    /// Findings of files scanned by earlier runs
+    disk_cache: Option<DiskCache>,
+}
+
+impl Engine {
+    /// Create an engine whose results are attributed to the given organization and code base version
+    pub fn new(organization_id: String, code_base_version: String) -> Self {
+        Self {
+            ast_cache: Arc::new(Mutex::new(HashMap::new())),
+            query_cache: Arc::new(Mutex::new(HashMap::new())),
+            organization_id,
+            code_base_version,
+            fail_fast: None,
+            cache_mode: CacheMode::default(),
+            quiet: false,
+            sample: None,
+            detect_pii: false,
+            complexity_threshold: None,
+            complexity_summary: Arc::new(Mutex::new(ComplexitySummary::default())),
+            coverage: None,
+            #[cfg(feature = "scripting")]
+            transform: None,
+            sources: None,
+            injections: false,
+            language_override: None,
+            threads: None,
+            progress: None,
+            disabled_languages: Vec::new(),
+            max_file_size: Some(DEFAULT_MAX_FILE_SIZE),
+            clock: None,
+            disk_cache: None,
+        }
+    }
+
+    /// Stop scanning as soon as a finding at or above `severity` is produced
+    pub fn with_fail_fast(mut self, severity: Severity) -> Self {
+        self.fail_fast = Some(severity);
+        self
+    }
+
+    /// Read files from these contents by path instead of from disk; paths missing from them
+    /// can't be parsed. Their paths are only unique among themselves, so the engine gets an
+    /// AST cache of its own that is never shared with other engines.
+    pub fn with_sources(mut self, sources: impl SourceFiles + 'static) -> Self {
+        self.sources = Some(Arc::new(sources));
+        self.ast_cache = Arc::new(Mutex::new(HashMap::new()));
+        self
+    }
+
+    /// The in-memory file contents read instead of the filesystem, if any
+    pub(crate) fn sources(&self) -> Option<&dyn SourceFiles> {
+        self.sources.as_deref()
+    }
+
+    pub(crate) fn progress(&self) -> Option<&dyn ScanProgress> {
+        self.progress.as_deref()
+    }
+
+    /// Also parse code embedded in files of another language, such as scripts in HTML, shell in
+    /// YAML `run:` steps and SQL in string literals, and run that language's queries on it
+    pub fn with_injections(mut self, enabled: bool) -> Self {
+        self.injections = enabled;
+        self
+    }
+
+    /// Decide the language of files with `resolve` before looking at their extension or
+    /// contents; files it returns None for are resolved as usual
+    pub fn with_language_override(mut self, resolve: impl Fn(&str) -> Option<&'static str> + Send + Sync + 'static) -> Self {
+        self.language_override = Some(Arc::new(resolve));
+        self
+    }
+
+    /// Control whether and for how long parsed ASTs are cached
+    pub fn with_cache_mode(mut self, mode: CacheMode) -> Self {
+        self.cache_mode = mode;
+        self
+    }
+
+    /// Suppress the per-file messages printed while parsing and matching
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Parse and query at most `threads` files at once instead of one per core
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads.max(1));
+        self
+    }
+
+    /// Skip files in these languages, given by canonical name, as if they weren't supported
+    pub fn with_disabled_languages(mut self, languages: Vec<&'static str>) -> Self {
+        self.disabled_languages = languages;
+        self
+    }
+
+    /// Skip files larger than `bytes`, or none at all for `None`, instead of those larger than
+    /// `DEFAULT_MAX_FILE_SIZE`
+    pub fn with_max_file_size(mut self, bytes: Option<u64>) -> Self {
+        self.max_file_size = bytes;
+        self
+    }
+
+    /// Stop parsing and querying files once the scan or its parse or query phase runs out of time
+    pub fn with_clock(mut self, clock: ScanClock) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Keep the findings of scanned files on disk, and reuse them for files whose contents,
+    /// language and queries are unchanged instead of parsing them again. Scans with a
+    /// complexity threshold and files of grammars loaded at runtime always parse.
+    pub fn with_disk_cache(mut self, cache: DiskCache) -> Self {
+        self.disk_cache = Some(cache);
+        self
+    }
+
+    /// Report the progress of scans of files to `progress`
+    pub fn with_progress(mut self, progress: Arc<dyn ScanProgress>) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// Keep at most `limit` matches per file for queries that don't set their own `sample`
+    pub fn with_sample(mut self, limit: usize) -> Self {
+        self.sample = Some(limit);
+        self
+    }
+
+    /// Also report email addresses, SSNs, card numbers and phone numbers found in string literals
+    pub fn with_pii_detection(mut self, enabled: bool) -> Self {
+        self.detect_pii = enabled;
+        self
+    }
+
+    /// Measure the cyclomatic complexity of every function and report those above `threshold`
+    pub fn with_complexity_threshold(mut self, threshold: usize) -> Self {
+        self.complexity_threshold = Some(threshold);
+        self
+    }
+
+    /// Record on each finding whether tests cover its line
+    pub fn with_coverage(mut self, coverage: Coverage) -> Self {
+        self.coverage = Some(Arc::new(coverage));
+        self
+    }
+
+    /// Run a script on every finding, which can drop, re-rate, tag or rewrite it
+    #[cfg(feature = "scripting")]
+    pub fn with_transform(mut self, transform: ResultTransform) -> Self {
+        self.transform = Some(Arc::new(transform));
+        self
+    }
+
+    pub fn organization_id(&self) -> &str {
+        &self.organization_id
+    }
+
+    pub fn code_base_version(&self) -> &str {
+        &self.code_base_version
+    }
+
+    /// The settings that change which findings a scan produces, for recording alongside its results
+    pub fn settings(&self) -> serde_json::Value {
+        #[cfg(feature = "scripting")]
+        let transform = self.transform.is_some();
+        #[cfg(not(feature = "scripting"))]
+        let transform = false;
+
+        serde_json::json!({
+            "sample": self.sample,
+            "detect_pii": self.detect_pii,
+            "complexity_threshold": self.complexity_threshold,
+            "coverage": self.coverage.is_some(),
+            "transform": transform,
+            "injections": self.injections,
+        })
+    }
+
+    /// The coverage report findings are annotated with, if any
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_deref()
+    }
+
+    /// Complexity metrics accumulated since the last call, or `None` if complexity isn't measured
+    pub fn take_complexity_summary(&self) -> Option<ComplexitySummary> {
+        self.complexity_threshold?;
+        Some(std::mem::take(&mut *self.complexity_summary.lock().unwrap()))
+    }
+
+    /// Use caches shared with other engines. Engines reading in-memory sources keep their
+    /// own AST cache, since other engines may have trees of different files at the same paths.
+    #[cfg(feature = "client")]
+    pub(crate) fn with_caches(mut self, ast_cache: AstCache, query_cache: QueryCache) -> Self {
+        if self.sources.is_none() {
+            self.ast_cache = ast_cache;
+        }
+        self.query_cache = query_cache;
+        self
+    }
+
+    #[cfg(feature = "client")]
+    pub(crate) fn cache_mode(&self) -> CacheMode {
+        self.cache_mode
+    }
+
+    #[cfg(feature = "client")]
+    pub(crate) fn is_quiet(&self) -> bool {
+        self.quiet
+    }
+
+    /// Look up a file in the AST cache, given its current stamp. In-memory sources never
+    /// change, while trees of files on disk are only current if the file's modification time
+    /// and size are unchanged or, failing that, its contents hash the same.
+    pub(crate) fn cached_tree(&self, file_path: &str, stamp: Option<FileStamp>) -> CacheLookup {
+        let Some(cached) = self.ast_cache.lock().unwrap().get(file_path).cloned() else {
+            return CacheLookup::Miss;
+        };
+        if self.sources.is_some() || cached.matches_stamp(stamp) {
+            CacheLookup::Hit(cached.parsed)
+        } else {
+            CacheLookup::Unsure(cached)
+        }
+    }
+
+    /// Add a parsed file to the AST cache, with the stamp taken at `read_at` before reading it
+    pub(crate) fn cache_tree(&self, file_path: &str, parsed: ParsedFile, stamp: Option<FileStamp>, read_at: SystemTime) {
+        self.ast_cache.lock().unwrap().insert(file_path.to_string(), CachedTree::new(parsed, stamp, read_at));
+    }
+
+    /// Stamp of a file on disk, taken before reading it; `None` for in-memory sources
+    pub(crate) fn file_stamp(&self, file_path: &str) -> Option<FileStamp> {
+        if self.sources.is_some() {
+            return None;
+        }
+        FileStamp::from_metadata(&fs::metadata(file_path).ok()?)
+    }
+
+    /// Number of files currently held in the AST cache
+    pub fn cached_files(&self) -> usize {
+        self.ast_cache.lock().unwrap().len()
+    }
+
+    /// Drop a file's cached AST, e.g. after it changed on disk
+    pub fn invalidate(&self, file_path: &str) {
+        self.ast_cache.lock().unwrap().remove(file_path);
+    }
+
+    /// Drop every cached AST
+    pub fn clear_cache(&self) {
+        self.ast_cache.lock().unwrap().clear();
+    }
+
+    /// Called when a scan run finishes; drops the cache if it is scoped to a single run
+    pub(crate) fn end_run(&self) {
+        if self.cache_mode == CacheMode::Run {
+            self.clear_cache();
+        }
+    }
+
+    /// Whether a finding of this severity aborts the scan under fail-fast mode
+    pub(crate) fn fails_fast_on(&self, severity: Severity) -> bool {
+        self.fail_fast.is_some_and(|threshold| severity >= threshold)
+    }
+
+    /// Size in bytes above which files are skipped, if any
+    pub fn max_file_size(&self) -> Option<u64> {
+        self.max_file_size
+    }
+
+    /// Deadlines of the scan, if it has any
+    pub fn clock(&self) -> Option<&ScanClock> {
+        self.clock.as_ref()
+    }
+
+    /// Whether `phase` or the whole scan has run out of time
+    pub(crate) fn out_of_time(&self, phase: ScanPhase) -> bool {
+        self.clock.as_ref().is_some_and(|clock| clock.check(phase))
+    }
+
+    /// Whether a file of `size` bytes is small enough to scan, warning about it if it isn't
+    pub(crate) fn within_size_limit(&self, file_path: &str, size: u64) -> bool {
+        match self.max_file_size {
+            Some(limit) if size > limit => {
+                warn!(file = file_path, size, "Skipping {}: {} bytes is over the {} byte limit", file_path, size, limit);
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Parse a file and cache the AST
+    pub fn parse_file(&self, file_path: &str, language_name: &str) -> Option<(Tree, String)> {
+        let src = self.read_source(file_path)?;
+        self.parse_source(src, language_name)
+    }
+
+    /// A file's contents from the in-memory sources or the filesystem, unless it is over the size limit
+    pub(crate) fn read_source(&self, file_path: &str) -> Option<String> {
+        match &self.sources {
+            Some(sources) => {
+                if !self.within_size_limit(file_path, sources.size(file_path)?) {
+                    return None;
+                }
+                sources.read(file_path)
+            }
+            None => {
+                if !self.within_size_limit(file_path, fs::metadata(file_path).ok()?.len()) {
+                    return None;
+                }
+                fs::read_to_string(PathBuf::from(file_path)).ok()
+            }
+        }
+    }
+
+    /// Get a file's tree and source from the AST cache, reading and parsing it on a miss or
+    /// when the file changed since it was cached
+    fn load_tree(&self, file_path: &str, lang_name: &str) -> Option<ParsedFile> {
+        self.refresh_tree(file_path, lang_name).map(|(parsed, _)| parsed)
+    }
+
+    /// Like `load_tree`, also telling how the tree came about. A file that changed since it
+    /// was cached is re-parsed incrementally from its cached tree.
+    fn refresh_tree(&self, file_path: &str, lang_name: &str) -> Option<(ParsedFile, TreeChange)> {
+        let caching = self.cache_mode != CacheMode::Disabled;
+        let read_at = SystemTime::now();
+        let stamp = if caching { self.file_stamp(file_path) } else { None };
+        let lookup = if caching { self.cached_tree(file_path, stamp) } else { CacheLookup::Miss };
+
+        let (parsed, change) = match lookup {
+            CacheLookup::Hit(parsed) => return Some((parsed, TreeChange::Unchanged)),
+            CacheLookup::Unsure(cached) => {
+                let source = self.read_source(file_path)?;
+                if cached.matches_source(&source) {
+                    self.cache_tree(file_path, cached.parsed.clone(), stamp, read_at);
+                    return Some((cached.parsed, TreeChange::Unchanged));
+                }
+                match incremental::reparse(&cached.parsed, &source, lang_name) {
+                    Some(reparsed) => {
+                        debug!(file = file_path, "Re-parsed lines {}-{} of {}", reparsed.span.start, reparsed.span.new_end, file_path);
+                        let change = TreeChange::Edited { span: reparsed.span, bytes: reparsed.bytes };
+                        (Some((reparsed.tree, source)), change)
+                    }
+                    None => {
+                        debug!(file = file_path, "Re-parsing {}, which changed since it was cached", file_path);
+                        (self.parse_source(source, lang_name), TreeChange::Parsed)
+                    }
+                }
+            }
+            CacheLookup::Miss => (self.parse_file(file_path, lang_name), TreeChange::Parsed),
+        };
+
+        let Some(parsed) = parsed.map(Arc::new) else {
+            if !self.quiet {
+                warn!(file = file_path, "Failed to parse {}", file_path);
+            }
+            return None;
+        };
+        if caching {
+            self.cache_tree(file_path, parsed.clone(), stamp, read_at);
+        }
+        Some((parsed, change))
+    }
+
+    /// Scan a file again after it may have changed, querying only the lines an edit affected
+    /// where that gives the same findings as a full scan. `Unchanged` and `Partial` describe
+    /// the file relative to its previous scan through this engine with the same queries, so
+    /// callers keep the findings of that scan to apply them to.
+    pub fn rescan_file(&self, file_path: &str, queries: &[TreeSitterQuery]) -> Rescan {
+        let router = QueryRouter::new(queries);
+        let Some(lang_name) = self.get_language_for_file(file_path) else {
+            return Rescan::Full(Vec::new());
+        };
+        let Some((parsed, change)) = self.refresh_tree(file_path, lang_name) else {
+            return Rescan::Full(Vec::new());
+        };
+        let (tree, source) = &*parsed;
+
+        match change {
+            TreeChange::Unchanged => Rescan::Unchanged,
+            TreeChange::Edited { span, bytes } if self.queries_by_range(file_path, lang_name, &router) => {
+                let mut results = Vec::new();
+                for query in router.queries_for(file_path, Some(lang_name)) {
+                    let captures = self.run_query_in_range(tree, source, &query.query, lang_name, Some(bytes.clone()));
+                    results.extend(captures.iter().map(|capture| self.match_result(file_path, query, capture, false)));
+                }
+                // Matches that merely overlap the span start before it and are already known
+                results.retain(|result| span.contains(result.line));
+                Rescan::Partial { span, results: self.annotate_results(results) }
+            }
+            _ => Rescan::Full(self.match_file(file_path, lang_name, tree, source, &router)),
+        }
+    }
+
+    /// Whether the findings of part of a file are those of a full scan within that part.
+    /// Sampling picks among all matches of a file, fail-fast stops at the first, and PII,
+    /// complexity and embedded code are found across the whole tree.
+    fn queries_by_range(&self, file_path: &str, lang_name: &str, router: &QueryRouter) -> bool {
+        self.sample.is_none() && self.fail_fast.is_none() && !self.detect_pii && !self.injections
+            && self.complexity_threshold.is_none()
+            && router.queries_for(file_path, Some(lang_name)).iter().all(|query| query.sample.is_none())
+    }
+
+    /// Parse in-memory source code with the given language
+    pub fn parse_source(&self, src: String, language_name: &str) -> Option<(Tree, String)> {
+        parse_with_language(src, language_name)
+    }
+
+    /// Get the language for a file from the override hook, or else based on its extension
+    /// (looking at the contents for ambiguous ones like `.h`), name or shebang; None if its
+    /// grammar isn't available in this build
+    pub fn get_language_for_file(&self, file_path: &str) -> Option<&'static str> {
+        self.resolve_language(file_path, || self.file_head(file_path))
+    }
+
+    /// Get the language for in-memory content as if it were a file at `file_path`, looking at
+    /// `content` where `get_language_for_file` would read the file
+    pub fn get_language_for_source(&self, file_path: &str, content: &str) -> Option<&'static str> {
+        self.resolve_language(file_path, || Some(head_of(content).to_string()))
+    }
+
+    /// The language of a file, calling `head` for its first few kilobytes only when the path
+    /// alone doesn't tell
+    fn resolve_language(&self, file_path: &str, head: impl Fn() -> Option<String>) -> Option<&'static str> {
+        let path = PathBuf::from(file_path);
+        let by_extension = || {
+            let extension = path.extension()?.to_str()?.to_lowercase();
+            if is_ambiguous_extension(&extension)
+                && let Some(head) = head()
+            {
+                return language_for_content(&extension, &head);
+            }
+            canonical_language(&extension)
+        };
+        // The language named by an extensionless script's shebang line
+        let by_shebang = || language_for_shebang(head()?.lines().next()?);
+
+        self.language_override.as_ref()
+            .and_then(|resolve| resolve(file_path))
+            .and_then(canonical_language)
+            .or_else(by_extension)
+            .filter(|language| get_language(language).is_some())
+            .or_else(|| language_for_file_name(path.file_name()?.to_str()?))
+            .or_else(|| if path.extension().is_none() { by_shebang() } else { None })
+            .filter(|language| !self.disabled_languages.contains(language))
+    }
+
+    /// The first few kilobytes of a file, enough to tell what kind of file it is
+    fn file_head(&self, file_path: &str) -> Option<String> {
+        match &self.sources {
+            Some(sources) => Some(head_of(&sources.read(file_path)?).to_string()),
+            None => {
+                let mut head = Vec::new();
+                File::open(file_path).ok()?.take(FILE_HEAD_LEN as u64).read_to_end(&mut head).ok()?;
+                Some(String::from_utf8_lossy(&head).into_owned())
+            }
+        }
+    }
+
+    /// Compile a query, reusing a previously compiled copy for the same language and text
+    fn compile_query(&self, language: Language, language_name: &str, query_text: &str) -> Option<Arc<Query>> {
+        let key = (language_name.to_string(), query_text.to_string());
+        if let Some(query) = self.query_cache.lock().unwrap().get(&key) {
+            return Some(query.clone());
+        }
+
+        match Query::new(language, query_text) {
+            Ok(query) => {
+                let query = Arc::new(query);
+                self.query_cache.lock().unwrap().insert(key, query.clone());
+                Some(query)
+            }
+            Err(e) => {
+                if !self.quiet {
+                    warn!("Failed to compile query: {}", e);
+                }
+                None
+            }
+        }
+    }
+
+    /// Run a query on a tree and return the matches
+    pub fn run_query_on_tree(&self, tree: &Tree, source: &str, query_text: &str, language_name: &str) -> Vec<CaptureResult> {
+        self.run_query_in_range(tree, source, query_text, language_name, None)
+    }
+
+    /// Run a query on a tree, only matching nodes that overlap `range` if one is given
+    fn run_query_in_range(&self, tree: &Tree, source: &str, query_text: &str, language_name: &str, range: Option<Range<usize>>) -> Vec<CaptureResult> {
+        let language = match get_language(language_name) {
+            Some(lang) => lang,
+            None => return vec![],
+        };
+
+        let query = match self.compile_query(language, language_name, query_text) {
+            Some(q) => q,
+            None => return vec![],
+        };
+
+        let mut cursor = QueryCursor::new();
+        if let Some(range) = range {
+            cursor.set_byte_range(range);
+        }
+        let matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
+
+        let mut results = Vec::new();
+        for m in matches {
+            for capture in m.captures {
+                let node = capture.node;
+                let start = node.start_position();
+                let text = &source[node.start_byte()..node.end_byte()];
+
+                let capture_name = match query.capture_names().get(capture.index as usize) {
+                    Some(name) => name.clone(),
+                    None => format!("capture_{}", capture.index),
+                };
+
+                results.push(CaptureResult {
+                    name: capture_name,
+                    value: text.to_string(),
+                    position: (start.row + 1, start.column + 1),
+                    node_type: node.kind().to_string(),
+                    file: None,
+                });
+            }
+        }
+
+        results
+    }
+
+    /// Scan files on disk with the given queries, reusing cached ASTs
+    pub fn scan_files(&self, files: &[String], queries: &[TreeSitterQuery]) -> Vec<MatchResult> {
+        let router = QueryRouter::new(queries);
+        if let Some(clock) = &self.clock {
+            clock.begin(ScanPhase::Parse);
+            clock.begin(ScanPhase::Query);
+        }
+        if let Some(progress) = self.progress() {
+            progress.started(files.len());
+        }
+        let per_file = match self.threads.and_then(|threads| ThreadPoolBuilder::new().num_threads(threads).build().ok()) {
+            Some(pool) => pool.install(|| self.scan_files_in_parallel(files, &router)),
+            None => self.scan_files_in_parallel(files, &router),
+        };
+
+        // Results keep the order of `files`; under fail-fast, files after the first blocking one are dropped
+        let mut results = Vec::new();
+        for (file_path, file_results) in files.iter().zip(per_file) {
+            let abort = file_results.iter().any(|r| self.fails_fast_on(r.severity));
+            results.extend(file_results);
+
+            if abort {
+                warn!(file = %file_path, "⛔ Fail-fast: stopping scan after blocking finding in {}", file_path);
+                break;
+            }
+        }
+
+        if let Some(progress) = self.progress() {
+            progress.finished();
+        }
+        if let Some(cache) = &self.disk_cache {
+            cache.record_lookups();
+            cache.prune_if_due();
+        }
+        self.end_run();
+        results
+    }
+
+    /// Scan each file on the current rayon pool, returning the results of every file in order.
+    /// Once a file produces a blocking finding, files not yet started are skipped.
+    fn scan_files_in_parallel(&self, files: &[String], router: &QueryRouter) -> Vec<Vec<MatchResult>> {
+        let stop = AtomicBool::new(false);
+        files.par_iter()
+            .map(|file_path| {
+                if stop.load(Ordering::Relaxed) {
+                    return Vec::new();
+                }
+                let file_results = self.scan_file(file_path, router);
+                if file_results.iter().any(|r| self.fails_fast_on(r.severity)) {
+                    stop.store(true, Ordering::Relaxed);
+                }
+                if let Some(progress) = self.progress() {
+                    progress.file_scanned(file_results.len());
+                }
+                file_results
+            })
+            .collect()
+    }
+
+    /// Scan one file on disk, or nothing if its language is unknown or it can't be parsed
+    fn scan_file(&self, file_path: &str, router: &QueryRouter) -> Vec<MatchResult> {
+        let Some(lang_name) = self.get_language_for_file(file_path) else {
+            return Vec::new();
+        };
+        if let Some(cache) = self.disk_cache_for(lang_name) {
+            return self.scan_file_cached(cache, file_path, lang_name, router);
+        }
+        if self.out_of_time(ScanPhase::Parse) {
+            return Vec::new();
+        }
+        let Some(parsed) = self.load_tree(file_path, lang_name) else {
+            return Vec::new();
+        };
+        if self.out_of_time(ScanPhase::Query) {
+            return Vec::new();
+        }
+        let (tree, source) = &*parsed;
+        self.match_file(file_path, lang_name, tree, source, router)
+    }
+
+    /// Scan one file through the disk cache: the findings stored for its contents and queries,
+    /// or else those of parsing and querying it, which are then stored
+    fn scan_file_cached(&self, cache: &DiskCache, file_path: &str, lang_name: &'static str, router: &QueryRouter) -> Vec<MatchResult> {
+        let Some(source) = self.read_source(file_path) else {
+            return Vec::new();
+        };
+        let key = self.disk_cache_key(file_path, lang_name, &source, router);
+        if let Some(mut results) = cache.get(&key) {
+            trace!(file = file_path, "Using cached findings for {}", file_path);
+            for result in &mut results {
+                result.organization_id.clone_from(&self.organization_id);
+                result.code_base_version.clone_from(&self.code_base_version);
+            }
+            return self.annotate_results(results);
+        }
+
+        if self.out_of_time(ScanPhase::Parse) {
+            return Vec::new();
+        }
+        let Some(parsed) = self.load_tree(file_path, lang_name) else {
+            return Vec::new();
+        };
+        if self.out_of_time(ScanPhase::Query) {
+            return Vec::new();
+        }
+        let (tree, source) = &*parsed;
+        let results = self.find_matches(file_path, lang_name, tree, source, router);
+        cache.put(&key, &results);
+        self.annotate_results(results)
+    }
+
+    /// Everything a file's findings, before coverage and transforms, depend on
+    fn disk_cache_key(&self, file_path: &str, lang_name: &str, source: &str, router: &QueryRouter) -> CacheKey {
+        let mut key = CacheKey::builder()
+            .part(env!("CARGO_PKG_VERSION"))
+            .part(GRAMMAR_VERSIONS.unwrap_or_default())
+            .part(lang_name)
+            .part(normalize_path(file_path))
+            .part(source)
+            .part(format!("{:?}", (self.fail_fast, self.sample, self.detect_pii, self.injections)));
+        // Embedded code is matched by the queries of its own language, which can be any
+        let queries = if self.injections { router.all_queries() } else { router.queries_for(file_path, Some(lang_name)) };
+        for query in queries {
+            key = key.part(serde_json::to_vec(query).unwrap_or_default());
+        }
+        key.finish()
+    }
+
+    /// The disk cache, if findings of files in `lang_name` can be taken from it
+    fn disk_cache_for(&self, lang_name: &str) -> Option<&DiskCache> {
+        // Complexity summaries are collected from the trees themselves, and grammars loaded
+        // at runtime, or of a build that doesn't know its grammar versions, can change without
+        // the key changing
+        if self.complexity_threshold.is_some() || registered_language(lang_name).is_some() || GRAMMAR_VERSIONS.is_none() {
+            return None;
+        }
+        self.disk_cache.as_ref()
+    }
+
+    /// Count the code, comment and blank lines of files on disk, by language
+    pub fn collect_stats(&self, files: &[String]) -> CodeStats {
+        let mut code_stats = CodeStats::default();
+
+        for file_path in files {
+            let Some(lang_name) = self.get_language_for_file(file_path) else {
+                continue;
+            };
+            if let Some(parsed) = self.load_tree(file_path, lang_name) {
+                let (tree, source) = &*parsed;
+                code_stats.record(lang_name, stats::count_lines(tree, source));
+            }
+        }
+
+        self.end_run();
+        code_stats
+    }
+
+    /// Parse files one at a time and run the queries targeting each, timing parsing per language
+    /// and each query separately, for capacity planning. Nothing is cached.
+    pub fn benchmark(&self, files: &[String], queries: &[TreeSitterQuery]) -> BenchReport {
+        let started = Instant::now();
+        let router = QueryRouter::new(queries);
+        let mut report = BenchReport::default();
+
+        for file_path in files {
+            let Some(lang_name) = self.get_language_for_file(file_path) else {
+                continue;
+            };
+            let Some(src) = self.read_source(file_path) else {
+                continue;
+            };
+
+            let bytes = src.len() as u64;
+            let parse_started = Instant::now();
+            let Some((tree, source)) = self.parse_source(src, lang_name) else {
+                continue;
+            };
+            report.record_parse(lang_name, bytes, parse_started.elapsed());
+
+            for query in router.queries_for(file_path, Some(lang_name)) {
+                let query_started = Instant::now();
+                let matches = self.run_query_on_tree(&tree, &source, &query.query, lang_name).len();
+                report.record_query(&query.question_id, matches, query_started.elapsed());
+            }
+        }
+
+        report.wall_ms = started.elapsed().as_secs_f64() * 1000.0;
+        report.peak_memory_bytes = bench::peak_memory_bytes();
+        report
+    }
+
+    /// Find code duplicated across files on disk, reporting clones of at least `min_tokens` tokens
+    pub fn find_duplicates(&self, files: &[String], min_tokens: usize) -> Vec<CloneGroup> {
+        let mut detector = DuplicateDetector::new(min_tokens);
+
+        for file_path in files {
+            let Some(lang_name) = self.get_language_for_file(file_path) else {
+                continue;
+            };
+            if let Some(parsed) = self.load_tree(file_path, lang_name) {
+                detector.add_file(&normalize_path(file_path), lang_name, &parsed.0);
+            }
+        }
+
+        self.end_run();
+        detector.find_clones()
+    }
+
+    /// Report exported functions, classes and types defined in `files` that no other file refers
+    /// to. Symbols whose names match an `allow` pattern, such as entry points, are skipped.
+    pub fn find_unused_symbols(&self, files: &[String], allow: &[String]) -> Vec<MatchResult> {
+        let mut index = SymbolIndex::new();
+
+        for file_path in files {
+            let Some(lang_name) = self.get_language_for_file(file_path) else {
+                continue;
+            };
+            if let Some(parsed) = self.load_tree(file_path, lang_name) {
+                let (tree, source) = &*parsed;
+                index.add_file(&normalize_path(file_path), lang_name, tree, source);
+            }
+        }
+        self.end_run();
+
+        index.unused(allow).into_iter()
+            .map(|symbol| MatchResult {
+                text: format!("{} {} is exported but never referenced in another file", symbol.kind, symbol.name),
+                file: symbol.file,
+                line: symbol.line,
+                column: symbol.column,
+                question_id: dead_code::UNUSED_EXPORT_QUESTION_ID.to_string(),
+                organization_id: self.organization_id.clone(),
+                code_base_version: self.code_base_version.clone(),
+                severity: Severity::Low,
+                sampled: false,
+                category: Some(dead_code::DEAD_CODE_CATEGORY.to_string()),
+                covered: None,
+                tags: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Scan in-memory sources, given as `(path, content)` pairs, with the given queries.
+    /// Sources are never read from disk, not even to tell their language, and are not added
+    /// to the AST cache.
+    pub fn scan_sources(&self, sources: Vec<(String, String)>, queries: &[TreeSitterQuery]) -> Vec<MatchResult> {
+        let mut results = Vec::new();
+        let router = QueryRouter::new(queries);
+
+        for (file_path, content) in sources {
+            let lang_name = match self.get_language_for_source(&file_path, &content) {
+                Some(lang) => lang,
+                None => continue,
+            };
+
+            let (tree, source) = match self.parse_source(content, lang_name) {
+                Some(parsed) => parsed,
+                None => {
+                    if !self.quiet {
+                        warn!(file = %file_path, "Failed to parse {}", file_path);
+                    }
+                    continue;
+                }
+            };
+
+            results.extend(self.match_file(&file_path, lang_name, &tree, &source, &router));
+        }
+
+        results
+    }
+
+    /// Scan one in-memory source as if it were a file at `file_path` written in `language`,
+    /// whatever the path's extension. Fails if the language is unknown or the source can't be parsed.
+    pub fn scan_source_as(&self, file_path: &str, content: String, language: &str, queries: &[TreeSitterQuery]) -> Result<Vec<MatchResult>, Box<dyn Error>> {
+        let lang_name = canonical_language(language).ok_or_else(|| format!("Unsupported language: {}", language))?;
+        let (tree, source) = self.parse_source(content, lang_name)
+            .ok_or_else(|| format!("Failed to parse {} as {}", file_path, lang_name))?;
+
+        let router = QueryRouter::new(queries);
+        Ok(self.match_file(file_path, lang_name, &tree, &source, &router))
+    }
+
+    /// Run every query targeting a file's language or extension against its parsed tree
+    pub(crate) fn match_file(
+        &self,
+        file_path: &str,
+        lang_name: &str,
+        tree: &Tree,
+        source: &str,
+        router: &QueryRouter,
+    ) -> Vec<MatchResult> {
+        let results = self.find_matches(file_path, lang_name, tree, source, router);
+        self.annotate_results(results)
+    }
+
+    /// Findings of the queries, PII detection and complexity checks in a parsed file
+    fn find_matches(
+        &self,
+        file_path: &str,
+        lang_name: &str,
+        tree: &Tree,
+        source: &str,
+        router: &QueryRouter,
+    ) -> Vec<MatchResult> {
+        let mut results = Vec::new();
+        let _file = debug_span!("file", path = file_path, language = lang_name).entered();
+
+        // Get relevant queries for this file
+        let relevant_queries = router.queries_for(file_path, Some(lang_name));
+        if !self.quiet {
+            debug!(queries = relevant_queries.len(), "🔍 Found {} relevant queries for {}", relevant_queries.len(), file_path);
+        }
+
+        let stopped = self.match_queries(file_path, lang_name, tree, source, &relevant_queries, &mut results);
+
+        if self.injections && !stopped {
+            for injected in self.find_injections(lang_name, tree, source) {
+                let queries = router.queries_for_language(injected.language);
+                let first = results.len();
+                let stopped = self.match_queries(file_path, injected.language, &injected.tree, source, &queries, &mut results);
+                for result in &mut results[first..] {
+                    result.tags.push(format!("{}{}", injection::INJECTED_TAG_PREFIX, injected.language));
+                }
+                if stopped {
+                    break;
+                }
+            }
+        }
+
+        if self.detect_pii {
+            results.extend(self.find_pii(file_path, lang_name, tree, source));
+        }
+
+        if let Some(threshold) = self.complexity_threshold {
+            results.extend(self.find_complex_functions(file_path, lang_name, tree, source, threshold));
+        }
+
+        results
+    }
+
+    /// Mark whether tests cover each finding and run the transform script over them
+    fn annotate_results(&self, mut results: Vec<MatchResult>) -> Vec<MatchResult> {
+        if let Some(coverage) = &self.coverage {
+            coverage.annotate(&mut results);
+        }
+
+        #[cfg(feature = "scripting")]
+        if let Some(transform) = &self.transform {
+            results = transform.apply_all(results);
+        }
+
+        results
+    }
+
+    /// Run queries on a tree parsed from `source`, adding their matches to `results`. Returns
+    /// whether a fail-fast finding stopped the run.
+    fn match_queries(
+        &self,
+        file_path: &str,
+        lang_name: &str,
+        tree: &Tree,
+        source: &str,
+        queries: &[&TreeSitterQuery],
+        results: &mut Vec<MatchResult>,
+    ) -> bool {
+        for query in queries {
+            let _query = trace_span!("query", question_id = %query.question_id).entered();
+            let mut captures = self.run_query_on_tree(tree, source, &query.query, lang_name);
+            trace!(matches = captures.len(), "Query matched {} captures", captures.len());
+
+            let sampled = match query.sample.or(self.sample) {
+                Some(limit) if captures.len() > limit => {
+                    captures = sample_captures(captures, limit, sample_seed(file_path, &query.question_id));
+                    true
+                }
+                _ => false,
+            };
+
+            results.extend(captures.iter().map(|capture| self.match_result(file_path, query, capture, sampled)));
+
+            if !captures.is_empty() && self.fails_fast_on(query.severity) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// The finding of one capture of a query
+    fn match_result(&self, file_path: &str, query: &TreeSitterQuery, capture: &CaptureResult, sampled: bool) -> MatchResult {
+        MatchResult {
+            file: normalize_path(file_path),
+            line: capture.position.0,
+            column: capture.position.1,
+            text: capture.value.clone(),
+            question_id: query.question_id.clone(),
+            organization_id: self.organization_id.clone(),
+            code_base_version: self.code_base_version.clone(),
+            severity: query.severity,
+            sampled,
+            category: None,
+            covered: None,
+            tags: Vec::new(),
+        }
+    }
+
+    /// Parse the code embedded in a file, such as scripts in HTML or SQL in string literals
+    fn find_injections(&self, lang_name: &str, tree: &Tree, source: &str) -> Vec<InjectedTree> {
+        let Some(host) = get_language(lang_name) else {
+            return Vec::new();
+        };
+
+        let mut injected = Vec::new();
+        for rule in injection::rules(lang_name) {
+            let Some(query) = self.compile_query(host, lang_name, &rule.query) else {
+                continue;
+            };
+            let Some(content) = query.capture_index_for_name("content") else {
+                continue;
+            };
+
+            let mut cursor = QueryCursor::new();
+            for m in cursor.matches(&query, tree.root_node(), source.as_bytes()) {
+                let regions = m.captures.iter()
+                    .filter(|capture| capture.index == content)
+                    .filter_map(|capture| injection::region(capture.node, source, rule.trim));
+                for range in regions {
+                    if (rule.accept)(&source[range.start_byte..range.end_byte]) {
+                        injected.extend(injection::parse_region(source, rule.language, range));
+                    }
+                }
+            }
+        }
+        injected
+    }
+
+    /// Measure the complexity of each function in a parsed file, adding it to the summary and
+    /// reporting the functions above `threshold`
+    fn find_complex_functions(&self, file_path: &str, lang_name: &str, tree: &Tree, source: &str, threshold: usize) -> Vec<MatchResult> {
+        let functions = complexity::analyze(tree, source, lang_name);
+
+        let mut summary = self.complexity_summary.lock().unwrap();
+        for function in &functions {
+            summary.record(function, threshold);
+        }
+        drop(summary);
+
+        functions.into_iter()
+            .filter(|f| f.complexity > threshold)
+            .map(|f| MatchResult {
+                file: normalize_path(file_path),
+                line: f.line,
+                column: f.column,
+                text: format!("{} has cyclomatic complexity {} (threshold {})", f.name, f.complexity, threshold),
+                question_id: complexity::COMPLEXITY_QUESTION_ID.to_string(),
+                organization_id: self.organization_id.clone(),
+                code_base_version: self.code_base_version.clone(),
+                severity: complexity::severity(f.complexity, threshold),
+                sampled: false,
+                category: Some(complexity::COMPLEXITY_CATEGORY.to_string()),
+                covered: None,
+                tags: Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Find PII in the string literals of a parsed file. Findings only carry the redacted text.
+    fn find_pii(&self, file_path: &str, lang_name: &str, tree: &Tree, source: &str) -> Vec<MatchResult> {
+        let mut results = Vec::new();
+
+        for kind in pii::string_literal_kinds(lang_name) {
+            let query = format!("({}) @string", kind);
+            for capture in self.run_query_on_tree(tree, source, &query, lang_name) {
+                for found in pii::detect(&capture.value) {
+                    let (line, column) = position_after(capture.position, &capture.value[..found.offset]);
+                    results.push(MatchResult {
+                        file: normalize_path(file_path),
+                        line,
+                        column,
+                        text: found.redacted,
+                        question_id: found.kind.question_id(),
+                        organization_id: self.organization_id.clone(),
+                        code_base_version: self.code_base_version.clone(),
+                        severity: found.kind.severity(),
+                        sampled: false,
+                        category: Some(pii::PII_CATEGORY.to_string()),
+                        covered: None,
+                        tags: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        results
+    }
+}
+
+/// The first `FILE_HEAD_LEN` bytes of `source`, cut back to a character boundary
+fn head_of(source: &str) -> &str {
+    let end = (0..=FILE_HEAD_LEN.min(source.len())).rev().find(|i| source.is_char_boundary(*i)).unwrap_or(0);
+    &source[..end]
+}
+
+/// Line and column reached by advancing from `start` over `text`
+fn position_after(start: (usize, usize), text: &str) -> (usize, usize) {
+    match text.rfind('\n') {
+        Some(last) => (start.0 + text.matches('\n').count(), text.len() - last),
+        None => (start.0, start.1 + text.len()),
+    }
+}
+
+/// Keep `limit` representative captures: the first half of the limit in order, then a
+/// random selection from the rest. The selection is seeded so repeated scans keep the same sample.
+fn sample_captures(captures: Vec<CaptureResult>, limit: usize, seed: u64) -> Vec<CaptureResult> {
+    let head = limit.div_ceil(2);
+    let mut tail: Vec<usize> = (head..captures.len()).collect();
+
+    // Partial Fisher-Yates shuffle, choosing the random part of the sample
+    let mut state = seed;
+    let random = limit - head;
+    for i in 0..random {
+        let j = i + (next_random(&mut state) % (tail.len() - i) as u64) as usize;
+        tail.swap(i, j);
+    }
+    let mut keep: Vec<usize> = (0..head).chain(tail.into_iter().take(random)).collect();
+    keep.sort_unstable();
+
+    let mut captures: Vec<Option<CaptureResult>> = captures.into_iter().map(Some).collect();
+    keep.into_iter().filter_map(|i| captures[i].take()).collect()
+}
+
+/// Hash of a file's contents, telling whether a cached tree was parsed from them
+fn content_hash(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Seed for sampling one query's matches in one file. SHA-256 rather than `DefaultHasher`,
+/// whose output may change between Rust releases, so a rebuilt scanner keeps the same sample.
+fn sample_seed(file_path: &str, question_id: &str) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(normalize_path(file_path).as_bytes());
+    hasher.update([0]);
+    hasher.update(question_id.as_bytes());
+    let digest = hasher.finalize();
+    u64::from_be_bytes(digest[..8].try_into().expect("SHA-256 digests are 32 bytes"))
+}
+
+/// splitmix64: small, fast and good enough for picking samples
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Parse source code with the named language
+pub(crate) fn parse_with_language(src: String, language_name: &str) -> Option<(Tree, String)> {
+    let language = get_language(language_name)?;
+
+    let mut parser = Parser::new();
+    parser.set_language(language).ok()?;
+    if canonical_language(language_name) == Some("svelte") {
+        // An empty range list means the whole file, so a component without scripts gets an empty one
+        let ranges = svelte::script_ranges(&src);
+        let empty = [tree_sitter::Range { start_byte: 0, end_byte: 0, start_point: Point::new(0, 0), end_point: Point::new(0, 0) }];
+        parser.set_included_ranges(if ranges.is_empty() { &empty } else { &ranges }).ok()?;
+    }
+    let tree = parser.parse(&src, None)?;
+
+    Some((tree, src))
+}
+
+/// The file type queries use to target a file: its lowercased extension with a leading dot
+pub(crate) fn file_type_for(file_path: &str) -> String {
+    PathBuf::from(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| format!(".{}", ext.to_lowercase()))
+        .unwrap_or_default()
+}
+
+/// Routes queries to the files they target, with each query's `file_type` parsed once
+pub(crate) struct QueryRouter<'a> {
+    routes: Vec<(&'a TreeSitterQuery, Vec<FileTarget>)>,
+}
+
+impl<'a> QueryRouter<'a> {
+    pub(crate) fn new(queries: &'a [TreeSitterQuery]) -> Self {
+        Self {
+            routes: queries.iter().map(|q| (q, q.file_targets())).collect(),
+        }
+    }
+
+    /// Indices of the queries that run against a file with the given resolved language
+    pub(crate) fn route(&self, file_path: &str, language: Option<&str>) -> Vec<usize> {
+        let extension = file_type_for(file_path);
+        self.routes.iter()
+            .enumerate()
+            .filter(|(_, (_, targets))| targets.iter().any(|t| t.matches(&extension, language)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// The queries targeting a language, for code of that language embedded in another file
+    pub(crate) fn queries_for_language(&self, language: &'static str) -> Vec<&'a TreeSitterQuery> {
+        self.routes.iter()
+            .filter(|(_, targets)| targets.contains(&FileTarget::Language(language)))
+            .map(|(query, _)| *query)
+            .collect()
+    }
+
+    /// Every query, in the order they were given
+    pub(crate) fn all_queries(&self) -> Vec<&'a TreeSitterQuery> {
+        self.routes.iter().map(|(query, _)| *query).collect()
+    }
+
+    /// The queries that run against a file with the given resolved language
+    pub(crate) fn queries_for(&self, file_path: &str, language: Option<&str>) -> Vec<&'a TreeSitterQuery> {
+        self.route(file_path, language).into_iter().map(|i| self.routes[i].0).collect()
+    }
+
+    #[cfg(feature = "client")]
+    pub(crate) fn query(&self, index: usize) -> &'a TreeSitterQuery {
+        self.routes[index].0
+    }
+}
@@ -0,0 +1,16 @@
+//! Process exit codes used by the magma-scanner binary
+
+/// The scan finished
+pub const SUCCESS: i32 = 0;
+
+/// Findings reached the `--fail-on` threshold, fail-fast stopped the scan, or a push was rejected
+pub const FINDINGS: i32 = 1;
+
+/// The scan itself failed, e.g. on unreadable files, invalid queries or bad options
+pub const SCAN_ERROR: i32 = 2;
+
+/// The Magma API could not be reached or rejected a request
+pub const API_FAILURE: i32 = 3;
+
+/// The scan was interrupted or cut short before every query ran
+pub const INCOMPLETE: i32 = 4;
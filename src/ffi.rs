@@ -0,0 +1,185 @@
+use crate::discovery::find_files;
+use crate::engine::Engine;
+use crate::rules::parse_queries;
+use crate::types::{MatchResult, TreeSitterQuery};
+use std::{
+    cell::RefCell,
+    error::Error,
+    ffi::{c_char, CStr, CString},
+    fs::File,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+thread_local! {
+    /// Message of the last error on this thread, returned by `magma_last_error`
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// A single finding. All strings are NUL-terminated UTF-8 owned by the enclosing `MagmaResults`.
+#[repr(C)]
+pub struct MagmaFinding {
+    pub file: *const c_char,
+    pub question_id: *const c_char,
+    pub text: *const c_char,
+    pub severity: *const c_char,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The findings of one scan, freed with `magma_results_free`.
+/// Opaque to C callers; see `include/magma_scanner.h`.
+pub struct MagmaResults {
+    findings: Vec<MagmaFinding>,
+    /// Backing storage for the string pointers in `findings`
+    _strings: Vec<CString>,
+}
+
+impl MagmaResults {
+    fn new(results: Vec<MatchResult>) -> Self {
+        let mut strings = Vec::new();
+        let mut findings = Vec::with_capacity(results.len());
+
+        for result in results {
+            let mut keep = |value: String| {
+                let value = to_c_string(value);
+                let pointer = value.as_ptr();
+                strings.push(value);
+                pointer
+            };
+
+            findings.push(MagmaFinding {
+                file: keep(result.file),
+                question_id: keep(result.question_id),
+                text: keep(result.text),
+                severity: keep(result.severity.to_string()),
+                line: result.line,
+                column: result.column,
+            });
+        }
+
+        Self { findings, _strings: strings }
+    }
+}
+
+/// Convert to a C string, dropping interior NULs that C callers couldn't see past
+fn to_c_string(value: String) -> CString {
+    CString::new(value.replace('\0', "")).unwrap_or_default()
+}
+
+fn set_last_error(message: String) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(to_c_string(message)));
+}
+
+/// Read a caller-provided string argument
+///
+/// # Safety
+/// `value` must be null or point to a NUL-terminated string valid for the duration of the call.
+unsafe fn read_arg(value: *const c_char, name: &str) -> Result<String, Box<dyn Error>> {
+    if value.is_null() {
+        return Err(format!("{} must not be null", name).into());
+    }
+    let value = unsafe { CStr::from_ptr(value) };
+    Ok(value.to_str().map_err(|_| format!("{} is not valid UTF-8", name))?.to_string())
+}
+
+/// Run a scan, turning errors into a null result and a message for `magma_last_error`.
+/// Panics are caught too, since unwinding into C is undefined behavior.
+fn run_scan(scan: impl FnOnce() -> Result<Vec<MatchResult>, Box<dyn Error>>) -> *mut MagmaResults {
+    match panic::catch_unwind(AssertUnwindSafe(scan)) {
+        Ok(Ok(results)) => Box::into_raw(Box::new(MagmaResults::new(results))),
+        Ok(Err(e)) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        }
+        Err(payload) => {
+            let message = payload.downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            set_last_error(format!("Scan panicked: {}", message));
+            ptr::null_mut()
+        }
+    }
+}
+
+fn quiet_engine() -> Engine {
+    Engine::new(String::new(), String::new()).with_quiet(true)
+}
+
+fn scan_paths(files: Vec<String>, queries: Vec<TreeSitterQuery>) -> Vec<MatchResult> {
+    quiet_engine().scan_files(&files, &queries)
+}
+
+/// Scan a single file with queries given as JSON (an array, or an object with a
+/// `TreeSitterQueries` array). Returns null on error, including a file that is missing or
+/// can't be read; see `magma_last_error`.
+///
+/// # Safety
+/// `path` and `queries_json` must be null or NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn magma_scan_file(path: *const c_char, queries_json: *const c_char) -> *mut MagmaResults {
+    run_scan(|| {
+        let path = unsafe { read_arg(path, "path") }?;
+        // The engine skips files it can't read, which would look like a clean scan
+        let file = File::open(&path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        if file.metadata()?.is_dir() {
+            return Err(format!("{} is a directory; use magma_scan_dir", path).into());
+        }
+        let queries = parse_queries(&unsafe { read_arg(queries_json, "queries_json") }?)?;
+        Ok(scan_paths(vec![path], queries))
+    })
+}
+
+/// Scan every supported file under a directory. Returns null on error; see `magma_last_error`.
+///
+/// # Safety
+/// `dir` and `queries_json` must be null or NUL-terminated strings.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn magma_scan_dir(dir: *const c_char, queries_json: *const c_char) -> *mut MagmaResults {
+    run_scan(|| {
+        let dir = unsafe { read_arg(dir, "dir") }?;
+        let queries = parse_queries(&unsafe { read_arg(queries_json, "queries_json") }?)?;
+        Ok(scan_paths(find_files(&dir)?, queries))
+    })
+}
+
+/// Number of findings in a result set; 0 for null
+///
+/// # Safety
+/// `results` must be null or a pointer returned by a scan function and not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn magma_results_count(results: *const MagmaResults) -> usize {
+    match unsafe { results.as_ref() } {
+        Some(results) => results.findings.len(),
+        None => 0,
+    }
+}
+
+/// The finding at `index`, or null if out of range. The pointer is valid until the results are freed.
+///
+/// # Safety
+/// `results` must be null or a pointer returned by a scan function and not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn magma_results_get(results: *const MagmaResults, index: usize) -> *const MagmaFinding {
+    unsafe { results.as_ref() }
+        .and_then(|results| results.findings.get(index))
+        .map_or(ptr::null(), |finding| finding as *const MagmaFinding)
+}
+
+/// Free a result set. Passing null is a no-op.
+///
+/// # Safety
+/// `results` must be null or a pointer returned by a scan function, and must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn magma_results_free(results: *mut MagmaResults) {
+    if !results.is_null() {
+        drop(unsafe { Box::from_raw(results) });
+    }
+}
+
+/// Message of the last error on the calling thread, or null if there was none.
+/// The string is valid until the next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn magma_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
@@ -0,0 +1,83 @@
+use crate::types::{MatchResult, Severity};
+use serde::Deserialize;
+use std::{error::Error, fmt, str::FromStr};
+
+/// Threshold that fails a scan, parsed from `--fail-on`: a severity (`high`, any finding at or
+/// above it), a count (`10`, that many findings of any severity) or both (`high:3`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct FailOn {
+    /// Least severe finding that counts toward the threshold
+    pub severity: Severity,
+    /// Number of counted findings that fails the scan
+    pub count: usize,
+}
+
+impl FromStr for FailOn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (severity, count) = match s.split_once(':') {
+            Some((severity, count)) => (severity.parse()?, parse_count(count)?),
+            None => match s.parse::<usize>() {
+                Ok(_) => (Severity::Info, parse_count(s)?),
+                Err(_) => (s.parse()?, 1),
+            },
+        };
+        Ok(FailOn { severity, count })
+    }
+}
+
+impl TryFrom<String> for FailOn {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+fn parse_count(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(0) | Err(_) => Err(format!("invalid finding count: {} (expected a number of at least 1)", s)),
+        Ok(count) => Ok(count),
+    }
+}
+
+impl FailOn {
+    /// Number of findings that count toward the threshold
+    pub fn counted(&self, findings: &[MatchResult]) -> usize {
+        findings.iter().filter(|finding| finding.severity >= self.severity).count()
+    }
+
+    /// Fail if the findings reach the threshold
+    pub fn check(&self, findings: &[MatchResult]) -> Result<(), FindingsAboveThreshold> {
+        let counted = self.counted(findings);
+        if counted < self.count {
+            return Ok(());
+        }
+        Err(FindingsAboveThreshold::new(format!(
+            "{} findings at or above {} severity, failing on {}",
+            counted, self.severity, self.count
+        )))
+    }
+}
+
+/// Returned by a scan whose findings reach the threshold it fails on, via `--fail-on` or fail-fast
+#[derive(Debug)]
+pub struct FindingsAboveThreshold {
+    message: String,
+}
+
+impl FindingsAboveThreshold {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { message: message.into() }
+    }
+}
+
+impl fmt::Display for FindingsAboveThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl Error for FindingsAboveThreshold {}
@@ -0,0 +1,299 @@
+use crate::engine::SourceFiles;
+use std::{collections::HashMap, error::Error, io::{self, BufRead, BufReader, Read, Write}, path::{Path, PathBuf}, process::{Child, ChildStdin, ChildStdout, Command, Stdio}, sync::Mutex};
+use tracing::debug;
+
+/// Get the current git commit hash of the repository at `dir`
+pub fn get_git_commit_hash(dir: &Path) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["rev-parse", "HEAD"])
+        .output()?;
+
+    if output.status.success() {
+        let hash = String::from_utf8(output.stdout)?;
+        Ok(hash.trim().to_string())
+    } else {
+        Err("Failed to get git commit hash".into())
+    }
+}
+
+/// Get the current git branch name of the repository at `dir`
+pub fn get_git_branch_name(dir: &Path) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .output()?;
+
+    if output.status.success() {
+        let branch = String::from_utf8(output.stdout)?;
+        Ok(branch.trim().to_string())
+    } else {
+        Err("Failed to get git branch name".into())
+    }
+}
+
+/// Get the git repository URL of the repository at `dir`
+pub fn get_git_repo_url(dir: &Path) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["config", "--get", "remote.origin.url"])
+        .output()?;
+
+    if output.status.success() {
+        let url = String::from_utf8(output.stdout)?;
+        Ok(url.trim().to_string())
+    } else {
+        Err("Failed to get git repository URL".into())
+    }
+}
+
+/// Files of the working tree at `dir` that are modified or staged, as absolute paths. Deleted
+/// and untracked files are left out; a renamed file is listed under its new name.
+pub fn changed_files(dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let toplevel = Command::new("git")
+        .current_dir(dir)
+        .args(["rev-parse", "--show-toplevel"])
+        .output()?;
+    if !toplevel.status.success() {
+        return Err(format!("{} is not inside a git working tree", dir.display()).into());
+    }
+    let root = PathBuf::from(String::from_utf8(toplevel.stdout)?.trim());
+
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["status", "--porcelain=v1", "-z", "--untracked-files=no"])
+        .output()?;
+    if !output.status.success() {
+        return Err(format!("Failed to get git status: {}", String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+
+    // Entries are "XY path", where X is the staged and Y the unstaged change; renames and
+    // copies are followed by an extra entry holding the original path
+    let status = String::from_utf8(output.stdout)?;
+    let mut entries = status.split('\0').filter(|entry| !entry.is_empty());
+    let mut files = Vec::new();
+    while let Some(entry) = entries.next() {
+        let (code, path) = entry.split_at(entry.len().min(3));
+        if code.starts_with(['R', 'C']) {
+            entries.next();
+        }
+        if !code.contains('D') {
+            files.push(root.join(path));
+        }
+    }
+
+    Ok(files)
+}
+
+/// Clone `url` into `dest`, or fetch and check out the latest `branch` if it was cloned before
+pub fn clone_or_update(url: &str, branch: Option<&str>, dest: &Path) -> Result<(), Box<dyn Error>> {
+    let status = if dest.join(".git").exists() {
+        let refspec = branch.unwrap_or("HEAD");
+        let fetched = Command::new("git")
+            .current_dir(dest)
+            .args(["fetch", "--depth", "1", "origin", refspec])
+            .status()?;
+
+        if !fetched.success() {
+            return Err(format!("Failed to fetch {} from {}", refspec, url).into());
+        }
+
+        Command::new("git")
+            .current_dir(dest)
+            .args(["checkout", "--force", "FETCH_HEAD"])
+            .status()?
+    } else {
+        let mut cmd = Command::new("git");
+        cmd.args(["clone", "--depth", "1"]);
+        if let Some(branch) = branch {
+            cmd.args(["--branch", branch]);
+        }
+        cmd.arg(url).arg(dest).status()?
+    };
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("Failed to clone or update {}", url).into())
+    }
+}
+
+/// Resolve a ref of the repository at `dir` (which may be bare) to a commit hash
+pub fn resolve_commit(dir: &Path, refname: &str) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", refname)])
+        .output()?;
+
+    if output.status.success() {
+        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    } else {
+        Err(format!("Failed to resolve {} in {}", refname, dir.display()).into())
+    }
+}
+
+/// Read one file of a commit from the repository at `dir`, or `None` if the commit doesn't have it
+pub fn read_commit_file(dir: &Path, commit: &str, path: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["cat-file", "blob", &format!("{}:{}", commit, path)])
+        .output()?;
+
+    if output.status.success() {
+        Ok(Some(String::from_utf8(output.stdout)?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Blob of a commit: its object ID and size in bytes
+#[derive(Debug)]
+struct BlobEntry {
+    oid: String,
+    size: u64,
+}
+
+/// The text files of a commit, listed up front and read from the object database of a
+/// repository, which may be bare, only when they are scanned. Blobs are read through one
+/// long-running `git cat-file --batch`, so memory doesn't grow with the size of the repository.
+#[derive(Debug, Default)]
+pub struct CommitBlobs {
+    dir: PathBuf,
+    blobs: HashMap<String, BlobEntry>,
+    reader: Mutex<Option<BlobReader>>,
+}
+
+impl CommitBlobs {
+    /// Paths of the blobs, relative to the repository root, in no particular order
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.blobs.keys().map(String::as_str)
+    }
+
+    /// Size in bytes of the blob at `path`
+    pub fn size(&self, path: &str) -> Option<u64> {
+        self.blobs.get(path).map(|blob| blob.size)
+    }
+
+    /// Contents of the blob at `path`, or `None` if the commit doesn't have it or it isn't
+    /// UTF-8 text, like binary files
+    pub fn read(&self, path: &str) -> Option<String> {
+        let blob = self.blobs.get(path)?;
+        let mut reader = self.reader.lock().unwrap();
+        if reader.is_none() {
+            match BlobReader::spawn(&self.dir) {
+                Ok(spawned) => *reader = Some(spawned),
+                Err(e) => {
+                    debug!("Failed to start git cat-file in {}: {}", self.dir.display(), e);
+                    return None;
+                }
+            }
+        }
+        match reader.as_mut()?.read(&blob.oid) {
+            Ok(content) => String::from_utf8(content).ok(),
+            Err(e) => {
+                // The process's output can't be trusted after a failed read, so start over
+                debug!("Failed to read {} from {}: {}", path, self.dir.display(), e);
+                *reader = None;
+                None
+            }
+        }
+    }
+}
+
+impl SourceFiles for CommitBlobs {
+    fn read(&self, path: &str) -> Option<String> {
+        CommitBlobs::read(self, path)
+    }
+
+    fn size(&self, path: &str) -> Option<u64> {
+        CommitBlobs::size(self, path)
+    }
+}
+
+/// A `git cat-file --batch` process answering one object ID at a time
+#[derive(Debug)]
+struct BlobReader {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl BlobReader {
+    fn spawn(dir: &Path) -> io::Result<Self> {
+        let mut child = Command::new("git")
+            .current_dir(dir)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| io::Error::other("Failed to open git cat-file input"))?;
+        let stdout = child.stdout.take().ok_or_else(|| io::Error::other("Failed to open git cat-file output"))?;
+        Ok(Self { child, stdin, stdout: BufReader::new(stdout) })
+    }
+
+    /// Contents of an object, which git answers with "<oid> <type> <size>\n<content>\n"
+    fn read(&mut self, oid: &str) -> io::Result<Vec<u8>> {
+        writeln!(self.stdin, "{}", oid)?;
+        self.stdin.flush()?;
+
+        let mut header = String::new();
+        self.stdout.read_line(&mut header)?;
+        let size: usize = header.trim_end().rsplit(' ').next().and_then(|size| size.parse().ok())
+            .ok_or_else(|| io::Error::other(format!("Unexpected git cat-file header: {}", header.trim_end())))?;
+        let mut content = vec![0; size + 1];
+        self.stdout.read_exact(&mut content)?;
+        content.pop();
+        Ok(content)
+    }
+}
+
+impl Drop for BlobReader {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// List the files of a commit in the object database of the repository at `dir`, so bare
+/// repositories can be scanned without a checkout. Only paths accepted by `include` are
+/// listed; symlinks, submodules and paths that aren't UTF-8 are skipped.
+///
+/// This shells out to `git` rather than linking libgit2: the git CLI is already needed to
+/// clone batch repositories, and it reads every object format and repository layout the
+/// server's own git does.
+pub fn commit_blobs(dir: &Path, commit: &str, include: impl Fn(&str) -> bool) -> Result<CommitBlobs, Box<dyn Error>> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(["ls-tree", "-r", "-z", "-l", "--full-tree", commit])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!("Failed to list files of {}: {}", commit, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+
+    // Entries are "<mode> <type> <oid> <size>\t<path>", with the size padded by spaces
+    let mut blobs = HashMap::new();
+    for entry in output.stdout.split(|&b| b == 0) {
+        let Some(tab) = entry.iter().position(|&b| b == b'\t') else {
+            continue;
+        };
+        let (Ok(meta), Ok(path)) = (std::str::from_utf8(&entry[..tab]), std::str::from_utf8(&entry[tab + 1..])) else {
+            debug!("Skipping {} in {}: its path isn't UTF-8", String::from_utf8_lossy(&entry[tab + 1..]), dir.display());
+            continue;
+        };
+        let fields: Vec<&str> = meta.split_whitespace().collect();
+        let [mode, kind, oid, size] = fields[..] else {
+            continue;
+        };
+        if kind == "blob"
+            && mode != "120000"
+            && include(path)
+            && let Ok(size) = size.parse()
+        {
+            blobs.insert(path.to_string(), BlobEntry { oid: oid.to_string(), size });
+        }
+    }
+
+    Ok(CommitBlobs { dir: dir.to_path_buf(), blobs, reader: Mutex::default() })
+}
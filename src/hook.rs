@@ -0,0 +1,114 @@
+use crate::engine::Engine;
+use crate::types::{MatchResult, Severity, TreeSitterQuery};
+use std::{error::Error, process::Command};
+
+/// Tree ID of the empty tree, used as the base when a push creates a new ref
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+/// A single `<old> <new> <ref>` line read from pre-receive stdin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefUpdate {
+    pub old: String,
+    pub new: String,
+    pub refname: String,
+}
+
+impl RefUpdate {
+    /// Whether the push deletes the ref (new object ID is all zeros)
+    pub fn is_delete(&self) -> bool {
+        is_null_oid(&self.new)
+    }
+
+    /// Whether the push creates the ref (old object ID is all zeros)
+    pub fn is_create(&self) -> bool {
+        is_null_oid(&self.old)
+    }
+}
+
+fn is_null_oid(oid: &str) -> bool {
+    oid.chars().all(|c| c == '0')
+}
+
+/// Parse the standard pre-receive input, skipping malformed lines
+pub fn parse_pre_receive_input(input: &str) -> Vec<RefUpdate> {
+    input.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let old = parts.next()?;
+            let new = parts.next()?;
+            let refname = parts.next()?;
+
+            Some(RefUpdate {
+                old: old.to_string(),
+                new: new.to_string(),
+                refname: refname.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Collect the paths and contents of blobs added or modified by a ref update.
+///
+/// Objects are read through git, so this also works while the pushed pack is
+/// still held in the pre-receive quarantine area.
+pub fn changed_blobs(update: &RefUpdate) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    if update.is_delete() {
+        return Ok(Vec::new());
+    }
+
+    let base = if update.is_create() { EMPTY_TREE } else { update.old.as_str() };
+
+    let output = Command::new("git")
+        .args(["diff-tree", "-r", "-z", "--name-only", "--no-renames", "--diff-filter=AM", base, &update.new])
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!("Failed to diff {}..{}: {}", base, update.new, String::from_utf8_lossy(&output.stderr).trim()).into());
+    }
+
+    let mut blobs = Vec::new();
+    for path in String::from_utf8(output.stdout)?.split('\0').filter(|p| !p.is_empty()) {
+        let blob = Command::new("git")
+            .args(["cat-file", "blob", &format!("{}:{}", update.new, path)])
+            .output()?;
+
+        if !blob.status.success() {
+            eprintln!("Failed to read {} at {}", path, update.new);
+            continue;
+        }
+
+        // Binary blobs can't be parsed by tree-sitter
+        match String::from_utf8(blob.stdout) {
+            Ok(content) => blobs.push((path.to_string(), content)),
+            Err(_) => continue,
+        }
+    }
+
+    Ok(blobs)
+}
+
+/// Scan every ref update in a push and return the findings at or above `block_on`
+pub fn check_push(engine: &Engine, updates: &[RefUpdate], queries: &[TreeSitterQuery], block_on: Severity) -> Result<Vec<MatchResult>, Box<dyn Error>> {
+    let mut blocking = Vec::new();
+
+    for update in updates {
+        let sources: Vec<(String, String)> = changed_blobs(update)?
+            .into_iter()
+            .filter(|(path, _)| engine.get_language_for_file(path).is_some())
+            .collect();
+
+        if sources.is_empty() {
+            continue;
+        }
+
+        println!("🔍 Scanning {} changed files on {}", sources.len(), update.refname);
+
+        blocking.extend(
+            engine.scan_sources(sources, queries)
+                .into_iter()
+                .filter(|r| r.severity >= block_on)
+        );
+    }
+
+    Ok(blocking)
+}
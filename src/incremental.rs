@@ -0,0 +1,151 @@
+use crate::engine::ParsedFile;
+use crate::language_loader::{canonical_language, get_language};
+use crate::types::MatchResult;
+use std::ops::Range;
+use tree_sitter::{InputEdit, Node, Parser, Point, Tree};
+
+/// Lines of a file affected by an edit, 1-based and inclusive like `MatchResult::line`. The
+/// span starts at the same line before and after the edit, and ends at `old_end` before it
+/// and `new_end` after it; lines past the span moved by the difference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineSpan {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+impl LineSpan {
+    /// Whether a line after the edit lies in the span
+    pub fn contains(&self, line: usize) -> bool {
+        (self.start..=self.new_end).contains(&line)
+    }
+
+    /// Where a line from before the edit is now, or `None` if it lies in the span
+    pub fn shift(&self, line: usize) -> Option<usize> {
+        if line < self.start {
+            Some(line)
+        } else if line > self.old_end {
+            Some(line + self.new_end - self.old_end)
+        } else {
+            None
+        }
+    }
+
+    /// Replace the findings a file had before the edit inside the span by `results`, found
+    /// in the span after it, moving the findings past the span to their new lines
+    pub fn merge(&self, previous: Vec<MatchResult>, results: Vec<MatchResult>) -> Vec<MatchResult> {
+        let (mut merged, mut after) = (Vec::new(), Vec::new());
+        for mut finding in previous {
+            match self.shift(finding.line) {
+                Some(line) if line < self.start => merged.push(finding),
+                Some(line) => {
+                    finding.line = line;
+                    after.push(finding);
+                }
+                None => {}
+            }
+        }
+        merged.extend(results);
+        merged.extend(after);
+        merged
+    }
+}
+
+/// A file re-parsed from the tree of its previous contents
+pub struct Reparsed {
+    pub tree: Tree,
+    /// Lines the edit affected, widened to whole top-level nodes and their neighbours, so
+    /// that matches spanning adjacent declarations are found again
+    pub span: LineSpan,
+    /// Bytes of the new contents covering `span`
+    pub bytes: Range<usize>,
+}
+
+/// Parse `source`, the new contents of a file, by editing the tree of its previous contents
+/// so that tree-sitter reuses the parts the edit didn't touch. Returns `None` if the contents
+/// are unchanged or the file can't be parsed incrementally, e.g. Svelte components, whose
+/// parsed ranges move with every edit.
+pub fn reparse(previous: &ParsedFile, source: &str, language_name: &str) -> Option<Reparsed> {
+    if canonical_language(language_name) == Some("svelte") {
+        return None;
+    }
+    let (old_tree, old_source) = &**previous;
+    let edit = edit_between(old_source, source)?;
+
+    let mut edited = old_tree.clone();
+    edited.edit(&edit);
+    let mut parser = Parser::new();
+    parser.set_language(get_language(language_name)?).ok()?;
+    let tree = parser.parse(source, Some(&edited))?;
+
+    // Nodes whose structure changed can lie outside the edited bytes, e.g. a closing brace
+    // that now ends a different block
+    let (mut start, mut end) = (edit.start_byte, edit.new_end_byte);
+    for range in edited.changed_ranges(&tree) {
+        start = start.min(range.start_byte);
+        end = end.max(range.end_byte);
+    }
+    let bytes = widen(tree.root_node(), source, start..end);
+
+    let start = point_at(source, bytes.start).row + 1;
+    let new_end = point_at(source, bytes.end).row + 1;
+    // Past the edit, every line moved by as many lines as the edit added or removed
+    let old_end = new_end + edit.old_end_position.row - edit.new_end_position.row;
+    Some(Reparsed { tree, span: LineSpan { start, old_end, new_end }, bytes })
+}
+
+/// The single edit turning `old` into `new`: everything between their common prefix and
+/// common suffix. `None` if they are the same.
+pub fn edit_between(old: &str, new: &str) -> Option<InputEdit> {
+    if old == new {
+        return None;
+    }
+    let mut prefix = old.bytes().zip(new.bytes()).take_while(|(a, b)| a == b).count();
+    while !old.is_char_boundary(prefix) || !new.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let longest = old.len().min(new.len()) - prefix;
+    let mut suffix = old.bytes().rev().zip(new.bytes().rev()).take(longest).take_while(|(a, b)| a == b).count();
+    while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix) {
+        suffix -= 1;
+    }
+
+    let (old_end, new_end) = (old.len() - suffix, new.len() - suffix);
+    Some(InputEdit {
+        start_byte: prefix,
+        old_end_byte: old_end,
+        new_end_byte: new_end,
+        start_position: point_at(new, prefix),
+        old_end_position: point_at(old, old_end),
+        new_end_position: point_at(new, new_end),
+    })
+}
+
+/// Widen changed bytes to the top-level nodes they touch plus one on either side, then to
+/// whole lines
+fn widen(root: Node, source: &str, changed: Range<usize>) -> Range<usize> {
+    let mut cursor = root.walk();
+    let children: Vec<Node> = root.children(&mut cursor).collect();
+    let touched: Vec<usize> = (0..children.len())
+        .filter(|&i| children[i].start_byte() <= changed.end && children[i].end_byte() >= changed.start)
+        .collect();
+
+    let (mut start, mut end) = (changed.start, changed.end);
+    if let (Some(&first), Some(&last)) = (touched.first(), touched.last()) {
+        start = start.min(children[first.saturating_sub(1)].start_byte());
+        end = end.max(children[(last + 1).min(children.len() - 1)].end_byte());
+    }
+
+    let start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let end = source[end..].find('\n').map_or(source.len(), |newline| end + newline);
+    start..end
+}
+
+/// Row and byte column of a byte offset
+fn point_at(source: &str, byte: usize) -> Point {
+    let before = &source[..byte];
+    match before.rfind('\n') {
+        Some(newline) => Point::new(before.matches('\n').count(), byte - newline - 1),
+        None => Point::new(0, byte),
+    }
+}
@@ -0,0 +1,137 @@
+use crate::language_loader::get_language;
+use crate::pii;
+use crate::svelte::point_at;
+use tree_sitter::{Node, Parser, Range, Tree};
+
+/// Tag recorded on findings in embedded code, followed by the embedded language
+pub const INJECTED_TAG_PREFIX: &str = "injected:";
+
+/// How much of a captured node is the embedded code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trim {
+    /// The whole node
+    None,
+    /// The node without its string prefix and quotes, e.g. `f"` and `"` around a Python string
+    Quotes,
+    /// The node without its first line, e.g. the `|` header of a YAML block scalar
+    FirstLine,
+}
+
+/// A place where a host language embeds another: nodes the query captures are parsed as `language`
+#[derive(Debug, Clone)]
+pub struct InjectionRule {
+    pub language: &'static str,
+    pub query: String,
+    pub trim: Trim,
+    /// Only captures whose embedded code passes this check are injected
+    pub accept: fn(&str) -> bool,
+}
+
+/// Code of one language embedded in a file of another
+pub struct InjectedTree {
+    pub language: &'static str,
+    /// Parsed from the host file's source limited to the embedded region, so positions in it
+    /// are positions in the host file
+    pub tree: Tree,
+}
+
+/// The languages a host language embeds and where: scripts and styles in HTML, shell in
+/// YAML `run:` steps, and SQL in string literals of general-purpose languages
+pub fn rules(host: &str) -> Vec<InjectionRule> {
+    let any = |_: &str| true;
+    match host {
+        "html" => vec![
+            InjectionRule { language: "javascript", query: "(script_element (raw_text) @content)".to_string(), trim: Trim::None, accept: any },
+            InjectionRule { language: "css", query: "(style_element (raw_text) @content)".to_string(), trim: Trim::None, accept: any },
+        ],
+        "yaml" => vec![
+            InjectionRule {
+                language: "bash",
+                query: r#"(block_mapping_pair key: (flow_node) @key (#eq? @key "run") value: (block_node (block_scalar) @content))"#.to_string(),
+                trim: Trim::FirstLine,
+                accept: any,
+            },
+            InjectionRule {
+                language: "bash",
+                query: r#"(block_mapping_pair key: (flow_node) @key (#eq? @key "run") value: (flow_node) @content)"#.to_string(),
+                trim: Trim::Quotes,
+                accept: any,
+            },
+        ],
+        "python" | "javascript" | "typescript" | "tsx" | "java" | "go" | "ruby" | "php" | "csharp" | "kotlin" | "scala" => {
+            pii::string_literal_kinds(host).iter()
+                .map(|kind| InjectionRule { language: "sql", query: format!("({}) @content", kind), trim: Trim::Quotes, accept: looks_like_sql })
+                .collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Whether a string reads like a SQL statement: it starts with a statement keyword that is
+/// later followed by one of the keywords that statement needs, so labels like "Select a user"
+/// are left alone
+pub fn looks_like_sql(text: &str) -> bool {
+    const OBJECTS: &[&str] = &["TABLE", "INDEX", "VIEW", "DATABASE", "SCHEMA", "USER", "ROLE", "FUNCTION", "PROCEDURE", "TRIGGER", "SEQUENCE"];
+    const STATEMENTS: [(&str, &[&str]); 11] = [
+        ("SELECT", &["FROM"]),
+        ("INSERT", &["INTO"]),
+        ("UPDATE", &["SET"]),
+        ("DELETE", &["FROM"]),
+        ("CREATE", OBJECTS),
+        ("ALTER", OBJECTS),
+        ("DROP", OBJECTS),
+        ("GRANT", &["ON", "TO"]),
+        ("REVOKE", &["ON", "FROM"]),
+        ("WITH", &["AS"]),
+        ("MERGE", &["INTO"]),
+    ];
+
+    let upper = text.to_ascii_uppercase();
+    let mut words = upper.split(|c: char| !c.is_ascii_alphanumeric() && c != '_').filter(|word| !word.is_empty());
+    let Some(first) = words.next() else {
+        return false;
+    };
+    STATEMENTS.iter()
+        .find(|(keyword, _)| *keyword == first)
+        .is_some_and(|(_, follows)| words.any(|word| follows.contains(&word)))
+}
+
+/// The region of a captured node that holds the embedded code
+pub fn region(node: Node, source: &str, trim: Trim) -> Option<Range> {
+    let (start, end) = (node.start_byte(), node.end_byte());
+    let text = &source[start..end];
+
+    let (from, to) = match trim {
+        Trim::None => (0, text.len()),
+        Trim::Quotes => match text.find(['"', '\'', '`']) {
+            // Only quotes after a string prefix like `f`, `rb` or `@` delimit the string
+            Some(open) if text[..open].chars().all(|c| c.is_ascii_alphabetic() || c == '@' || c == '$') => {
+                let quote = text.as_bytes()[open] as char;
+                // Triple-quoted strings open and close with three quotes
+                let quotes = (text[open..].len() - text[open..].trim_start_matches(quote).len()).min(3);
+                (open + quotes, text.len().saturating_sub(quotes))
+            }
+            _ => (0, text.len()),
+        },
+        Trim::FirstLine => (text.find('\n').map_or(text.len(), |newline| newline + 1), text.len()),
+    };
+    if from >= to {
+        return None;
+    }
+
+    Some(Range {
+        start_byte: start + from,
+        end_byte: start + to,
+        start_point: point_at(source, start + from),
+        end_point: point_at(source, start + to),
+    })
+}
+
+/// Parse one embedded region of `source` as `language`
+pub fn parse_region(source: &str, language: &'static str, range: Range) -> Option<InjectedTree> {
+    let mut parser = Parser::new();
+    parser.set_language(get_language(language)?).ok()?;
+    parser.set_included_ranges(&[range]).ok()?;
+    let tree = parser.parse(source, None)?;
+    Some(InjectedTree { language, tree })
+}
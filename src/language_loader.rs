@@ -1,16 +1,302 @@
+use std::sync::RwLock;
 use tree_sitter::Language;
+#[cfg(feature = "dynamic-grammars")]
+use std::{error::Error, fs, path::Path};
 
+/// A grammar registered at runtime instead of being compiled in
+struct RegisteredGrammar {
+    name: &'static str,
+    extensions: Vec<String>,
+    language: Language,
+}
+
+/// Grammars registered with `register_language`
+static REGISTERED: RwLock<Vec<RegisteredGrammar>> = RwLock::new(Vec::new());
+
+/// Resolve a language name, alias, or file extension (without the dot) to its canonical language name
+pub fn canonical_language(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    match name.as_str() {
+        "rust" | "rs" => Some("rust"),
+        "javascript" | "js" | "jsx" => Some("javascript"),
+        "python" | "py" => Some("python"),
+        "go" => Some("go"),
+        "typescript" | "ts" => Some("typescript"),
+        // TSX needs its own grammar for JSX nodes; plain JSX is covered by the JavaScript grammar
+        "tsx" => Some("tsx"),
+        "svelte" => Some("svelte"),
+        "java" => Some("java"),
+        // Headers default to C++, whose grammar also parses C headers; files on disk are
+        // resolved by their contents with `language_for_content`
+        "cpp" | "c++" | "h" | "hpp" | "cc" => Some("cpp"),
+        "c" => Some("c"),
+        "ruby" | "rb" => Some("ruby"),
+        "php" => Some("php"),
+        "csharp" | "c#" | "cs" => Some("csharp"),
+        "kotlin" | "kt" | "kts" => Some("kotlin"),
+        "swift" => Some("swift"),
+        "scala" | "sc" => Some("scala"),
+        "hcl" | "terraform" | "tf" | "tfvars" => Some("hcl"),
+        "yaml" | "yml" => Some("yaml"),
+        "sql" => Some("sql"),
+        "html" | "htm" => Some("html"),
+        // SCSS goes through the CSS grammar: plain rules, imports and `url()` calls parse
+        // normally, while nesting and `$variables` come back as error nodes
+        "css" | "scss" => Some("css"),
+        "solidity" | "sol" => Some("solidity"),
+        "lua" => Some("lua"),
+        "dart" => Some("dart"),
+        "zig" => Some("zig"),
+        "powershell" | "ps1" | "psm1" => Some("powershell"),
+        "groovy" | "gradle" | "jenkinsfile" => Some("groovy"),
+        "bash" | "sh" => Some("bash"),
+        _ => registered_language(&name),
+    }
+}
+
+/// Extensions used by more than one kind of file, whose language is decided by the file's contents
+const AMBIGUOUS_EXTENSIONS: [&str; 2] = ["h", "ts"];
+
+/// Whether files with this extension (without the dot) need `language_for_content`
+pub fn is_ambiguous_extension(extension: &str) -> bool {
+    AMBIGUOUS_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+}
+
+/// Resolve the language of a file with an ambiguous extension from the start of its contents:
+/// `.h` headers are C unless they use C++ constructs, and `.ts` files are TypeScript unless
+/// they are Qt Linguist translations, which are XML and have no grammar here
+pub fn language_for_content(extension: &str, head: &str) -> Option<&'static str> {
+    const CPP_MARKERS: [&str; 12] = [
+        "class ", "namespace ", "template<", "template <", "public:", "private:", "protected:",
+        "std::", "virtual ", "#include <iostream>", "#include <string>", "#include <vector>",
+    ];
+
+    match extension.to_lowercase().as_str() {
+        "h" => Some(if CPP_MARKERS.iter().any(|marker| head.contains(marker)) { "cpp" } else { "c" }),
+        "ts" => {
+            let head = head.trim_start();
+            let translation = head.starts_with("<?xml") || head.starts_with("<!DOCTYPE TS") || head.starts_with("<TS");
+            if translation { None } else { Some("typescript") }
+        }
+        other => canonical_language(other),
+    }
+}
+
+/// Parse `PATTERN=LANGUAGE` overrides, such as `include/legacy/*.h=c`, into a hook for
+/// `Engine::with_language_override`. Patterns are globs matched against the end of a file's
+/// path, and the first matching pattern decides.
+pub fn language_overrides(specs: &[String]) -> Result<impl Fn(&str) -> Option<&'static str> + Send + Sync + 'static, String> {
+    let mut overrides = Vec::new();
+    for spec in specs {
+        let (pattern, language) = spec.rsplit_once('=')
+            .ok_or_else(|| format!("Invalid language override {:?}; expected PATTERN=LANGUAGE", spec))?;
+        let language = canonical_language(language.trim()).ok_or_else(|| format!("Unknown language in override {:?}", spec))?;
+        let pattern = pattern.trim().replace('\\', "/");
+        let pattern = if pattern.starts_with('/') || pattern.starts_with("**") { pattern } else { format!("**/{}", pattern) };
+        let pattern = glob::Pattern::new(&pattern).map_err(|e| format!("Invalid pattern in language override {:?}: {}", spec, e))?;
+        overrides.push((pattern, language));
+    }
+
+    let options = glob::MatchOptions { require_literal_separator: true, ..Default::default() };
+    Ok(move |path: &str| {
+        let path = path.replace('\\', "/");
+        overrides.iter()
+            .find(|(pattern, _)| pattern.matches_with(&path, options))
+            .map(|(_, language)| *language)
+    })
+}
+
+/// Resolve the language of a file that is recognized by its name rather than its extension,
+/// such as `Jenkinsfile` or `Jenkinsfile.release`
+pub fn language_for_file_name(file_name: &str) -> Option<&'static str> {
+    let name = file_name.to_lowercase();
+    let language = if name == "jenkinsfile" || name.starts_with("jenkinsfile.") { Some("groovy") } else { None };
+    language.filter(|language| get_language(language).is_some())
+}
+
+/// Resolve the language of a script from its shebang line, such as `#!/usr/bin/env python3`
+/// or `#!/bin/bash`
+pub fn language_for_shebang(line: &str) -> Option<&'static str> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut interpreter = words.next()?.rsplit('/').next()?;
+    if interpreter == "env" {
+        // Skip env's own options, e.g. `env -S node --harmony`
+        interpreter = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+    }
+
+    // python3.11 -> python
+    let language = match interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+        "python" | "pypy" => Some("python"),
+        "node" | "nodejs" | "deno" | "bun" => Some("javascript"),
+        "ts-node" | "tsx" => Some("typescript"),
+        "ruby" => Some("ruby"),
+        "php" => Some("php"),
+        "bash" | "sh" | "dash" | "ksh" | "zsh" => Some("bash"),
+        "lua" | "luajit" => Some("lua"),
+        "groovy" => Some("groovy"),
+        "pwsh" | "powershell" => Some("powershell"),
+        "swift" => Some("swift"),
+        "kotlin" => Some("kotlin"),
+        "scala" => Some("scala"),
+        "dart" => Some("dart"),
+        _ => None,
+    };
+    language.filter(|language| get_language(language).is_some())
+}
+
+/// The grammar of a language, or None if it is unknown or its `lang-*` feature is disabled
+/// and no grammar was registered for it at runtime
 pub fn get_language(language_name: &str) -> Option<Language> {
-    match language_name {
-        "rust" | "rs" => Some(tree_sitter_rust::language()),
-        "javascript" | "js" => Some(tree_sitter_javascript::language()),
-        "python" | "py" => Some(tree_sitter_python::language()),
+    match canonical_language(language_name)? {
+        #[cfg(feature = "lang-rust")]
+        "rust" => Some(tree_sitter_rust::language()),
+        #[cfg(feature = "lang-javascript")]
+        "javascript" => Some(tree_sitter_javascript::language()),
+        #[cfg(feature = "lang-python")]
+        "python" => Some(tree_sitter_python::language()),
+        #[cfg(feature = "lang-go")]
         "go" => Some(tree_sitter_go::language()),
-        "typescript" | "ts" => Some(tree_sitter_typescript::language_typescript()),
+        #[cfg(feature = "lang-typescript")]
+        "typescript" => Some(tree_sitter_typescript::language_typescript()),
+        #[cfg(feature = "lang-typescript")]
+        "tsx" => Some(tree_sitter_typescript::language_tsx()),
+        // Only a component's <script> blocks are parsed; see `svelte::script_ranges`
+        #[cfg(feature = "lang-typescript")]
+        "svelte" => Some(tree_sitter_typescript::language_typescript()),
+        #[cfg(feature = "lang-java")]
         "java" => Some(tree_sitter_java::language()),
-        "cpp" | "c++" | "h" | "hpp" | "cc" => Some(tree_sitter_cpp::language()),
-        "ruby" | "rb" => Some(tree_sitter_ruby::language()),
+        #[cfg(feature = "lang-cpp")]
+        "cpp" => Some(tree_sitter_cpp::language()),
+        #[cfg(feature = "lang-c")]
+        "c" => Some(tree_sitter_c::language()),
+        #[cfg(feature = "lang-ruby")]
+        "ruby" => Some(tree_sitter_ruby::language()),
+        #[cfg(feature = "lang-php")]
         "php" => Some(tree_sitter_php::language()),
-        _ => None,
+        #[cfg(feature = "lang-csharp")]
+        "csharp" => Some(tree_sitter_c_sharp::language()),
+        #[cfg(feature = "lang-kotlin")]
+        "kotlin" => Some(tree_sitter_kotlin::language()),
+        #[cfg(feature = "lang-swift")]
+        "swift" => Some(tree_sitter_swift::language()),
+        #[cfg(feature = "lang-scala")]
+        "scala" => Some(tree_sitter_scala::language()),
+        #[cfg(feature = "lang-yaml")]
+        "yaml" => Some(tree_sitter_yaml::language()),
+        #[cfg(feature = "lang-html")]
+        "html" => Some(tree_sitter_html::language()),
+        #[cfg(feature = "lang-css")]
+        "css" => Some(tree_sitter_css::language()),
+        #[cfg(feature = "lang-solidity")]
+        "solidity" => Some(tree_sitter_solidity::language()),
+        #[cfg(feature = "lang-lua")]
+        "lua" => Some(tree_sitter_lua::language()),
+        #[cfg(feature = "lang-dart")]
+        "dart" => Some(tree_sitter_dart::language()),
+        #[cfg(feature = "lang-bash")]
+        "bash" => Some(tree_sitter_bash::language()),
+        // Includes languages like HCL that have aliases above but no grammar crate for this
+        // tree-sitter version, once their grammar is loaded with `load_grammar_dir`
+        name => REGISTERED.read().unwrap_or_else(|e| e.into_inner()).iter()
+            .find(|grammar| grammar.name == name)
+            .map(|grammar| grammar.language),
+    }
+}
+
+/// Make a grammar loaded at runtime available under `name` and for files with the given
+/// extensions (without the dot). Built-in languages and their extensions take precedence.
+/// Returns the canonical name the language is registered under.
+pub fn register_language(name: &str, extensions: &[String], language: Language) -> &'static str {
+    let mut registered = REGISTERED.write().unwrap_or_else(|e| e.into_inner());
+    let extensions = extensions.iter().map(|ext| ext.trim_start_matches('.').to_lowercase()).collect();
+    let name = name.to_lowercase();
+
+    if let Some(grammar) = registered.iter_mut().find(|grammar| grammar.name == name) {
+        grammar.extensions = extensions;
+        grammar.language = language;
+        return grammar.name;
     }
+    // Canonical names are 'static; a process only ever registers a handful of grammars
+    let name: &'static str = Box::leak(name.into_boxed_str());
+    registered.push(RegisteredGrammar { name, extensions, language });
+    name
+}
+
+/// The canonical name of a registered grammar, looked up by its name or one of its extensions
+pub fn registered_language(name: &str) -> Option<&'static str> {
+    let name = name.to_lowercase();
+    REGISTERED.read().unwrap_or_else(|e| e.into_inner()).iter()
+        .find(|grammar| grammar.name == name || grammar.extensions.contains(&name))
+        .map(|grammar| grammar.name)
+}
+
+/// Load every tree-sitter grammar shared library in `dir` and register it, returning the
+/// names of the loaded languages.
+///
+/// A library named `tree-sitter-<name>` or `libtree-sitter-<name>` with a `.so`, `.dylib` or
+/// `.dll` extension must export `tree_sitter_<name>`, with dashes in the name replaced by
+/// underscores. An optional `<name>.json` next to it lists the language's `extensions`;
+/// without one the language's name is its only extension. Libraries that can't be loaded,
+/// or whose `<name>.json` can't be read, are reported and skipped.
+#[cfg(feature = "dynamic-grammars")]
+pub fn load_grammar_dir(dir: &Path) -> Result<Vec<&'static str>, Box<dyn Error>> {
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read grammar directory {}: {}", dir.display(), e))?;
+
+    let mut loaded = Vec::new();
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        let is_library = path.extension().and_then(|ext| ext.to_str())
+            .is_some_and(|ext| matches!(ext, "so" | "dylib" | "dll"));
+        let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()).filter(|_| is_library) else {
+            continue;
+        };
+        let name = stem.strip_prefix("lib").unwrap_or(stem);
+        let name = name.strip_prefix("tree-sitter-").unwrap_or(name);
+
+        // A malformed `<name>.json` skips its grammar like a library that can't be loaded
+        let grammar = grammar_extensions(dir, name)
+            .and_then(|extensions| Ok((extensions, load_grammar_library(&path, name)?)));
+        match grammar {
+            Ok((extensions, language)) => loaded.push(register_language(name, &extensions, language)),
+            Err(e) => eprintln!("⚠️ Skipping grammar {}: {}", path.display(), e),
+        }
+    }
+
+    Ok(loaded)
+}
+
+/// Open a grammar shared library and call its language constructor
+#[cfg(feature = "dynamic-grammars")]
+fn load_grammar_library(path: &Path, name: &str) -> Result<Language, Box<dyn Error>> {
+    let symbol = format!("tree_sitter_{}", name.replace('-', "_"));
+    let library = unsafe { libloading::Library::new(path) }?;
+    let language = unsafe {
+        let constructor = library.get::<unsafe extern "C" fn() -> Language>(symbol.as_bytes())?;
+        constructor()
+    };
+
+    let version = language.version();
+    if !(tree_sitter::MIN_COMPATIBLE_LANGUAGE_VERSION..=tree_sitter::LANGUAGE_VERSION).contains(&version) {
+        return Err(format!("grammar ABI version {} is not supported by this build", version).into());
+    }
+
+    // The language's tables live in the library, which must stay loaded for the rest of the process
+    std::mem::forget(library);
+    Ok(language)
+}
+
+/// The extensions listed in a grammar's `<name>.json`, or just its name without one
+#[cfg(feature = "dynamic-grammars")]
+fn grammar_extensions(dir: &Path, name: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let path = dir.join(format!("{}.json", name));
+    if !path.exists() {
+        return Ok(vec![name.to_string()]);
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let config: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Invalid grammar config {}: {}", path.display(), e))?;
+    Ok(config["extensions"].as_array()
+        .map(|extensions| extensions.iter().filter_map(|ext| ext.as_str().map(str::to_string)).collect())
+        .unwrap_or_else(|| vec![name.to_string()]))
 }
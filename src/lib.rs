@@ -1,3 +1,67 @@
 pub mod types;
+pub mod config;
+pub mod project;
 pub mod language_loader;
+pub mod svelte;
+pub mod injection;
+pub mod engine;
+pub mod incremental;
+#[cfg(feature = "client")]
 pub mod scanner;
+pub mod rules;
+pub mod validate;
+pub mod plan;
+pub mod playground;
+pub mod hook;
+pub mod pii;
+pub mod complexity;
+pub mod stats;
+pub mod bench;
+pub mod duplication;
+pub mod dead_code;
+pub mod coverage;
+pub mod discovery;
+pub mod git;
+#[cfg(feature = "client")]
+pub mod batch;
+pub mod baseline;
+pub mod bundle;
+pub mod results;
+pub mod reporter;
+pub mod disk_cache;
+pub mod manifest;
+pub mod sbom;
+#[cfg(feature = "client")]
+pub mod pipeline;
+pub mod exit_codes;
+pub mod gate;
+pub mod polling;
+pub mod timeout;
+#[cfg(feature = "client")]
+pub mod shutdown;
+#[cfg(feature = "client")]
+pub mod rpc;
+#[cfg(feature = "client")]
+pub mod serve;
+pub mod dead_letter;
+pub mod color;
+#[cfg(feature = "cli")]
+pub mod logging;
+#[cfg(feature = "cli")]
+pub mod progress;
+#[cfg(feature = "cli")]
+pub mod watch;
+pub mod redact;
+pub mod trends;
+#[cfg(feature = "cli")]
+pub mod tui;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "store")]
+pub mod store;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+#[cfg(feature = "scripting")]
+pub mod scripting;
@@ -0,0 +1,103 @@
+use crate::{color, progress, redact};
+use std::{
+    io::{self, Write},
+    str::FromStr,
+};
+use tracing::Level;
+
+/// How diagnostics written to stderr are formatted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per event, including the enclosing file and query spans
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("unknown log format: {} (expected text or json)", other)),
+        }
+    }
+}
+
+/// Map `-q` and the number of `-v` flags to the most verbose level that is logged
+pub fn level(verbose: u8, quiet: bool) -> Level {
+    if quiet {
+        return Level::WARN;
+    }
+    match verbose {
+        0 => Level::INFO,
+        1 => Level::DEBUG,
+        _ => Level::TRACE,
+    }
+}
+
+/// A writer that removes secrets from everything written through it with `redact::redact`.
+/// Each write should hold whole lines, as the log subscriber's writes of one event do, so
+/// that no secret is split across two of them.
+pub struct RedactingWriter<W>(W);
+
+impl<W: Write> RedactingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self(inner)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.0
+    }
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Redacted text differs in length, so all of it is written or none
+        self.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.write_all(redact::redact(&String::from_utf8_lossy(buf)).as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Stderr with secrets redacted, hiding the progress bar while a line is written so the two
+/// don't interleave
+struct LogWriter;
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        progress::suspend(|| RedactingWriter::new(io::stderr()).write(buf))
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        progress::suspend(|| RedactingWriter::new(io::stderr()).write_all(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// Install the global subscriber writing to stderr, redacting registered secrets and
+/// credential-looking values from every event. Call after `color::init` so text logs follow
+/// the color choice.
+pub fn init(level: Level, format: LogFormat) {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(|| LogWriter)
+        .with_target(false);
+    let _ = match format {
+        LogFormat::Text => builder.without_time().with_ansi(color::enabled()).try_init(),
+        LogFormat::Json => builder.json().with_current_span(true).with_span_list(true).try_init(),
+    };
+}
@@ -0,0 +1,121 @@
+use crate::bundle::{self, config_hash};
+use crate::engine::Engine;
+use crate::types::TreeSitterQuery;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{error::Error, fs, path::Path, time::{SystemTime, UNIX_EPOCH}};
+
+/// A file covered by a scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub path: String,
+    /// Hex SHA-256 of the file's contents when it was scanned
+    pub sha256: String,
+    pub language: Option<String>,
+    pub bytes: u64,
+}
+
+/// Record of everything a scan covered and the configuration it ran with, so its
+/// completeness and integrity can be proven during a later audit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanManifest {
+    /// Seconds since the Unix epoch when the manifest was built
+    pub created_at: u64,
+    pub organization_id: String,
+    pub code_base_version: String,
+    pub report_id: Option<String>,
+    pub files: Vec<ManifestFile>,
+    /// SHA-256 of the engine settings the scan ran with
+    pub config_hash: String,
+    /// SHA-256 of the queries the scan ran, as in export bundles
+    pub rules_hash: String,
+    /// Hex HMAC-SHA256 with the organization's signing key of the manifest without this field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl ScanManifest {
+    /// Hash every scanned file and record the settings and queries of the scan. Files that
+    /// can't be read are left out and reported.
+    pub fn build(files: &[String], engine: &Engine, queries: &[TreeSitterQuery], report_id: Option<String>) -> Self {
+        let read = |path: &String| match engine.sources() {
+            Some(sources) => sources.read(path).map(String::into_bytes)
+                .ok_or_else(|| "not among the scanned sources".to_string()),
+            None => fs::read(path).map_err(|e| e.to_string()),
+        };
+        let mut entries: Vec<ManifestFile> = files.iter()
+            .filter_map(|path| match read(path) {
+                Ok(content) => Some(ManifestFile {
+                    path: path.clone(),
+                    sha256: format!("{:x}", Sha256::digest(&content)),
+                    language: engine.get_language_for_file(path).map(str::to_string),
+                    bytes: content.len() as u64,
+                }),
+                Err(e) => {
+                    eprintln!("Leaving {} out of the scan manifest: {}", path, e);
+                    None
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let settings = serde_json::to_vec(&engine.settings()).unwrap_or_default();
+
+        Self {
+            created_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+            organization_id: engine.organization_id().to_string(),
+            code_base_version: engine.code_base_version().to_string(),
+            report_id,
+            files: entries,
+            config_hash: format!("{:x}", Sha256::digest(&settings)),
+            rules_hash: config_hash(queries, None),
+            signature: None,
+        }
+    }
+
+    /// Sign the manifest with the organization's shared key
+    pub fn sign(&mut self, key: &[u8]) -> Result<(), Box<dyn Error>> {
+        self.signature = None;
+        let body = serde_json::to_vec(self)?;
+        self.signature = Some(bundle::to_hex(&bundle::sign(key, &body)?));
+        Ok(())
+    }
+
+    /// Check the manifest's signature against `key`
+    pub fn verify(&self, key: &[u8]) -> Result<(), Box<dyn Error>> {
+        let signature = self.signature.as_deref().ok_or("Manifest is not signed")?;
+        let signature = bundle::from_hex(signature).ok_or("Malformed manifest signature")?;
+        let mut unsigned = self.clone();
+        unsigned.signature = None;
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(|_| "Invalid signing key")?;
+        mac.update(&serde_json::to_vec(&unsigned)?);
+        mac.verify_slice(&signature)
+            .map_err(|_| "Manifest signature does not match; it was modified or signed with another key".into())
+    }
+
+    /// Files listed in the manifest that are now missing or whose contents changed
+    pub fn changed_files(&self) -> Vec<String> {
+        self.files.iter()
+            .filter(|file| !fs::read(&file.path).is_ok_and(|content| format!("{:x}", Sha256::digest(&content)) == file.sha256))
+            .map(|file| file.path.clone())
+            .collect()
+    }
+
+    /// Write the manifest as pretty-printed JSON
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Read a manifest written by `write`
+    pub fn read(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read manifest {}: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+}
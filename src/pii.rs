@@ -0,0 +1,187 @@
+use crate::types::Severity;
+use regex::Regex;
+use std::{fmt, sync::OnceLock};
+
+/// Category recorded on findings of the PII detector
+pub const PII_CATEGORY: &str = "pii";
+
+/// A kind of personally identifiable information
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PiiKind {
+    Email,
+    Ssn,
+    CreditCard,
+    Phone,
+}
+
+impl fmt::Display for PiiKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PiiKind::Email => "email",
+            PiiKind::Ssn => "ssn",
+            PiiKind::CreditCard => "credit-card",
+            PiiKind::Phone => "phone",
+        };
+        f.write_str(name)
+    }
+}
+
+impl PiiKind {
+    /// Rule ID reported on findings, e.g. `pii/email`
+    pub fn question_id(&self) -> String {
+        format!("{}/{}", PII_CATEGORY, self)
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            PiiKind::Ssn | PiiKind::CreditCard => Severity::High,
+            PiiKind::Email | PiiKind::Phone => Severity::Medium,
+        }
+    }
+}
+
+/// PII found in a piece of text. Only the redacted form is kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PiiMatch {
+    pub kind: PiiKind,
+    /// Byte offset of the match within the searched text
+    pub offset: usize,
+    pub redacted: String,
+}
+
+struct Patterns {
+    email: Regex,
+    ssn: Regex,
+    card: Regex,
+    phone: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        email: Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}").unwrap(),
+        ssn: Regex::new(r"\b(\d{3})-(\d{2})-(\d{4})\b").unwrap(),
+        card: Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").unwrap(),
+        phone: Regex::new(r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{3}\)|\d{3})[ .-]\d{3}[ .-]\d{4}\b").unwrap(),
+    })
+}
+
+/// Find PII in `text`. Candidates must pass a validator (Luhn for card numbers, number
+/// ranges the SSA never issues for SSNs), and text claimed by one match is not reported again.
+pub fn detect(text: &str) -> Vec<PiiMatch> {
+    let patterns = patterns();
+    let mut found: Vec<(usize, usize, PiiKind, String)> = Vec::new();
+
+    let candidates = [
+        (PiiKind::Ssn, &patterns.ssn),
+        (PiiKind::CreditCard, &patterns.card),
+        (PiiKind::Phone, &patterns.phone),
+        (PiiKind::Email, &patterns.email),
+    ];
+    for (kind, pattern) in candidates {
+        for m in pattern.find_iter(text) {
+            let overlaps = found.iter().any(|(start, end, _, _)| m.start() < *end && *start < m.end());
+            if !overlaps && is_valid(kind, m.as_str()) {
+                found.push((m.start(), m.end(), kind, m.as_str().to_string()));
+            }
+        }
+    }
+
+    found.sort_by_key(|(start, _, _, _)| *start);
+    found.into_iter()
+        .map(|(offset, _, kind, value)| PiiMatch { kind, offset, redacted: redact(kind, &value) })
+        .collect()
+}
+
+fn is_valid(kind: PiiKind, value: &str) -> bool {
+    match kind {
+        PiiKind::Email => true,
+        PiiKind::Ssn => valid_ssn(value),
+        PiiKind::CreditCard => luhn_valid(&digits(value)),
+        PiiKind::Phone => !digits(value).chars().all(|c| c == '0'),
+    }
+}
+
+/// Whether an `AAA-GG-SSSS` number could have been issued: no zero groups, and an area
+/// number other than 666 or 900-999
+fn valid_ssn(value: &str) -> bool {
+    let parts: Vec<&str> = value.split('-').collect();
+    let [area, group, serial] = parts[..] else {
+        return false;
+    };
+    area != "000" && area != "666" && !area.starts_with('9') && group != "00" && serial != "0000"
+}
+
+/// Luhn checksum used by payment card numbers
+pub fn luhn_valid(digits: &str) -> bool {
+    if digits.len() < 2 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+
+    let sum: u32 = digits.chars()
+        .rev()
+        .filter_map(|c| c.to_digit(10))
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 1 { if d * 2 > 9 { d * 2 - 9 } else { d * 2 } } else { d })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+fn digits(value: &str) -> String {
+    value.chars().filter(char::is_ascii_digit).collect()
+}
+
+/// Mask a value so findings never carry the PII itself: numbers keep their last four
+/// digits and emails their first character and domain
+pub fn redact(kind: PiiKind, value: &str) -> String {
+    match kind {
+        PiiKind::Email => match value.split_once('@') {
+            Some((local, domain)) => format!("{}***@{}", local.chars().next().unwrap_or('*'), domain),
+            None => "***".to_string(),
+        },
+        PiiKind::Ssn | PiiKind::CreditCard | PiiKind::Phone => {
+            let total = value.chars().filter(char::is_ascii_digit).count();
+            let mut seen = 0;
+            value.chars()
+                .map(|c| {
+                    if !c.is_ascii_digit() {
+                        return c;
+                    }
+                    seen += 1;
+                    if seen > total.saturating_sub(4) { c } else { '*' }
+                })
+                .collect()
+        }
+    }
+}
+
+/// Tree-sitter node kinds of string literals in each language
+pub(crate) fn string_literal_kinds(language: &str) -> &'static [&'static str] {
+    match language {
+        "rust" => &["string_literal", "raw_string_literal"],
+        "javascript" | "typescript" | "tsx" | "svelte" => &["string", "template_string"],
+        "python" | "ruby" => &["string"],
+        "go" => &["interpreted_string_literal", "raw_string_literal"],
+        "java" => &["string_literal"],
+        "cpp" => &["string_literal", "raw_string_literal"],
+        "c" => &["string_literal"],
+        "php" => &["string", "encapsed_string"],
+        "csharp" => &["string_literal", "verbatim_string_literal", "interpolated_string_expression"],
+        "kotlin" => &["string_literal"],
+        "swift" => &["line_string_literal", "multi_line_string_literal"],
+        "scala" => &["string", "interpolated_string_expression"],
+        "hcl" => &["string_lit"],
+        "yaml" => &["double_quote_scalar", "single_quote_scalar"],
+        "sql" => &["literal"],
+        "html" => &["attribute_value", "text"],
+        "css" => &["string_value"],
+        "solidity" => &["string"],
+        "lua" => &["string"],
+        "dart" => &["string_literal"],
+        "zig" => &["STRINGLITERALSINGLE"],
+        "powershell" => &["string_literal"],
+        "groovy" => &["string"],
+        "bash" => &["string", "raw_string"],
+        _ => &[],
+    }
+}
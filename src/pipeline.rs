@@ -0,0 +1,387 @@
+use crate::engine::{ParsedFile, QueryRouter};
+use crate::scanner::Scanner;
+use crate::stats::{self, CodeStats, LineCounts};
+use crate::timeout::ScanPhase;
+use crate::types::{MatchResult, TreeSitterQuery};
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc},
+    thread,
+};
+use tokio::{sync::{mpsc, Mutex}, task::JoinSet};
+use tracing::{error, warn};
+
+/// Worker counts and buffering for the discover → parse → query → upload pipeline.
+/// Upload concurrency is governed by the scanner's upload caps.
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    /// Number of tasks reading and parsing files
+    pub parse_workers: usize,
+    /// Number of tasks running queries against parsed trees
+    pub query_workers: usize,
+    /// Capacity of each channel between stages
+    pub channel_capacity: usize,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        let cores = thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+
+        Self {
+            parse_workers: cores,
+            query_workers: cores,
+            channel_capacity: 64,
+        }
+    }
+}
+
+/// Counters for one pipeline stage
+#[derive(Debug, Default)]
+pub struct StageProfile {
+    processed: AtomicUsize,
+    max_queue_depth: AtomicUsize,
+}
+
+impl StageProfile {
+    /// Number of items the stage has handled
+    pub fn processed(&self) -> usize {
+        self.processed.load(Ordering::Relaxed)
+    }
+
+    /// Largest number of items observed waiting in the stage's input queue
+    pub fn max_queue_depth(&self) -> usize {
+        self.max_queue_depth.load(Ordering::Relaxed)
+    }
+
+    fn record_item(&self) {
+        self.processed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_depth(&self, depth: usize) {
+        self.max_queue_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+}
+
+/// Per-stage counters collected during a pipeline run
+#[derive(Debug, Default)]
+pub struct PipelineProfile {
+    pub discover: StageProfile,
+    pub parse: StageProfile,
+    pub query: StageProfile,
+    pub upload: StageProfile,
+    /// Line counts of the files that were parsed
+    code_stats: std::sync::Mutex<CodeStats>,
+}
+
+impl PipelineProfile {
+    /// Code, comment and blank lines by language over the files the run parsed
+    pub fn code_stats(&self) -> CodeStats {
+        self.code_stats.lock().unwrap().clone()
+    }
+
+    /// Print items processed and peak input queue depth for each stage
+    pub fn print(&self) {
+        println!("📊 Pipeline profile:");
+        let stages = [
+            ("discover", &self.discover),
+            ("parse", &self.parse),
+            ("query", &self.query),
+            ("upload", &self.upload),
+        ];
+        for (name, stage) in stages {
+            println!("  {:<8} processed {:>6}, max queue depth {:>4}", name, stage.processed(), stage.max_queue_depth());
+        }
+    }
+}
+
+/// A file that went through the parse stage; `parsed` is None if it failed to parse
+struct ParsedEntry {
+    path: String,
+    lang: &'static str,
+    parsed: Option<ParsedFile>,
+}
+
+/// All matches found in one file, and its line counts if it parsed
+struct FileResults {
+    path: String,
+    lang: &'static str,
+    results: Vec<MatchResult>,
+    lines: Option<LineCounts>,
+}
+
+/// Send an item downstream and record the receiving stage's queue depth.
+/// Returns false once the receiving stage has shut down.
+async fn send_tracked<T>(tx: &mpsc::Sender<T>, item: T, capacity: usize, stage: &StageProfile) -> bool {
+    if tx.send(item).await.is_err() {
+        return false;
+    }
+    stage.record_depth(capacity - tx.capacity());
+    true
+}
+
+/// Queue an upload of the evidence for `queries` built from `results`
+fn spawn_upload(uploads: &mut JoinSet<Result<(), String>>, scanner: &Scanner, queries: Vec<TreeSitterQuery>, results: Vec<MatchResult>) {
+    if queries.is_empty() {
+        return;
+    }
+
+    let scanner = scanner.clone();
+    uploads.spawn(async move {
+        scanner.post_query_results(&queries, &results).await.map_err(|e| e.to_string())
+    });
+}
+
+impl Scanner {
+    /// Scan files with a staged pipeline connected by bounded channels.
+    ///
+    /// Files flow through discover, parse, and query stages, each with its own workers,
+    /// so a slow stage only applies backpressure to the one before it. Evidence for a
+    /// query is uploaded as soon as every file it targets has been queried, while other
+    /// files are still being parsed.
+    pub async fn run_pipeline(&self, files: Vec<String>, queries: Vec<TreeSitterQuery>, config: &PipelineConfig) -> Result<(Vec<MatchResult>, Arc<PipelineProfile>), Box<dyn Error>> {
+        let capacity = config.channel_capacity.max(1);
+        let profile = Arc::new(PipelineProfile::default());
+        let aborted = Arc::new(AtomicBool::new(false));
+        let queries = Arc::new(queries);
+        let clock = self.engine().clock().cloned();
+        if let Some(clock) = &clock {
+            clock.begin(ScanPhase::Parse);
+            clock.begin(ScanPhase::Query);
+            clock.begin(ScanPhase::Upload);
+        }
+
+        // Only files in a supported language enter the pipeline
+        let mut supported: Vec<(String, &'static str)> = Vec::with_capacity(files.len());
+        for file in files {
+            if let Some(lang) = self.language_for_file_async(&file).await {
+                supported.push((file, lang));
+            }
+        }
+        let files = supported;
+        let progress = self.engine().progress();
+        if let Some(progress) = progress {
+            progress.started(files.len());
+        }
+
+        // Number of files each query targets, so the upload stage knows when a query is complete
+        let router = QueryRouter::new(&queries);
+        let mut remaining = vec![0usize; queries.len()];
+        for (file, lang) in &files {
+            for index in router.route(file, Some(*lang)) {
+                remaining[index] += 1;
+            }
+        }
+
+        let (path_tx, path_rx) = mpsc::channel::<(String, &'static str)>(capacity);
+        let (parsed_tx, parsed_rx) = mpsc::channel::<ParsedEntry>(capacity);
+        let (results_tx, mut results_rx) = mpsc::channel::<FileResults>(capacity);
+        let mut stages = JoinSet::new();
+
+        // Discover: feed supported files into the parse queue, stopping early on fail-fast,
+        // shutdown, or once parsing or querying runs out of time
+        {
+            let profile = profile.clone();
+            let aborted = aborted.clone();
+            let shutdown = self.shutdown().clone();
+            let clock = clock.clone();
+            stages.spawn(async move {
+                let out_of_time = || clock.as_ref().is_some_and(|clock| clock.check(ScanPhase::Parse) || clock.check(ScanPhase::Query));
+                for item in files {
+                    if aborted.load(Ordering::Relaxed) || shutdown.is_triggered() || out_of_time() {
+                        break;
+                    }
+                    profile.discover.record_item();
+                    if !send_tracked(&path_tx, item, capacity, &profile.parse).await {
+                        break;
+                    }
+                }
+            });
+        }
+
+        // Parse: load trees from the AST cache or parse them
+        let path_rx = Arc::new(Mutex::new(path_rx));
+        for _ in 0..config.parse_workers.max(1) {
+            let scanner = self.clone();
+            let rx = path_rx.clone();
+            let tx = parsed_tx.clone();
+            let profile = profile.clone();
+            let aborted = aborted.clone();
+
+            stages.spawn(async move {
+                loop {
+                    let next = rx.lock().await.recv().await;
+                    let (path, lang) = match next {
+                        Some(item) => item,
+                        None => break,
+                    };
+                    if aborted.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    // Out of time: drain the queue without parsing, so the discover stage isn't blocked
+                    if scanner.engine().out_of_time(ScanPhase::Parse) || scanner.engine().out_of_time(ScanPhase::Query) {
+                        continue;
+                    }
+
+                    let parsed = scanner.load_tree(&path, lang).await;
+                    profile.parse.record_item();
+
+                    let file = ParsedEntry { path, lang, parsed };
+                    if !send_tracked(&tx, file, capacity, &profile.query).await {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(parsed_tx);
+
+        // Query: run the relevant queries against each tree on the blocking pool
+        let parsed_rx = Arc::new(Mutex::new(parsed_rx));
+        for _ in 0..config.query_workers.max(1) {
+            let scanner = self.clone();
+            let rx = parsed_rx.clone();
+            let tx = results_tx.clone();
+            let queries = queries.clone();
+            let profile = profile.clone();
+            let aborted = aborted.clone();
+
+            stages.spawn(async move {
+                loop {
+                    let next = rx.lock().await.recv().await;
+                    let file = match next {
+                        Some(file) => file,
+                        None => break,
+                    };
+
+                    if scanner.engine().out_of_time(ScanPhase::Query) {
+                        continue;
+                    }
+
+                    let ParsedEntry { path, lang, parsed } = file;
+                    let (results, lines) = match parsed {
+                        Some(parsed) => {
+                            let scanner = scanner.clone();
+                            let queries = queries.clone();
+                            let path = path.clone();
+                            tokio::task::spawn_blocking(move || {
+                                let (tree, source) = &*parsed;
+                                let router = QueryRouter::new(&queries);
+                                let results = scanner.engine().match_file(&path, lang, tree, source, &router);
+                                (results, Some(stats::count_lines(tree, source)))
+                            })
+                            .await
+                            .unwrap_or_default()
+                        }
+                        None => (Vec::new(), None),
+                    };
+
+                    if results.iter().any(|r| scanner.fails_fast_on(r.severity)) {
+                        warn!("⛔ Fail-fast: stopping scan after blocking finding");
+                        aborted.store(true, Ordering::Relaxed);
+                    }
+                    profile.query.record_item();
+
+                    if !send_tracked(&tx, FileResults { path, lang, results, lines }, capacity, &profile.upload).await {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(results_tx);
+
+        // Upload: post evidence for a query once every file it targets has been queried
+        let mut pending_results: HashMap<String, Vec<MatchResult>> = HashMap::new();
+        let mut uploaded = vec![false; queries.len()];
+        let mut all_results = Vec::new();
+        let mut code_stats = CodeStats::default();
+        let mut uploads = JoinSet::new();
+
+        while let Some(file) = results_rx.recv().await {
+            profile.upload.record_item();
+            if let Some(progress) = progress {
+                progress.file_scanned(file.results.len());
+            }
+            if let Some(lines) = file.lines {
+                code_stats.record(file.lang, lines);
+            }
+            all_results.extend(file.results.iter().cloned());
+            for result in file.results {
+                pending_results.entry(result.question_id.clone()).or_default().push(result);
+            }
+
+            for index in router.route(&file.path, Some(file.lang)) {
+                remaining[index] -= 1;
+                if remaining[index] == 0 {
+                    let query = router.query(index);
+                    let results = pending_results.get(&query.question_id).cloned().unwrap_or_default();
+                    spawn_upload(&mut uploads, self, vec![query.clone()], results);
+                    uploaded[index] = true;
+                }
+            }
+        }
+
+        // Queries that targeted no files, or didn't finish because of fail-fast or shutdown.
+        // Unfinished queries are only flushed if they matched, so that queries which never
+        // ran aren't reported as having no matches.
+        for (index, done) in uploaded.iter().enumerate() {
+            if *done {
+                continue;
+            }
+
+            let query = router.query(index);
+            let results = pending_results.get(&query.question_id).cloned().unwrap_or_default();
+            if remaining[index] == 0 || !results.is_empty() {
+                spawn_upload(&mut uploads, self, vec![query.clone()], results);
+            }
+        }
+
+        while let Some(joined) = stages.join_next().await {
+            if let Err(e) = joined {
+                error!("Pipeline stage failed: {}", e);
+            }
+        }
+        if let Some(progress) = progress {
+            progress.finished();
+        }
+        self.end_run();
+        *profile.code_stats.lock().unwrap() = code_stats;
+
+        // Uploads still running when the scan or upload phase runs out of time are abandoned
+        let mut first_error = None;
+        loop {
+            let next = match clock.as_ref().and_then(|clock| clock.remaining(Some(ScanPhase::Upload))) {
+                Some(remaining) => match tokio::time::timeout(remaining, uploads.join_next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        if let Some(clock) = &clock {
+                            // Records which limit ran out
+                            clock.check(ScanPhase::Upload);
+                        }
+                        warn!("⏰ Abandoning {} unfinished evidence uploads", uploads.len());
+                        uploads.abort_all();
+                        break;
+                    }
+                },
+                None => uploads.join_next().await,
+            };
+            let Some(joined) = next else {
+                break;
+            };
+            let outcome = match joined {
+                Ok(result) => result,
+                Err(e) => Err(e.to_string()),
+            };
+
+            if let Err(e) = outcome
+                && first_error.is_none()
+            {
+                first_error = Some(e);
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e.into()),
+            None => Ok((all_results, profile)),
+        }
+    }
+}
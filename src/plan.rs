@@ -0,0 +1,124 @@
+use crate::engine::{file_type_for, Engine, QueryRouter};
+use crate::types::TreeSitterQuery;
+use serde::Serialize;
+use std::{collections::BTreeMap, fs};
+
+/// What a scan would do, worked out without parsing files or contacting the API beyond
+/// reading queries, so that include and exclude settings can be checked before a long run
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanPlan {
+    pub files: Vec<PlannedFile>,
+    /// Files grouped by extension and language, with the queries that would run on them
+    pub file_types: Vec<FileTypePlan>,
+    /// Requests the scan would send, in order
+    pub requests: Vec<PlannedRequest>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedFile {
+    pub path: String,
+    /// Language the file would be parsed as; unset files are skipped
+    pub language: Option<&'static str>,
+    pub bytes: u64,
+    /// Whether the file would be skipped for exceeding the size limit
+    pub too_large: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileTypePlan {
+    /// Extension including the dot, or empty for extensionless files
+    pub file_type: String,
+    pub language: Option<&'static str>,
+    pub files: usize,
+    /// Question IDs of the queries that apply; `None` if the queries aren't known before the
+    /// scan starts
+    pub queries: Option<Vec<String>>,
+}
+
+/// An API request a scan would send
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedRequest {
+    pub method: &'static str,
+    pub url: String,
+    /// How many times the request is sent, e.g. once per query; unset if that depends on
+    /// queries that aren't known yet
+    pub count: Option<usize>,
+    /// Size of the JSON body in bytes, when it can be known without scanning
+    pub body_bytes: Option<usize>,
+    pub purpose: String,
+}
+
+impl ScanPlan {
+    /// Plan a scan of `files` with `engine`'s language and size settings. Without `queries`,
+    /// the files are planned without the queries that would run on them.
+    pub fn new(engine: &Engine, files: &[String], queries: Option<&[TreeSitterQuery]>) -> Self {
+        let router = queries.map(QueryRouter::new);
+        let mut file_types: BTreeMap<(String, Option<&'static str>), FileTypePlan> = BTreeMap::new();
+
+        let files: Vec<PlannedFile> = files.iter()
+            .map(|path| {
+                let language = engine.get_language_for_file(path);
+                let bytes = engine.sources()
+                    .and_then(|sources| sources.size(path))
+                    .or_else(|| fs::metadata(path).ok().map(|metadata| metadata.len()))
+                    .unwrap_or_default();
+                let too_large = engine.max_file_size().is_some_and(|limit| bytes > limit);
+
+                let file_type = file_type_for(path);
+                file_types.entry((file_type.clone(), language))
+                    .or_insert_with(|| FileTypePlan {
+                        file_type,
+                        language,
+                        files: 0,
+                        queries: router.as_ref().map(|router| {
+                            router.queries_for(path, language).into_iter().map(|query| query.question_id.clone()).collect()
+                        }),
+                    })
+                    .files += 1;
+
+                PlannedFile { path: path.clone(), language, bytes, too_large }
+            })
+            .collect();
+
+        Self { files, file_types: file_types.into_values().collect(), requests: Vec::new() }
+    }
+
+    /// Add the requests the scan would send
+    pub fn with_requests(mut self, requests: Vec<PlannedRequest>) -> Self {
+        self.requests = requests;
+        self
+    }
+
+    /// Print the plan for a terminal
+    pub fn print(&self) {
+        println!("📂 {} files would be scanned:", self.files.iter().filter(|file| file.language.is_some() && !file.too_large).count());
+        for file in &self.files {
+            let note = match file.language {
+                None => " (skipped: language not supported or disabled)".to_string(),
+                Some(_) if file.too_large => " (skipped: over the size limit)".to_string(),
+                Some(language) => format!(" [{}]", language),
+            };
+            println!("  {} — {} bytes{}", file.path, file.bytes, note);
+        }
+
+        println!("\n📋 Queries by file type:");
+        for file_type in &self.file_types {
+            let name = if file_type.file_type.is_empty() { "(no extension)" } else { &file_type.file_type };
+            let language = file_type.language.unwrap_or("unsupported");
+            match &file_type.queries {
+                Some(queries) if queries.is_empty() => println!("  {} ({}, {} files): no queries", name, language, file_type.files),
+                Some(queries) => println!("  {} ({}, {} files): {}", name, language, file_type.files, queries.join(", ")),
+                None => println!("  {} ({}, {} files): queries are fetched once the scan starts", name, language, file_type.files),
+            }
+        }
+
+        if !self.requests.is_empty() {
+            println!("\n🌐 API requests:");
+            for request in &self.requests {
+                let size = request.body_bytes.map(|bytes| format!(", {} byte body", bytes)).unwrap_or_default();
+                let count = request.count.map(|count| count.to_string()).unwrap_or_else(|| "n".to_string());
+                println!("  {} × {} {}{} — {}", count, request.method, request.url, size, request.purpose);
+            }
+        }
+    }
+}
@@ -0,0 +1,256 @@
+use crate::color::{self, Color};
+use crate::engine::Engine;
+use crate::language_loader::get_language;
+use crate::rules;
+use crate::types::{CaptureResult, Severity, TreeSitterQuery};
+use std::{error::Error, fmt::Write as _, fs, io::{self, BufRead, Write}, path::Path};
+use tree_sitter::{Query, Tree};
+
+/// Longest leaf text shown inline in the AST view
+const MAX_LEAF_TEXT: usize = 40;
+
+const HELP: &str = "\
+Type a query to run it against the file; it may span several lines until its parentheses balance.
+  :ast                      show the file's syntax tree
+  :history                  list the queries run so far
+  !N                        run query N from the history again
+  :save <file> <id> [sev]   save the last query as a rule in a local queries file
+  :help                     show this help
+  :quit                     leave the playground";
+
+/// One file loaded for interactively writing and testing queries
+pub struct Playground {
+    engine: Engine,
+    path: String,
+    language: &'static str,
+    tree: Tree,
+    source: String,
+    history: Vec<String>,
+}
+
+impl Playground {
+    /// Load and parse a file, using the language implied by its extension
+    pub fn open(path: &str) -> Result<Self, Box<dyn Error>> {
+        let engine = Engine::new(String::new(), String::new()).with_quiet(true);
+        let language = engine.get_language_for_file(path).ok_or_else(|| format!("Unsupported file type: {}", path))?;
+        let source = fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+
+        Self::from_source(path, language, source)
+    }
+
+    /// Parse in-memory source as if it were the file at `path`
+    pub fn from_source(path: &str, language: &'static str, source: String) -> Result<Self, Box<dyn Error>> {
+        let engine = Engine::new(String::new(), String::new()).with_quiet(true);
+        let (tree, source) = engine.parse_source(source, language)
+            .ok_or_else(|| format!("Failed to parse {} as {}", path, language))?;
+
+        Ok(Self { engine, path: path.to_string(), language, tree, source, history: Vec::new() })
+    }
+
+    pub fn language(&self) -> &'static str {
+        self.language
+    }
+
+    /// Queries run so far, oldest first
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// The named nodes of the syntax tree, one per line and indented by depth, with field
+    /// names, positions and the text of leaves
+    pub fn ast(&self) -> String {
+        let mut out = String::new();
+        let mut cursor = self.tree.walk();
+        let mut depth = 0;
+
+        loop {
+            let node = cursor.node();
+            if node.is_named() {
+                let start = node.start_position();
+                let end = node.end_position();
+                let field = cursor.field_name().map(|f| format!("{}: ", f)).unwrap_or_default();
+                let _ = write!(out, "{}{}{} [{}:{}-{}:{}]", "  ".repeat(depth), field, node.kind(), start.row + 1, start.column + 1, end.row + 1, end.column + 1);
+                if node.named_child_count() == 0 {
+                    let text = &self.source[node.start_byte()..node.end_byte()];
+                    if !text.contains('\n') && text.len() <= MAX_LEAF_TEXT {
+                        let _ = write!(out, " {:?}", text);
+                    }
+                }
+                out.push('\n');
+            }
+
+            if cursor.goto_first_child() {
+                depth += 1;
+                continue;
+            }
+            while !cursor.goto_next_sibling() {
+                if !cursor.goto_parent() {
+                    return out;
+                }
+                depth -= 1;
+            }
+        }
+    }
+
+    /// Run a query against the file and record it in the history. Fails with the compiler's
+    /// message if the query is invalid for the file's language.
+    pub fn run(&mut self, query: &str) -> Result<Vec<CaptureResult>, String> {
+        let language = get_language(self.language).ok_or_else(|| format!("Unsupported language: {}", self.language))?;
+        Query::new(language, query).map_err(|e| e.to_string())?;
+
+        self.history.push(query.to_string());
+        Ok(self.engine.run_query_on_tree(&self.tree, &self.source, query, self.language))
+    }
+
+    /// The source lines holding captures, each followed by a line marking the captured text
+    pub fn highlight(&self, captures: &[CaptureResult]) -> String {
+        let lines: Vec<&str> = self.source.lines().collect();
+        let mut out = String::new();
+
+        for capture in captures {
+            let (line, column) = capture.position;
+            let Some(text) = lines.get(line - 1) else {
+                continue;
+            };
+            let first_line = capture.value.lines().next().unwrap_or_default();
+            let width = first_line.len().clamp(1, text.len().saturating_sub(column - 1).max(1));
+            let more = if capture.value.contains('\n') { " …" } else { "" };
+
+            let _ = writeln!(out, "{:>5} | {}", line, text);
+            let _ = writeln!(
+                out,
+                "      | {}{} @{}{}",
+                " ".repeat(column - 1),
+                color::paint("^".repeat(width), Color::Red),
+                capture.name,
+                more
+            );
+        }
+
+        out
+    }
+
+    /// Save the most recent query as a rule for this file's language in a local queries file,
+    /// replacing any rule there with the same question ID
+    pub fn save(&self, rules_path: &Path, question_id: &str, severity: Severity) -> Result<TreeSitterQuery, Box<dyn Error>> {
+        let query = self.history.last().ok_or("No query to save yet")?;
+        let rule = TreeSitterQuery {
+            question_id: question_id.to_string(),
+            file_type: self.language.to_string(),
+            query: query.clone(),
+            severity,
+            ..Default::default()
+        };
+
+        rules::save_query(rules_path, rule.clone())?;
+        Ok(rule)
+    }
+
+    /// Read queries and commands from `input` until it ends or `:quit`, writing results to `output`
+    pub fn repl(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        writeln!(output, "🧪 {} ({}), {} lines. Type :help for commands.", self.path, self.language, self.source.lines().count())?;
+        let mut pending = String::new();
+        let mut lines = input.lines();
+
+        loop {
+            write!(output, "{}", if pending.is_empty() { "query> " } else { "   ... " })?;
+            output.flush()?;
+            let Some(line) = lines.next().transpose()? else {
+                return Ok(());
+            };
+
+            if pending.is_empty() {
+                let command = line.trim();
+                if command.is_empty() {
+                    continue;
+                }
+                if command.starts_with(':') || command.starts_with('!') {
+                    if !self.command(command, &mut output)? {
+                        return Ok(());
+                    }
+                    continue;
+                }
+            }
+
+            pending.push_str(&line);
+            pending.push('\n');
+            if is_balanced(&pending) {
+                let query = std::mem::take(&mut pending);
+                self.show_matches(query.trim(), &mut output)?;
+            }
+        }
+    }
+
+    /// Handle one `:` or `!` command. Returns false on `:quit`.
+    fn command(&mut self, command: &str, output: &mut impl Write) -> io::Result<bool> {
+        let mut words = command.split_whitespace();
+        match words.next().unwrap_or_default() {
+            ":quit" | ":q" | ":exit" => return Ok(false),
+            ":help" | ":h" => writeln!(output, "{}", HELP)?,
+            ":ast" => write!(output, "{}", self.ast())?,
+            ":history" => {
+                for (i, query) in self.history.iter().enumerate() {
+                    writeln!(output, "{:>3}: {}", i + 1, query.replace('\n', " "))?;
+                }
+            }
+            ":save" => {
+                let (Some(file), Some(id)) = (words.next(), words.next()) else {
+                    writeln!(output, "Usage: :save <file> <question_id> [severity]")?;
+                    return Ok(true);
+                };
+                let saved = words.next().unwrap_or("medium").parse::<Severity>()
+                    .map_err(|e| e.into())
+                    .and_then(|severity| self.save(Path::new(file), id, severity));
+                match saved {
+                    Ok(rule) => writeln!(output, "💾 Saved {} to {}", rule.question_id, file)?,
+                    Err(e) => writeln!(output, "❌ {}", e)?,
+                }
+            }
+            other => match other.strip_prefix('!').and_then(|n| n.parse::<usize>().ok()) {
+                Some(n) if (1..=self.history.len()).contains(&n) => {
+                    let query = self.history[n - 1].clone();
+                    self.show_matches(&query, output)?;
+                }
+                _ => writeln!(output, "Unknown command {}; type :help", other)?,
+            },
+        }
+        Ok(true)
+    }
+
+    fn show_matches(&mut self, query: &str, output: &mut impl Write) -> io::Result<()> {
+        match self.run(query) {
+            Ok(captures) => {
+                write!(output, "{}", self.highlight(&captures))?;
+                writeln!(output, "{} captures", captures.len())
+            }
+            Err(e) => writeln!(output, "❌ Invalid query: {}", e),
+        }
+    }
+}
+
+/// Whether the parentheses and brackets outside string literals are balanced
+fn is_balanced(query: &str) -> bool {
+    let mut depth: i32 = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for c in query.chars() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            _ => {}
+        }
+    }
+
+    depth <= 0 && !in_string
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+/// Shortest delay the server can ask for
+pub const MIN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Longest delay the server can ask for
+pub const MAX_POLL_INTERVAL: Duration = Duration::from_secs(300);
+/// Idle polls back off up to this many times the configured interval
+const MAX_BACKOFF_FACTOR: u32 = 8;
+
+/// What a query-fetch response says about when to poll next
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PollHint {
+    /// Delay the server suggests before the next poll, from the `nextPollSeconds` field
+    /// of the response body or else its `Poll-Interval` header
+    pub next_poll: Option<Duration>,
+    /// Whether the server is still generating queries for the report (`moreQueriesPending`)
+    pub more_pending: bool,
+}
+
+impl PollHint {
+    /// Read the hint from a query-fetch response's body and `Poll-Interval` header value
+    pub fn from_response(body: &serde_json::Value, poll_interval_header: Option<&str>) -> Self {
+        let next_poll = body["nextPollSeconds"].as_f64()
+            .or_else(|| poll_interval_header.and_then(|h| h.trim().parse::<f64>().ok()))
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .map(Duration::from_secs_f64);
+        let more_pending = body["moreQueriesPending"].as_bool().unwrap_or(false);
+
+        Self { next_poll, more_pending }
+    }
+}
+
+/// Delay between polls: the server's suggestion when it gives one, otherwise doubling
+/// while polls bring no new queries and resetting to the configured interval when they do
+#[derive(Debug, Clone)]
+pub struct PollSchedule {
+    base: Duration,
+    max: Duration,
+    current: Duration,
+}
+
+impl PollSchedule {
+    pub fn new(base: Duration) -> Self {
+        Self { base, max: base * MAX_BACKOFF_FACTOR, current: base }
+    }
+
+    /// The delay before the next poll, given the last poll's hint and whether it brought
+    /// new or changed queries
+    pub fn next_delay(&mut self, hint: &PollHint, found_new: bool) -> Duration {
+        if let Some(delay) = hint.next_poll {
+            self.current = self.base;
+            return delay.clamp(MIN_POLL_INTERVAL, MAX_POLL_INTERVAL);
+        }
+        if hint.more_pending {
+            self.current = self.base;
+            return MIN_POLL_INTERVAL.min(self.base);
+        }
+
+        if found_new {
+            self.current = self.base;
+        } else {
+            self.current = (self.current * 2).min(self.max);
+        }
+        self.current
+    }
+}
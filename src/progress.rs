@@ -0,0 +1,63 @@
+use crate::engine::ScanProgress;
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::{
+    io::{self, IsTerminal},
+    sync::{atomic::{AtomicUsize, Ordering}, Mutex},
+};
+
+/// The bar being drawn, if any, so that log lines can be written around it
+static ACTIVE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+const TEMPLATE: &str = "{spinner} {bar:40} {pos}/{len} files · {msg} · ETA {eta}";
+
+/// Progress bar on stdout counting scanned files and matches, drawn afresh for every scan
+pub struct ScanProgressBar {
+    matches: AtomicUsize,
+}
+
+impl ScanProgressBar {
+    /// A progress bar, or `None` when stdout isn't a terminal
+    pub fn for_terminal() -> Option<Self> {
+        io::stdout().is_terminal().then(|| ScanProgressBar { matches: AtomicUsize::new(0) })
+    }
+}
+
+impl ScanProgress for ScanProgressBar {
+    fn started(&self, files: usize) {
+        self.matches.store(0, Ordering::Relaxed);
+        let bar = ProgressBar::with_draw_target(Some(files as u64), ProgressDrawTarget::stdout());
+        if let Ok(style) = ProgressStyle::with_template(TEMPLATE) {
+            bar.set_style(style.progress_chars("=> "));
+        }
+        bar.set_message("0 matches");
+        if let Some(previous) = ACTIVE.lock().unwrap().replace(bar) {
+            previous.finish_and_clear();
+        }
+    }
+
+    fn file_scanned(&self, matches: usize) {
+        let total = self.matches.fetch_add(matches, Ordering::Relaxed) + matches;
+        if let Some(bar) = ACTIVE.lock().unwrap().as_ref() {
+            bar.inc(1);
+            if matches > 0 {
+                bar.set_message(format!("{} matches", total));
+            }
+        }
+    }
+
+    fn finished(&self) {
+        if let Some(bar) = ACTIVE.lock().unwrap().take() {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+/// Run `f` with the progress bar, if one is drawn, hidden, so that output written by `f`
+/// doesn't tear it
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    let bar = ACTIVE.lock().unwrap().clone();
+    match bar {
+        Some(bar) => bar.suspend(f),
+        None => f(),
+    }
+}
@@ -0,0 +1,144 @@
+use crate::gate::FailOn;
+use crate::language_loader::canonical_language;
+use crate::timeout::Timeouts;
+use crate::types::{Severity, TreeSitterQuery};
+use serde::Deserialize;
+use std::{collections::BTreeMap, error::Error, fs, path::{Path, PathBuf}};
+
+/// Name of the per-repository settings file, looked up from the target directory upwards
+pub const PROJECT_FILE: &str = "magma.toml";
+
+/// Settings a repository checks in next to its code, so that every run over it scans the same
+/// way. Command line flags and environment variables take precedence over everything here.
+///
+/// ```toml
+/// [api]
+/// url = "https://api.example.com/v1"
+/// organization_id = "org_1"
+///
+/// [scan]
+/// exclude = ["**/generated/**"]
+/// fail_on = "high:3"
+///
+/// [scan.timeouts]
+/// total = "30m"
+/// parse = "10m"
+///
+/// [languages]
+/// php = false
+///
+/// [rules."style/todo"]
+/// enabled = false
+///
+/// [rules."secrets/hardcoded-token"]
+/// severity = "critical"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    pub api: ApiSettings,
+    pub scan: ScanSettings,
+    /// Languages switched on or off by name; languages not listed stay on
+    pub languages: BTreeMap<String, bool>,
+    pub rules: RuleOverrides,
+}
+
+/// Where to reach the API. API keys never go in this file, only in flags, the environment or
+/// the profiles of the user's config file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiSettings {
+    pub url: Option<String>,
+    pub organization_id: Option<String>,
+    /// Profile of the user's config file to connect with
+    pub profile: Option<String>,
+}
+
+/// Defaults for the scan flags of the same names
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanSettings {
+    pub include: Vec<String>,
+    /// Globs of files to skip, added to the target's `.magmaignore`
+    pub exclude: Vec<String>,
+    pub fail_on: Option<FailOn>,
+    pub fail_fast: Option<Severity>,
+    pub max_complexity: Option<usize>,
+    pub detect_pii: bool,
+    pub injections: bool,
+    /// Limits of the whole scan and of its phases, for `--timeout` and `--phase-timeout`
+    pub timeouts: Timeouts,
+}
+
+/// Adjustments to individual rules by question ID
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(transparent)]
+pub struct RuleOverrides(BTreeMap<String, RuleOverride>);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleOverride {
+    /// Run the rule at all
+    pub enabled: bool,
+    /// Severity reported for the rule's findings instead of the one it was published with
+    pub severity: Option<Severity>,
+}
+
+impl Default for RuleOverride {
+    fn default() -> Self {
+        Self { enabled: true, severity: None }
+    }
+}
+
+impl RuleOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Drop disabled rules and change the severity of overridden ones
+    pub fn apply(&self, queries: Vec<TreeSitterQuery>) -> Vec<TreeSitterQuery> {
+        queries.into_iter()
+            .filter_map(|mut query| {
+                if let Some(rule) = self.0.get(&query.question_id) {
+                    if !rule.enabled {
+                        return None;
+                    }
+                    if let Some(severity) = rule.severity {
+                        query.severity = severity;
+                    }
+                }
+                Some(query)
+            })
+            .collect()
+    }
+}
+
+impl ProjectConfig {
+    /// Parse the contents of a `magma.toml`
+    pub fn parse(content: &str) -> Result<Self, Box<dyn Error>> {
+        let config: Self = toml::from_str(content)?;
+        config.disabled_languages()?;
+        Ok(config)
+    }
+
+    /// Load the `magma.toml` in `dir` or its closest ancestor that has one, along with its path
+    pub fn find(dir: &Path) -> Result<Option<(PathBuf, Self)>, Box<dyn Error>> {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        let Some(path) = dir.ancestors().map(|dir| dir.join(PROJECT_FILE)).find(|path| path.is_file()) else {
+            return Ok(None);
+        };
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let config = Self::parse(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+        Ok(Some((path, config)))
+    }
+
+    /// Canonical names of the languages switched off
+    pub fn disabled_languages(&self) -> Result<Vec<&'static str>, Box<dyn Error>> {
+        self.languages.iter()
+            .filter(|(_, enabled)| !**enabled)
+            .map(|(name, _)| canonical_language(name).ok_or_else(|| format!("unknown language: {}", name).into()))
+            .collect()
+    }
+}
@@ -0,0 +1,60 @@
+use regex::Regex;
+use std::sync::{OnceLock, RwLock};
+
+/// Text that replaces a redacted secret
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Shorter values are too likely to occur in ordinary output to be replaced everywhere
+const MIN_SECRET_LEN: usize = 6;
+
+/// Secrets registered for redaction, such as the resolved API key
+static SECRETS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Register a secret so that `redact` removes it from any text shown or written to disk
+pub fn register(secret: &str) {
+    let secret = secret.trim();
+    if secret.len() < MIN_SECRET_LEN {
+        return;
+    }
+    let mut secrets = SECRETS.write().unwrap_or_else(|e| e.into_inner());
+    if !secrets.iter().any(|s| s == secret) {
+        secrets.push(secret.to_string());
+        // Replace longer secrets first, so one containing another is removed whole
+        secrets.sort_by_key(|s| std::cmp::Reverse(s.len()));
+    }
+}
+
+/// Remove registered secrets and credential-looking values (authorization headers, `api_key=`
+/// style parameters) from text before it is logged
+pub fn redact(text: &str) -> String {
+    static CREDENTIAL: OnceLock<Regex> = OnceLock::new();
+    let credential = CREDENTIAL.get_or_init(|| {
+        Regex::new(r#"(?i)(\b(?:APIKey|Bearer|Basic)\s+|\b(?:api[_-]?key|token|secret|password)\s*[=:]\s*"?)[^\s"&,;]+"#).unwrap()
+    });
+
+    let mut text = text.to_string();
+    for secret in SECRETS.read().unwrap_or_else(|e| e.into_inner()).iter() {
+        if text.contains(secret.as_str()) {
+            text = text.replace(secret.as_str(), REDACTED);
+        }
+    }
+
+    credential.replace_all(&text, |caps: &regex::Captures| {
+        if &caps[0][caps[1].len()..] == REDACTED {
+            caps[0].to_string()
+        } else {
+            format!("{}{}", &caps[1], REDACTED)
+        }
+    }).into_owned()
+}
+
+/// A secret as it may be shown to confirm which one is in use: only its last four
+/// characters, and only when it is long enough that they give nothing away
+pub fn mask(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() < 16 {
+        return "****".to_string();
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("****{}", tail)
+}
@@ -0,0 +1,141 @@
+use crate::color::{self, Color};
+use crate::results::ScanResults;
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+};
+
+/// Renders the results of a scan in one output format
+pub trait Reporter {
+    fn report(&self, results: &ScanResults, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Output formats selectable with `--format`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// One line per finding followed by a summary, for people reading a terminal
+    #[default]
+    Console,
+    /// The whole `ScanResults` document, for other tools
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "console" => Ok(ReportFormat::Console),
+            "json" => Ok(ReportFormat::Json),
+            other => Err(format!("unknown report format: {} (expected console or json)", other)),
+        }
+    }
+}
+
+impl ReportFormat {
+    /// The reporter implementing this format
+    pub fn reporter(self) -> Box<dyn Reporter> {
+        match self {
+            ReportFormat::Console => Box::new(ConsoleReporter),
+            ReportFormat::Json => Box::new(JsonReporter),
+        }
+    }
+}
+
+/// Where a report is written with `--output`: a file, or stdout for "-"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReportOutput {
+    Stdout,
+    File(PathBuf),
+}
+
+impl FromStr for ReportOutput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err("output path is empty".to_string()),
+            "-" => Ok(ReportOutput::Stdout),
+            path => Ok(ReportOutput::File(PathBuf::from(path))),
+        }
+    }
+}
+
+impl ReportOutput {
+    /// Render `results` in `format` to this output. Files are replaced atomically, so a
+    /// failed or interrupted run never leaves a truncated report behind.
+    pub fn write(&self, format: ReportFormat, results: &ScanResults) -> io::Result<()> {
+        match self {
+            ReportOutput::Stdout => {
+                let stdout = io::stdout();
+                let mut out = stdout.lock();
+                format.reporter().report(results, &mut out)?;
+                out.flush()
+            }
+            ReportOutput::File(path) => write_atomic(path, |out| format.reporter().report(results, out)),
+        }
+    }
+}
+
+/// Write a file through a temporary file next to it that is renamed over `path` once
+/// everything was written and synced, creating missing parent directories. Readers see
+/// either the old file or the whole new one.
+pub fn write_atomic(path: &Path, write: impl FnOnce(&mut dyn Write) -> io::Result<()>) -> io::Result<()> {
+    let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    fs::create_dir_all(parent)?;
+    let name = path.file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a file path", path.display())))?;
+    let temp = parent.join(format!(".{}.{}.tmp", name.to_string_lossy(), process::id()));
+
+    let written = (|| {
+        let mut out = BufWriter::new(File::create(&temp)?);
+        write(&mut out)?;
+        out.into_inner().map_err(io::IntoInnerError::into_error)?.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+/// Lists findings as `[severity] file:line:column rule — "text"` and then counts per rule
+pub struct ConsoleReporter;
+
+impl Reporter for ConsoleReporter {
+    fn report(&self, results: &ScanResults, out: &mut dyn Write) -> io::Result<()> {
+        for finding in &results.findings {
+            writeln!(
+                out,
+                "  [{}] {}:{}:{} {} — \"{}\"",
+                color::severity(finding.severity),
+                finding.file,
+                finding.line,
+                finding.column,
+                finding.question_id,
+                finding.text
+            )?;
+        }
+
+        let summary = &results.summary;
+        let headline = format!("{} findings in {} files", summary.findings, summary.files_scanned);
+        let color = if summary.findings == 0 { Color::Green } else { Color::Yellow };
+        writeln!(out, "📊 {}", color::paint(headline, color))?;
+        for (rule, count) in &summary.by_rule {
+            writeln!(out, "  {:>5}  {}", count, rule)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the results as one pretty-printed JSON document
+pub struct JsonReporter;
+
+impl Reporter for JsonReporter {
+    fn report(&self, results: &ScanResults, out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(results.to_json()?.as_bytes())
+    }
+}
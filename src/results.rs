@@ -0,0 +1,100 @@
+use crate::bundle::BundleSummary;
+use crate::types::{CaptureResult, MatchResult, Severity};
+use serde::{Deserialize, Serialize};
+
+/// Version of the `ScanResults` layout, bumped on incompatible changes
+pub const RESULTS_FORMAT_VERSION: u32 = 1;
+
+/// Everything a local scan produced, written as one JSON document for other tools to consume
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanResults {
+    pub format_version: u32,
+    /// Version of the scanner that produced the results
+    pub scanner_version: String,
+    pub organization_id: String,
+    pub code_base_version: String,
+    pub branch_name: String,
+    pub repo_url: String,
+    /// Directory or bare repository that was scanned
+    pub target: String,
+    /// Unix time in seconds
+    pub started_at: u64,
+    pub finished_at: u64,
+    /// Question IDs of the queries that ran
+    pub queries: Vec<String>,
+    pub summary: BundleSummary,
+    pub findings: Vec<MatchResult>,
+}
+
+/// A completed report as the API returns it, with the evidence uploaded for each query
+#[derive(Debug, Clone, Deserialize)]
+pub struct StoredReport {
+    pub report_id: String,
+    #[serde(default)]
+    pub code_base_version: String,
+    #[serde(default)]
+    pub branch_name: String,
+    #[serde(default)]
+    pub repo_url: String,
+    #[serde(default)]
+    pub evidence: Vec<StoredEvidence>,
+}
+
+/// The evidence uploaded for one query
+#[derive(Debug, Clone, Deserialize)]
+pub struct StoredEvidence {
+    pub question_id: String,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default)]
+    pub sampled: bool,
+    pub evidence: Vec<CaptureResult>,
+}
+
+impl ScanResults {
+    /// Serialize the results as pretty-printed JSON ending in a newline
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+
+    /// Rebuild the results of a completed report from its uploaded evidence, so they can be
+    /// rendered again without rescanning. "No matches" records produce no findings, and
+    /// evidence uploaded without file paths leaves the file empty. Reports don't record how
+    /// many files were scanned or when, so those are zero.
+    pub fn from_report(organization_id: &str, report: StoredReport) -> Self {
+        let queries: Vec<String> = report.evidence.iter().map(|evidence| evidence.question_id.clone()).collect();
+        let findings: Vec<MatchResult> = report.evidence.iter()
+            .flat_map(|evidence| evidence.evidence.iter()
+                .filter(|capture| capture.name != "no_match")
+                .map(|capture| MatchResult {
+                    file: capture.file.clone().unwrap_or_default(),
+                    line: capture.position.0,
+                    column: capture.position.1,
+                    text: capture.value.clone(),
+                    question_id: evidence.question_id.clone(),
+                    organization_id: organization_id.to_string(),
+                    code_base_version: report.code_base_version.clone(),
+                    severity: evidence.severity,
+                    sampled: evidence.sampled,
+                    category: None,
+                    covered: None,
+                    tags: Vec::new(),
+                }))
+            .collect();
+
+        Self {
+            format_version: RESULTS_FORMAT_VERSION,
+            scanner_version: env!("CARGO_PKG_VERSION").to_string(),
+            organization_id: organization_id.to_string(),
+            summary: BundleSummary::new(0, &findings),
+            code_base_version: report.code_base_version,
+            branch_name: report.branch_name,
+            target: report.repo_url.clone(),
+            repo_url: report.repo_url,
+            started_at: 0,
+            finished_at: 0,
+            queries,
+            findings,
+        }
+    }
+}
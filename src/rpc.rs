@@ -0,0 +1,309 @@
+use crate::discovery::find_files_quietly;
+use crate::engine::{CacheMode, Engine};
+use crate::rules::load_queries_file;
+use crate::types::{MatchResult, TreeSitterQuery};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    sync::mpsc::{self, UnboundedSender},
+};
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Server-defined error for requests that fail while being carried out
+const REQUEST_FAILED: i64 = -32000;
+
+/// State of a scan started over RPC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanStatus {
+    Running,
+    Completed,
+    Cancelled,
+}
+
+struct ScanState {
+    status: ScanStatus,
+    results: Vec<MatchResult>,
+    cancel: Arc<AtomicBool>,
+}
+
+/// The currently loaded rules and the file they came from
+#[derive(Default)]
+struct Rules {
+    path: Option<PathBuf>,
+    queries: Arc<Vec<TreeSitterQuery>>,
+}
+
+/// One unit of work for a scan: a file on disk or an in-memory source
+enum ScanInput {
+    File(String),
+    Source(String, String),
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanParams {
+    /// Directory to discover files in
+    #[serde(default)]
+    target: Option<String>,
+    #[serde(default)]
+    files: Vec<String>,
+    #[serde(default)]
+    sources: Vec<SourceParam>,
+}
+
+#[derive(Deserialize)]
+struct SourceParam {
+    path: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScanIdParams {
+    scan_id: u64,
+}
+
+#[derive(Default, Deserialize)]
+struct ReloadRulesParams {
+    #[serde(default)]
+    path: Option<PathBuf>,
+}
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into() }
+    }
+}
+
+/// A long-lived JSON-RPC 2.0 server driving local scans.
+///
+/// Methods: `reloadRules {path?}`, `scan {target? | files? | sources?}` (returns a scan ID
+/// immediately), `cancel {scanId}` and `getResults {scanId}`. Scans report `scan/progress`
+/// and `scan/finished` notifications through the outgoing channel.
+#[derive(Clone)]
+pub struct RpcServer {
+    engine: Engine,
+    rules: Arc<Mutex<Rules>>,
+    scans: Arc<Mutex<HashMap<u64, ScanState>>>,
+    next_scan_id: Arc<AtomicU64>,
+    out: UnboundedSender<Value>,
+}
+
+impl RpcServer {
+    /// Create a server sending notifications to `out`. Files are re-read on every scan,
+    /// since they may change while the server is running.
+    pub fn new(out: UnboundedSender<Value>) -> Self {
+        Self {
+            engine: Engine::new(String::new(), String::new())
+                .with_cache_mode(CacheMode::Disabled)
+                .with_quiet(true),
+            rules: Arc::new(Mutex::new(Rules::default())),
+            scans: Arc::new(Mutex::new(HashMap::new())),
+            next_scan_id: Arc::new(AtomicU64::new(1)),
+            out,
+        }
+    }
+
+    /// Handle one line of input, returning the response to send, if any.
+    /// Notifications (requests without an `id`) get no response.
+    pub fn handle_line(&self, line: &str) -> Option<Value> {
+        let request: Value = match serde_json::from_str(line) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string()))),
+        };
+
+        let id = request.get("id").cloned();
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(method) => method,
+            None => return Some(error_response(id.unwrap_or(Value::Null), RpcError::new(INVALID_REQUEST, "Missing method"))),
+        };
+        let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+
+        let outcome = match method {
+            "reloadRules" => self.reload_rules(params),
+            "scan" => self.start_scan(params),
+            "cancel" => self.cancel(params),
+            "getResults" => self.get_results(params),
+            other => Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", other))),
+        };
+
+        let id = id?;
+        Some(match outcome {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, e),
+        })
+    }
+
+    /// Cancel every running scan, e.g. when the client disconnects
+    pub fn cancel_all(&self) {
+        for scan in self.scans.lock().unwrap().values() {
+            scan.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn reload_rules(&self, params: Value) -> Result<Value, RpcError> {
+        let params: ReloadRulesParams = parse_params(params)?;
+        let mut rules = self.rules.lock().unwrap();
+
+        let path = params.path.or_else(|| rules.path.clone())
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "No rules file loaded yet; pass a path"))?;
+        let queries = load_queries_file(&path).map_err(|e| RpcError::new(REQUEST_FAILED, e.to_string()))?;
+
+        let count = queries.len();
+        rules.path = Some(path);
+        rules.queries = Arc::new(queries);
+        Ok(json!({ "rules": count }))
+    }
+
+    fn start_scan(&self, params: Value) -> Result<Value, RpcError> {
+        let params: ScanParams = parse_params(params)?;
+
+        let mut inputs: Vec<ScanInput> = params.files.into_iter().map(ScanInput::File).collect();
+        if let Some(target) = &params.target {
+            let files = find_files_quietly(target).map_err(|e| RpcError::new(REQUEST_FAILED, e.to_string()))?;
+            inputs.extend(files.into_iter().map(ScanInput::File));
+        }
+        inputs.extend(params.sources.into_iter().map(|s| ScanInput::Source(s.path, s.content)));
+
+        let queries = self.rules.lock().unwrap().queries.clone();
+        if queries.is_empty() {
+            return Err(RpcError::new(REQUEST_FAILED, "No rules loaded; call reloadRules first"));
+        }
+
+        let scan_id = self.next_scan_id.fetch_add(1, Ordering::Relaxed);
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.scans.lock().unwrap().insert(scan_id, ScanState {
+            status: ScanStatus::Running,
+            results: Vec::new(),
+            cancel: cancel.clone(),
+        });
+
+        let server = self.clone();
+        tokio::spawn(async move { server.run_scan(scan_id, inputs, queries, cancel).await });
+
+        Ok(json!({ "scanId": scan_id }))
+    }
+
+    async fn run_scan(&self, scan_id: u64, inputs: Vec<ScanInput>, queries: Arc<Vec<TreeSitterQuery>>, cancel: Arc<AtomicBool>) {
+        let total = inputs.len();
+
+        for (index, input) in inputs.into_iter().enumerate() {
+            if cancel.load(Ordering::Relaxed) {
+                break;
+            }
+
+            let engine = self.engine.clone();
+            let queries = queries.clone();
+            let results = tokio::task::spawn_blocking(move || match input {
+                ScanInput::File(path) => engine.scan_files(&[path], &queries),
+                ScanInput::Source(path, content) => engine.scan_sources(vec![(path, content)], &queries),
+            })
+            .await
+            .unwrap_or_default();
+
+            let findings = results.len();
+            if let Some(scan) = self.scans.lock().unwrap().get_mut(&scan_id) {
+                scan.results.extend(results);
+            }
+            self.notify("scan/progress", json!({ "scanId": scan_id, "scanned": index + 1, "total": total, "findings": findings }));
+        }
+
+        let status = if cancel.load(Ordering::Relaxed) { ScanStatus::Cancelled } else { ScanStatus::Completed };
+        let findings = match self.scans.lock().unwrap().get_mut(&scan_id) {
+            Some(scan) => {
+                scan.status = status;
+                scan.results.len()
+            }
+            None => 0,
+        };
+        self.notify("scan/finished", json!({ "scanId": scan_id, "status": status, "findings": findings }));
+    }
+
+    fn cancel(&self, params: Value) -> Result<Value, RpcError> {
+        let params: ScanIdParams = parse_params(params)?;
+        let scans = self.scans.lock().unwrap();
+        let scan = scans.get(&params.scan_id)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Unknown scan {}", params.scan_id)))?;
+
+        let running = scan.status == ScanStatus::Running;
+        if running {
+            scan.cancel.store(true, Ordering::Relaxed);
+        }
+        Ok(json!({ "cancelled": running }))
+    }
+
+    fn get_results(&self, params: Value) -> Result<Value, RpcError> {
+        let params: ScanIdParams = parse_params(params)?;
+        let scans = self.scans.lock().unwrap();
+        let scan = scans.get(&params.scan_id)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Unknown scan {}", params.scan_id)))?;
+
+        Ok(json!({ "scanId": params.scan_id, "status": scan.status, "results": scan.results }))
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        // The client may already be gone; there is nobody left to tell
+        let _ = self.out.send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+    }
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": error.code, "message": error.message } })
+}
+
+/// Serve JSON-RPC requests read line by line from stdin, writing one JSON message per line
+/// to stdout, until stdin is closed
+pub async fn serve() -> Result<(), Box<dyn Error>> {
+    let (out_tx, mut out_rx) = mpsc::unbounded_channel::<Value>();
+
+    let writer = tokio::spawn(async move {
+        let mut stdout = tokio::io::stdout();
+        while let Some(message) = out_rx.recv().await {
+            let line = format!("{}\n", message);
+            if stdout.write_all(line.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                break;
+            }
+        }
+    });
+
+    let server = RpcServer::new(out_tx.clone());
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_line(&line) {
+            let _ = out_tx.send(response);
+        }
+    }
+
+    server.cancel_all();
+    drop(server);
+    drop(out_tx);
+    writer.await?;
+    Ok(())
+}
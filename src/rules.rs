@@ -0,0 +1,242 @@
+use crate::types::{FileTarget, MatchResult, TreeSitterQuery};
+use crate::validate::check_query;
+use serde::Serialize;
+use std::{collections::{BTreeMap, BTreeSet, HashMap}, error::Error, fmt, fs, path::{Path, PathBuf}};
+use walkdir::WalkDir;
+
+/// Load query definitions from a local JSON file, or YAML if it ends in `.yml` or `.yaml`.
+///
+/// The file may contain either a bare array of queries or an object with a
+/// `TreeSitterQueries` array, matching the shape returned by the API.
+pub fn load_queries_file(path: &Path) -> Result<Vec<TreeSitterQuery>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read queries file {}: {}", path.display(), e))?;
+
+    if is_yaml(path) {
+        parse_queries_yaml(&content)
+    } else {
+        parse_queries(&content)
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yml") || ext.eq_ignore_ascii_case("yaml"))
+}
+
+/// Parse query definitions from JSON in either of the shapes accepted by `load_queries_file`
+pub fn parse_queries(content: &str) -> Result<Vec<TreeSitterQuery>, Box<dyn Error>> {
+    queries_from_value(serde_json::from_str(content)?)
+}
+
+/// Parse query definitions from YAML in either of the shapes accepted by `load_queries_file`
+pub fn parse_queries_yaml(content: &str) -> Result<Vec<TreeSitterQuery>, Box<dyn Error>> {
+    queries_from_value(serde_yaml::from_str(content)?)
+}
+
+fn queries_from_value(value: serde_json::Value) -> Result<Vec<TreeSitterQuery>, Box<dyn Error>> {
+    let queries = match value.get("TreeSitterQueries") {
+        Some(list) => serde_json::from_value(list.clone())?,
+        None => serde_json::from_value(value)?,
+    };
+
+    Ok(queries)
+}
+
+/// Add a query to a local queries file, replacing any query with the same question ID.
+/// The file is created if missing and keeps its shape (bare array or `TreeSitterQueries` object).
+pub fn save_query(path: &Path, query: TreeSitterQuery) -> Result<(), Box<dyn Error>> {
+    let (mut queries, wrapped) = if path.exists() {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read queries file {}: {}", path.display(), e))?;
+        (parse_queries(&content)?, content.contains("\"TreeSitterQueries\""))
+    } else {
+        (Vec::new(), false)
+    };
+
+    match queries.iter_mut().find(|q| q.question_id == query.question_id) {
+        Some(existing) => *existing = query,
+        None => queries.push(query),
+    }
+
+    let content = if wrapped {
+        serde_json::to_string_pretty(&serde_json::json!({ "TreeSitterQueries": queries }))?
+    } else {
+        serde_json::to_string_pretty(&queries)?
+    };
+    fs::write(path, content + "\n")
+        .map_err(|e| format!("Failed to write queries file {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// A rule and the file it was loaded from
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadedRule {
+    pub source: PathBuf,
+    pub query: TreeSitterQuery,
+}
+
+/// Load the rules of local rule files and downloaded rule packs. Directories are searched for
+/// `.json`, `.yml` and `.yaml` files; files are loaded whatever their extension.
+pub fn load_rules(paths: &[PathBuf]) -> Result<Vec<LoadedRule>, Box<dyn Error>> {
+    let mut rules = Vec::new();
+    for path in paths {
+        let files: Vec<PathBuf> = if path.is_dir() {
+            let mut files: Vec<PathBuf> = WalkDir::new(path)
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_file())
+                .map(|entry| entry.into_path())
+                .filter(|file| is_yaml(file) || file.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")))
+                .collect();
+            files.sort();
+            files
+        } else if path.exists() {
+            vec![path.clone()]
+        } else {
+            return Err(format!("Rule file not found: {}", path.display()).into());
+        };
+
+        for file in files {
+            let queries = load_queries_file(&file)?;
+            rules.extend(queries.into_iter().map(|query| LoadedRule { source: file.clone(), query }));
+        }
+    }
+    Ok(rules)
+}
+
+/// Something wrong with a rule that would make it fail or silently match nothing in a scan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleProblem {
+    pub source: PathBuf,
+    pub question_id: String,
+    pub message: String,
+}
+
+impl fmt::Display for RuleProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.source.display(), self.question_id, self.message)
+    }
+}
+
+/// Check rules for missing IDs, IDs defined more than once, file types with no grammar, and
+/// queries that don't compile or capture nothing for the languages they target
+pub fn lint_rules(rules: &[LoadedRule]) -> Vec<RuleProblem> {
+    let mut problems = Vec::new();
+    let mut seen: HashMap<&str, &Path> = HashMap::new();
+
+    for rule in rules {
+        let query = &rule.query;
+        let mut problem = |message: String| problems.push(RuleProblem {
+            source: rule.source.clone(),
+            question_id: query.question_id.clone(),
+            message,
+        });
+
+        if query.question_id.trim().is_empty() {
+            problem("missing question_id".to_string());
+        } else if let Some(first) = seen.get(query.question_id.as_str()) {
+            problem(format!("question_id is also defined in {}", first.display()));
+        } else {
+            seen.insert(&query.question_id, &rule.source);
+        }
+
+        let targets = query.file_targets();
+        if targets.is_empty() {
+            problem("file_type names no language or extension".to_string());
+        }
+        for target in targets {
+            let language = match target {
+                FileTarget::Language(language) => language,
+                FileTarget::Extension(extension) => {
+                    problem(format!("no grammar for {} files, so the rule never runs", extension));
+                    continue;
+                }
+            };
+            match check_query(language, &query.query) {
+                Ok(check) => match check.error {
+                    Some(error) => problem(format!("does not compile for {}: {}", language, error)),
+                    None if check.captures.is_empty() => problem(format!("captures nothing in {}, so it can never produce findings", language)),
+                    None => {}
+                },
+                Err(error) => problem(error),
+            }
+        }
+    }
+    problems
+}
+
+/// Lines of a test fixture that must match, and lines that must not, per question ID
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FixtureExpectations {
+    pub matches: BTreeMap<String, BTreeSet<usize>>,
+    pub non_matches: BTreeMap<String, BTreeSet<usize>>,
+}
+
+impl FixtureExpectations {
+    /// Read the annotations of a fixture. A comment containing `ruleid: <question_id>` expects
+    /// the rule to match the next line that isn't an annotation; `ok: <question_id>` expects it
+    /// not to. Several IDs can be separated by commas.
+    pub fn parse(source: &str) -> Self {
+        let mut expectations = Self::default();
+        let mut pending: Vec<(bool, String)> = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let annotations = annotations(line);
+            if !annotations.is_empty() {
+                pending.extend(annotations);
+                continue;
+            }
+            for (expect_match, question_id) in pending.drain(..) {
+                let lines = if expect_match { &mut expectations.matches } else { &mut expectations.non_matches };
+                lines.entry(question_id).or_default().insert(index + 1);
+            }
+        }
+        expectations
+    }
+
+    /// Question IDs the fixture has annotations for
+    pub fn question_ids(&self) -> BTreeSet<&str> {
+        self.matches.keys().chain(self.non_matches.keys()).map(String::as_str).collect()
+    }
+
+    /// Compare a scan of the fixture with its annotations. Only annotated rules are checked,
+    /// and each of their matches must be on a `ruleid` line.
+    pub fn check(&self, results: &[MatchResult]) -> Vec<String> {
+        let mut failures = Vec::new();
+        for question_id in self.question_ids() {
+            let found: BTreeSet<usize> = results.iter()
+                .filter(|result| result.question_id == question_id)
+                .map(|result| result.line)
+                .collect();
+            let expected = self.matches.get(question_id).cloned().unwrap_or_default();
+
+            for line in expected.difference(&found) {
+                failures.push(format!("line {}: expected {} to match", line, question_id));
+            }
+            for line in found.difference(&expected) {
+                failures.push(format!("line {}: {} matched unexpectedly", line, question_id));
+            }
+        }
+        failures
+    }
+}
+
+fn annotations(line: &str) -> Vec<(bool, String)> {
+    for (marker, expect_match) in [("ruleid:", true), ("ok:", false)] {
+        if let Some(index) = line.find(marker) {
+            let before = line[..index].trim_end();
+            if !(before.ends_with("//") || before.ends_with('#') || before.ends_with("--") || before.ends_with("/*") || before.ends_with("<!--")) {
+                continue;
+            }
+            let ids = line[index + marker.len()..].trim().trim_end_matches("-->").trim_end_matches("*/");
+            return ids.split(',')
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(|id| (expect_match, id.to_string()))
+                .collect();
+        }
+    }
+    Vec::new()
+}
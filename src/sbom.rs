@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+use walkdir::WalkDir;
+
+/// A third-party package the scanned code depends on
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Component {
+    /// Package ecosystem: "cargo", "npm" or "pypi"
+    pub ecosystem: String,
+    pub name: String,
+    pub version: String,
+    /// Lockfile the component was read from, relative to the scanned root
+    pub source: String,
+}
+
+/// List the dependencies pinned in the lockfiles under `root`: `Cargo.lock`,
+/// `package-lock.json` and `requirements.txt`. Unreadable lockfiles are skipped.
+pub fn collect_components(root: &Path) -> Vec<Component> {
+    let mut components = Vec::new();
+
+    for entry in WalkDir::new(root).into_iter().filter_entry(|e| e.file_name() != "node_modules" && e.file_name() != ".git").flatten() {
+        let path = entry.path();
+        let Ok(content) = fs::read_to_string(path) else {
+            continue;
+        };
+        let source = path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+
+        let found = match entry.file_name().to_str() {
+            Some("Cargo.lock") => parse_cargo_lock(&content),
+            Some("package-lock.json") => parse_package_lock(&content),
+            Some("requirements.txt") => parse_requirements(&content),
+            _ => continue,
+        };
+        components.extend(found.into_iter().map(|(ecosystem, name, version)| Component {
+            ecosystem: ecosystem.to_string(),
+            name,
+            version,
+            source: source.clone(),
+        }));
+    }
+
+    components.sort();
+    components.dedup();
+    components
+}
+
+/// `[[package]]` entries of a Cargo lockfile
+fn parse_cargo_lock(content: &str) -> Vec<(&'static str, String, String)> {
+    let mut packages = Vec::new();
+    let mut name = None;
+
+    for line in content.lines().map(str::trim) {
+        if line == "[[package]]" {
+            name = None;
+        } else if let Some(value) = line.strip_prefix("name = ") {
+            name = Some(value.trim_matches('"').to_string());
+        } else if let Some(value) = line.strip_prefix("version = ")
+            && let Some(name) = name.take()
+        {
+            packages.push(("cargo", name, value.trim_matches('"').to_string()));
+        }
+    }
+
+    packages
+}
+
+/// Installed packages of an npm lockfile (v2/v3 `packages` map)
+fn parse_package_lock(content: &str) -> Vec<(&'static str, String, String)> {
+    let Ok(lock) = serde_json::from_str::<serde_json::Value>(content) else {
+        return Vec::new();
+    };
+    let Some(packages) = lock.get("packages").and_then(|p| p.as_object()) else {
+        return Vec::new();
+    };
+
+    packages.iter()
+        .filter_map(|(path, package)| {
+            // Keys are install paths like "node_modules/a/node_modules/b"; "" is the root project
+            let name = path.rsplit("node_modules/").next().filter(|n| !n.is_empty() && path.contains("node_modules/"))?;
+            let version = package.get("version")?.as_str()?;
+            Some(("npm", name.to_string(), version.to_string()))
+        })
+        .collect()
+}
+
+/// Pinned `name==version` lines of a pip requirements file
+fn parse_requirements(content: &str) -> Vec<(&'static str, String, String)> {
+    content.lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter_map(|line| line.split_once("=="))
+        .map(|(name, version)| ("pypi", name.trim().to_string(), version.split(';').next().unwrap_or_default().trim().to_string()))
+        .collect()
+}
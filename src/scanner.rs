@@ -1,15 +1,88 @@
-use crate::types::{TreeSitterQuery, MatchResult, CaptureResult, ApiResponse};
-use crate::language_loader::get_language;
-use std::{collections::HashMap, fs, path::PathBuf, sync::{Arc, Mutex}, time::Duration, env};
-use tree_sitter::{Parser, Query, QueryCursor, Tree};
+use crate::types::{TreeSitterQuery, MatchResult, CaptureResult, ApiKeyInfo, ApiResponse, Severity};
+use crate::trends::ReportSummary;
+use crate::results::StoredReport;
+use crate::stats::CodeStats;
+use crate::coverage::Coverage;
+#[cfg(feature = "store")]
+use crate::store::ResultsStore;
+#[cfg(feature = "encryption")]
+use crate::encryption::EvidenceEncryptor;
+#[cfg(feature = "scripting")]
+use crate::scripting::ResultTransform;
+use crate::manifest::ScanManifest;
+use crate::polling::{PollHint, PollSchedule};
+use crate::project::RuleOverrides;
+use crate::redact;
+use crate::dead_letter::{self, DeadLetter};
+use crate::engine::{parse_with_language, AstCache, CacheLookup, Engine, FileStamp, QueryCache, QueryRouter, ScanProgress, SourceFiles};
+use crate::pipeline::PipelineConfig;
+use crate::plan::PlannedRequest;
+use crate::shutdown::{self, Checkpoint, ScanInterrupted, ShutdownSignal};
+use crate::timeout::ScanClock;
+use crate::incremental;
+use crate::gate::FindingsAboveThreshold;
+use std::{collections::{BTreeMap, HashMap, HashSet}, error::Error, fmt, path::{Path, PathBuf}, sync::{Arc, Mutex}, time::{Duration, SystemTime}, env};
+use tree_sitter::Tree;
 use reqwest::{Client, header};
 use serde_json::json;
-use std::thread;
+use tokio::{sync::Semaphore, task::JoinSet};
+use tracing::{debug, error, info, info_span, warn, Instrument};
+
+pub use crate::engine::{CacheMode, ParsedFile};
+
+/// Default number of concurrent evidence uploads per scanner
+const DEFAULT_UPLOAD_CONCURRENCY: usize = 4;
+
+/// Default number of attempts for each evidence upload before it is dead-lettered
+const DEFAULT_UPLOAD_ATTEMPTS: u32 = 3;
+
+/// Delay before the first upload retry; doubled on each further retry
+const UPLOAD_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Response header in which the API may suggest how many seconds to wait before the next poll
+const POLL_INTERVAL_HEADER: &str = "Poll-Interval";
+
+/// Returned when the Magma API could not be reached or rejected a request, wrapping the cause
+#[derive(Debug)]
+pub struct ApiFailure(pub Box<dyn Error>);
+
+impl fmt::Display for ApiFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "API request failed: {}", self.0)
+    }
+}
+
+impl Error for ApiFailure {}
+
+/// Caches and limits shared by scanners working on several targets in one process
+#[derive(Clone)]
+pub struct SharedResources {
+    ast_cache: AstCache,
+    query_cache: QueryCache,
+    /// Global cap on concurrent evidence uploads across all targets
+    upload_slots: Arc<Semaphore>,
+}
+
+impl SharedResources {
+    /// Create shared resources allowing at most `max_uploads` concurrent uploads in total
+    pub fn new(max_uploads: usize) -> Self {
+        Self {
+            ast_cache: Arc::new(Mutex::new(HashMap::new())),
+            query_cache: Arc::new(Mutex::new(HashMap::new())),
+            upload_slots: Arc::new(Semaphore::new(max_uploads.max(1))),
+        }
+    }
+}
 
 /// A scanner that caches parsed ASTs to avoid re-parsing files when new queries are received
+#[derive(Clone)]
 pub struct Scanner {
-    /// Cache of parsed ASTs by file path
-    ast_cache: Arc<Mutex<HashMap<String, (Tree, String)>>>,
+    /// Parsing and query engine holding the AST and compiled query caches
+    engine: Engine,
+    /// Cap on this scanner's concurrent evidence uploads
+    target_upload_slots: Arc<Semaphore>,
+    /// Cap on concurrent evidence uploads shared with other scanners
+    shared_upload_slots: Option<Arc<Semaphore>>,
     /// HTTP client for API requests
     client: Client,
     /// API key for authentication
@@ -22,6 +95,30 @@ pub struct Scanner {
     code_base_version: String,
     /// Base URL for API requests
     api_base_url: String,
+    /// Stage concurrency for the scan pipeline
+    pipeline: PipelineConfig,
+    /// Parse all files into the AST cache while the first queries are being fetched
+    warm_up: bool,
+    /// Set when the process is asked to shut down
+    shutdown: ShutdownSignal,
+    /// Directory for partial reports and checkpoints written on shutdown
+    state_dir: PathBuf,
+    /// Attempts per evidence upload before it is written to the dead-letter file
+    upload_attempts: u32,
+    /// JSONL file receiving uploads that failed every attempt
+    dead_letter_path: PathBuf,
+    /// Build a manifest of the scanned files when the scan is finalized
+    scan_manifest: bool,
+    /// Organization key the manifest is signed with
+    manifest_key: Option<Vec<u8>>,
+    /// Rules disabled or given another severity as they are fetched
+    rule_overrides: RuleOverrides,
+    /// SQLite database recording each poll's findings, and the target they were scanned from
+    #[cfg(feature = "store")]
+    results_store: Option<(PathBuf, String)>,
+    /// Encrypts evidence before it is uploaded or dead-lettered
+    #[cfg(feature = "encryption")]
+    evidence_encryptor: Option<Arc<EvidenceEncryptor>>,
 }
 
 impl Scanner {
@@ -35,16 +132,256 @@ impl Scanner {
         });
 
         Self {
-            ast_cache: Arc::new(Mutex::new(HashMap::new())),
+            engine: Engine::new(organization_id.clone(), code_base_version.clone()),
+            target_upload_slots: Arc::new(Semaphore::new(DEFAULT_UPLOAD_CONCURRENCY)),
+            shared_upload_slots: None,
             client,
             api_key,
             organization_id,
             report_id,
             code_base_version,
             api_base_url,
+            pipeline: PipelineConfig::default(),
+            warm_up: false,
+            shutdown: ShutdownSignal::new(),
+            state_dir: PathBuf::from(".magma"),
+            upload_attempts: DEFAULT_UPLOAD_ATTEMPTS,
+            dead_letter_path: PathBuf::from(".magma/dead-letter.jsonl"),
+            scan_manifest: false,
+            manifest_key: None,
+            rule_overrides: RuleOverrides::default(),
+            #[cfg(feature = "store")]
+            results_store: None,
+            #[cfg(feature = "encryption")]
+            evidence_encryptor: None,
+        }
+    }
+
+    /// Build a manifest of every scanned file with its hash when the scan is finalized, write it
+    /// to the state directory and upload it with the finalize call, signed with `key` if given
+    pub fn with_scan_manifest(mut self, key: Option<Vec<u8>>) -> Self {
+        self.scan_manifest = true;
+        self.manifest_key = key;
+        self
+    }
+
+    /// Send API requests to `url` instead of the `API_BASE_URL` environment variable's value
+    pub fn with_api_base_url(mut self, url: impl Into<String>) -> Self {
+        self.api_base_url = url.into();
+        self
+    }
+
+    /// Try each evidence upload up to `attempts` times before dead-lettering it
+    pub fn with_upload_attempts(mut self, attempts: u32) -> Self {
+        self.upload_attempts = attempts.max(1);
+        self
+    }
+
+    /// Write uploads that fail every attempt to this JSONL file
+    pub fn with_dead_letter_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.dead_letter_path = path.as_ref().to_path_buf();
+        self
+    }
+
+    /// Record each poll's findings in the SQLite database at `path`, fingerprinted relative to `target`
+    #[cfg(feature = "store")]
+    pub fn with_results_store(mut self, path: impl AsRef<Path>, target: &str) -> Self {
+        self.results_store = Some((path.as_ref().to_path_buf(), target.to_string()));
+        self
+    }
+
+    /// Encrypt the evidence of every upload with the organization's public key
+    #[cfg(feature = "encryption")]
+    pub fn with_evidence_encryption(mut self, encryptor: EvidenceEncryptor) -> Self {
+        self.evidence_encryptor = Some(Arc::new(encryptor));
+        self
+    }
+
+    /// Suppress the per-file messages printed while parsing and matching
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.engine = self.engine.with_quiet(quiet);
+        self
+    }
+
+    /// Stop scanning gracefully once `signal` is triggered
+    pub fn with_shutdown(mut self, signal: ShutdownSignal) -> Self {
+        self.shutdown = signal;
+        self
+    }
+
+    /// Stop scanning once the scan or one of its phases runs out of time, reporting what
+    /// finished like an interrupted scan
+    pub fn with_clock(mut self, clock: ScanClock) -> Self {
+        self.engine = self.engine.with_clock(clock);
+        self
+    }
+
+    /// Write partial reports and checkpoints into `dir` when a scan is interrupted
+    pub fn with_state_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.state_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// The shutdown signal observed by this scanner
+    pub(crate) fn shutdown(&self) -> &ShutdownSignal {
+        &self.shutdown
+    }
+
+    /// Control whether and for how long parsed ASTs are cached
+    pub fn with_cache_mode(mut self, mode: CacheMode) -> Self {
+        self.engine = self.engine.with_cache_mode(mode);
+        self
+    }
+
+    /// The parsing and query engine used by this scanner
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    /// Number of files currently held in the AST cache
+    pub fn cached_files(&self) -> usize {
+        self.engine.cached_files()
+    }
+
+    /// Drop every cached AST
+    pub fn clear_cache(&self) {
+        self.engine.clear_cache();
+    }
+
+    /// Drop the cached ASTs of `files`, e.g. once this target's scan finished and the cache is
+    /// shared with others that are still scanning
+    pub fn evict_files(&self, files: &[String]) {
+        for file in files {
+            self.engine.invalidate(file);
         }
     }
 
+    /// Called when a scan run finishes; drops the cache if it is scoped to a single run
+    pub(crate) fn end_run(&self) {
+        self.engine.end_run();
+    }
+
+    /// Pre-parse every file into the AST cache while the first poll's queries are in flight,
+    /// so the first query run hits a warm cache
+    pub fn with_warm_up(mut self, enabled: bool) -> Self {
+        self.warm_up = enabled;
+        self
+    }
+
+    /// Skip files larger than `bytes`, or none at all for `None`
+    pub fn with_max_file_size(mut self, bytes: Option<u64>) -> Self {
+        self.engine = self.engine.with_max_file_size(bytes);
+        self
+    }
+
+    /// Parse and query at most `threads` files at once in each pipeline stage, instead of one per core
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        let threads = threads.max(1);
+        self.pipeline.parse_workers = threads;
+        self.pipeline.query_workers = threads;
+        self.engine = self.engine.with_threads(threads);
+        self
+    }
+
+    /// Report the progress of scans, including each poll's pipeline run, to `progress`
+    pub fn with_progress(mut self, progress: Arc<dyn ScanProgress>) -> Self {
+        self.engine = self.engine.with_progress(progress);
+        self
+    }
+
+    /// Set the worker counts and channel capacity of the scan pipeline
+    pub fn with_pipeline_config(mut self, config: PipelineConfig) -> Self {
+        self.pipeline = config;
+        self
+    }
+
+    /// Stop scanning as soon as a finding at or above `severity` is produced.
+    /// Findings collected up to that point are still returned and posted.
+    pub fn with_fail_fast(mut self, severity: Severity) -> Self {
+        self.engine = self.engine.with_fail_fast(severity);
+        self
+    }
+
+    /// Also report PII found in string literals. PII findings stay local and are never uploaded as evidence.
+    pub fn with_pii_detection(mut self, enabled: bool) -> Self {
+        self.engine = self.engine.with_pii_detection(enabled);
+        self
+    }
+
+    /// Also run queries on code embedded in other languages, like SQL in string literals
+    pub fn with_injections(mut self, enabled: bool) -> Self {
+        self.engine = self.engine.with_injections(enabled);
+        self
+    }
+
+    /// Decide the language of files with `resolve` before looking at their extension or contents
+    pub fn with_language_override(mut self, resolve: impl Fn(&str) -> Option<&'static str> + Send + Sync + 'static) -> Self {
+        self.engine = self.engine.with_language_override(resolve);
+        self
+    }
+
+    /// Skip files in these languages, given by canonical name
+    pub fn with_disabled_languages(mut self, languages: Vec<&'static str>) -> Self {
+        self.engine = self.engine.with_disabled_languages(languages);
+        self
+    }
+
+    /// Disable rules or change their severity as they are fetched
+    pub fn with_rule_overrides(mut self, overrides: RuleOverrides) -> Self {
+        self.rule_overrides = overrides;
+        self
+    }
+
+    /// Report functions whose cyclomatic complexity exceeds `threshold`
+    pub fn with_complexity_threshold(mut self, threshold: usize) -> Self {
+        self.engine = self.engine.with_complexity_threshold(threshold);
+        self
+    }
+
+    /// Scan these file contents by path instead of reading the files from disk
+    pub fn with_sources(mut self, sources: impl SourceFiles + 'static) -> Self {
+        self.engine = self.engine.with_sources(sources);
+        self
+    }
+
+    /// Record on each finding whether tests cover its line, and report untested files with critical findings
+    pub fn with_coverage(mut self, coverage: Coverage) -> Self {
+        self.engine = self.engine.with_coverage(coverage);
+        self
+    }
+
+    /// Run a script on every finding before it is reported or uploaded as evidence
+    #[cfg(feature = "scripting")]
+    pub fn with_transform(mut self, transform: ResultTransform) -> Self {
+        self.engine = self.engine.with_transform(transform);
+        self
+    }
+
+    /// Keep at most `limit` matches per file for queries the API sends without a `sample`
+    pub fn with_sample(mut self, limit: usize) -> Self {
+        self.engine = self.engine.with_sample(limit);
+        self
+    }
+
+    /// Share the AST cache, compiled queries, and global upload cap with other scanners.
+    /// Scanners given in-memory sources keep an AST cache of their own.
+    pub fn with_shared_resources(mut self, shared: &SharedResources) -> Self {
+        self.engine = self.engine.with_caches(shared.ast_cache.clone(), shared.query_cache.clone());
+        self.shared_upload_slots = Some(shared.upload_slots.clone());
+        self
+    }
+
+    /// Limit how many evidence uploads this scanner runs at once
+    pub fn with_upload_concurrency(mut self, max_uploads: usize) -> Self {
+        self.target_upload_slots = Arc::new(Semaphore::new(max_uploads.max(1)));
+        self
+    }
+
+    /// Whether a finding of this severity aborts the scan under fail-fast mode
+    pub(crate) fn fails_fast_on(&self, severity: Severity) -> bool {
+        self.engine.fails_fast_on(severity)
+    }
+
     /// Initialize a code scan and get a report ID
     pub async fn initialize_code_scan(&mut self, file_types: Vec<String>, commit_hash: &str, branch_name: &str, repo_url: &str) -> Result<String, Box<dyn std::error::Error>> {
         // If we already have a report ID, return it
@@ -52,20 +389,10 @@ impl Scanner {
             return Ok(report_id.clone());
         }
 
-        // Create a CSV string of the file extensions
-        let file_types_csv = file_types.join(",");
-        println!("File extensions (CSV): {}", file_types_csv);
-
         let url = format!("{}/org/{}/rpc/initiate-code-scan-report/", self.api_base_url, self.organization_id);
+        let request_body = initiate_request_body(&file_types, commit_hash, branch_name, repo_url);
 
-        let request_body = json!({
-            "file_types": file_types_csv,
-            "commit_hash": commit_hash,
-            "branch_name": branch_name,
-            "repo_url": repo_url
-        });
-
-        println!("Request body: {}", serde_json::to_string_pretty(&request_body).unwrap());
+        debug!("Request body: {}", serde_json::to_string_pretty(&request_body).unwrap());
 
         let response = self.client.post(&url)
             .header(header::CONTENT_TYPE, "application/json")
@@ -79,17 +406,63 @@ impl Scanner {
         }
 
         let data: ApiResponse = response.json().await?;
-        println!("✅ Code scan initialized successfully. Report ID: {}", data.report_id);
+        info!(report_id = %data.report_id, "✅ Code scan initialized successfully. Report ID: {}", data.report_id);
 
         self.report_id = Some(data.report_id.clone());
         Ok(data.report_id)
     }
 
+    /// The requests a scan of files with these extensions would send, for a dry run. `queries`
+    /// is the number of queries the scan would run, if known.
+    pub fn planned_requests(&self, file_types: &[String], commit_hash: &str, branch_name: &str, repo_url: &str, queries: Option<usize>) -> Vec<PlannedRequest> {
+        let org_url = format!("{}/org/{}", self.api_base_url, self.organization_id);
+        let report_id = self.report_id.clone().unwrap_or_else(|| "<report-id>".to_string());
+        let mut requests = Vec::new();
+
+        if self.report_id.is_none() {
+            let body = initiate_request_body(file_types, commit_hash, branch_name, repo_url);
+            requests.push(PlannedRequest {
+                method: "POST",
+                url: format!("{}/rpc/initiate-code-scan-report/", org_url),
+                count: Some(1),
+                body_bytes: Some(body.to_string().len()),
+                purpose: "initiate the report".to_string(),
+            });
+        }
+        requests.push(PlannedRequest {
+            method: "GET",
+            url: format!("{}/rpc/get-preloaded-queries/{}", org_url, report_id),
+            count: Some(1),
+            body_bytes: None,
+            purpose: "fetch the queries, repeated every poll".to_string(),
+        });
+        requests.push(PlannedRequest {
+            method: "POST",
+            url: format!("{}/evidence", org_url),
+            count: queries,
+            body_bytes: None,
+            purpose: "upload the evidence of each query, sized by its findings".to_string(),
+        });
+        requests.push(PlannedRequest {
+            method: "POST",
+            url: format!("{}/rpc/finalize-code-scan/{}", org_url, report_id),
+            count: Some(1),
+            body_bytes: None,
+            purpose: "mark the report completed with line counts".to_string(),
+        });
+        requests
+    }
+
     /// Fetch available queries for the current report
     pub async fn fetch_available_queries(&self) -> Result<Vec<TreeSitterQuery>, Box<dyn std::error::Error>> {
+        self.fetch_queries_with_hint().await.map(|(queries, _)| queries)
+    }
+
+    /// Fetch available queries for the current report, along with the server's hint on when to poll next
+    pub async fn fetch_queries_with_hint(&self) -> Result<(Vec<TreeSitterQuery>, PollHint), Box<dyn std::error::Error>> {
         let report_id = self.report_id.as_ref().ok_or("No report ID available")?;
 
-        println!("Fetching queries for report ID: {}", report_id);
+        debug!("Fetching queries for report ID: {}", report_id);
 
         let url = format!(
             "{}/org/{}/rpc/get-preloaded-queries/{}",
@@ -108,6 +481,9 @@ impl Scanner {
             return Err(format!("Error fetching queries: {}", response.status()).into());
         }
 
+        let poll_interval = response.headers().get(POLL_INTERVAL_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
         let data: serde_json::Value = response.json().await?;
         let queries = data["TreeSitterQueries"].as_array()
             .ok_or("Invalid response format")?
@@ -115,20 +491,129 @@ impl Scanner {
             .map(|q| serde_json::from_value(q.clone()))
             .collect::<Result<Vec<TreeSitterQuery>, _>>()?;
 
-        Ok(queries)
+        Ok((self.rule_overrides.apply(queries), PollHint::from_response(&data, poll_interval.as_deref())))
     }
 
-    /// Post evidence to the API
-    pub async fn post_evidence(&self, question_id: &str, evidence: Vec<CaptureResult>, query: &TreeSitterQuery) -> Result<(), Box<dyn std::error::Error>> {
-        let url = format!("{}/org/{}/evidence", self.api_base_url, self.organization_id);
+    /// Check the API key against the API, returning what it grants. A key the API rejects
+    /// for the organization fails with `ApiFailure`.
+    pub async fn verify_api_key(&self) -> Result<ApiKeyInfo, Box<dyn std::error::Error>> {
+        let url = format!("{}/org/{}/rpc/get-api-key-info", self.api_base_url, self.organization_id);
+
+        let response = self.client.get(&url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("APIKey {}", self.api_key))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status.as_u16() == 401 || status.as_u16() == 403 {
+            let cause = format!("API key is not valid for organization {}: {}", self.organization_id, status);
+            return Err(Box::new(ApiFailure(cause.into())));
+        }
+        if !status.is_success() {
+            return Err(Box::new(ApiFailure(format!("Error verifying API key: {}", status).into())));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Fetch finding summaries of the organization's most recent `last` reports
+    pub async fn fetch_report_summaries(&self, last: usize) -> Result<Vec<ReportSummary>, Box<dyn std::error::Error>> {
+        let url = format!(
+            "{}/org/{}/rpc/get-code-scan-summaries?limit={}",
+            self.api_base_url,
+            self.organization_id,
+            last
+        );
+
+        let response = self.client.get(&url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("APIKey {}", self.api_key))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Error fetching report summaries: {}", response.status()).into());
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        let reports = data.get("reports").ok_or("Invalid response format")?;
+        Ok(serde_json::from_value(reports.clone())?)
+    }
+
+    /// Fetch a completed report and the evidence uploaded for it
+    pub async fn fetch_report(&self, report_id: &str) -> Result<StoredReport, Box<dyn std::error::Error>> {
+        let url = format!(
+            "{}/org/{}/rpc/get-code-scan-report/{}",
+            self.api_base_url,
+            self.organization_id,
+            report_id
+        );
+
+        let response = self.client.get(&url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("APIKey {}", self.api_key))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Error fetching report {}: {}", report_id, response.status()).into());
+        }
+
+        let data: serde_json::Value = response.json().await?;
+        let report = data.get("report").ok_or("Invalid response format")?;
+        Ok(serde_json::from_value(report.clone())?)
+    }
+
+    /// Report the state of the current scan (e.g. "aborted") to the API
+    pub async fn update_scan_status(&self, status: &str, details: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let report_id = self.report_id.as_ref().ok_or("No report ID available")?;
+
+        let url = format!(
+            "{}/org/{}/rpc/update-code-scan-status/{}",
+            self.api_base_url,
+            self.organization_id,
+            report_id
+        );
 
         let request_body = json!({
-            "question_id": question_id,
-            "source_id": query.object_id,
-            "source_type": "tree-sitter-query",
-            "evidence": evidence,
-            "evidence_context": query.reasoning
+            "status": status,
+            "details": details
+        });
+
+        let response = self.client.post(&url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("APIKey {}", self.api_key))
+            .json(&request_body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Error updating scan status: {}", response.status()).into());
+        }
+
+        Ok(())
+    }
+
+    /// Mark the current scan as completed, posting the line counts of the scanned code so
+    /// the report can state what it covered
+    pub async fn finalize_code_scan(&self, code_stats: Option<&CodeStats>, manifest: Option<&ScanManifest>) -> Result<(), Box<dyn std::error::Error>> {
+        let report_id = self.report_id.as_ref().ok_or("No report ID available")?;
+
+        let url = format!(
+            "{}/org/{}/rpc/finalize-code-scan/{}",
+            self.api_base_url,
+            self.organization_id,
+            report_id
+        );
+
+        let mut request_body = json!({
+            "status": "completed",
+            "stats": code_stats
         });
+        if let Some(manifest) = manifest {
+            request_body["manifest"] = serde_json::to_value(manifest)?;
+        }
 
         let response = self.client.post(&url)
             .header(header::CONTENT_TYPE, "application/json")
@@ -137,218 +622,620 @@ impl Scanner {
             .send()
             .await?;
 
+        if !response.status().is_success() {
+            return Err(format!("Error finalizing scan: {}", response.status()).into());
+        }
+
+        Ok(())
+    }
+
+    /// Post evidence to the API
+    pub async fn post_evidence(&self, question_id: &str, evidence: Vec<CaptureResult>, query: &TreeSitterQuery) -> Result<(), Box<dyn std::error::Error>> {
+        let request_body = self.prepare_evidence(question_id, evidence, query, false)?;
+        self.send_evidence_payload(&request_body).await
+    }
+
+    /// Build the request body for posting evidence, encrypting the evidence if a key is configured
+    fn prepare_evidence(&self, question_id: &str, evidence: Vec<CaptureResult>, query: &TreeSitterQuery, sampled: bool) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+        let request_body = evidence_payload(question_id, evidence, query, sampled);
+
+        #[cfg(feature = "encryption")]
+        if let Some(encryptor) = &self.evidence_encryptor {
+            return encryptor.seal_evidence(request_body);
+        }
+
+        Ok(request_body)
+    }
+
+    /// Post a prepared evidence request body to the API
+    async fn send_evidence_payload(&self, request_body: &serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+        let url = format!("{}/org/{}/evidence", self.api_base_url, self.organization_id);
+
+        let response = self.client.post(&url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, format!("APIKey {}", self.api_key))
+            .json(request_body)
+            .send()
+            .await?;
+
         if !response.status().is_success() {
             return Err(format!("Error posting evidence: {}", response.status()).into());
         }
 
-        println!("Evidence posted successfully");
+        debug!("Evidence posted successfully");
         Ok(())
     }
 
-    /// Parse a file and cache the AST
-    pub fn parse_file(&self, file_path: &str, language_name: &str) -> Option<(Tree, String)> {
-        let language = get_language(language_name)?;
-        let path = PathBuf::from(file_path);
-        let src = fs::read_to_string(&path).ok()?;
+    /// Post an evidence request body, retrying with exponential backoff.
+    /// Returns the last error if every attempt failed.
+    async fn send_evidence_with_retry(&self, request_body: &serde_json::Value) -> Result<(), String> {
+        let mut delay = UPLOAD_RETRY_BASE_DELAY;
+        let mut attempt = 1;
 
-        let mut parser = Parser::new();
-        parser.set_language(language).ok()?;
-        let tree = parser.parse(&src, None)?;
+        loop {
+            match self.send_evidence_payload(request_body).await {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= self.upload_attempts => return Err(redact::redact(&e.to_string())),
+                Err(e) => {
+                    warn!(attempt, "Evidence upload attempt {} failed: {}; retrying in {:?}", attempt, redact::redact(&e.to_string()), delay);
+                }
+            }
 
-        Some((tree, src))
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+            attempt += 1;
+        }
     }
 
-    /// Get the language for a file based on its extension
-    pub fn get_language_for_file(&self, file_path: &str) -> Option<&'static str> {
-        let extension = PathBuf::from(file_path)
-            .extension()?
-            .to_str()?
-            .to_lowercase();
+    /// Upload evidence with retries, writing it to the dead-letter file if every attempt fails.
+    /// Only fails if the dead-letter file itself can't be written.
+    async fn deliver_evidence(&self, request_body: serde_json::Value) -> Result<(), String> {
+        let error = match self.send_evidence_with_retry(&request_body).await {
+            Ok(()) => return Ok(()),
+            Err(e) => e,
+        };
+
+        error!(
+            "❌ Evidence upload failed after {} attempts, writing to {}: {}",
+            self.upload_attempts,
+            self.dead_letter_path.display(),
+            error
+        );
+        let letter = DeadLetter::new(request_body, error, self.upload_attempts);
+        dead_letter::append(&self.dead_letter_path, &letter).map_err(|e| e.to_string())
+    }
+
+    /// Resubmit every upload in a dead-letter file. Uploads that fail again stay in the file.
+    /// Returns the number of uploads that were delivered and that remain.
+    pub async fn flush_dead_letters(&self, path: &Path) -> Result<(usize, usize), Box<dyn std::error::Error>> {
+        let letters = dead_letter::read_all(path)?;
+        let mut remaining = Vec::new();
+        let mut delivered = 0;
 
-        match extension.as_str() {
-            "rs" => Some("rust"),
-            "js" => Some("javascript"),
-            "py" => Some("python"),
-            "go" => Some("go"),
-            "ts" => Some("typescript"),
-            "java" => Some("java"),
-            "cpp" | "h" | "hpp" | "cc" => Some("cpp"),
-            "rb" => Some("ruby"),
-            "php" => Some("php"),
-            _ => None,
+        for letter in letters {
+            match self.send_evidence_with_retry(&letter.payload).await {
+                Ok(()) => delivered += 1,
+                Err(e) => remaining.push(DeadLetter::new(letter.payload, e, letter.attempts + self.upload_attempts)),
+            }
         }
+
+        dead_letter::rewrite(path, &remaining)?;
+        Ok((delivered, remaining.len()))
     }
 
-    /// Run a query on a tree and return the matches
-    pub fn run_query_on_tree(&self, tree: &Tree, source: &str, query_text: &str, language_name: &str) -> Vec<CaptureResult> {
-        let language = match get_language(language_name) {
-            Some(lang) => lang,
-            None => return vec![],
+    /// Parse a file and cache the AST
+    pub fn parse_file(&self, file_path: &str, language_name: &str) -> Option<(Tree, String)> {
+        self.engine.parse_file(file_path, language_name)
+    }
+
+    /// Get a file's tree and source from the AST cache, parsing and caching the file on a miss
+    /// or when the file changed since it was cached. The cache lock is only held for the lookup
+    /// and the insert, never while reading or parsing.
+    pub(crate) async fn load_tree(&self, file_path: &str, lang_name: &'static str) -> Option<ParsedFile> {
+        if self.engine.cache_mode() == CacheMode::Disabled {
+            let parsed = self.parse_file_async(file_path, lang_name).await;
+            if parsed.is_none() && !self.engine.is_quiet() {
+                warn!(file = file_path, "Failed to parse {}", file_path);
+            }
+            return parsed.map(Arc::new);
+        }
+
+        let read_at = SystemTime::now();
+        let stamp = match self.engine.sources() {
+            Some(_) => None,
+            None => tokio::fs::metadata(file_path).await.ok().as_ref().and_then(FileStamp::from_metadata),
+        };
+        let source = match self.engine.cached_tree(file_path, stamp) {
+            CacheLookup::Hit(cached) => {
+                if !self.engine.is_quiet() {
+                    debug!(file = file_path, "Using cached AST for {}", file_path);
+                }
+                return Some(cached);
+            }
+            CacheLookup::Unsure(cached) => {
+                let source = self.read_source_async(file_path).await?;
+                if cached.matches_source(&source) {
+                    self.engine.cache_tree(file_path, cached.parsed().clone(), stamp, read_at);
+                    return Some(cached.parsed().clone());
+                }
+                debug!(file = file_path, "Re-parsing {}, which changed since it was cached", file_path);
+                Some((source, cached.parsed().clone()))
+            }
+            CacheLookup::Miss => None,
         };
 
-        let query = match Query::new(language, query_text) {
-            Ok(q) => q,
-            Err(e) => {
-                eprintln!("Failed to compile query: {}", e);
-                return vec![];
+        // Parse the file, incrementally from its previous tree if it changed, and add it to the cache
+        let parsed = match source {
+            Some((source, previous)) => {
+                tokio::task::spawn_blocking(move || match incremental::reparse(&previous, &source, lang_name) {
+                    Some(reparsed) => Some((reparsed.tree, source)),
+                    None => parse_with_language(source, lang_name),
+                })
+                .await
+                .ok()
+                .flatten()
             }
+            None => self.parse_file_async(file_path, lang_name).await,
         };
+        match parsed {
+            Some(parsed) => {
+                if !self.engine.is_quiet() {
+                    debug!(file = file_path, "Parsed and cached AST for {}", file_path);
+                }
+                let parsed = Arc::new(parsed);
+                self.engine.cache_tree(file_path, parsed.clone(), stamp, read_at);
+                Some(parsed)
+            }
+            None => {
+                if !self.engine.is_quiet() {
+                    warn!(file = file_path, "Failed to parse {}", file_path);
+                }
+                None
+            }
+        }
+    }
 
-        let mut cursor = QueryCursor::new();
-        let matches = cursor.matches(&query, tree.root_node(), source.as_bytes());
+    /// Parse files into the AST cache ahead of the first query run, with at most as many
+    /// files in flight as the pipeline has parse workers. Returns the number of cached files.
+    pub async fn warm_cache(&self, files: &[String]) -> usize {
+        if self.engine.cache_mode() == CacheMode::Disabled {
+            return 0;
+        }
 
-        let mut results = Vec::new();
-        for m in matches {
-            for capture in m.captures {
-                let node = capture.node;
-                let start = node.start_position();
-                let text = &source[node.start_byte()..node.end_byte()];
-
-                let capture_name = match query.capture_names().get(capture.index as usize) {
-                    Some(name) => name.clone(),
-                    None => format!("capture_{}", capture.index),
-                };
+        let slots = Arc::new(Semaphore::new(self.pipeline.parse_workers.max(1)));
+        let mut tasks = JoinSet::new();
 
-                results.push(CaptureResult {
-                    name: capture_name,
-                    value: text.to_string(),
-                    position: (start.row + 1, start.column + 1),
-                    node_type: node.kind().to_string(),
-                });
+        for file in files {
+            let lang = match self.language_for_file_async(file).await {
+                Some(lang) => lang,
+                None => continue,
+            };
+
+            let scanner = self.clone();
+            let file = file.clone();
+            let slots = slots.clone();
+            tasks.spawn(async move {
+                let _permit = slots.acquire_owned().await;
+                scanner.load_tree(&file, lang).await.is_some()
+            });
+        }
+
+        let mut warmed = 0;
+        while let Some(joined) = tasks.join_next().await {
+            if matches!(joined, Ok(true)) {
+                warmed += 1;
             }
         }
 
-        results
+        warmed
+    }
+
+    /// Read and parse a file without blocking the async runtime: the read goes through
+    /// `tokio::fs`, unless the scanner was given the files' contents, and parsing runs on the blocking thread pool
+    pub async fn parse_file_async(&self, file_path: &str, language_name: &'static str) -> Option<(Tree, String)> {
+        let src = self.read_source_async(file_path).await?;
+        parse_on_blocking_pool(src, language_name).await
+    }
+
+    /// A file's contents from the given sources or through `tokio::fs`, unless it is over the size limit
+    async fn read_source_async(&self, file_path: &str) -> Option<String> {
+        match self.engine.sources() {
+            // Sources may be read from a git process, so they are read off the runtime too
+            Some(_) => {
+                let (engine, file_path) = (self.engine.clone(), file_path.to_string());
+                tokio::task::spawn_blocking(move || engine.read_source(&file_path)).await.ok().flatten()
+            }
+            None => {
+                if !self.engine.within_size_limit(file_path, tokio::fs::metadata(file_path).await.ok()?.len()) {
+                    return None;
+                }
+                tokio::fs::read_to_string(file_path).await.ok()
+            }
+        }
+    }
+
+    /// Parse in-memory source code with the given language
+    pub fn parse_source(&self, src: String, language_name: &str) -> Option<(Tree, String)> {
+        self.engine.parse_source(src, language_name)
+    }
+
+    /// Get the language for a file based on its extension
+    pub fn get_language_for_file(&self, file_path: &str) -> Option<&'static str> {
+        self.engine.get_language_for_file(file_path)
+    }
+
+    /// Get the language for a file on the blocking thread pool, since files with an ambiguous
+    /// extension or none are read to tell
+    pub(crate) async fn language_for_file_async(&self, file_path: &str) -> Option<&'static str> {
+        let (engine, file_path) = (self.engine.clone(), file_path.to_string());
+        tokio::task::spawn_blocking(move || engine.get_language_for_file(&file_path)).await.ok().flatten()
+    }
+
+    /// Run a query on a tree and return the matches
+    pub fn run_query_on_tree(&self, tree: &Tree, source: &str, query_text: &str, language_name: &str) -> Vec<CaptureResult> {
+        self.engine.run_query_on_tree(tree, source, query_text, language_name)
+    }
+
+    /// Scan in-memory sources, given as `(path, content)` pairs, with the given queries.
+    /// Sources are never read from disk and are not added to the AST cache.
+    pub fn scan_sources(&self, sources: Vec<(String, String)>, queries: &[TreeSitterQuery]) -> Vec<MatchResult> {
+        self.engine.scan_sources(sources, queries)
     }
 
     /// Scan files with the given queries
     pub async fn scan_files(&self, files: Vec<String>, queries: Vec<TreeSitterQuery>) -> Vec<MatchResult> {
         let mut results = Vec::new();
-        let mut cache = self.ast_cache.lock().unwrap();
-
-        // Group queries by file type
-        let queries_by_type: HashMap<String, Vec<&TreeSitterQuery>> = queries.iter()
-            .fold(HashMap::new(), |mut acc, q| {
-                acc.entry(q.file_type.clone()).or_insert_with(Vec::new).push(q);
-                acc
-            });
+        let router = QueryRouter::new(&queries);
+        let progress = self.engine.progress();
+        if let Some(progress) = progress {
+            progress.started(files.len());
+        }
 
         // Process each file
         for file_path in &files {
-            let lang_name = match self.get_language_for_file(file_path) {
-                Some(lang) => lang,
-                None => continue,
-            };
+            let file_results = self.scan_file(file_path, &router).await;
+            if let Some(progress) = progress {
+                progress.file_scanned(file_results.len());
+            }
+            let abort = file_results.iter().any(|r| self.fails_fast_on(r.severity));
+            results.extend(file_results);
 
-            println!("📄 Scanning: {}", file_path);
+            if abort {
+                warn!(file = %file_path, "⛔ Fail-fast: stopping scan after blocking finding in {}", file_path);
+                break;
+            }
+        }
 
-            // Check if the file is already in the cache
-            let (tree, source) = if let Some(cached) = cache.get(file_path) {
-                println!("Using cached AST for {}", file_path);
-                cached.clone()
-            } else {
-                // Parse the file and add it to the cache
-                match self.parse_file(file_path, lang_name) {
-                    Some((tree, src)) => {
-                        println!("Parsed and cached AST for {}", file_path);
-                        let result = (tree, src);
-                        cache.insert(file_path.clone(), result.clone());
-                        result
-                    },
-                    None => {
-                        eprintln!("Failed to parse {}", file_path);
-                        continue;
-                    }
-                }
+        if let Some(progress) = progress {
+            progress.finished();
+        }
+        self.end_run();
+        results
+    }
+
+    /// Scan one file, or nothing if its language is unknown or it can't be parsed
+    async fn scan_file(&self, file_path: &str, router: &QueryRouter<'_>) -> Vec<MatchResult> {
+        let lang_name = match self.language_for_file_async(file_path).await {
+            Some(lang) => lang,
+            None => return Vec::new(),
+        };
+
+        if !self.engine.is_quiet() {
+            debug!(file = %file_path, "📄 Scanning: {}", file_path);
+        }
+
+        let parsed = match self.load_tree(file_path, lang_name).await {
+            Some(parsed) => parsed,
+            None => return Vec::new(),
+        };
+        let (tree, source) = &*parsed;
+
+        self.engine.match_file(file_path, lang_name, tree, source, router)
+    }
+
+    /// Post the evidence for each query, or a "no matches" record for queries without results.
+    ///
+    /// Uploads run concurrently, bounded by this scanner's upload cap and the shared cap if any.
+    /// Uploads that keep failing are written to the dead-letter file instead of aborting the run.
+    pub async fn post_query_results(&self, queries: &[TreeSitterQuery], results: &[MatchResult]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut uploads = JoinSet::new();
+
+        for query in queries {
+            let matches: Vec<&MatchResult> = results.iter()
+                .filter(|r| r.question_id == query.question_id)
+                .collect();
+            let sampled = matches.iter().any(|r| r.sampled);
+
+            let mut evidence: Vec<CaptureResult> = matches.into_iter()
+                .map(|r| CaptureResult {
+                    name: "match".to_string(),
+                    value: r.text.clone(),
+                    position: (r.line, r.column),
+                    node_type: "unknown".to_string(),
+                    file: Some(r.file.clone()),
+                })
+                .collect();
+
+            if evidence.is_empty() {
+                // If no matches, still post a "no matches" evidence
+                evidence.push(CaptureResult {
+                    name: "no_match".to_string(),
+                    value: "No matches found".to_string(),
+                    position: (0, 0),
+                    node_type: "none".to_string(),
+                    file: None,
+                });
+            }
+
+            let scanner = self.clone();
+            let query = query.clone();
+            let span = info_span!("query", question_id = %query.question_id);
+            uploads.spawn(async move {
+                let _target_permit = scanner.target_upload_slots.clone().acquire_owned().await;
+                let _shared_permit = match &scanner.shared_upload_slots {
+                    Some(slots) => Some(slots.clone().acquire_owned().await),
+                    None => None,
+                };
+
+                let request_body = scanner.prepare_evidence(&query.question_id, evidence, &query, sampled)
+                    .map_err(|e| format!("Failed to prepare evidence for {}: {}", query.question_id, e))?;
+                scanner.deliver_evidence(request_body).await
+            }.instrument(span));
+        }
+
+        let mut first_error = None;
+        while let Some(joined) = uploads.join_next().await {
+            let outcome = match joined {
+                Ok(result) => result,
+                Err(e) => Err(e.to_string()),
             };
 
-            // Get relevant queries for this file type
-            let file_ext = PathBuf::from(file_path)
-                .extension()
-                .and_then(|ext| ext.to_str())
-                .map(|ext| format!(".{}", ext.to_lowercase()))
-                .unwrap_or_default();
-
-            let relevant_queries = queries_by_type.get(&file_ext).cloned().unwrap_or_default();
-            println!("🔍 Found {} relevant queries for {}", relevant_queries.len(), file_path);
-
-            // Process each query
-            for query in relevant_queries {
-                let captures = self.run_query_on_tree(&tree, &source, &query.query, lang_name);
-
-                if !captures.is_empty() {
-                    // println!("⚠️ Matched Rule: {} — {}", query.object_id, query.prompt);
-
-                    for capture in &captures {
-                        // println!("  📌 {}: \"{}\" @ line {}", capture.name, capture.value, capture.position.0);
-
-                        results.push(MatchResult {
-                            file: file_path.clone(),
-                            line: capture.position.0,
-                            column: capture.position.1,
-                            text: capture.value.clone(),
-                            question_id: query.question_id.clone(),
-                            organization_id: self.organization_id.clone(),
-                            code_base_version: self.code_base_version.clone(),
-                        });
-                    }
-                } else {
-                    // println!("✅ No matches for rule {}", query.prompt);
+            if let Err(e) = outcome {
+                error!("{}", e);
+                if first_error.is_none() {
+                    first_error = Some(e);
                 }
             }
         }
 
-        results
+        match first_error {
+            Some(e) => Err(e.into()),
+            None => Ok(()),
+        }
     }
 
-    /// Start a continuous scan that polls for new queries
-    pub async fn start_continuous_scan(&self, files: Vec<String>, poll_interval_secs: u64, max_polls: usize) -> Result<(), Box<dyn std::error::Error>> {
+    /// Start a continuous scan that polls for new queries, returning the findings of the latest
+    /// run of every query
+    pub async fn start_continuous_scan(&self, files: Vec<String>, poll_interval_secs: u64, max_polls: usize) -> Result<Vec<MatchResult>, Box<dyn std::error::Error>> {
+        self.poll_and_scan(files, poll_interval_secs, max_polls, false).await
+    }
+
+    /// Fetch the queries once, scan, upload the evidence and finalize the report. Unlike a
+    /// continuous scan, failing to finalize the report fails the scan, so the outcome of the
+    /// whole run is in the result.
+    pub async fn scan_once(&self, files: Vec<String>) -> Result<Vec<MatchResult>, Box<dyn std::error::Error>> {
+        self.poll_and_scan(files, 0, 1, true).await
+    }
+
+    /// Poll for queries up to `max_polls` times, scanning with the new or changed ones, then
+    /// finalize the report. `strict` turns a failure to finalize into an error.
+    async fn poll_and_scan(&self, files: Vec<String>, poll_interval_secs: u64, max_polls: usize, strict: bool) -> Result<Vec<MatchResult>, Box<dyn std::error::Error>> {
         let mut poll_count = 0;
+        // Hashes of the queries returned by the previous poll
+        let mut previous_hashes: HashSet<u64> = HashSet::new();
+        // Line counts from the latest poll that scanned files
+        let mut code_stats: Option<CodeStats> = None;
+        let mut schedule = PollSchedule::new(Duration::from_secs(poll_interval_secs));
+        // Latest version of every query run, for the scan manifest
+        let mut rules: BTreeMap<String, TreeSitterQuery> = BTreeMap::new();
+        // Findings of the latest run of every rule, so queries changed between polls aren't counted twice
+        let mut findings: BTreeMap<String, Vec<MatchResult>> = BTreeMap::new();
 
         while poll_count < max_polls {
-            println!("Polling for new queries...");
-
-            // Fetch available queries
-            let queries = self.fetch_available_queries().await?;
-            println!("Available Queries: {:?}", queries);
-
-            // Scan files with the fetched queries
-            let results = self.scan_files(files.clone(), queries.clone()).await;
-
-            // Post evidence for each query
-            for query in &queries {
-                let evidence: Vec<CaptureResult> = results.iter()
-                    .filter(|r| r.question_id == query.question_id)
-                    .map(|r| CaptureResult {
-                        name: "match".to_string(),
-                        value: r.text.clone(),
-                        position: (r.line, r.column),
-                        node_type: "unknown".to_string(),
-                    })
-                    .collect();
-
-                if evidence.is_empty() {
-                    // If no matches, still post a "no matches" evidence
-                    let no_match = CaptureResult {
-                        name: "no_match".to_string(),
-                        value: "No matches found".to_string(),
-                        position: (0, 0),
-                        node_type: "none".to_string(),
-                    };
-                    self.post_evidence(&query.question_id, vec![no_match], query).await?;
-                } else {
-                    self.post_evidence(&query.question_id, evidence, query).await?;
+            info!(poll = poll_count + 1, "Polling for new queries...");
+
+            // Fetch available queries and keep only those added or changed since the last poll.
+            // On the first poll, optionally warm the AST cache while the fetch is in flight.
+            let available = if poll_count == 0 && self.warm_up {
+                // The error is held across the join, so it is made a string to keep the future `Send`
+                let fetch = async { self.fetch_queries_with_hint().await.map_err(|e| e.to_string()) };
+                let (warmed, available) = tokio::join!(self.warm_cache(&files), fetch);
+                info!(files = warmed, "🔥 Warmed AST cache with {} files", warmed);
+                available.map_err(|e| ApiFailure(e.into()))?
+            } else {
+                self.fetch_queries_with_hint().await.map_err(ApiFailure)?
+            };
+            let (available, hint) = available;
+            let current_hashes: HashSet<u64> = available.iter().map(TreeSitterQuery::content_hash).collect();
+            let queries: Vec<TreeSitterQuery> = available.into_iter()
+                .filter(|q| !previous_hashes.contains(&q.content_hash()))
+                .collect();
+            info!(available = current_hashes.len(), new = queries.len(), "{} queries available, {} new or changed", current_hashes.len(), queries.len());
+            previous_hashes = current_hashes;
+            let found_new = !queries.is_empty();
+
+            if queries.is_empty() {
+                info!("No new or changed queries, skipping scan");
+            } else {
+                let question_ids: Vec<String> = queries.iter().map(|q| q.question_id.clone()).collect();
+                if self.scan_manifest {
+                    rules.extend(queries.iter().map(|q| (q.question_id.clone(), q.clone())));
+                }
+
+                // Scan files with the fetched queries, uploading evidence as each file type completes
+                let (results, profile) = self.run_pipeline(files.clone(), queries, &self.pipeline).await?;
+                profile.print();
+                code_stats = Some(profile.code_stats());
+                if let Some(complexity) = self.engine.take_complexity_summary() {
+                    complexity.print();
+                }
+                if let Some(coverage) = self.engine.coverage() {
+                    coverage.print_untested(&results);
+                }
+                #[cfg(feature = "store")]
+                self.record_results(&results);
+
+                let mut polled: BTreeMap<String, Vec<MatchResult>> = question_ids.iter().map(|id| (id.clone(), Vec::new())).collect();
+                for result in &results {
+                    polled.entry(result.question_id.clone()).or_default().push(result.clone());
+                }
+                findings.extend(polled);
+
+                if self.shutdown.is_triggered() || self.timed_out() {
+                    return self.abort_scan(&results, poll_count, question_ids).await;
+                }
+
+                if let Some(blocker) = results.iter().find(|r| self.fails_fast_on(r.severity)) {
+                    return Err(Box::new(FindingsAboveThreshold::new(format!(
+                        "Fail-fast: {} finding for {} at {}:{}",
+                        blocker.severity, blocker.question_id, blocker.file, blocker.line
+                    ))));
                 }
             }
 
             poll_count += 1;
 
-            // Sleep before the next poll
+            // Sleep before the next poll, waking early on shutdown
             if poll_count < max_polls {
-                thread::sleep(Duration::from_secs(poll_interval_secs));
+                let delay = schedule.next_delay(&hint, found_new);
+                if delay != Duration::from_secs(poll_interval_secs) {
+                    info!("⏱️ Next poll in {:.1}s", delay.as_secs_f64());
+                }
+                // Wake when the scan runs out of time rather than sleeping past it
+                let delay = match self.engine.clock().and_then(|clock| clock.remaining(None)) {
+                    Some(remaining) => delay.min(remaining),
+                    None => delay,
+                };
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = self.shutdown.wait() => {}
+                }
+
+                if self.shutdown.is_triggered() || self.timed_out() {
+                    return self.abort_scan(&[], poll_count, Vec::new()).await;
+                }
             }
         }
 
-        Ok(())
+        if let Some(code_stats) = &code_stats {
+            code_stats.print();
+        }
+        let manifest = if self.scan_manifest {
+            Some(self.write_manifest(&files, &rules.into_values().collect::<Vec<_>>())?)
+        } else {
+            None
+        };
+        if let Err(e) = self.finalize_code_scan(code_stats.as_ref(), manifest.as_ref()).await {
+            if strict {
+                return Err(Box::new(ApiFailure(e)));
+            }
+            error!("Failed to finalize scan: {}", redact::redact(&e.to_string()));
+        }
+
+        Ok(findings.into_values().flatten().collect())
+    }
+
+    /// Build the scan manifest, sign it if a key is configured, and write it to the state directory
+    fn write_manifest(&self, files: &[String], rules: &[TreeSitterQuery]) -> Result<ScanManifest, Box<dyn std::error::Error>> {
+        let mut manifest = ScanManifest::build(files, &self.engine, rules, self.report_id.clone());
+        if let Some(key) = &self.manifest_key {
+            manifest.sign(key)?;
+        }
+
+        let path = self.state_dir.join(format!("manifest-{}.json", self.report_id.as_deref().unwrap_or("local")));
+        manifest.write(&path)?;
+        info!(
+            "🧾 Wrote {}scan manifest of {} files to {}",
+            if manifest.signature.is_some() { "signed " } else { "" },
+            manifest.files.len(),
+            path.display()
+        );
+        Ok(manifest)
+    }
+
+    /// Write a poll's findings to the results store, if one is configured.
+    /// Failures are reported but don't stop the scan.
+    #[cfg(feature = "store")]
+    fn record_results(&self, results: &[MatchResult]) {
+        let Some((path, target)) = &self.results_store else {
+            return;
+        };
+
+        let recorded = ResultsStore::open(path)
+            .and_then(|mut store| store.record_scan(target, &self.code_base_version, self.report_id.as_deref(), results));
+        match recorded {
+            Ok(scan_id) => info!("🗄️ Recorded {} findings as scan {} in {}", results.len(), scan_id, path.display()),
+            Err(e) => warn!("Failed to record results in {}: {}", path.display(), e),
+        }
+    }
+
+    /// Save what an interrupted or timed out scan collected, tell the API the scan was
+    /// aborted, and return `ScanInterrupted` or `ScanTimedOut`
+    async fn abort_scan(&self, results: &[MatchResult], completed_polls: usize, interrupted_queries: Vec<String>) -> Result<Vec<MatchResult>, Box<dyn std::error::Error>> {
+        warn!("🛑 Stopping scan after {} completed polls", completed_polls);
+
+        let checkpoint = Checkpoint::new(
+            self.report_id.clone(),
+            self.code_base_version.clone(),
+            completed_polls,
+            interrupted_queries,
+        );
+        if let Err(e) = shutdown::write_partial_report(&self.state_dir, &checkpoint, results) {
+            error!("Failed to write partial report: {}", e);
+        }
+
+        let (details, error): (String, Box<dyn std::error::Error + Send + Sync>) = match self.engine.clock().and_then(ScanClock::expired) {
+            Some(timed_out) => (format!("Scan stopped: {}", timed_out), Box::new(timed_out)),
+            None => ("Scan interrupted by signal".to_string(), Box::new(ScanInterrupted)),
+        };
+        if let Err(e) = self.update_scan_status("aborted", &details).await {
+            error!("Failed to report aborted scan: {}", redact::redact(&e.to_string()));
+        }
+
+        Err(error)
+    }
+
+    /// Whether the scan or one of its phases ran out of time
+    fn timed_out(&self) -> bool {
+        self.engine.clock().is_some_and(|clock| clock.expired().is_some())
+    }
+}
+
+/// Build the request body for initiating a code scan report, listing the extensions of the
+/// files to scan as CSV
+/// Parse source on the blocking thread pool, keeping the async runtime free
+async fn parse_on_blocking_pool(src: String, language_name: &'static str) -> Option<(Tree, String)> {
+    tokio::task::spawn_blocking(move || parse_with_language(src, language_name))
+        .await
+        .ok()?
+}
+
+fn initiate_request_body(file_types: &[String], commit_hash: &str, branch_name: &str, repo_url: &str) -> serde_json::Value {
+    let file_types_csv = file_types.join(",");
+    debug!("File extensions (CSV): {}", file_types_csv);
+
+    json!({
+        "file_types": file_types_csv,
+        "commit_hash": commit_hash,
+        "branch_name": branch_name,
+        "repo_url": repo_url
+    })
+}
+
+/// Build the request body for posting evidence for a query.
+/// `sampled` marks evidence that holds only a sample of the query's matches, and is only
+/// sent when set.
+fn evidence_payload(question_id: &str, evidence: Vec<CaptureResult>, query: &TreeSitterQuery, sampled: bool) -> serde_json::Value {
+    let mut payload = json!({
+        "question_id": question_id,
+        "source_id": query.object_id,
+        "source_type": "tree-sitter-query",
+        "evidence": evidence,
+        "evidence_context": query.reasoning,
+        "severity": query.severity
+    });
+    if sampled {
+        payload["sampled"] = json!(true);
     }
+    payload
 }
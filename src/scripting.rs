@@ -0,0 +1,117 @@
+use crate::types::{MatchResult, Severity};
+use rhai::{Array, Dynamic, Map, Scope, AST};
+use std::{error::Error, fs, path::Path};
+
+/// Name of the function a transform script must define
+const TRANSFORM_FN: &str = "transform";
+/// Bound on the work a script may do per finding, so a runaway loop can't hang a scan
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A Rhai script run on every finding before it is output or uploaded as evidence.
+///
+/// The script defines `fn transform(finding)`, which receives the finding as a map with
+/// `file`, `line`, `column`, `text`, `question_id`, `severity`, `category`, `tags`, `sampled`
+/// and `covered`. It returns the finding, changed as needed, or `()` to drop it.
+pub struct ResultTransform {
+    engine: rhai::Engine,
+    ast: AST,
+}
+
+impl ResultTransform {
+    /// Compile a transform script
+    pub fn from_script(script: &str) -> Result<Self, Box<dyn Error>> {
+        let mut engine = rhai::Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let ast = engine.compile(script).map_err(|e| format!("Invalid transform script: {}", e))?;
+        if !ast.iter_functions().any(|f| f.name == TRANSFORM_FN && f.params.len() == 1) {
+            return Err(format!("Transform script must define fn {}(finding)", TRANSFORM_FN).into());
+        }
+
+        Ok(Self { engine, ast })
+    }
+
+    /// Load and compile a transform script from a file
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let script = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read transform script {}: {}", path.display(), e))?;
+        Self::from_script(&script)
+    }
+
+    /// Run the script on one finding. Returns `None` if the script dropped it.
+    pub fn apply(&self, result: MatchResult) -> Result<Option<MatchResult>, Box<dyn Error>> {
+        let finding = Dynamic::from_map(to_map(&result));
+        let output: Dynamic = self.engine.call_fn(&mut Scope::new(), &self.ast, TRANSFORM_FN, (finding,))?;
+
+        if output.is_unit() {
+            return Ok(None);
+        }
+        let map = output.try_cast::<Map>().ok_or("transform must return the finding map or ()")?;
+        from_map(result, &map).map(Some)
+    }
+
+    /// Run the script on every finding. A finding the script fails on is kept unchanged and
+    /// the error is reported, so one bad case doesn't lose the scan's results.
+    pub fn apply_all(&self, results: Vec<MatchResult>) -> Vec<MatchResult> {
+        results.into_iter()
+            .filter_map(|result| {
+                let location = format!("{}:{}", result.file, result.line);
+                match self.apply(result.clone()) {
+                    Ok(transformed) => transformed,
+                    Err(e) => {
+                        eprintln!("Transform script failed on {}: {}", location, e);
+                        Some(result)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+fn to_map(result: &MatchResult) -> Map {
+    let mut map = Map::new();
+    map.insert("file".into(), result.file.clone().into());
+    map.insert("line".into(), (result.line as i64).into());
+    map.insert("column".into(), (result.column as i64).into());
+    map.insert("text".into(), result.text.clone().into());
+    map.insert("question_id".into(), result.question_id.clone().into());
+    map.insert("severity".into(), result.severity.to_string().into());
+    map.insert("category".into(), result.category.clone().map_or(Dynamic::UNIT, Dynamic::from));
+    map.insert("tags".into(), Dynamic::from_array(result.tags.iter().cloned().map(Dynamic::from).collect()));
+    map.insert("sampled".into(), result.sampled.into());
+    map.insert("covered".into(), result.covered.map_or(Dynamic::UNIT, Dynamic::from));
+    map
+}
+
+/// Apply the fields of a map returned by the script to the finding it was built from.
+/// Organization and code base version can't be changed.
+fn from_map(mut result: MatchResult, map: &Map) -> Result<MatchResult, Box<dyn Error>> {
+    let field = |name: &str| map.get(name).cloned().unwrap_or(Dynamic::UNIT);
+    let string = |name: &str| field(name).into_string().map_err(|t| format!("finding.{} must be a string, not {}", name, t));
+    let number = |name: &str| {
+        field(name).as_int()
+            .ok()
+            .and_then(|n| usize::try_from(n).ok())
+            .ok_or_else(|| format!("finding.{} must be a non-negative integer", name))
+    };
+
+    result.file = string("file")?;
+    result.line = number("line")?;
+    result.column = number("column")?;
+    result.text = string("text")?;
+    result.question_id = string("question_id")?;
+    result.severity = string("severity")?.parse::<Severity>()?;
+    result.category = match field("category") {
+        category if category.is_unit() => None,
+        _ => Some(string("category")?),
+    };
+    result.tags = field("tags")
+        .try_cast::<Array>()
+        .ok_or("finding.tags must be an array")?
+        .into_iter()
+        .map(|tag| tag.into_string().map_err(|t| format!("tags must be strings, not {}", t)))
+        .collect::<Result<_, _>>()?;
+
+    Ok(result)
+}
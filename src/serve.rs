@@ -0,0 +1,246 @@
+use crate::discovery::{discover_files_matching, normalize_path, PathFilter};
+use crate::engine::{CacheMode, Engine};
+use crate::types::{MatchResult, TreeSitterQuery};
+use rayon::prelude::*;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    io,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{info, warn};
+
+/// Largest request body accepted; diffs of whole branches can be large, but not unbounded
+const MAX_BODY_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Default, Deserialize)]
+struct ScanRequest {
+    /// Files or directories relative to the target
+    #[serde(default)]
+    paths: Vec<String>,
+    /// Unified diff, as printed by `git diff`
+    #[serde(default)]
+    diff: Option<String>,
+}
+
+/// Keeps the ASTs and findings of a repository between scans, so that scans triggered over
+/// HTTP only re-parse the files that changed since they were last scanned, incrementally,
+/// and only query the lines around each edit.
+///
+/// Endpoints: `GET /health` and `POST /scan` with a JSON body of `paths` relative to the
+/// target, a unified `diff`, or neither to scan the whole target.
+pub struct ScanDaemon {
+    engine: Engine,
+    target: String,
+    filter: PathFilter,
+    queries: Vec<TreeSitterQuery>,
+    /// Findings of every file scanned so far, by path
+    findings: Mutex<HashMap<String, Vec<MatchResult>>>,
+}
+
+impl ScanDaemon {
+    /// Scan the files `filter` allows in `target_dir` on request. The engine's ASTs are cached
+    /// for the lifetime of the daemon.
+    pub fn new(engine: Engine, target_dir: &str, filter: PathFilter, queries: Vec<TreeSitterQuery>) -> Self {
+        Self {
+            engine: engine.with_cache_mode(CacheMode::Persistent),
+            target: target_dir.to_string(),
+            filter,
+            queries,
+            findings: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Parse every file of the target ahead of the first request, returning how many there are
+    pub fn warm(&self) -> Result<usize, Box<dyn Error>> {
+        let files = discover_files_matching(&self.target, &self.filter)?.files;
+        self.scan(&files);
+        Ok(files.len())
+    }
+
+    /// Number of files whose ASTs are cached
+    pub fn cached_files(&self) -> usize {
+        self.engine.cached_files()
+    }
+
+    /// Scan files, rescanning those modified since they were last scanned
+    pub fn scan(&self, files: &[String]) -> Vec<MatchResult> {
+        // Held throughout, so that concurrent requests apply each file's rescans in order
+        let mut findings = self.findings.lock().unwrap();
+        let rescans: Vec<_> = files.par_iter().map(|file| self.engine.rescan_file(file, &self.queries)).collect();
+        let mut results = Vec::new();
+        for (file, rescan) in files.iter().zip(rescans) {
+            let key = normalize_path(file);
+            let previous = findings.remove(&key).unwrap_or_default();
+            let current = rescan.apply(previous);
+            results.extend(current.iter().cloned());
+            findings.insert(key, current);
+        }
+        results
+    }
+
+    /// Answer a request with an HTTP status and a JSON body
+    pub fn handle(&self, method: &str, path: &str, body: &[u8]) -> (u16, Value) {
+        match (method, path) {
+            ("GET", "/health") => (200, json!({ "status": "ok", "cachedFiles": self.cached_files() })),
+            ("POST", "/scan") => match self.handle_scan(body) {
+                Ok(response) => (200, response),
+                Err(e) => (400, json!({ "error": e.to_string() })),
+            },
+            (_, "/health") | (_, "/scan") => (405, json!({ "error": format!("{} is not allowed on {}", method, path) })),
+            _ => (404, json!({ "error": format!("No endpoint at {}", path) })),
+        }
+    }
+
+    /// Scan the requested paths and the files a diff touches. Findings in files of the diff
+    /// are kept only on the lines it adds.
+    fn handle_scan(&self, body: &[u8]) -> Result<Value, Box<dyn Error>> {
+        let request: ScanRequest = if body.iter().all(u8::is_ascii_whitespace) {
+            ScanRequest::default()
+        } else {
+            serde_json::from_slice(body)?
+        };
+        let changed = request.diff.as_deref().map(changed_lines);
+
+        let mut paths = request.paths;
+        if let Some(changed) = &changed {
+            paths.extend(changed.keys().cloned());
+        }
+
+        let root = Path::new(&self.target);
+        let mut files = discover_files_matching(&self.target, &self.filter)?.files;
+        if !paths.is_empty() || changed.is_some() {
+            let requested: Vec<_> = paths.iter().map(|path| root.join(path.trim_start_matches("./"))).collect();
+            files.retain(|file| requested.iter().any(|path| Path::new(file).starts_with(path)));
+        }
+
+        let mut results = self.scan(&files);
+        if let Some(changed) = &changed {
+            let relative: HashMap<String, String> = files.iter()
+                .map(|file| {
+                    let relative = Path::new(file).strip_prefix(root).unwrap_or(Path::new(file));
+                    (normalize_path(file), normalize_path(&relative.to_string_lossy()))
+                })
+                .collect();
+            results.retain(|result| {
+                match relative.get(&result.file).and_then(|relative| changed.get(relative)) {
+                    Some(lines) => lines.contains(&result.line),
+                    None => true,
+                }
+            });
+        }
+
+        Ok(json!({ "files": files.len(), "results": results }))
+    }
+}
+
+/// Lines each file of a unified diff adds or changes, by the path of the file after the
+/// change; deleted files are left out
+pub fn changed_lines(diff: &str) -> BTreeMap<String, BTreeSet<usize>> {
+    let mut changed: BTreeMap<String, BTreeSet<usize>> = BTreeMap::new();
+    let mut file: Option<String> = None;
+    let mut line = 0;
+    let mut previous = "";
+
+    for text in diff.lines() {
+        if let Some(path) = text.strip_prefix("+++ ").filter(|_| previous.starts_with("--- ")) {
+            let path = path.split('\t').next().unwrap_or_default().trim();
+            file = (path != "/dev/null").then(|| path.strip_prefix("b/").unwrap_or(path).to_string());
+            if let Some(file) = &file {
+                changed.entry(file.clone()).or_default();
+            }
+        } else if let Some(hunk) = text.strip_prefix("@@ ") {
+            // "@@ -start,count +start,count @@": the lines after it start at the second start
+            line = hunk.split_whitespace()
+                .find_map(|range| range.strip_prefix('+'))
+                .and_then(|range| range.split(',').next()?.parse().ok())
+                .unwrap_or(0);
+        } else if let Some(file) = &file {
+            if text.starts_with('+') {
+                changed.entry(file.clone()).or_default().insert(line);
+                line += 1;
+            } else if text.starts_with(' ') || text.is_empty() {
+                line += 1;
+            }
+        }
+        previous = text;
+    }
+    changed
+}
+
+/// Answer HTTP requests on `address` until the process is stopped, one request per connection
+pub async fn serve(daemon: ScanDaemon, address: &str) -> Result<(), Box<dyn Error>> {
+    let listener = TcpListener::bind(address).await?;
+    info!("🛰️ Listening on http://{}", listener.local_addr()?);
+
+    let daemon = Arc::new(daemon);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let daemon = daemon.clone();
+        tokio::spawn(async move {
+            if let Err(e) = answer(daemon, stream).await {
+                warn!("Failed to answer request from {}: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn answer(daemon: Arc<ScanDaemon>, stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().split('?').next().unwrap_or_default().to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.trim().eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+
+    let (status, body) = if content_length > MAX_BODY_BYTES {
+        (413, json!({ "error": format!("Request body is over {} bytes", MAX_BODY_BYTES) }))
+    } else {
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).await?;
+        // Scans parse and query files, which would otherwise block the runtime
+        tokio::task::spawn_blocking(move || daemon.handle(&method, &path, &body))
+            .await
+            .unwrap_or_else(|e| (500, json!({ "error": e.to_string() })))
+    };
+
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason(status), body.len(), body
+    );
+    let mut stream = reader.into_inner();
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    }
+}
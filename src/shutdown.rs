@@ -0,0 +1,135 @@
+use crate::exit_codes;
+use crate::types::MatchResult;
+use serde::Serialize;
+use std::{
+    error::Error,
+    fmt,
+    fs,
+    path::Path,
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Notify;
+
+/// A flag shared between the signal handler and running scans, set once shutdown is requested
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownSignal {
+    requested: Arc<AtomicBool>,
+    notify: Arc<Notify>,
+}
+
+impl ShutdownSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request shutdown and wake everything waiting on it
+    pub fn trigger(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    /// Whether shutdown has been requested
+    pub fn is_triggered(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Wait until shutdown is requested
+    pub async fn wait(&self) {
+        let notified = self.notify.notified();
+        if self.is_triggered() {
+            return;
+        }
+        notified.await;
+    }
+
+    /// Trigger on the first SIGINT/SIGTERM and exit immediately on the second
+    pub fn listen_for_signals(&self) {
+        let signal = self.clone();
+        tokio::spawn(async move {
+            wait_for_signal().await;
+            eprintln!("\n🛑 Interrupt received, flushing evidence before exiting (interrupt again to exit immediately)");
+            signal.trigger();
+
+            wait_for_signal().await;
+            std::process::exit(exit_codes::INCOMPLETE);
+        });
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(_) => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Returned by a scan that stopped because shutdown was requested
+#[derive(Debug)]
+pub struct ScanInterrupted;
+
+impl fmt::Display for ScanInterrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("scan interrupted before completion")
+    }
+}
+
+impl Error for ScanInterrupted {}
+
+/// Where an interrupted scan stopped
+#[derive(Debug, Serialize)]
+pub struct Checkpoint {
+    pub report_id: Option<String>,
+    pub code_base_version: String,
+    /// Polls that finished before the interruption
+    pub completed_polls: usize,
+    /// Question IDs of the queries that were running when the scan stopped
+    pub interrupted_queries: Vec<String>,
+    /// Seconds since the Unix epoch when the scan stopped
+    pub interrupted_at: u64,
+}
+
+impl Checkpoint {
+    pub fn new(report_id: Option<String>, code_base_version: String, completed_polls: usize, interrupted_queries: Vec<String>) -> Self {
+        Self {
+            report_id,
+            code_base_version,
+            completed_polls,
+            interrupted_queries,
+            interrupted_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Write the findings collected so far and the checkpoint into `dir`
+pub fn write_partial_report(dir: &Path, checkpoint: &Checkpoint, results: &[MatchResult]) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    let suffix = checkpoint.report_id.as_deref().unwrap_or("local");
+
+    let report_path = dir.join(format!("partial-report-{}.json", suffix));
+    fs::write(&report_path, serde_json::to_string_pretty(results)?)?;
+
+    let checkpoint_path = dir.join(format!("checkpoint-{}.json", suffix));
+    fs::write(&checkpoint_path, serde_json::to_string_pretty(checkpoint)?)?;
+
+    println!("💾 Wrote partial report to {} and checkpoint to {}", report_path.display(), checkpoint_path.display());
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tree_sitter::Tree;
+
+/// File and line counts for one language, or for a whole scan
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineCounts {
+    pub files: usize,
+    pub code: usize,
+    pub comment: usize,
+    pub blank: usize,
+}
+
+impl LineCounts {
+    pub fn lines(&self) -> usize {
+        self.code + self.comment + self.blank
+    }
+
+    fn add(&mut self, other: &LineCounts) {
+        self.files += other.files;
+        self.code += other.code;
+        self.comment += other.comment;
+        self.blank += other.blank;
+    }
+}
+
+/// cloc-style breakdown of the files a scan parsed, by language
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CodeStats {
+    pub languages: BTreeMap<String, LineCounts>,
+    pub total: LineCounts,
+}
+
+impl CodeStats {
+    /// Add the counts of one or more files in `language`
+    pub fn record(&mut self, language: &str, counts: LineCounts) {
+        self.languages.entry(language.to_string()).or_default().add(&counts);
+        self.total.add(&counts);
+    }
+
+    /// Print a table with a row per language and a total
+    pub fn print(&self) {
+        println!("📏 {:<12} {:>7} {:>9} {:>9} {:>9}", "Language", "Files", "Code", "Comment", "Blank");
+        for (language, counts) in &self.languages {
+            println!("   {:<12} {:>7} {:>9} {:>9} {:>9}", language, counts.files, counts.code, counts.comment, counts.blank);
+        }
+        let total = &self.total;
+        println!("   {:<12} {:>7} {:>9} {:>9} {:>9}", "Total", total.files, total.code, total.comment, total.blank);
+    }
+}
+
+/// Count the code, comment and blank lines of one parsed file. A line is a comment line if
+/// all its non-whitespace text lies inside comment nodes of the tree, so comments trailing
+/// code still count as code.
+pub fn count_lines(tree: &Tree, source: &str) -> LineCounts {
+    let comments = comment_ranges(tree);
+    let mut counts = LineCounts { files: 1, ..Default::default() };
+    let mut next_comment = 0;
+    let mut offset = 0;
+
+    for line in source.split_inclusive('\n') {
+        let mut has_text = false;
+        let mut has_code = false;
+
+        for (i, byte) in line.bytes().enumerate() {
+            if byte.is_ascii_whitespace() {
+                continue;
+            }
+            has_text = true;
+
+            let position = offset + i;
+            while next_comment < comments.len() && comments[next_comment].1 <= position {
+                next_comment += 1;
+            }
+            if comments.get(next_comment).is_none_or(|(start, _)| *start > position) {
+                has_code = true;
+                break;
+            }
+        }
+
+        match (has_text, has_code) {
+            (false, _) => counts.blank += 1,
+            (true, false) => counts.comment += 1,
+            (true, true) => counts.code += 1,
+        }
+        offset += line.len();
+    }
+
+    counts
+}
+
+/// Byte ranges of the comment nodes in a tree, in source order
+fn comment_ranges(tree: &Tree) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut stack = vec![tree.root_node()];
+
+    while let Some(node) = stack.pop() {
+        if node.kind().contains("comment") {
+            ranges.push((node.start_byte(), node.end_byte()));
+            continue;
+        }
+
+        let mut cursor = node.walk();
+        let children: Vec<_> = node.children(&mut cursor).collect();
+        stack.extend(children.into_iter().rev());
+    }
+
+    ranges
+}
@@ -0,0 +1,188 @@
+use crate::baseline::fingerprint;
+use crate::types::MatchResult;
+use rusqlite::{params, types::ValueRef, Connection};
+use std::{
+    collections::{BTreeMap, HashSet},
+    error::Error,
+    fs,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS scans (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    recorded_at INTEGER NOT NULL,
+    target TEXT NOT NULL,
+    code_base_version TEXT NOT NULL,
+    report_id TEXT
+);
+CREATE TABLE IF NOT EXISTS findings (
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    question_id TEXT NOT NULL,
+    file TEXT NOT NULL,
+    line INTEGER NOT NULL,
+    \"column\" INTEGER NOT NULL,
+    text TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    sampled INTEGER NOT NULL,
+    fingerprint TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS summaries (
+    scan_id INTEGER NOT NULL REFERENCES scans(id),
+    question_id TEXT NOT NULL,
+    findings INTEGER NOT NULL,
+    PRIMARY KEY (scan_id, question_id)
+);
+CREATE INDEX IF NOT EXISTS findings_by_scan ON findings(scan_id);
+CREATE INDEX IF NOT EXISTS findings_by_fingerprint ON findings(fingerprint);
+";
+
+/// A scan recorded in the store
+#[derive(Debug, Clone)]
+pub struct StoredScan {
+    pub id: i64,
+    /// Seconds since the Unix epoch when the scan was recorded
+    pub recorded_at: i64,
+    pub target: String,
+    pub code_base_version: String,
+    pub report_id: Option<String>,
+    pub findings: usize,
+}
+
+/// Column names and stringified rows returned by `ResultsStore::query`
+#[derive(Debug, Default)]
+pub struct QueryRows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// A local SQLite database recording the findings, per-rule summaries and
+/// fingerprints of every scan
+pub struct ResultsStore {
+    conn: Connection,
+}
+
+impl ResultsStore {
+    /// Open the database at `path`, creating it and its tables if needed
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open results store {}: {}", path.display(), e))?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// Record the findings of one scan of `target`, returning the new scan's ID.
+    /// Fingerprints are computed relative to `target`, as in baselines.
+    pub fn record_scan(&mut self, target: &str, code_base_version: &str, report_id: Option<&str>, results: &[MatchResult]) -> Result<i64, Box<dyn Error>> {
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO scans (recorded_at, target, code_base_version, report_id) VALUES (?1, ?2, ?3, ?4)",
+            params![now_secs(), target, code_base_version, report_id],
+        )?;
+        let scan_id = tx.last_insert_rowid();
+
+        let root = Path::new(target);
+        let mut by_rule: BTreeMap<&str, usize> = BTreeMap::new();
+        {
+            let mut insert = tx.prepare(
+                "INSERT INTO findings (scan_id, question_id, file, line, \"column\", text, severity, sampled, fingerprint)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            )?;
+            for result in results {
+                insert.execute(params![
+                    scan_id,
+                    result.question_id,
+                    result.file,
+                    result.line as i64,
+                    result.column as i64,
+                    result.text,
+                    result.severity.to_string(),
+                    result.sampled,
+                    fingerprint(result, root),
+                ])?;
+                *by_rule.entry(&result.question_id).or_default() += 1;
+            }
+
+            let mut summary = tx.prepare("INSERT INTO summaries (scan_id, question_id, findings) VALUES (?1, ?2, ?3)")?;
+            for (rule, count) in by_rule {
+                summary.execute(params![scan_id, rule, count as i64])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(scan_id)
+    }
+
+    /// All recorded scans, most recent first
+    pub fn scans(&self) -> Result<Vec<StoredScan>, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT s.id, s.recorded_at, s.target, s.code_base_version, s.report_id,
+                    (SELECT COUNT(*) FROM findings f WHERE f.scan_id = s.id)
+             FROM scans s ORDER BY s.id DESC",
+        )?;
+        let scans = stmt.query_map([], |row| {
+            Ok(StoredScan {
+                id: row.get(0)?,
+                recorded_at: row.get(1)?,
+                target: row.get(2)?,
+                code_base_version: row.get(3)?,
+                report_id: row.get(4)?,
+                findings: row.get::<_, i64>(5)? as usize,
+            })
+        })?;
+        Ok(scans.collect::<Result<_, _>>()?)
+    }
+
+    /// Finding counts per rule for one scan
+    pub fn summary(&self, scan_id: i64) -> Result<BTreeMap<String, usize>, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare("SELECT question_id, findings FROM summaries WHERE scan_id = ?1")?;
+        let rows = stmt.query_map([scan_id], |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Fingerprints of the findings of one scan
+    pub fn fingerprints(&self, scan_id: i64) -> Result<HashSet<String>, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT fingerprint FROM findings WHERE scan_id = ?1")?;
+        let rows = stmt.query_map([scan_id], |row| row.get(0))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    /// Run an arbitrary SQL query, returning every value as text
+    pub fn query(&self, sql: &str) -> Result<QueryRows, Box<dyn Error>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let columns: Vec<String> = stmt.column_names().into_iter().map(str::to_string).collect();
+        let width = columns.len();
+
+        let mut rows = Vec::new();
+        let mut cursor = stmt.query([])?;
+        while let Some(row) = cursor.next()? {
+            let values = (0..width)
+                .map(|i| row.get_ref(i).map(value_to_string))
+                .collect::<Result<_, _>>()?;
+            rows.push(values);
+        }
+
+        Ok(QueryRows { columns, rows })
+    }
+}
+
+fn value_to_string(value: ValueRef) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+        ValueRef::Blob(blob) => format!("<{} bytes>", blob.len()),
+    }
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,41 @@
+use tree_sitter::{Point, Range};
+
+/// Byte ranges of the contents of a Svelte component's `<script>` blocks, which are
+/// parsed as TypeScript while the markup and styles around them are skipped
+pub fn script_ranges(src: &str) -> Vec<Range> {
+    let lower = src.to_ascii_lowercase();
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+
+    while let Some(open) = lower[offset..].find("<script") {
+        let tag_start = offset + open;
+        // Skip tags that only start with "script", like <scripts>
+        if !lower[tag_start + 7..].starts_with(|c: char| c == '>' || c.is_ascii_whitespace()) {
+            offset = tag_start + 7;
+            continue;
+        }
+        let Some(tag_end) = lower[tag_start..].find('>') else { break };
+        let start = tag_start + tag_end + 1;
+        let end = lower[start..].find("</script").map_or(src.len(), |close| start + close);
+
+        if end > start {
+            ranges.push(Range {
+                start_byte: start,
+                end_byte: end,
+                start_point: point_at(src, start),
+                end_point: point_at(src, end),
+            });
+        }
+        offset = end;
+    }
+
+    ranges
+}
+
+/// Row and byte column of a byte offset
+pub(crate) fn point_at(src: &str, byte: usize) -> Point {
+    let before = &src[..byte];
+    let row = before.matches('\n').count();
+    let column = before.rfind('\n').map_or(byte, |newline| byte - newline - 1);
+    Point { row, column }
+}
@@ -0,0 +1,249 @@
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::warn;
+
+/// Stages of a scan that can be given their own time limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScanPhase {
+    /// Walking the target for files to scan
+    Discovery,
+    /// Reading and parsing files
+    Parse,
+    /// Running queries against parsed files
+    Query,
+    /// Uploading evidence to the API
+    Upload,
+}
+
+impl fmt::Display for ScanPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ScanPhase::Discovery => "discovery",
+            ScanPhase::Parse => "parse",
+            ScanPhase::Query => "query",
+            ScanPhase::Upload => "upload",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for ScanPhase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "discovery" => Ok(ScanPhase::Discovery),
+            "parse" => Ok(ScanPhase::Parse),
+            "query" => Ok(ScanPhase::Query),
+            "upload" => Ok(ScanPhase::Upload),
+            other => Err(format!("unknown scan phase: {} (expected discovery, parse, query or upload)", other)),
+        }
+    }
+}
+
+/// Time limits of a whole scan and of its phases, as set by `--timeout`, `--phase-timeout`
+/// or the `[scan.timeouts]` table of magma.toml; unset limits never expire
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub total: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub discovery: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub parse: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub query: Option<Duration>,
+    #[serde(deserialize_with = "deserialize_duration")]
+    pub upload: Option<Duration>,
+}
+
+impl Timeouts {
+    /// Limit of one phase
+    pub fn phase(&self, phase: ScanPhase) -> Option<Duration> {
+        match phase {
+            ScanPhase::Discovery => self.discovery,
+            ScanPhase::Parse => self.parse,
+            ScanPhase::Query => self.query,
+            ScanPhase::Upload => self.upload,
+        }
+    }
+
+    /// Set the limit of one phase
+    pub fn set_phase(&mut self, phase: ScanPhase, limit: Duration) {
+        let slot = match phase {
+            ScanPhase::Discovery => &mut self.discovery,
+            ScanPhase::Parse => &mut self.parse,
+            ScanPhase::Query => &mut self.query,
+            ScanPhase::Upload => &mut self.upload,
+        };
+        *slot = Some(limit);
+    }
+
+    /// Use the limits of `other` wherever these are unset
+    pub fn or(self, other: Timeouts) -> Self {
+        Self {
+            total: self.total.or(other.total),
+            discovery: self.discovery.or(other.discovery),
+            parse: self.parse.or(other.parse),
+            query: self.query.or(other.query),
+            upload: self.upload.or(other.upload),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Parse a duration given in seconds, or with an `s`, `m`, `h` or `d` suffix, e.g. "90", "15m"
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let number: f64 = number.parse().map_err(|_| format!("invalid duration: {}", value))?;
+    let seconds = match unit {
+        "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        "d" => number * 86400.0,
+        _ => return Err(format!("invalid duration unit in {}; use s, m, h or d", value)),
+    };
+    if seconds <= 0.0 {
+        return Err(format!("duration must be positive: {}", value));
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
+
+/// Parse a `PHASE=DURATION` pair, e.g. "parse=10m"
+pub fn parse_phase_timeout(value: &str) -> Result<(ScanPhase, Duration), String> {
+    let (phase, duration) = value.split_once('=')
+        .ok_or_else(|| format!("expected PHASE=DURATION, got {}", value))?;
+    Ok((phase.trim().parse()?, parse_duration(duration)?))
+}
+
+fn deserialize_duration<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Value {
+        Seconds(u64),
+        Text(String),
+    }
+
+    match Value::deserialize(deserializer)? {
+        Value::Seconds(0) => Err(serde::de::Error::custom("duration must be positive")),
+        Value::Seconds(seconds) => Ok(Some(Duration::from_secs(seconds))),
+        Value::Text(text) => parse_duration(&text).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Returned by a scan that ran out of time, after what it finished has been reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScanTimedOut {
+    /// Phase whose limit ran out; `None` for the limit of the whole scan
+    pub phase: Option<ScanPhase>,
+    pub limit: Duration,
+}
+
+impl fmt::Display for ScanTimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.phase {
+            Some(phase) => write!(f, "{} phase timed out after {}s", phase, self.limit.as_secs_f64()),
+            None => write!(f, "scan timed out after {}s", self.limit.as_secs_f64()),
+        }
+    }
+}
+
+impl Error for ScanTimedOut {}
+
+/// The deadlines of a running scan, shared by everything taking part in it. Work that runs
+/// past a deadline stops early, keeping what it finished, and the first expiry is remembered
+/// so the scan can be reported as incomplete.
+#[derive(Debug, Clone)]
+pub struct ScanClock {
+    started: Instant,
+    timeouts: Timeouts,
+    /// When each phase last began
+    phases: Arc<Mutex<HashMap<ScanPhase, Instant>>>,
+    expired: Arc<Mutex<Option<ScanTimedOut>>>,
+}
+
+impl ScanClock {
+    /// Start the clock of the whole scan
+    pub fn start(timeouts: Timeouts) -> Self {
+        Self {
+            started: Instant::now(),
+            timeouts,
+            phases: Arc::new(Mutex::new(HashMap::new())),
+            expired: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Start timing a phase. Phases overlap when files are pipelined, so each run of a phase is
+    /// timed from when it began, e.g. from the start of every poll's scan.
+    pub fn begin(&self, phase: ScanPhase) {
+        self.phases.lock().unwrap().insert(phase, Instant::now());
+    }
+
+    /// Time left before the scan or `phase`, if given, runs out; `None` if neither has a limit
+    pub fn remaining(&self, phase: Option<ScanPhase>) -> Option<Duration> {
+        let total = self.timeouts.total.map(|limit| limit.saturating_sub(self.started.elapsed()));
+        let phase = phase.and_then(|phase| {
+            let limit = self.timeouts.phase(phase)?;
+            let began = *self.phases.lock().unwrap().get(&phase)?;
+            Some(limit.saturating_sub(began.elapsed()))
+        });
+        match (total, phase) {
+            (Some(total), Some(phase)) => Some(total.min(phase)),
+            (total, phase) => total.or(phase),
+        }
+    }
+
+    /// Whether the scan, or `phase` since it began, has run out of time. The first expiry is
+    /// recorded for `expired`.
+    pub fn check(&self, phase: ScanPhase) -> bool {
+        if let Some(limit) = self.timeouts.total
+            && self.started.elapsed() >= limit
+        {
+            self.expire(None, limit);
+            return true;
+        }
+
+        let began = self.phases.lock().unwrap().get(&phase).copied();
+        match (self.timeouts.phase(phase), began) {
+            (Some(limit), Some(began)) if began.elapsed() >= limit => {
+                self.expire(Some(phase), limit);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Record that a limit ran out, unless an earlier one already did
+    pub fn expire(&self, phase: Option<ScanPhase>, limit: Duration) {
+        let mut expired = self.expired.lock().unwrap();
+        if expired.is_none() {
+            warn!("⏰ {}; reporting what finished", ScanTimedOut { phase, limit });
+            *expired = Some(ScanTimedOut { phase, limit });
+        }
+    }
+
+    /// The first limit that ran out, including the scan's own once it has passed
+    pub fn expired(&self) -> Option<ScanTimedOut> {
+        if let Some(limit) = self.timeouts.total
+            && self.started.elapsed() >= limit
+        {
+            self.expire(None, limit);
+        }
+        *self.expired.lock().unwrap()
+    }
+}
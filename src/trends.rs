@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::{BTreeMap, BTreeSet}, str::FromStr};
+
+/// Finding counts of one past report, as returned by the API
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportSummary {
+    pub report_id: String,
+    /// When the report was created, as an RFC 3339 timestamp
+    pub created_at: String,
+    #[serde(default)]
+    pub code_base_version: String,
+    /// Number of findings per rule (question ID)
+    #[serde(default)]
+    pub findings_by_rule: BTreeMap<String, usize>,
+}
+
+/// How a trend report is rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TrendFormat {
+    #[default]
+    Table,
+    Csv,
+    Json,
+}
+
+impl FromStr for TrendFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "table" => Ok(TrendFormat::Table),
+            "csv" => Ok(TrendFormat::Csv),
+            "json" => Ok(TrendFormat::Json),
+            other => Err(format!("unknown trend format: {} (expected table, csv or json)", other)),
+        }
+    }
+}
+
+/// Finding counts per rule across reports, oldest report first
+#[derive(Debug, Serialize)]
+pub struct Trends {
+    pub reports: Vec<ReportSummary>,
+    /// For every rule seen in any report, its finding count in each report (0 when absent)
+    pub rules: BTreeMap<String, Vec<usize>>,
+}
+
+impl Trends {
+    /// Line up per-rule counts across reports, ordered by creation time
+    pub fn new(mut reports: Vec<ReportSummary>) -> Self {
+        reports.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+
+        let names: BTreeSet<&String> = reports.iter().flat_map(|r| r.findings_by_rule.keys()).collect();
+        let rules = names.into_iter()
+            .map(|rule| {
+                let counts = reports.iter().map(|r| r.findings_by_rule.get(rule).copied().unwrap_or(0)).collect();
+                (rule.clone(), counts)
+            })
+            .collect();
+
+        Self { reports, rules }
+    }
+
+    /// Total findings in each report
+    pub fn totals(&self) -> Vec<usize> {
+        (0..self.reports.len())
+            .map(|i| self.rules.values().map(|counts| counts[i]).sum())
+            .collect()
+    }
+
+    /// Render the trends as a text table, CSV or JSON
+    pub fn render(&self, format: TrendFormat) -> Result<String, serde_json::Error> {
+        match format {
+            TrendFormat::Table => Ok(self.to_table()),
+            TrendFormat::Csv => Ok(self.to_csv()),
+            TrendFormat::Json => serde_json::to_string_pretty(self),
+        }
+    }
+
+    /// Aligned table with one column per report and the change from the first to the last
+    fn to_table(&self) -> String {
+        let mut header = vec!["rule".to_string()];
+        header.extend(self.reports.iter().map(column_label));
+        header.push("change".to_string());
+
+        let mut rows: Vec<Vec<String>> = self.rules.iter()
+            .map(|(rule, counts)| row(rule, counts))
+            .collect();
+        rows.push(row("total", &self.totals()));
+
+        let widths: Vec<usize> = (0..header.len())
+            .map(|col| rows.iter().chain([&header]).map(|r| r[col].chars().count()).max().unwrap_or(0))
+            .collect();
+
+        let mut out = String::new();
+        for line in [&header].into_iter().chain(&rows) {
+            let cells: Vec<String> = line.iter()
+                .enumerate()
+                .map(|(col, cell)| if col == 0 { format!("{:<w$}", cell, w = widths[col]) } else { format!("{:>w$}", cell, w = widths[col]) })
+                .collect();
+            out.push_str(cells.join("  ").trim_end());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// CSV with one row per rule and one column per report
+    fn to_csv(&self) -> String {
+        let mut out = String::from("rule");
+        for report in &self.reports {
+            out.push(',');
+            out.push_str(&csv_field(&report.created_at));
+        }
+        out.push('\n');
+
+        for (rule, counts) in &self.rules {
+            out.push_str(&csv_field(rule));
+            for count in counts {
+                out.push_str(&format!(",{}", count));
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Report column label: the creation date, falling back to the report ID
+fn column_label(report: &ReportSummary) -> String {
+    match report.created_at.get(..10) {
+        Some(date) => date.to_string(),
+        None => report.report_id.clone(),
+    }
+}
+
+fn row(name: &str, counts: &[usize]) -> Vec<String> {
+    let mut row = vec![name.to_string()];
+    row.extend(counts.iter().map(|c| c.to_string()));
+
+    let change = match (counts.first(), counts.last()) {
+        (Some(&first), Some(&last)) => last as i64 - first as i64,
+        _ => 0,
+    };
+    row.push(format!("{:+}", change));
+    row
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
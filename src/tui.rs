@@ -0,0 +1,329 @@
+use crate::scanner::Scanner;
+use crate::types::{MatchResult, Severity, TreeSitterQuery};
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Gauge, List, ListItem, ListState, Paragraph},
+    Frame, Terminal,
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    error::Error,
+    fs, io,
+    time::Duration,
+};
+use tokio::sync::mpsc::{self, error::TryRecvError, UnboundedReceiver};
+
+/// Lines of context shown above and below a finding in the detail pane
+const SNIPPET_CONTEXT: usize = 5;
+
+/// Filters applied to the findings list
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FindingFilter {
+    /// Only show findings of this rule
+    pub rule: Option<String>,
+    /// Only show findings at or above this severity
+    pub min_severity: Option<Severity>,
+    /// Only show findings whose path contains this text (case-insensitive)
+    pub file: String,
+}
+
+impl FindingFilter {
+    /// Whether a finding passes every active filter
+    pub fn matches(&self, finding: &MatchResult) -> bool {
+        self.rule.as_ref().is_none_or(|rule| *rule == finding.question_id)
+            && self.min_severity.is_none_or(|min| finding.severity >= min)
+            && finding.file.to_lowercase().contains(&self.file.to_lowercase())
+    }
+}
+
+/// Findings for one file, sent from the background scan to the UI
+struct ScannedFile {
+    results: Vec<MatchResult>,
+}
+
+/// State of the terminal UI
+struct App {
+    findings: Vec<MatchResult>,
+    total_files: usize,
+    scanned_files: usize,
+    filter: FindingFilter,
+    list_state: ListState,
+    /// Whether keystrokes are being typed into the file filter
+    editing_file: bool,
+    /// Source lines of files shown in the detail pane, read on first use
+    sources: HashMap<String, Vec<String>>,
+}
+
+impl App {
+    fn new(total_files: usize) -> Self {
+        Self {
+            findings: Vec::new(),
+            total_files,
+            scanned_files: 0,
+            filter: FindingFilter::default(),
+            list_state: ListState::default(),
+            editing_file: false,
+            sources: HashMap::new(),
+        }
+    }
+
+    fn visible(&self) -> Vec<&MatchResult> {
+        self.findings.iter().filter(|f| self.filter.matches(f)).collect()
+    }
+
+    fn selected(&self) -> Option<&MatchResult> {
+        self.visible().get(self.list_state.selected()?).copied()
+    }
+
+    /// Keep the selection inside the visible list after findings or filters change
+    fn clamp_selection(&mut self) {
+        let len = self.visible().len();
+        let selected = match self.list_state.selected() {
+            _ if len == 0 => None,
+            Some(i) => Some(i.min(len - 1)),
+            None => Some(0),
+        };
+        self.list_state.select(selected);
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let len = self.visible().len();
+        if len == 0 {
+            return;
+        }
+        let current = self.list_state.selected().unwrap_or(0) as isize;
+        self.list_state.select(Some((current + delta).clamp(0, len as isize - 1) as usize));
+    }
+
+    /// Step the rule filter through all rules seen so far, then back to none
+    fn cycle_rule(&mut self) {
+        let rules: BTreeSet<&String> = self.findings.iter().map(|f| &f.question_id).collect();
+        let next = match &self.filter.rule {
+            None => rules.iter().next(),
+            Some(current) => rules.iter().skip_while(|r| **r != current).nth(1),
+        };
+        self.filter.rule = next.map(|r| r.to_string());
+    }
+
+    /// Step the minimum severity filter from info up to critical, then back to none
+    fn cycle_severity(&mut self) {
+        self.filter.min_severity = match self.filter.min_severity {
+            None => Some(Severity::Info),
+            Some(Severity::Info) => Some(Severity::Low),
+            Some(Severity::Low) => Some(Severity::Medium),
+            Some(Severity::Medium) => Some(Severity::High),
+            Some(Severity::High) => Some(Severity::Critical),
+            Some(Severity::Critical) => None,
+        };
+    }
+
+    /// Handle a key press, returning false when the UI should exit
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        if self.editing_file {
+            match code {
+                KeyCode::Enter | KeyCode::Esc => self.editing_file = false,
+                KeyCode::Backspace => {
+                    self.filter.file.pop();
+                }
+                KeyCode::Char(c) => self.filter.file.push(c),
+                _ => {}
+            }
+            self.clamp_selection();
+            return true;
+        }
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(10),
+            KeyCode::PageUp => self.move_selection(-10),
+            KeyCode::Char('r') => self.cycle_rule(),
+            KeyCode::Char('s') => self.cycle_severity(),
+            KeyCode::Char('/') => self.editing_file = true,
+            KeyCode::Char('c') => self.filter = FindingFilter::default(),
+            _ => {}
+        }
+        self.clamp_selection();
+        true
+    }
+
+    /// Source lines around the selected finding, reading the file on first use
+    fn snippet(&mut self) -> Vec<Line<'static>> {
+        let Some(finding) = self.selected().cloned() else {
+            return vec![Line::from("No finding selected")];
+        };
+
+        let lines = self.sources.entry(finding.file.clone()).or_insert_with(|| {
+            fs::read_to_string(&finding.file)
+                .map(|content| content.lines().map(str::to_string).collect())
+                .unwrap_or_default()
+        });
+
+        let mut snippet = vec![
+            Line::from(vec![
+                Span::styled(format!("[{}] ", finding.severity), severity_style(finding.severity)),
+                Span::styled(finding.question_id.clone(), Style::default().add_modifier(Modifier::BOLD)),
+            ]),
+            Line::from(format!("{}:{}:{}", finding.file, finding.line, finding.column)),
+            Line::from(""),
+        ];
+
+        let first = finding.line.saturating_sub(SNIPPET_CONTEXT).max(1);
+        let last = (finding.line + SNIPPET_CONTEXT).min(lines.len());
+        for number in first..=last {
+            let text = format!("{:>5} │ {}", number, lines[number - 1]);
+            snippet.push(if number == finding.line {
+                Line::from(Span::styled(text, Style::default().add_modifier(Modifier::REVERSED)))
+            } else {
+                Line::from(text)
+            });
+        }
+
+        snippet
+    }
+}
+
+fn severity_style(severity: Severity) -> Style {
+    let color = match severity {
+        Severity::Critical => Color::Magenta,
+        Severity::High => Color::Red,
+        Severity::Medium => Color::Yellow,
+        Severity::Low => Color::Blue,
+        Severity::Info => Color::DarkGray,
+    };
+    Style::default().fg(color)
+}
+
+/// Scan `files` in the background while showing progress and findings in a terminal UI.
+///
+/// Keys: ↑/↓ (or j/k) select, `r` cycles the rule filter, `s` the minimum severity,
+/// `/` edits the file filter, `c` clears filters and `q` quits.
+pub async fn run(scanner: Scanner, files: Vec<String>, queries: Vec<TreeSitterQuery>) -> Result<(), Box<dyn Error>> {
+    let total_files = files.len();
+    let scanner = scanner.with_quiet(true);
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    let scan = tokio::spawn(async move {
+        for file in files {
+            let results = scanner.scan_files(vec![file], queries.clone()).await;
+            if tx.send(ScannedFile { results }).is_err() {
+                break;
+            }
+        }
+    });
+
+    let ui = tokio::task::spawn_blocking(move || run_terminal(rx, total_files)).await;
+    scan.abort();
+    ui??;
+
+    Ok(())
+}
+
+/// Set up the terminal, run the event loop, and restore the terminal even if the loop fails
+fn run_terminal(rx: UnboundedReceiver<ScannedFile>, total_files: usize) -> io::Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let result = event_loop(&mut terminal, rx, App::new(total_files));
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn event_loop(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, mut rx: UnboundedReceiver<ScannedFile>, mut app: App) -> io::Result<()> {
+    let mut scan_running = true;
+
+    loop {
+        while scan_running {
+            match rx.try_recv() {
+                Ok(file) => {
+                    app.scanned_files += 1;
+                    app.findings.extend(file.results);
+                    app.clamp_selection();
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => scan_running = false,
+            }
+        }
+
+        terminal.draw(|frame| draw(frame, &mut app))?;
+
+        if event::poll(Duration::from_millis(100))?
+            && let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && !app.handle_key(key.code)
+        {
+            return Ok(());
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(0), Constraint::Length(1)])
+        .split(frame.size());
+
+    let ratio = if app.total_files == 0 { 1.0 } else { app.scanned_files as f64 / app.total_files as f64 };
+    let progress = Gauge::default()
+        .block(Block::default().borders(Borders::ALL).title("Scan progress"))
+        .gauge_style(Style::default().fg(Color::Green))
+        .ratio(ratio.min(1.0))
+        .label(format!("{}/{} files, {} findings", app.scanned_files, app.total_files, app.findings.len()));
+    frame.render_widget(progress, rows[0]);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(45), Constraint::Percentage(55)])
+        .split(rows[1]);
+    draw_findings(frame, app, columns[0]);
+
+    let detail = Paragraph::new(app.snippet())
+        .block(Block::default().borders(Borders::ALL).title("Detail"));
+    frame.render_widget(detail, columns[1]);
+
+    frame.render_widget(Paragraph::new(status_line(app)), rows[2]);
+}
+
+fn draw_findings(frame: &mut Frame, app: &mut App, area: Rect) {
+    let items: Vec<ListItem> = app.visible()
+        .into_iter()
+        .map(|f| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<8}", f.severity), severity_style(f.severity)),
+                Span::raw(format!("{}:{} ", f.file, f.line)),
+                Span::styled(f.question_id.clone(), Style::default().fg(Color::Cyan)),
+            ]))
+        })
+        .collect();
+
+    let title = format!("Findings ({})", items.len());
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, area, &mut app.list_state);
+}
+
+fn status_line(app: &App) -> String {
+    let rule = app.filter.rule.as_deref().unwrap_or("any");
+    let severity = app.filter.min_severity.map(|s| s.to_string()).unwrap_or_else(|| "any".to_string());
+    let cursor = if app.editing_file { "_" } else { "" };
+
+    format!(
+        " rule: {} [r]  severity ≥ {} [s]  file: {}{} [/]  clear [c]  quit [q]",
+        rule, severity, app.filter.file, cursor
+    )
+}
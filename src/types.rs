@@ -1,7 +1,71 @@
+use crate::language_loader::canonical_language;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Deserialize)]
+/// How serious a finding produced by a query is, ordered from least to most severe
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Low,
+    #[default]
+    Medium,
+    High,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "low" => Ok(Severity::Low),
+            "medium" => Ok(Severity::Medium),
+            "high" => Ok(Severity::High),
+            "critical" => Ok(Severity::Critical),
+            other => Err(format!("unknown severity: {}", other)),
+        }
+    }
+}
+
+/// A set of files a query runs against, parsed from one entry of its `file_type`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileTarget {
+    /// Every file resolving to the language, whatever its extension
+    Language(&'static str),
+    /// Files with this extension (including the leading dot), for extensions with no known language
+    Extension(String),
+}
+
+impl FileTarget {
+    /// Whether a file with the given extension and resolved language is targeted
+    pub fn matches(&self, extension: &str, language: Option<&str>) -> bool {
+        match self {
+            FileTarget::Language(lang) => language == Some(*lang),
+            FileTarget::Extension(ext) => ext.eq_ignore_ascii_case(extension),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TreeSitterQuery {
     pub question_id: String,
     pub file_type: String,
@@ -12,6 +76,51 @@ pub struct TreeSitterQuery {
     pub prompt: String,
     #[serde(default)]
     pub reasoning: String,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Keep at most this many matches per file, for broad exploratory queries
+    #[serde(default)]
+    pub sample: Option<usize>,
+}
+
+impl TreeSitterQuery {
+    /// Hash of everything that affects how the query runs and how its evidence is posted,
+    /// used to detect queries that changed between polls
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.question_id.hash(&mut hasher);
+        self.file_type.hash(&mut hasher);
+        self.query.hash(&mut hasher);
+        self.object_id.hash(&mut hasher);
+        self.reasoning.hash(&mut hasher);
+        self.severity.hash(&mut hasher);
+        self.sample.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Parse `file_type` into the files the query targets.
+    ///
+    /// Accepts language names (`rust`), extensions (`.rs`), or a comma-separated mix
+    /// (`.cpp,.h`). Entries naming a known language or one of its extensions target the
+    /// whole language; anything else is matched as a literal extension.
+    pub fn file_targets(&self) -> Vec<FileTarget> {
+        let mut targets = Vec::new();
+        for entry in self.file_type.split(',') {
+            let name = entry.trim().trim_start_matches('.').to_lowercase();
+            if name.is_empty() {
+                continue;
+            }
+
+            let target = match canonical_language(&name) {
+                Some(lang) => FileTarget::Language(lang),
+                None => FileTarget::Extension(format!(".{}", name)),
+            };
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        targets
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -25,7 +134,7 @@ pub struct InputData {
     pub code_base_version: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MatchResult {
     pub file: String,
     pub line: usize,
@@ -34,6 +143,20 @@ pub struct MatchResult {
     pub question_id: String,
     pub organization_id: String,
     pub code_base_version: String,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Whether this match was kept from a file whose matches were sampled
+    #[serde(default)]
+    pub sampled: bool,
+    /// Category of findings from built-in detectors, e.g. "pii"; none for query matches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// Whether tests execute the matched line, when a coverage report was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub covered: Option<bool>,
+    /// Labels added by a result transform script
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -42,6 +165,10 @@ pub struct CaptureResult {
     pub value: String,
     pub position: (usize, usize), // (line, column)
     pub node_type: String,
+    /// File the capture was found in, sent with uploaded evidence so reports can be rendered
+    /// with locations later
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,6 +185,20 @@ pub struct ApiResponse {
     pub report_id: String,
 }
 
+/// What an API key grants, as reported by the API
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApiKeyInfo {
+    /// Organizations the key can act for
+    #[serde(default)]
+    pub organizations: Vec<String>,
+    /// Permissions on the organization's reports, e.g. "reports:read" or "evidence:write"
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    /// When the key expires, as an RFC 3339 timestamp; unset if it doesn't
+    #[serde(default)]
+    pub expires_at: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct PosInputData {
     pub api_key: String,
@@ -0,0 +1,65 @@
+use crate::language_loader::{canonical_language, get_language};
+use serde::Serialize;
+use std::fmt;
+use tree_sitter::{Query, QueryError, QueryErrorKind};
+
+/// What stops a query from compiling, located in the query text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueryProblem {
+    /// 1-based line in the query text
+    pub line: usize,
+    /// 1-based column in the query text
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl QueryProblem {
+    fn new(error: &QueryError, language: &str) -> Self {
+        let name = &error.message;
+        let message = match error.kind {
+            QueryErrorKind::Syntax => "syntax error".to_string(),
+            QueryErrorKind::NodeType => format!("unknown node kind `{}` in {}", name, language),
+            QueryErrorKind::Field => format!("unknown field `{}` in {}", name, language),
+            QueryErrorKind::Capture => format!("unknown capture `@{}`; predicates can only use captures of their pattern", name),
+            QueryErrorKind::Predicate => format!("invalid predicate: {}", name),
+            QueryErrorKind::Structure => format!("impossible pattern: {} nodes can't be nested this way", language),
+            QueryErrorKind::Language => format!("grammar is incompatible with this version of tree-sitter: {}", name),
+        };
+        Self { line: error.row + 1, column: error.column + 1, message }
+    }
+
+    /// The problem followed by the offending line of `query` and a caret under the column
+    pub fn render(&self, query: &str) -> String {
+        let line = query.lines().nth(self.line - 1).unwrap_or_default();
+        format!("{}\n  {}\n  {}^", self, line, " ".repeat(self.column - 1))
+    }
+}
+
+/// Outcome of compiling a query
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueryCheck {
+    /// Canonical name of the language the query was compiled for
+    pub language: &'static str,
+    /// Capture names, in the order the query defines them
+    pub captures: Vec<String>,
+    /// Set if the query doesn't compile
+    pub error: Option<QueryProblem>,
+}
+
+/// Compile `query` for a language given by name, alias or extension, reporting where and why
+/// it fails instead of letting a broken query match nothing during a scan
+pub fn check_query(language: &str, query: &str) -> Result<QueryCheck, String> {
+    let name = canonical_language(language).ok_or_else(|| format!("unknown language: {}", language))?;
+    let grammar = get_language(name).ok_or_else(|| format!("no grammar built in for {}", name))?;
+
+    Ok(match Query::new(grammar, query) {
+        Ok(compiled) => QueryCheck { language: name, captures: compiled.capture_names().to_vec(), error: None },
+        Err(error) => QueryCheck { language: name, captures: Vec::new(), error: Some(QueryProblem::new(&error, name)) },
+    })
+}
@@ -0,0 +1,30 @@
+use crate::engine::Engine;
+use crate::rules::parse_queries;
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+/// A file handed to the scanner by the host, e.g. a file from a code review diff
+#[derive(Debug, Deserialize)]
+struct SourceFile {
+    path: String,
+    content: String,
+}
+
+/// Scan in-memory files with the given queries and return the findings as a JSON array.
+///
+/// `sources_json` is an array of `{"path": ..., "content": ...}` objects; the path only
+/// selects the language and which queries apply. `queries_json` accepts the same shapes
+/// as a local queries file. Nothing is read from disk or sent over the network.
+#[wasm_bindgen(js_name = scan)]
+pub fn scan(sources_json: &str, queries_json: &str, organization_id: &str, code_base_version: &str) -> Result<String, JsError> {
+    let sources: Vec<SourceFile> = serde_json::from_str(sources_json)
+        .map_err(|e| JsError::new(&format!("Invalid sources: {}", e)))?;
+    let queries = parse_queries(queries_json)
+        .map_err(|e| JsError::new(&format!("Invalid queries: {}", e)))?;
+
+    let engine = Engine::new(organization_id.to_string(), code_base_version.to_string()).with_quiet(true);
+    let sources = sources.into_iter().map(|s| (s.path, s.content)).collect();
+    let results = engine.scan_sources(sources, &queries);
+
+    serde_json::to_string(&results).map_err(|e| JsError::new(&e.to_string()))
+}
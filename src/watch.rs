@@ -0,0 +1,131 @@
+use crate::discovery::{is_scannable, normalize_path, MagmaIgnore, PathFilter};
+use crate::engine::Engine;
+use crate::types::{MatchResult, TreeSitterQuery};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    path::Path,
+    sync::mpsc,
+    time::Duration,
+};
+use tracing::warn;
+
+/// Findings of every watched file, kept current by rescanning only the files that change.
+/// Unchanged files keep their ASTs in the engine's cache between rescans, and changed files
+/// are re-parsed incrementally from theirs.
+pub struct WatchSession {
+    engine: Engine,
+    queries: Vec<TreeSitterQuery>,
+    /// Findings by normalized file path, including files without any
+    findings: BTreeMap<String, Vec<MatchResult>>,
+}
+
+impl WatchSession {
+    pub fn new(engine: Engine, queries: Vec<TreeSitterQuery>) -> Self {
+        Self { engine, queries, findings: BTreeMap::new() }
+    }
+
+    /// Scan every file, replacing all known findings
+    pub fn scan_all(&mut self, files: &[String]) -> Vec<MatchResult> {
+        self.findings = files.iter().map(|file| (normalize_path(file), Vec::new())).collect();
+        self.record(files)
+    }
+
+    /// Rescan files that changed. Edited files are re-parsed from their cached trees and only
+    /// the lines around each edit are queried again. Files that no longer exist are forgotten.
+    /// Returns the findings of the files that were rescanned.
+    pub fn rescan(&mut self, changed: &[String]) -> Vec<MatchResult> {
+        let mut rescanned = Vec::new();
+        for file in changed {
+            let key = normalize_path(file);
+            if !Path::new(file).is_file() {
+                self.engine.invalidate(file);
+                self.findings.remove(&key);
+                continue;
+            }
+            let previous = self.findings.remove(&key).unwrap_or_default();
+            let findings = self.engine.rescan_file(file, &self.queries).apply(previous);
+            rescanned.extend(findings.iter().cloned());
+            self.findings.insert(key, findings);
+        }
+        rescanned
+    }
+
+    fn record(&mut self, files: &[String]) -> Vec<MatchResult> {
+        let results = self.engine.scan_files(files, &self.queries);
+        for result in &results {
+            self.findings.entry(result.file.clone()).or_default().push(result.clone());
+        }
+        results
+    }
+
+    /// Whether the session holds findings for a file, which it then keeps track of even
+    /// once the file stops looking scannable, e.g. because it was deleted
+    pub fn knows(&self, file: &str) -> bool {
+        self.findings.contains_key(&normalize_path(file))
+    }
+
+    /// Number of files being tracked
+    pub fn files(&self) -> usize {
+        self.findings.len()
+    }
+
+    /// Current findings of every file, by file path
+    pub fn findings(&self) -> impl Iterator<Item = &MatchResult> {
+        self.findings.values().flatten()
+    }
+}
+
+/// Watch `target_dir` for changes and rescan the files discovery would pick up, honoring
+/// `filter` and the target's `.magmaignore`. Events arriving within `debounce` of each other
+/// are handled together, since editors often save in several steps. `on_rescan` is called
+/// with the changed files and their findings after each rescan. Runs until the watcher fails.
+pub fn watch(
+    target_dir: &str,
+    filter: &PathFilter,
+    session: &mut WatchSession,
+    debounce: Duration,
+    mut on_rescan: impl FnMut(&[String], &[MatchResult], &WatchSession),
+) -> Result<(), Box<dyn Error>> {
+    let root = Path::new(target_dir).canonicalize()?;
+    let ignore = MagmaIgnore::load(&root)?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+
+    let watched_file = |path: &Path, session: &WatchSession| {
+        let relative = path.strip_prefix(&root).ok()?;
+        let file = Path::new(target_dir).join(relative).to_string_lossy().to_string();
+        let scannable = is_scannable(path, &relative.to_string_lossy(), filter, ignore.as_ref());
+        (scannable || session.knows(&file)).then_some(file)
+    };
+
+    while let Ok(event) = rx.recv() {
+        let mut events = vec![event];
+        while let Ok(event) = rx.recv_timeout(debounce) {
+            events.push(event);
+        }
+
+        let mut changed = BTreeSet::new();
+        for event in events {
+            match event {
+                Ok(Event { kind: EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_), paths, .. }) => {
+                    changed.extend(paths.iter().filter_map(|path| watched_file(path, session)));
+                }
+                Ok(_) => {}
+                Err(e) => warn!("File watcher error: {}", e),
+            }
+        }
+        if changed.is_empty() {
+            continue;
+        }
+
+        let changed: Vec<String> = changed.into_iter().collect();
+        let results = session.rescan(&changed);
+        on_rescan(&changed, &results, session);
+    }
+
+    Err("File watcher stopped".into())
+}
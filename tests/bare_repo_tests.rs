@@ -0,0 +1,91 @@
+use magma_scanner::discovery::discover_commit;
+use magma_scanner::engine::Engine;
+use magma_scanner::types::TreeSitterQuery;
+use std::{fs, path::Path, process::Command};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+/// A bare clone of a repository with a supported file, an ignored directory, an unsupported
+/// file, a binary file and a path that isn't UTF-8, with `main` at a second commit changing
+/// the supported file
+fn bare_repo(root: &Path) -> std::path::PathBuf {
+    let work = root.join("work");
+    fs::create_dir_all(work.join("src")).unwrap();
+    fs::create_dir_all(work.join("node_modules/lib")).unwrap();
+    fs::write(work.join("src/lib.rs"), "fn first() {}\n").unwrap();
+    fs::write(work.join("node_modules/lib/index.js"), "function vendored() {}\n").unwrap();
+    fs::write(work.join("README.md"), "# Readme\n").unwrap();
+    fs::write(work.join("src/blob.rs"), [0xff, 0xfe, 0x00]).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        fs::write(work.join("src").join(std::ffi::OsStr::from_bytes(b"caf\xe9.rs")), "fn latin1() {}\n").unwrap();
+    }
+
+    git(&work, &["init", "-q", "-b", "main"]);
+    git(&work, &["add", "."]);
+    git(&work, &["commit", "-q", "-m", "first"]);
+    git(&work, &["tag", "v1"]);
+    fs::write(work.join("src/lib.rs"), "fn second() {}\n").unwrap();
+    git(&work, &["commit", "-q", "-am", "second"]);
+
+    let bare = root.join("repo.git");
+    git(root, &["clone", "-q", "--bare", "work", "repo.git"]);
+    bare
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discover_commit_reads_supported_files_at_ref() {
+        let dir = tempfile::tempdir().unwrap();
+        let bare = bare_repo(dir.path());
+
+        let head = discover_commit(&bare, "HEAD").unwrap();
+        // Binary files are listed, but have no text to scan
+        assert_eq!(head.files, vec!["src/blob.rs".to_string(), "src/lib.rs".to_string()]);
+        assert_eq!(head.sources.read("src/lib.rs").unwrap(), "fn second() {}\n");
+        assert_eq!(head.sources.size("src/lib.rs"), Some(15));
+        assert!(head.sources.read("src/blob.rs").is_none());
+        assert!(head.sources.read("src/missing.rs").is_none());
+        assert_eq!(head.commit.len(), 40);
+
+        let tagged = discover_commit(&bare, "v1").unwrap();
+        assert_eq!(tagged.sources.read("src/lib.rs").unwrap(), "fn first() {}\n");
+        assert_ne!(tagged.commit, head.commit);
+
+        assert!(discover_commit(&bare, "no-such-branch").is_err());
+    }
+
+    #[test]
+    fn test_engine_scans_in_memory_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let bare = bare_repo(dir.path());
+        let commit = discover_commit(&bare, "main").unwrap();
+
+        let engine = Engine::new("org".to_string(), commit.commit.clone()).with_sources(commit.sources);
+        let (_, source) = engine.parse_file("src/lib.rs", "rust").unwrap();
+        assert_eq!(source, "fn second() {}\n");
+        assert!(engine.parse_file("src/missing.rs", "rust").is_none());
+
+        let query = TreeSitterQuery {
+            question_id: "fns".to_string(),
+            file_type: "rust".to_string(),
+            query: "(function_item name: (identifier) @name)".to_string(),
+            ..Default::default()
+        };
+        let results = engine.scan_files(&commit.files, &[query]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].text, "second");
+    }
+}
@@ -0,0 +1,64 @@
+mod test_utils;
+
+use test_utils::finding;
+use magma_scanner::baseline::{fingerprint, Baseline};
+use std::path::Path;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_ignores_line_and_root() {
+        let before = finding("repo/src/main.rs", 10, "password", "q1");
+        let after = finding("checkout/src/main.rs", 42, "password", "q1");
+
+        assert_eq!(
+            fingerprint(&before, Path::new("repo")),
+            fingerprint(&after, Path::new("checkout"))
+        );
+        assert_ne!(
+            fingerprint(&before, Path::new("repo")),
+            fingerprint(&finding("repo/src/main.rs", 10, "password", "q2"), Path::new("repo"))
+        );
+    }
+
+    #[test]
+    fn test_prune_and_stats() {
+        let root = Path::new("repo");
+        let results = vec![
+            finding("repo/src/a.rs", 1, "secret", "q1"),
+            finding("repo/src/a.rs", 1, "secret", "q1"),
+            finding("repo/src/b.rs", 5, "token", "q1"),
+            finding("repo/src/b.rs", 9, "eval", "q2"),
+        ];
+
+        let mut baseline = Baseline::from_results(&results, root);
+
+        // Duplicate findings collapse into a single entry
+        assert_eq!(baseline.findings.len(), 3);
+        assert_eq!(baseline.findings[0].file, "src/a.rs");
+
+        let removed = baseline.prune(&results[2..], root);
+        assert_eq!(removed, 1);
+
+        let stats = baseline.stats();
+        assert_eq!(stats.total, 2);
+        assert_eq!(stats.by_rule.get("q1"), Some(&1));
+        assert_eq!(stats.by_file.get("src/b.rs"), Some(&2));
+    }
+
+    #[test]
+    fn test_new_findings_skips_known_ones() {
+        let baseline = Baseline::from_results(&[finding("repo/src/a.rs", 3, "secret", "q1")], Path::new("repo"));
+
+        // The known finding moved down the file and the checkout moved; only the new one is kept
+        let current = vec![
+            finding("checkout/src/a.rs", 12, "secret", "q1"),
+            finding("checkout/src/a.rs", 20, "token", "q1"),
+        ];
+        let fresh = baseline.new_findings(current, Path::new("checkout"));
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].text, "token");
+    }
+}
@@ -0,0 +1,217 @@
+use magma_scanner::batch::{load_manifest, BatchManifest};
+use magma_scanner::discovery::discover_commit;
+use magma_scanner::scanner::{Scanner, SharedResources};
+use magma_scanner::types::TreeSitterQuery;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::{fs, path::{Path, PathBuf}, process::Command, sync::Arc, time::Duration};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+/// A bare repository named `name` whose only file is `src/lib.rs`
+fn bare_repo(root: &Path, name: &str, lib: &str) -> PathBuf {
+    let work = root.join(name);
+    fs::create_dir_all(work.join("src")).unwrap();
+    fs::write(work.join("src/lib.rs"), lib).unwrap();
+    git(&work, &["init", "-q", "-b", "main"]);
+    git(&work, &["add", "."]);
+    git(&work, &["commit", "-q", "-m", "initial"]);
+
+    let bare = format!("{}.git", name);
+    git(root, &["clone", "-q", "--bare", name, &bare]);
+    root.join(bare)
+}
+
+fn functions_query() -> TreeSitterQuery {
+    TreeSitterQuery {
+        question_id: "fns".to_string(),
+        file_type: "rust".to_string(),
+        query: "(function_item name: (identifier) @name)".to_string(),
+        ..Default::default()
+    }
+}
+
+/// Requests an API server is handling right now, the most it handled at once, and how many it
+/// handled in total
+#[derive(Default)]
+struct Load {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+    total: AtomicUsize,
+}
+
+/// An API server answering every request with `200 OK` after a short delay, one request per
+/// connection, so overlapping uploads are seen overlapping
+async fn slow_api() -> (String, Arc<Load>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let load = Arc::new(Load::default());
+    let counted = load.clone();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let load = counted.clone();
+            tokio::spawn(async move {
+                let current = load.current.fetch_add(1, Ordering::SeqCst) + 1;
+                load.peak.fetch_max(current, Ordering::SeqCst);
+                read_request(&mut stream).await;
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                // Counted out before answering, since the answer frees the client's upload slot
+                load.current.fetch_sub(1, Ordering::SeqCst);
+                load.total.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}").await;
+            });
+        }
+    });
+    (url, load)
+}
+
+/// Read one HTTP request's headers and `Content-Length` body
+async fn read_request(stream: &mut tokio::net::TcpStream) {
+    let mut request = Vec::new();
+    let mut buf = [0; 4096];
+    loop {
+        let read = stream.read(&mut buf).await.unwrap();
+        if read == 0 {
+            return;
+        }
+        request.extend_from_slice(&buf[..read]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some(end) = text.find("\r\n\r\n") {
+            let length = text[..end].lines()
+                .find_map(|line| line.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                .unwrap_or(0);
+            if request.len() >= end + 4 + length {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest_path = dir.path().join("repos.json");
+        fs::write(&manifest_path, r#"{
+            "parallelism": 4,
+            "repos": [
+                { "url": "git@github.com:acme/payments.git", "branch": "main" },
+                { "name": "billing", "url": "https://github.com/acme/billing-service/" },
+                { "path": "/srv/checkouts/frontend", "organization_id": "org_frontend" }
+            ]
+        }"#).unwrap();
+
+        let manifest = load_manifest(&manifest_path).unwrap();
+
+        assert_eq!(manifest.parallelism, Some(4));
+        assert_eq!(manifest.repos.len(), 3);
+        assert_eq!(manifest.repos[0].display_name(), "payments");
+        assert_eq!(manifest.repos[0].branch.as_deref(), Some("main"));
+        assert_eq!(manifest.repos[1].display_name(), "billing");
+        assert_eq!(manifest.repos[2].display_name(), "/srv/checkouts/frontend");
+        assert_eq!(manifest.repos[2].organization_id.as_deref(), Some("org_frontend"));
+    }
+
+    #[test]
+    fn test_checkout_dirs_of_same_named_repos_differ() {
+        let manifest: BatchManifest = serde_json::from_str(r#"{
+            "repos": [
+                { "url": "git@github.com:org-a/api.git" },
+                { "url": "git@github.com:org-b/api.git" },
+                { "name": "team/api", "url": "https://github.com/org-c/api" }
+            ]
+        }"#).unwrap();
+        let workdir = Path::new("/tmp/batch");
+
+        let dirs: Vec<PathBuf> = manifest.repos.iter().map(|entry| entry.checkout_dir(workdir)).collect();
+
+        assert_ne!(dirs[0], dirs[1]);
+        assert!(dirs.iter().all(|dir| dir.parent() == Some(workdir)));
+        assert!(dirs[0].file_name().unwrap().to_string_lossy().starts_with("api-"));
+        assert!(dirs[2].file_name().unwrap().to_string_lossy().starts_with("team-api-"));
+        // The same entry is checked out to the same place on every run, so it is updated in place
+        assert_eq!(manifest.repos[0].checkout_dir(workdir), dirs[0]);
+    }
+
+    #[tokio::test]
+    async fn test_bare_repos_sharing_a_path_scan_their_own_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let shared = SharedResources::new(1);
+        let query = functions_query();
+
+        let mut names = Vec::new();
+        for (name, lib) in [("payments", "fn charge() {}\n"), ("billing", "fn invoice() {}\n")] {
+            let commit = discover_commit(&bare_repo(dir.path(), name, lib), "HEAD").unwrap();
+            let scanner = Scanner::new("key".to_string(), "org".to_string(), commit.commit.clone(), Some("report".to_string()))
+                .with_shared_resources(&shared)
+                .with_sources(commit.sources);
+            let results = scanner.scan_files(commit.files, vec![query.clone()]).await;
+            names.extend(results.into_iter().map(|result| result.text));
+        }
+
+        assert_eq!(names, vec!["charge", "invoice"]);
+    }
+
+    #[tokio::test]
+    async fn test_shared_ast_cache_drops_a_finished_repo() {
+        let dir = tempfile::tempdir().unwrap();
+        let shared = SharedResources::new(1);
+        let scan = |name: &str| {
+            let file = dir.path().join(name).join("lib.rs");
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(&file, "fn main() {}\n").unwrap();
+            let scanner = Scanner::new("key".to_string(), "org".to_string(), "commit".to_string(), Some("report".to_string()))
+                .with_shared_resources(&shared);
+            (scanner, vec![file.to_string_lossy().to_string()])
+        };
+        let (payments, payments_files) = scan("payments");
+        let (billing, billing_files) = scan("billing");
+
+        payments.scan_files(payments_files.clone(), vec![functions_query()]).await;
+        billing.scan_files(billing_files.clone(), vec![functions_query()]).await;
+        // Both scanners see every tree in the one cache
+        assert_eq!((payments.cached_files(), billing.cached_files()), (2, 2));
+
+        payments.evict_files(&payments_files);
+        assert_eq!(billing.cached_files(), 1);
+        billing.evict_files(&billing_files);
+        assert_eq!(payments.cached_files(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_uploads_across_repos_share_the_global_cap() {
+        let (url, load) = slow_api().await;
+        let shared = SharedResources::new(2);
+        let queries: Vec<TreeSitterQuery> = (0..4)
+            .map(|i| TreeSitterQuery { question_id: format!("q{}", i), ..functions_query() })
+            .collect();
+        let scanner = || Scanner::new("key".to_string(), "org".to_string(), "commit".to_string(), Some("report".to_string()))
+            .with_api_base_url(url.clone())
+            .with_shared_resources(&shared)
+            .with_upload_concurrency(4);
+        let (payments, billing) = (scanner(), scanner());
+
+        let (first, second) = tokio::join!(
+            payments.post_query_results(&queries, &[]),
+            billing.post_query_results(&queries, &[]),
+        );
+
+        assert!(first.is_ok() && second.is_ok());
+        assert_eq!(load.total.load(Ordering::SeqCst), 8);
+        // Each repository alone may upload four at once, but together they stay within two
+        assert_eq!(load.peak.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,43 @@
+use magma_scanner::bench::standard_queries;
+use magma_scanner::engine::Engine;
+use std::fs;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_standard_queries_only_use_node_kinds_of_the_grammar() {
+        let queries = standard_queries(&["rust", "python"]);
+        let rust: Vec<&str> = queries.iter()
+            .filter(|query| query.file_type == "rust")
+            .map(|query| query.question_id.as_str())
+            .collect();
+
+        // Rust has no `string` or `comment` nodes, but `string_literal` and `line_comment`
+        assert!(rust.contains(&"bench/identifiers"));
+        assert!(rust.contains(&"bench/calls"));
+        assert!(!rust.contains(&"bench/strings"));
+        assert!(queries.iter().any(|query| query.file_type == "python" && query.question_id == "bench/strings"));
+    }
+
+    #[test]
+    fn test_benchmark_times_parsing_and_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let rust = dir.path().join("main.rs");
+        let python = dir.path().join("app.py");
+        fs::write(&rust, "fn main() { run(); run(); }\n").unwrap();
+        fs::write(&python, "print('hi')\n").unwrap();
+        let files = vec![rust.to_string_lossy().to_string(), python.to_string_lossy().to_string()];
+
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string());
+        let report = engine.benchmark(&files, &standard_queries(&["rust", "python"]));
+
+        assert_eq!(report.languages["rust"].files, 1);
+        assert_eq!(report.languages["python"].bytes, 12);
+        assert_eq!(report.queries["bench/calls"].runs, 1);
+        assert_eq!(report.queries["bench/calls"].matches, 2);
+        assert_eq!(report.queries["bench/strings"].runs, 1);
+        assert!(report.wall_ms > 0.0);
+    }
+}
@@ -0,0 +1,86 @@
+use magma_scanner::bundle::{config_hash, Bundle, BundleSummary};
+use magma_scanner::sbom::collect_components;
+use magma_scanner::types::{MatchResult, Severity, TreeSitterQuery};
+use std::fs;
+
+fn sample_bundle() -> Bundle {
+    let findings = vec![MatchResult {
+        file: "src/main.rs".to_string(),
+        line: 3,
+        column: 5,
+        text: "password".to_string(),
+        question_id: "secrets".to_string(),
+        organization_id: "test_org_id".to_string(),
+        code_base_version: "abc123".to_string(),
+        severity: Severity::High,
+        sampled: false,
+        category: None,
+        covered: None,
+        tags: Vec::new(),
+    }];
+    let queries = vec![TreeSitterQuery {
+        question_id: "secrets".to_string(),
+        file_type: ".rs".to_string(),
+        query: "(string_literal) @s".to_string(),
+        ..Default::default()
+    }];
+
+    Bundle {
+        created_at: 0,
+        organization_id: "test_org_id".to_string(),
+        code_base_version: "abc123".to_string(),
+        branch_name: "main".to_string(),
+        repo_url: "git@example.com:repo.git".to_string(),
+        file_types: vec!["rs".to_string()],
+        config_hash: config_hash(&queries, None),
+        summary: BundleSummary::new(1, &findings),
+        sbom: Vec::new(),
+        skipped_files: vec!["README.md".to_string()],
+        queries,
+        findings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signed_bundle_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.json");
+
+        sample_bundle().write_signed(&path, b"shared secret").unwrap();
+        let bundle = Bundle::read_signed(&path, b"shared secret").unwrap();
+
+        assert_eq!(bundle.findings.len(), 1);
+        assert_eq!(bundle.summary.by_rule["secrets"], 1);
+        assert_eq!(bundle.skipped_files, vec!["README.md"]);
+        assert!(Bundle::read_signed(&path, b"another key").is_err());
+    }
+
+    #[test]
+    fn test_tampered_bundle_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.json");
+        sample_bundle().write_signed(&path, b"shared secret").unwrap();
+
+        let tampered = fs::read_to_string(&path).unwrap().replace("\"line\":3", "\"line\":4");
+        fs::write(&path, tampered).unwrap();
+
+        assert!(Bundle::read_signed(&path, b"shared secret").is_err());
+    }
+
+    #[test]
+    fn test_collect_components_from_lockfiles() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.lock"), "[[package]]\nname = \"serde\"\nversion = \"1.0.200\"\n").unwrap();
+        fs::write(dir.path().join("requirements.txt"), "requests==2.31.0  # http\nflask>=2\n").unwrap();
+
+        let components = collect_components(dir.path());
+
+        assert_eq!(components.len(), 2);
+        assert!(components.iter().any(|c| c.ecosystem == "cargo" && c.name == "serde" && c.version == "1.0.200"));
+        assert!(components.iter().any(|c| c.ecosystem == "pypi" && c.name == "requests" && c.source == "requirements.txt"));
+    }
+}
@@ -0,0 +1,58 @@
+use magma_scanner::complexity::analyze;
+use magma_scanner::engine::Engine;
+use magma_scanner::types::Severity;
+
+const RUST_SOURCE: &str = "fn simple() {}
+
+fn branchy(x: i32) -> i32 {
+    if x > 0 && x < 10 {
+        return 1;
+    }
+    for i in 0..x {
+        let f = |y: i32| if y > 0 { y } else { 0 };
+        f(i);
+    }
+    match x {
+        1 => 1,
+        2 => 2,
+        _ => 0,
+    }
+}
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_decisions_per_function() {
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string());
+        let (tree, source) = engine.parse_source(RUST_SOURCE.to_string(), "rust").unwrap();
+
+        let functions = analyze(&tree, &source, "rust");
+        let measured: Vec<(&str, usize)> = functions.iter().map(|f| (f.name.as_str(), f.complexity)).collect();
+
+        // branchy: if, &&, for and three match arms; the closure's `if` counts only for the closure
+        assert_eq!(measured, vec![("simple", 1), ("branchy", 7), ("<anonymous>", 2)]);
+        assert_eq!((functions[1].line, functions[1].column), (3, 1));
+    }
+
+    #[test]
+    fn test_engine_reports_functions_over_threshold() {
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string())
+            .with_quiet(true)
+            .with_complexity_threshold(3);
+
+        let results = engine.scan_source_as("lib.rs", RUST_SOURCE.to_string(), "rust", &[]).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].question_id, "complexity/cyclomatic");
+        assert_eq!(results[0].category.as_deref(), Some("complexity"));
+        assert_eq!(results[0].severity, Severity::Medium);
+        assert_eq!(results[0].text, "branchy has cyclomatic complexity 7 (threshold 3)");
+
+        let summary = engine.take_complexity_summary().unwrap();
+        assert_eq!((summary.functions, summary.total, summary.max, summary.over_threshold), (3, 10, 7, 1));
+        assert_eq!(engine.take_complexity_summary().unwrap().functions, 0);
+    }
+}
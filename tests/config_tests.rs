@@ -0,0 +1,67 @@
+use magma_scanner::config::{read_secret_file, Config};
+use std::fs;
+use std::path::Path;
+
+fn write_config(dir: &Path, key_file: &Path) -> Config {
+    let path = dir.join("config.json");
+    let config = serde_json::json!({
+        "default_profile": "acme",
+        "profiles": {
+            "acme": { "api_url": "https://acme.example/v1", "organization_id": "org_acme", "api_key_file": key_file },
+            "globex": { "organization_id": "org_globex", "api_key_env": "MAGMA_CONFIG_TESTS_UNSET_KEY" }
+        }
+    });
+    fs::write(&path, config.to_string()).unwrap();
+    Config::load(&path).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_profile_supplies_connection() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_file = dir.path().join("acme-key");
+        fs::write(&key_file, "secret-acme\n").unwrap();
+        let config = write_config(dir.path(), &key_file);
+
+        let connection = config.connection(Some("acme"), None, None).unwrap();
+        assert_eq!(connection.api_key.as_deref(), Some("secret-acme"));
+        assert_eq!(connection.organization_id.as_deref(), Some("org_acme"));
+        assert_eq!(connection.api_url.as_deref(), Some("https://acme.example/v1"));
+    }
+
+    #[test]
+    fn test_flags_override_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_file = dir.path().join("acme-key");
+        fs::write(&key_file, "secret-acme").unwrap();
+        let config = write_config(dir.path(), &key_file);
+
+        // The profile's key source is not read when the flag gives one, so an unset variable is fine
+        let connection = config.connection(Some("globex"), Some("flag-key".into()), Some("org_flag".into())).unwrap();
+        assert_eq!(connection.api_key.as_deref(), Some("flag-key"));
+        assert_eq!(connection.organization_id.as_deref(), Some("org_flag"));
+    }
+
+    #[test]
+    fn test_unknown_profile_and_missing_key_source_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_file = dir.path().join("acme-key");
+        fs::write(&key_file, "secret-acme").unwrap();
+        let config = write_config(dir.path(), &key_file);
+
+        let err = config.connection(Some("initech"), None, None).unwrap_err().to_string();
+        assert!(err.contains("initech") && err.contains("acme, globex"), "{}", err);
+        assert!(config.connection(Some("globex"), None, None).is_err());
+    }
+
+    #[test]
+    fn test_read_secret_file_rejects_empty_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty");
+        fs::write(&path, " \n").unwrap();
+        assert!(read_secret_file(&path).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use magma_scanner::coverage::{Coverage, UntestedFile};
+use magma_scanner::engine::Engine;
+use magma_scanner::types::{Severity, TreeSitterQuery};
+use std::fs;
+
+const LCOV: &str = "TN:
+SF:/ci/build/src/auth.rs
+DA:1,4
+DA:2,0
+DA:3,1
+end_of_record
+SF:/ci/build/src/admin.rs
+DA:1,0
+DA:2,0
+end_of_record
+";
+
+const COBERTURA: &str = r#"<?xml version="1.0" ?>
+<coverage line-rate="0.5">
+  <sources><source>/ci/build</source></sources>
+  <packages><package name="app"><classes>
+    <class name="views" filename="app/views.py" line-rate="0.5">
+      <lines>
+        <line number="3" hits="2"/>
+        <line hits="0" number="4"/>
+      </lines>
+    </class>
+  </classes></package></packages>
+</coverage>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_lcov_and_cobertura() {
+        let lcov = Coverage::parse_lcov(LCOV);
+        assert_eq!(lcov.files(), 2);
+        // Paths match when one ends with the other
+        assert_eq!(lcov.is_covered("repo/src/auth.rs", 1), Some(true));
+        assert_eq!(lcov.is_covered("repo/src/auth.rs", 2), Some(false));
+        assert_eq!(lcov.is_covered("repo/src/auth.rs", 9), None);
+        assert_eq!(lcov.is_covered("repo/src/other.rs", 1), Some(false));
+
+        let cobertura = Coverage::parse_cobertura(COBERTURA);
+        assert_eq!(cobertura.is_covered("./app/views.py", 3), Some(true));
+        assert_eq!(cobertura.is_covered("./app/views.py", 4), Some(false));
+    }
+
+    #[test]
+    fn test_annotates_findings_and_lists_untested_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = dir.path().join("lcov.info");
+        fs::write(&report, LCOV).unwrap();
+        let coverage = Coverage::load(&report).unwrap();
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string())
+            .with_quiet(true)
+            .with_coverage(coverage);
+
+        let queries = vec![TreeSitterQuery {
+            question_id: "unsafe".to_string(),
+            file_type: ".rs".to_string(),
+            query: "(unsafe_block) @unsafe".to_string(),
+            severity: Severity::Critical,
+            ..Default::default()
+        }];
+        let source = "fn a() {\n    unsafe { f() }\n}\n".to_string();
+        let mut results = engine.scan_source_as("src/auth.rs", source.clone(), "rust", &queries).unwrap();
+        results.extend(engine.scan_source_as("src/admin.rs", source, "rust", &queries).unwrap());
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].covered, Some(false));
+
+        let untested = engine.coverage().unwrap().untested_files(&results, Severity::Critical);
+        assert_eq!(untested, vec![UntestedFile { file: "src/admin.rs".to_string(), findings: 1 }]);
+    }
+}
@@ -0,0 +1,62 @@
+use magma_scanner::dead_code::{matches_pattern, SymbolIndex, DEFAULT_ENTRY_POINTS};
+use magma_scanner::engine::Engine;
+
+fn index_for(sources: &[(&str, &'static str, &str)]) -> SymbolIndex {
+    let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string());
+    let mut index = SymbolIndex::new();
+    for (path, language, source) in sources {
+        let (tree, source) = engine.parse_source(source.to_string(), language).unwrap();
+        index.add_file(path, language, &tree, &source);
+    }
+    index
+}
+
+fn defaults() -> Vec<String> {
+    DEFAULT_ENTRY_POINTS.iter().map(|p| p.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_exports_unreferenced_elsewhere() {
+        let index = index_for(&[
+            ("lib.rs", "rust", "pub fn used() {}\npub fn orphan() { helper() }\nfn helper() {}\npub struct Config;\n"),
+            ("main.rs", "rust", "fn main() { used(); let c: Config = todo!(); }\n"),
+        ]);
+
+        let unused = index.unused(&defaults());
+
+        // helper is private and main is an entry point, so only orphan is reported
+        let names: Vec<(&str, &str, usize)> = unused.iter().map(|s| (s.name.as_str(), s.kind.as_str(), s.line)).collect();
+        assert_eq!(names, vec![("orphan", "function", 2)]);
+    }
+
+    #[test]
+    fn test_per_language_export_rules() {
+        let index = index_for(&[
+            ("util.py", "python", "def public_api():\n    pass\n\ndef _private():\n    pass\n"),
+            ("server.go", "go", "package server\n\nfunc Serve() {}\n\nfunc listen() {}\n"),
+            ("app.js", "javascript", "export function render() {}\nexport default function App() {}\nfunction local() {}\n"),
+        ]);
+
+        let mut names: Vec<String> = index.unused(&defaults()).into_iter().map(|s| s.name).collect();
+        names.sort();
+        assert_eq!(names, vec!["Serve", "public_api", "render"]);
+
+        let allowed = vec!["public_*".to_string(), "Serve".to_string()];
+        let names: Vec<String> = index.unused(&allowed).into_iter().map(|s| s.name).collect();
+        assert_eq!(names, vec!["render"]);
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("main", "main"));
+        assert!(!matches_pattern("main", "main2"));
+        assert!(matches_pattern("test*", "test_parse"));
+        assert!(matches_pattern("*_handler", "http_handler"));
+        assert!(matches_pattern("on*Event", "onClickEvent"));
+        assert!(!matches_pattern("on*Event", "onClick"));
+    }
+}
@@ -0,0 +1,163 @@
+use magma_scanner::discovery::{discover_files, discover_files_matching, find_targets_matching, parse_file_list, retain_changed, MagmaIgnore, PathFilter, IGNORE_FILE};
+use std::{fs, path::Path, process::Command};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+    let strings = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+    PathFilter::new(&strings(include), &strings(exclude)).unwrap()
+}
+
+/// A tree with owned code, generated code and vendored code
+fn sample_tree(root: &Path) {
+    for (path, content) in [
+        ("src/main.rs", "fn main() {}\n"),
+        ("src/generated/schema.rs", "fn schema() {}\n"),
+        ("src/api/generated/client.rs", "fn client() {}\n"),
+        ("vendor/lib.js", "function lib() {}\n"),
+        ("scripts/build.py", "print('build')\n"),
+    ] {
+        let file = root.join(path);
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(file, content).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_filter_patterns() {
+        let filter = filter(&["src/**", "*.py"], &["**/generated/**"]);
+
+        assert!(filter.allows("src/main.rs"));
+        assert!(filter.allows("scripts/build.py"));
+        assert!(!filter.allows("src/generated/schema.rs"));
+        assert!(!filter.allows("vendor/lib.js"));
+
+        // A leading slash anchors the pattern to the target directory
+        let anchored = self::filter(&["/src/*.rs"], &[]);
+        assert!(anchored.allows("src/main.rs"));
+        assert!(!anchored.allows("lib/src/main.rs"));
+        assert!(!anchored.allows("src/api/client.rs"));
+
+        assert!(PathFilter::new(&["[".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_discovery_applies_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        sample_tree(dir.path());
+        let target = dir.path().to_string_lossy().to_string();
+
+        let discovered = discover_files_matching(&target, &filter(&[], &["**/generated/**", "/vendor/**"])).unwrap();
+        let mut files: Vec<String> = discovered.files.iter()
+            .map(|file| Path::new(file).strip_prefix(dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["scripts/build.py", "src/main.rs"]);
+        assert!(discovered.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_magmaignore_uses_gitignore_syntax() {
+        let ignore = MagmaIgnore::parse("# third-party snapshots\nvendor/\n*.py\n!scripts/build.py\n/src/generated\n").unwrap();
+
+        assert!(ignore.is_ignored("vendor/lib.js", false));
+        assert!(ignore.is_ignored("tools/gen.py", false));
+        assert!(!ignore.is_ignored("scripts/build.py", false));
+        assert!(ignore.is_ignored("src/generated/schema.rs", false));
+        // Only the top-level directory is anchored by the leading slash
+        assert!(!ignore.is_ignored("src/api/generated/client.rs", false));
+        assert!(!ignore.is_ignored("src/main.rs", false));
+    }
+
+    #[test]
+    fn test_discovery_honors_magmaignore() {
+        let dir = tempfile::tempdir().unwrap();
+        sample_tree(dir.path());
+        fs::write(dir.path().join(IGNORE_FILE), "vendor/\n**/generated/\n").unwrap();
+
+        let discovered = discover_files(&dir.path().to_string_lossy()).unwrap();
+        let mut files: Vec<String> = discovered.files.iter()
+            .map(|file| Path::new(file).strip_prefix(dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["scripts/build.py", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_retain_changed_keeps_modified_and_staged_files() {
+        let dir = tempfile::tempdir().unwrap();
+        sample_tree(dir.path());
+        git(dir.path(), &["init", "-q"]);
+        git(dir.path(), &["add", "."]);
+        git(dir.path(), &["commit", "-q", "-m", "initial"]);
+
+        fs::write(dir.path().join("src/main.rs"), "fn main() { run(); }\n").unwrap();
+        fs::write(dir.path().join("src/staged.rs"), "fn staged() {}\n").unwrap();
+        git(dir.path(), &["add", "src/staged.rs"]);
+        fs::write(dir.path().join("src/untracked.rs"), "fn untracked() {}\n").unwrap();
+
+        let target = dir.path().to_string_lossy().to_string();
+        let files = retain_changed(discover_files(&target).unwrap().files, &target).unwrap();
+        let mut files: Vec<String> = files.iter()
+            .map(|file| Path::new(file).strip_prefix(dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["src/main.rs", "src/staged.rs"]);
+    }
+
+    #[test]
+    fn test_find_targets_combines_directories_and_files() {
+        let dir = tempfile::tempdir().unwrap();
+        sample_tree(dir.path());
+        let path = |relative: &str| dir.path().join(relative).to_string_lossy().to_string();
+
+        let targets = vec![path("src"), path("scripts/build.py"), path("src/main.rs")];
+        let files = find_targets_matching(&targets, &filter(&[], &["**/generated/**"]), false).unwrap();
+        let mut files: Vec<String> = files.iter()
+            .map(|file| Path::new(file).strip_prefix(dir.path()).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        files.sort();
+        assert_eq!(files, vec!["scripts/build.py", "src/main.rs"]);
+
+        assert!(find_targets_matching(&[path("missing.rs")], &filter(&[], &[]), false).is_err());
+        assert_eq!(parse_file_list("src/a.rs\n\n  src/b.py \r\n"), vec!["src/a.rs", "src/b.py"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_discovery_follows_symlinks_only_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        sample_tree(dir.path());
+        fs::write(outside.path().join("shared.rs"), "fn shared() {}\n").unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("linked")).unwrap();
+        std::os::unix::fs::symlink(dir.path().join("src/main.rs"), dir.path().join("alias.rs")).unwrap();
+
+        let target = dir.path().to_string_lossy().to_string();
+        let relative = |filter: PathFilter| {
+            let mut files: Vec<String> = discover_files_matching(&target, &filter).unwrap().files.iter()
+                .map(|file| Path::new(file).strip_prefix(dir.path()).unwrap().to_string_lossy().to_string())
+                .collect();
+            files.sort();
+            files
+        };
+
+        let skipped = relative(filter(&["src/main.rs", "linked/**", "alias.rs"], &[]));
+        assert_eq!(skipped, vec!["src/main.rs"]);
+
+        let followed = relative(filter(&["src/main.rs", "linked/**", "alias.rs"], &[]).with_follow_symlinks(true));
+        assert_eq!(followed, vec!["alias.rs", "linked/shared.rs", "src/main.rs"]);
+    }
+}
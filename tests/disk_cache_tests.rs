@@ -0,0 +1,136 @@
+mod test_utils;
+
+use test_utils::{create_test_query, finding};
+use magma_scanner::disk_cache::{CacheKey, CacheStats, DiskCache, GRAMMAR_VERSIONS};
+use magma_scanner::engine::Engine;
+use magma_scanner::types::MatchResult;
+use std::{fs, thread, time::Duration};
+
+fn texts(results: &[MatchResult]) -> Vec<&str> {
+    results.iter().map(|result| result.text.as_str()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_by_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(dir.path());
+        let key = CacheKey::builder().part("main.rs").part("fn main() {}").finish();
+        assert_ne!(key, CacheKey::builder().part("main.rs").part("fn main() { }").finish());
+        assert_ne!(CacheKey::builder().part("ab").part("c").finish(), CacheKey::builder().part("a").part("bc").finish());
+
+        assert!(cache.get(&key).is_none());
+        cache.put(&key, &[finding("main.rs", 1, "main", "functions")]);
+        assert_eq!(texts(&cache.get(&key).unwrap()), vec!["main"]);
+        assert_eq!(cache.len(), 1);
+
+        // Corrupt entries are misses
+        let entry = dir.path().join("1").join(&key.as_str()[..2]).join(format!("{}.json", key.as_str()));
+        fs::write(&entry, "{").unwrap();
+        assert!(cache.get(&key).is_none());
+
+        cache.clear().unwrap();
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_pruning_by_ttl_and_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(dir.path());
+        for file in ["a.rs", "b.rs", "c.rs"] {
+            cache.put(&CacheKey::builder().part(file).finish(), &[finding(file, 1, "main", "functions")]);
+        }
+        assert_eq!(cache.prune(), 0);
+
+        let capped = DiskCache::new(dir.path()).with_max_bytes(0);
+        assert_eq!(capped.prune(), 3);
+        assert!(cache.is_empty());
+
+        let key = CacheKey::builder().part("a.rs").finish();
+        cache.put(&key, &[finding("a.rs", 1, "main", "functions")]);
+        thread::sleep(Duration::from_millis(20));
+        let expired = DiskCache::new(dir.path()).with_ttl(Duration::ZERO);
+        assert!(expired.get(&key).is_none());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_pruning_is_throttled() {
+        let dir = tempfile::tempdir().unwrap();
+        let capped = DiskCache::new(dir.path()).with_max_bytes(0);
+        capped.put(&CacheKey::builder().part("a.rs").finish(), &[finding("a.rs", 1, "main", "functions")]);
+        assert_eq!(capped.prune_if_due(), 1);
+
+        // Pruned moments ago, so later scans leave the cache alone
+        capped.put(&CacheKey::builder().part("b.rs").finish(), &[finding("b.rs", 1, "main", "functions")]);
+        assert_eq!(capped.prune_if_due(), 0);
+        assert_eq!(capped.len(), 1);
+    }
+
+    #[test]
+    fn test_stats_add_up_lookups_across_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DiskCache::new(dir.path());
+        let key = CacheKey::builder().part("main.rs").finish();
+        assert_eq!(cache.stats(), CacheStats::default());
+        assert_eq!(cache.stats().hit_rate(), None);
+
+        assert!(cache.get(&key).is_none());
+        cache.put(&key, &[finding("main.rs", 1, "main", "functions")]);
+        assert!(cache.get(&key).is_some());
+        cache.record_lookups();
+
+        // A later run sees the lookups of earlier ones
+        let later = DiskCache::new(dir.path());
+        assert!(later.get(&key).is_some());
+        later.record_lookups();
+        let stats = later.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (1, 2, 1));
+        assert!(stats.bytes > 0);
+        assert_eq!(stats.hit_rate(), Some(2.0 / 3.0));
+
+        later.clear().unwrap();
+        assert_eq!(later.stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn test_grammar_versions_come_from_the_lockfile() {
+        let versions = GRAMMAR_VERSIONS.unwrap();
+        assert!(versions.lines().any(|line| line.starts_with("tree-sitter ")));
+        assert!(versions.lines().any(|line| line.starts_with("tree-sitter-rust ")));
+    }
+
+    #[test]
+    fn test_engine_reuses_findings_of_unchanged_files() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("main.rs").to_string_lossy().to_string();
+        fs::write(&file, "fn main() {}\nfn helper() {}\n").unwrap();
+        let functions = create_test_query("rs", "(function_item name: (identifier) @name)");
+        let engine = |version: &str| {
+            Engine::new("org".to_string(), version.to_string()).with_disk_cache(DiskCache::new(cache_dir.path()))
+        };
+
+        let first = engine("v1");
+        assert_eq!(texts(&first.scan_files(std::slice::from_ref(&file), std::slice::from_ref(&functions))), vec!["main", "helper"]);
+        assert_eq!(first.cached_files(), 1);
+
+        // A later run takes the findings from disk without parsing the file
+        let second = engine("v2");
+        let results = second.scan_files(std::slice::from_ref(&file), std::slice::from_ref(&functions));
+        assert_eq!(texts(&results), vec!["main", "helper"]);
+        assert!(results.iter().all(|result| result.code_base_version == "v2"));
+        assert_eq!(second.cached_files(), 0);
+
+        // Changed queries and changed files are scanned again
+        let parameters = create_test_query("rs", "(parameters) @parameters");
+        assert_eq!(texts(&engine("v3").scan_files(std::slice::from_ref(&file), &[parameters])), vec!["()", "()"]);
+        fs::write(&file, "fn other() {}\n").unwrap();
+        let third = engine("v4");
+        assert_eq!(texts(&third.scan_files(&[file], &[functions])), vec!["other"]);
+        assert_eq!(third.cached_files(), 1);
+    }
+}
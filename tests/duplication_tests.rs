@@ -0,0 +1,60 @@
+use magma_scanner::duplication::DuplicateDetector;
+use magma_scanner::engine::Engine;
+
+const CHECK_TOKEN: &str = "fn check_token(token: &str, secret: &str) -> bool {
+    if token.len() != secret.len() {
+        return false;
+    }
+    token.bytes().zip(secret.bytes()).all(|(a, b)| a == b)
+}
+";
+
+const RENAMED_COPY: &str = "// copied from auth.rs
+fn verify(key: &str, expected: &str) -> bool {
+    if key.len() != expected.len() {
+        return false;
+    }
+    key.bytes().zip(expected.bytes()).all(|(x, y)| x == y)
+}
+
+fn unrelated() -> u32 {
+    42
+}
+";
+
+fn detector_for(sources: &[(&str, &str)], min_tokens: usize) -> DuplicateDetector {
+    let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string());
+    let mut detector = DuplicateDetector::new(min_tokens);
+    for (path, source) in sources {
+        let (tree, _) = engine.parse_source(source.to_string(), "rust").unwrap();
+        detector.add_file(path, "rust", &tree);
+    }
+    detector
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_renamed_clone_once_at_full_length() {
+        let detector = detector_for(&[("auth.rs", CHECK_TOKEN), ("legacy.rs", RENAMED_COPY)], 20);
+
+        let groups = detector.find_clones();
+
+        // Identifiers are normalized, so the renamed copy matches as one merged clone
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].language, "rust");
+        let spans: Vec<(&str, usize, usize)> = groups[0].locations.iter()
+            .map(|l| (l.file.as_str(), l.start_line, l.end_line))
+            .collect();
+        assert_eq!(spans, vec![("auth.rs", 1, 6), ("legacy.rs", 2, 7)]);
+    }
+
+    #[test]
+    fn test_ignores_clones_below_threshold() {
+        let detector = detector_for(&[("auth.rs", CHECK_TOKEN), ("legacy.rs", RENAMED_COPY)], 500);
+
+        assert!(detector.find_clones().is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+use aes_gcm::{aead::{Aead, KeyInit, OsRng}, Aes256Gcm, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use magma_scanner::encryption::{Envelope, EvidenceEncryptor, ENVELOPE_ALGORITHM};
+use rsa::{pkcs8::{EncodePublicKey, LineEnding}, Oaep, RsaPrivateKey};
+use serde_json::json;
+use sha2::Sha256;
+
+/// Decrypt an envelope the way the customer's compliance tooling would
+fn open(envelope: &Envelope, private_key: &RsaPrivateKey) -> Vec<u8> {
+    let data_key = private_key
+        .decrypt(Oaep::new::<Sha256>(), &STANDARD.decode(&envelope.encrypted_key).unwrap())
+        .unwrap();
+    let nonce = STANDARD.decode(&envelope.nonce).unwrap();
+
+    Aes256Gcm::new_from_slice(&data_key)
+        .unwrap()
+        .decrypt(Nonce::from_slice(&nonce), STANDARD.decode(&envelope.ciphertext).unwrap().as_slice())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealed_evidence_round_trip() {
+        let private_key = RsaPrivateKey::new(&mut OsRng, 1024).unwrap();
+        let pem = private_key.to_public_key().to_public_key_pem(LineEnding::LF).unwrap();
+        let encryptor = EvidenceEncryptor::from_pem(&pem).unwrap();
+
+        let request_body = json!({
+            "question_id": "secrets",
+            "source_id": "object_1",
+            "evidence": [{ "name": "match", "value": "let password = \"hunter2\";" }],
+            "evidence_context": "Hardcoded credentials"
+        });
+        let sealed = encryptor.seal_evidence(request_body).unwrap();
+
+        // Routing fields stay readable, code excerpts don't
+        assert_eq!(sealed["question_id"], "secrets");
+        assert!(sealed.get("evidence").is_none());
+        assert!(!sealed.to_string().contains("hunter2"));
+
+        let envelope: Envelope = serde_json::from_value(sealed["encrypted_evidence"].clone()).unwrap();
+        assert_eq!(envelope.algorithm, ENVELOPE_ALGORITHM);
+        assert_eq!(envelope.key_id, encryptor.key_id());
+
+        let opened: serde_json::Value = serde_json::from_slice(&open(&envelope, &private_key)).unwrap();
+        assert_eq!(opened["evidence"][0]["value"], "let password = \"hunter2\";");
+        assert_eq!(opened["evidence_context"], "Hardcoded credentials");
+    }
+
+    #[test]
+    fn test_invalid_public_key() {
+        assert!(EvidenceEncryptor::from_pem("-----BEGIN PUBLIC KEY-----\nnope\n-----END PUBLIC KEY-----\n").is_err());
+    }
+}
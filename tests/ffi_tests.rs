@@ -0,0 +1,37 @@
+#![cfg(feature = "ffi")]
+
+use magma_scanner::ffi::{magma_last_error, magma_results_count, magma_results_free, magma_scan_file};
+use std::ffi::{CStr, CString};
+
+const QUERIES: &str = r#"[{"question_id": "fns", "file_type": "rust", "query": "(function_item name: (identifier) @name)"}]"#;
+
+/// Scan `path` through the C ABI: the number of findings, or the last error's message
+fn scan_file(path: &str) -> Result<usize, String> {
+    let (path, queries) = (CString::new(path).unwrap(), CString::new(QUERIES).unwrap());
+    let results = unsafe { magma_scan_file(path.as_ptr(), queries.as_ptr()) };
+    if results.is_null() {
+        return Err(unsafe { CStr::from_ptr(magma_last_error()) }.to_string_lossy().into_owned());
+    }
+    let count = unsafe { magma_results_count(results) };
+    unsafe { magma_results_free(results) };
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_file_fails_for_paths_it_cannot_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("lib.rs");
+        std::fs::write(&file, "fn first() {}\nfn second() {}\n").unwrap();
+
+        assert_eq!(scan_file(&file.to_string_lossy()), Ok(2));
+
+        let missing = scan_file(&dir.path().join("missing.rs").to_string_lossy()).unwrap_err();
+        assert!(missing.starts_with("Failed to open"), "{}", missing);
+        let directory = scan_file(&dir.path().to_string_lossy()).unwrap_err();
+        assert!(directory.contains("magma_scan_dir"), "{}", directory);
+    }
+}
@@ -0,0 +1,36 @@
+mod test_utils;
+
+use test_utils::finding;
+use magma_scanner::gate::FailOn;
+use magma_scanner::types::{MatchResult, Severity};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fail_on() {
+        assert_eq!("high".parse::<FailOn>().unwrap(), FailOn { severity: Severity::High, count: 1 });
+        assert_eq!("10".parse::<FailOn>().unwrap(), FailOn { severity: Severity::Info, count: 10 });
+        assert_eq!("Medium:5".parse::<FailOn>().unwrap(), FailOn { severity: Severity::Medium, count: 5 });
+
+        assert!("0".parse::<FailOn>().is_err());
+        assert!("high:".parse::<FailOn>().is_err());
+        assert!("severe".parse::<FailOn>().is_err());
+    }
+
+    #[test]
+    fn test_fail_on_counts_findings_at_or_above_severity() {
+        let findings: Vec<MatchResult> = [Severity::Low, Severity::High, Severity::Critical].into_iter()
+            .map(|severity| MatchResult { severity, ..finding("src/main.rs", 1, "password", "secrets") })
+            .collect();
+
+        assert!("critical".parse::<FailOn>().unwrap().check(&findings).is_err());
+        assert!("3".parse::<FailOn>().unwrap().check(&findings).is_err());
+        assert!("4".parse::<FailOn>().unwrap().check(&findings).is_ok());
+
+        assert!("high:3".parse::<FailOn>().unwrap().check(&findings).is_ok());
+        let err = "high:2".parse::<FailOn>().unwrap().check(&findings).unwrap_err();
+        assert_eq!(err.to_string(), "2 findings at or above high severity, failing on 2");
+    }
+}
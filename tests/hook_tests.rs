@@ -0,0 +1,47 @@
+mod test_utils;
+
+use test_utils::{create_test_query, create_test_scanner};
+use magma_scanner::hook::parse_pre_receive_input;
+use magma_scanner::types::Severity;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pre_receive_input() {
+        let input = "\
+0000000000000000000000000000000000000000 1111111111111111111111111111111111111111 refs/heads/feature
+2222222222222222222222222222222222222222 0000000000000000000000000000000000000000 refs/heads/old
+malformed line
+";
+
+        let updates = parse_pre_receive_input(input);
+
+        // The malformed line is skipped
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].refname, "refs/heads/feature");
+        assert!(updates[0].is_create());
+        assert!(!updates[0].is_delete());
+        assert!(updates[1].is_delete());
+    }
+
+    #[test]
+    fn test_scan_sources_in_memory() {
+        let scanner = create_test_scanner();
+        let mut query = create_test_query("rs", "(function_item name: (identifier) @function_name)");
+        query.severity = Severity::High;
+
+        let sources = vec![
+            ("src/pushed.rs".to_string(), "fn deploy() {}".to_string()),
+            ("README.md".to_string(), "# not code".to_string()),
+        ];
+
+        let results = scanner.scan_sources(sources, &[query]);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file, "src/pushed.rs");
+        assert_eq!(results[0].text, "deploy");
+        assert_eq!(results[0].severity, Severity::High);
+    }
+}
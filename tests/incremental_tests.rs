@@ -0,0 +1,94 @@
+mod test_utils;
+
+use test_utils::{create_test_query, finding};
+use magma_scanner::engine::{CacheMode, Engine, Rescan};
+use magma_scanner::incremental::{edit_between, reparse, LineSpan};
+use magma_scanner::types::MatchResult;
+use std::{fs, sync::Arc};
+
+const FUNCTIONS: &str = "fn first() {}\n\nfn second() {}\n\nfn third() {}\n\nfn fourth() {}\n";
+const EDITED: &str = "fn first() {}\n\nfn second() {\n    let renamed = 1;\n}\n\nfn third() {}\n\nfn fourth() {}\n";
+
+fn engine() -> Engine {
+    Engine::new("org".to_string(), "commit".to_string()).with_cache_mode(CacheMode::Persistent)
+}
+
+fn lines(results: &[MatchResult]) -> Vec<(usize, String)> {
+    results.iter().map(|result| (result.line, result.text.clone())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_between_contents() {
+        assert!(edit_between(FUNCTIONS, FUNCTIONS).is_none());
+
+        let edit = edit_between(FUNCTIONS, EDITED).unwrap();
+        // The body inserted between the braces of `second`
+        assert_eq!((edit.start_byte, edit.old_end_byte), (28, 28));
+        assert_eq!(&EDITED[edit.start_byte..edit.new_end_byte], "\n    let renamed = 1;\n");
+        assert_eq!((edit.start_position.row, edit.old_end_position.row, edit.new_end_position.row), (2, 2, 4));
+
+        // Edits never split a multi-byte character
+        let edit = edit_between("let s = \"é\";", "let s = \"è\";").unwrap();
+        assert_eq!((edit.start_byte, edit.old_end_byte, edit.new_end_byte), (9, 11, 11));
+    }
+
+    #[test]
+    fn test_line_spans_move_later_findings() {
+        let span = LineSpan { start: 3, old_end: 3, new_end: 5 };
+        assert_eq!(span.shift(1), Some(1));
+        assert_eq!(span.shift(3), None);
+        assert_eq!(span.shift(7), Some(9));
+        assert!(span.contains(5) && !span.contains(6));
+
+        let previous = vec![finding("main.rs", 1, "first", "functions"), finding("main.rs", 3, "second", "functions"), finding("main.rs", 7, "fourth", "functions")];
+        let merged = span.merge(previous, vec![finding("main.rs", 3, "renamed", "functions")]);
+        assert_eq!(lines(&merged), vec![(1, "first".to_string()), (3, "renamed".to_string()), (9, "fourth".to_string())]);
+    }
+
+    #[test]
+    fn test_reparse_matches_a_full_parse() {
+        let engine = engine();
+        let previous = Arc::new(engine.parse_source(FUNCTIONS.to_string(), "rust").unwrap());
+        let reparsed = reparse(&previous, EDITED, "rust").unwrap();
+        let (full, _) = engine.parse_source(EDITED.to_string(), "rust").unwrap();
+        assert_eq!(reparsed.tree.root_node().to_sexp(), full.root_node().to_sexp());
+
+        // The span covers the edited function and its neighbours, in whole lines
+        assert_eq!(reparsed.span, LineSpan { start: 1, old_end: 5, new_end: 7 });
+        assert_eq!(&EDITED[reparsed.bytes], "fn first() {}\n\nfn second() {\n    let renamed = 1;\n}\n\nfn third() {}");
+
+        assert!(reparse(&previous, FUNCTIONS, "rust").is_none());
+    }
+
+    #[test]
+    fn test_rescans_query_only_the_edited_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("main.rs").to_string_lossy().to_string();
+        fs::write(&file, FUNCTIONS).unwrap();
+        let queries = vec![create_test_query("rs", "(function_item name: (identifier) @name)")];
+
+        let watching = engine();
+        let Rescan::Full(previous) = watching.rescan_file(&file, &queries) else {
+            panic!("files are scanned in full the first time");
+        };
+        assert_eq!(previous.len(), 4);
+        assert!(matches!(watching.rescan_file(&file, &queries), Rescan::Unchanged));
+
+        fs::write(&file, EDITED).unwrap();
+        let rescan = watching.rescan_file(&file, &queries);
+        assert!(matches!(rescan, Rescan::Partial { .. }));
+        let full = Engine::new("org".to_string(), "commit".to_string()).scan_files(std::slice::from_ref(&file), &queries);
+        assert_eq!(lines(&rescan.apply(previous)), lines(&full));
+        assert_eq!(lines(&full).iter().map(|(line, _)| *line).collect::<Vec<_>>(), vec![1, 3, 7, 9]);
+
+        // Sampling picks among every match of a file, so it needs a full scan
+        let sampled = engine().with_sample(10);
+        sampled.rescan_file(&file, &queries);
+        fs::write(&file, FUNCTIONS).unwrap();
+        assert!(matches!(sampled.rescan_file(&file, &queries), Rescan::Full(_)));
+    }
+}
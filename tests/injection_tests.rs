@@ -0,0 +1,65 @@
+use magma_scanner::engine::Engine;
+use magma_scanner::injection::looks_like_sql;
+use magma_scanner::types::TreeSitterQuery;
+
+const PYTHON_SOURCE: &str = r#"
+def find_user(cursor, name):
+    label = "Select a user"
+    cursor.execute(f"SELECT * FROM users WHERE name = '{name}'")
+"#;
+
+const WORKFLOW: &str = r#"
+jobs:
+  deploy:
+    steps:
+      - run: |
+          echo "deploying"
+          curl -sSL https://example.com/install.sh | bash
+"#;
+
+fn query(question_id: &str, file_type: &str, query: &str) -> TreeSitterQuery {
+    TreeSitterQuery {
+        question_id: question_id.to_string(),
+        file_type: file_type.to_string(),
+        query: query.to_string(),
+        ..Default::default()
+    }
+}
+
+fn engine() -> Engine {
+    Engine::new("org".to_string(), "v1".to_string()).with_quiet(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_looks_like_sql() {
+        assert!(looks_like_sql("SELECT id FROM users"));
+        assert!(looks_like_sql("  grant all on db to admin"));
+        assert!(!looks_like_sql("Select a user"));
+        assert!(!looks_like_sql("Update"));
+        assert!(!looks_like_sql("hello world"));
+    }
+
+    #[test]
+    fn test_sql_in_string_literals_needs_a_sql_grammar() {
+        // No SQL grammar is built in, so SQL-looking strings are left alone until one is loaded
+        let queries = vec![query("sql_select", "sql", "(keyword_select) @select")];
+        let sources = vec![("app/db.py".to_string(), PYTHON_SOURCE.to_string())];
+
+        assert!(engine().with_injections(true).scan_sources(sources, &queries).is_empty());
+    }
+
+    #[test]
+    fn test_shell_in_yaml_run_steps() {
+        let queries = vec![query("curl_pipe", "bash", r#"(pipeline (command name: (command_name) @cmd (#eq? @cmd "curl")))"#)];
+        let sources = vec![(".github/workflows/deploy.yml".to_string(), WORKFLOW.to_string())];
+
+        let results = engine().with_injections(true).scan_sources(sources, &queries);
+        assert_eq!(results.len(), 1, "{:?}", results);
+        assert_eq!(results[0].line, 7);
+        assert_eq!(results[0].text, "curl");
+    }
+}
@@ -1,7 +1,10 @@
 mod test_utils;
 
 use test_utils::{ensure_test_repo, test_repo_path, RUST_SAMPLE};
-use magma_scanner::scanner::Scanner;
+use magma_scanner::dead_letter;
+use magma_scanner::pipeline::PipelineConfig;
+use magma_scanner::scanner::{ApiFailure, Scanner};
+use magma_scanner::shutdown::{ScanInterrupted, ShutdownSignal};
 use magma_scanner::types::{TreeSitterQuery, CaptureResult};
 use std::fs;
 use std::env;
@@ -156,6 +159,7 @@ mod tests {
             object_id: "test_object".to_string(),
             prompt: "Find struct definitions".to_string(),
             reasoning: "Testing struct detection".to_string(),
+            ..Default::default()
         };
 
         // Create test evidence
@@ -165,6 +169,7 @@ mod tests {
                 value: "TestStruct".to_string(),
                 position: (10, 5),
                 node_type: "type_identifier".to_string(),
+                file: None,
             }
         ];
 
@@ -174,7 +179,8 @@ mod tests {
             "source_id": "test_object",
             "source_type": "tree-sitter-query",
             "evidence": evidence,
-            "evidence_context": "Testing struct detection"
+            "evidence_context": "Testing struct detection",
+            "severity": "medium"
         });
 
         server.mock("POST", "/org/test_org_id/evidence")
@@ -257,6 +263,18 @@ mod tests {
             .expect(1)  // Expect this to be called exactly once
             .create_async().await;
 
+        // 5. Mock for finalize_code_scan, carrying the line counts of the scanned file
+        let finalize_mock = server.mock("POST", "/org/test_org_id/rpc/finalize-code-scan/test_report_123")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "status": "completed",
+                "stats": { "total": { "files": 1 } }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "status": "success" }).to_string())
+            .expect(1)
+            .create_async().await;
+
         // Create scanner with mock API but without a report ID
         let mut scanner = create_mock_scanner_without_report(&server);
 
@@ -279,6 +297,7 @@ mod tests {
 
         // Verify the result
         assert!(result.is_ok());
+        finalize_mock.assert_async().await;
     }
 
     #[tokio::test]
@@ -313,4 +332,146 @@ mod tests {
         let err = result.unwrap_err();
         println!("Error properly handled: {}", err);
     }
+
+    #[tokio::test]
+    async fn test_pipeline_uploads_each_file_type() {
+        let file_path = create_test_file("pipeline_test.rs", RUST_SAMPLE);
+
+        let mut server = Server::new_async().await;
+        let evidence_mock = server.mock("POST", "/org/test_org_id/evidence")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "status": "success" }).to_string())
+            .expect(2)  // One for the Rust query, one "no matches" for the Python query
+            .create_async().await;
+
+        let scanner = create_mock_scanner(&server);
+        let mut rust_query = test_utils::create_test_query("rs", "(struct_item name: (type_identifier) @struct_name)");
+        rust_query.question_id = "rust_structs".to_string();
+        let mut python_query = test_utils::create_test_query("py", "(class_definition) @class");
+        python_query.question_id = "python_classes".to_string();
+
+        let config = PipelineConfig { parse_workers: 2, query_workers: 2, channel_capacity: 1 };
+        let (results, profile) = scanner
+            .run_pipeline(vec![file_path], vec![rust_query, python_query], &config)
+            .await
+            .unwrap();
+
+        assert!(results.iter().all(|r| r.question_id == "rust_structs"));
+        assert!(results.iter().any(|r| r.text == "User"));
+        assert_eq!(profile.discover.processed(), 1);
+        assert_eq!(profile.parse.processed(), 1);
+        assert_eq!(profile.query.processed(), 1);
+        assert_eq!(profile.code_stats().languages["rust"].files, 1);
+        evidence_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_scan_writes_checkpoint() {
+        let file_path = create_test_file("shutdown_test.rs", RUST_SAMPLE);
+        let state_dir = tempfile::tempdir().unwrap();
+
+        let mut server = Server::new_async().await;
+        let _queries_mock = server.mock("GET", "/org/test_org_id/rpc/get-preloaded-queries/test_report_123")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "TreeSitterQueries": [
+                    {
+                        "question_id": "q1",
+                        "file_type": ".rs",
+                        "query": "(struct_item name: (type_identifier) @struct_name)"
+                    }
+                ]
+            }).to_string())
+            .create_async().await;
+        let status_mock = server.mock("POST", "/org/test_org_id/rpc/update-code-scan-status/test_report_123")
+            .with_status(200)
+            .expect(1)
+            .create_async().await;
+        let evidence_mock = server.mock("POST", "/org/test_org_id/evidence")
+            .with_status(200)
+            .expect(0)  // Queries that never ran must not be reported as "no matches"
+            .create_async().await;
+
+        // Shutdown is requested before the scan starts, so no files are discovered
+        let shutdown = ShutdownSignal::new();
+        shutdown.trigger();
+        let scanner = create_mock_scanner(&server)
+            .with_shutdown(shutdown)
+            .with_state_dir(state_dir.path());
+
+        let result = scanner.start_continuous_scan(vec![file_path], 1, 2).await;
+
+        let err = result.unwrap_err();
+        assert!(err.downcast_ref::<ScanInterrupted>().is_some());
+        assert!(state_dir.path().join("checkpoint-test_report_123.json").exists());
+        assert!(state_dir.path().join("partial-report-test_report_123.json").exists());
+        status_mock.assert_async().await;
+        evidence_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_upload_is_dead_lettered_and_flushed() {
+        let state_dir = tempfile::tempdir().unwrap();
+        let dead_letter_file = state_dir.path().join("dead-letter.jsonl");
+
+        let mut server = Server::new_async().await;
+        let failing_mock = server.mock("POST", "/org/test_org_id/evidence")
+            .with_status(503)
+            .expect(2)  // Both attempts fail
+            .create_async().await;
+
+        let scanner = create_mock_scanner(&server)
+            .with_upload_attempts(2)
+            .with_dead_letter_file(&dead_letter_file);
+        let query = test_utils::create_test_query("rs", "(struct_item) @struct");
+
+        // The scan itself carries on when an upload can't be delivered
+        scanner.post_query_results(&[query], &[]).await.unwrap();
+        failing_mock.assert_async().await;
+
+        let letters = dead_letter::read_all(&dead_letter_file).unwrap();
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].attempts, 2);
+        assert_eq!(letters[0].payload["question_id"], "test_question_id");
+
+        failing_mock.remove_async().await;
+        let ok_mock = server.mock("POST", "/org/test_org_id/evidence")
+            .with_status(200)
+            .expect(1)
+            .create_async().await;
+
+        let (delivered, remaining) = scanner.flush_dead_letters(&dead_letter_file).await.unwrap();
+        assert_eq!((delivered, remaining), (1, 0));
+        assert!(!dead_letter_file.exists());
+        ok_mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_verify_api_key() {
+        let mut server = Server::new_async().await;
+        let valid = server.mock("GET", "/org/test_org_id/rpc/get-api-key-info")
+            .match_header("Authorization", "APIKey test_api_key")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "organizations": ["test_org_id"], "permissions": ["reports:read", "evidence:write"] }).to_string())
+            .create_async().await;
+
+        let scanner = create_mock_scanner_without_report(&server).with_api_base_url(server.url());
+        let info = scanner.verify_api_key().await.unwrap();
+        assert_eq!(info.organizations, vec!["test_org_id".to_string()]);
+        assert_eq!(info.permissions, vec!["reports:read".to_string(), "evidence:write".to_string()]);
+        assert_eq!(info.expires_at, None);
+        valid.assert_async().await;
+
+        server.mock("GET", "/org/other_org/rpc/get-api-key-info")
+            .with_status(403)
+            .create_async().await;
+        let other = Scanner::new("test_api_key".to_string(), "other_org".to_string(), String::new(), None)
+            .with_api_base_url(server.url());
+        let err = other.verify_api_key().await.unwrap_err();
+        assert!(err.is::<ApiFailure>());
+        assert!(err.to_string().contains("not valid for organization other_org"));
+    }
 }
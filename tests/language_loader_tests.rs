@@ -0,0 +1,92 @@
+use magma_scanner::language_loader::{canonical_language, get_language, language_for_content, language_overrides, load_grammar_dir, register_language};
+use magma_scanner::engine::Engine;
+use std::{collections::HashMap, fs};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_language_resolves_by_name_and_extension() {
+        let name = register_language("ecmascript", &[".mjs".to_string(), "cjs".to_string()], tree_sitter_javascript::language());
+        assert_eq!(name, "ecmascript");
+
+        assert_eq!(canonical_language("ECMAScript"), Some("ecmascript"));
+        assert_eq!(canonical_language("mjs"), Some("ecmascript"));
+        assert!(get_language("cjs").is_some());
+
+        let engine = Engine::new("org".to_string(), "v1".to_string());
+        assert_eq!(engine.get_language_for_file("src/index.mjs"), Some("ecmascript"));
+    }
+
+    #[test]
+    fn test_default_build_has_every_builtin_grammar() {
+        for name in ["rust", "javascript", "python", "go", "typescript", "tsx", "svelte", "java", "cpp", "c", "ruby", "php",
+            "csharp", "kotlin", "swift", "scala", "yaml", "html", "css", "solidity", "lua", "dart", "bash"] {
+            assert!(get_language(name).is_some(), "no grammar for {}", name);
+        }
+        // Known languages without a grammar crate for this tree-sitter version wait for a loaded grammar
+        for name in ["hcl", "sql", "zig", "powershell", "groovy"] {
+            assert_eq!(canonical_language(name), Some(name));
+            assert!(get_language(name).is_none(), "unexpected grammar for {}", name);
+        }
+    }
+
+    #[test]
+    fn test_builtin_languages_take_precedence() {
+        register_language("rust-nightly", &["rs".to_string()], tree_sitter_javascript::language());
+        assert_eq!(canonical_language("rs"), Some("rust"));
+        assert_eq!(canonical_language("rust-nightly"), Some("rust-nightly"));
+    }
+
+    #[test]
+    fn test_ambiguous_extensions_are_resolved_by_content() {
+        assert_eq!(language_for_content("h", "#include <stdio.h>\nint add(int a, int b);\n"), Some("c"));
+        assert_eq!(language_for_content("h", "namespace util {\nclass Widget;\n}\n"), Some("cpp"));
+        assert_eq!(language_for_content("ts", "export const x: number = 1;\n"), Some("typescript"));
+        assert_eq!(language_for_content("ts", "<?xml version=\"1.0\"?>\n<!DOCTYPE TS>\n<TS version=\"2.1\">\n"), None);
+
+        let sources = HashMap::from([
+            ("include/util.h".to_string(), "int add(int a, int b);\n".to_string()),
+            ("include/widget.h".to_string(), "template <typename T> struct Box { T value; };\n".to_string()),
+            ("i18n/app_de.ts".to_string(), "<?xml version=\"1.0\"?>\n<TS/>\n".to_string()),
+        ]);
+        let engine = Engine::new("org".to_string(), "v1".to_string()).with_sources(sources);
+        assert_eq!(engine.get_language_for_file("include/util.h"), Some("c"));
+        assert_eq!(engine.get_language_for_file("include/widget.h"), Some("cpp"));
+        assert_eq!(engine.get_language_for_file("i18n/app_de.ts"), None);
+        // Files that can't be read keep the extension's default
+        assert_eq!(engine.get_language_for_file("missing.h"), Some("cpp"));
+    }
+
+    #[test]
+    fn test_language_overrides_take_precedence() {
+        let resolve = language_overrides(&["vendor/legacy/*.h=c".to_string(), "*.inc=php".to_string()]).unwrap();
+        assert_eq!(resolve("/repo/vendor/legacy/list.h"), Some("c"));
+        assert_eq!(resolve("vendor/legacy/nested/list.h"), None);
+        assert_eq!(resolve("lib\\config.inc"), Some("php"));
+
+        let engine = Engine::new("org".to_string(), "v1".to_string()).with_language_override(resolve);
+        assert_eq!(engine.get_language_for_file("vendor/legacy/list.h"), Some("c"));
+        assert_eq!(engine.get_language_for_file("src/main.rs"), Some("rust"));
+
+        assert!(language_overrides(&["*.h".to_string()]).is_err());
+        assert!(language_overrides(&["*.h=cobol".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_load_grammar_dir_skips_invalid_libraries() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("tree-sitter-broken.so"), b"not a shared library").unwrap();
+        fs::write(dir.path().join("README.md"), b"grammars").unwrap();
+        fs::write(dir.path().join("libtree-sitter-malformed.so"), b"not a shared library").unwrap();
+        fs::write(dir.path().join("malformed.json"), b"{ \"extensions\": [").unwrap();
+
+        let loaded = load_grammar_dir(dir.path()).unwrap();
+        assert!(loaded.is_empty());
+        assert_eq!(canonical_language("broken"), None);
+        assert_eq!(canonical_language("malformed"), None);
+
+        assert!(load_grammar_dir(&dir.path().join("missing")).is_err());
+    }
+}
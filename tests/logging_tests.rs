@@ -0,0 +1,36 @@
+use magma_scanner::logging::{level, LogFormat, RedactingWriter};
+use magma_scanner::redact;
+use std::io::Write;
+use tracing::Level;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity_levels() {
+        assert_eq!(level(0, false), Level::INFO);
+        assert_eq!(level(1, false), Level::DEBUG);
+        assert_eq!(level(2, false), Level::TRACE);
+        assert_eq!(level(5, false), Level::TRACE);
+        assert_eq!(level(0, true), Level::WARN);
+    }
+
+    #[test]
+    fn test_parse_log_format() {
+        assert_eq!("text".parse::<LogFormat>().unwrap(), LogFormat::Text);
+        assert_eq!("JSON".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn test_log_lines_are_redacted() {
+        redact::register("sk_live_logged_secret");
+        let mut writer = RedactingWriter::new(Vec::new());
+        writer.write_all(b"DEBUG GET https://api.example.com/org?api_key=abc123 failed\n").unwrap();
+        assert_eq!(writer.write(b"WARN retrying with sk_live_logged_secret\n").unwrap(), 41);
+
+        let logged = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(logged, "DEBUG GET https://api.example.com/org?api_key=[REDACTED] failed\nWARN retrying with [REDACTED]\n");
+    }
+}
@@ -0,0 +1,66 @@
+use magma_scanner::engine::Engine;
+use magma_scanner::manifest::ScanManifest;
+use magma_scanner::types::TreeSitterQuery;
+use std::fs;
+
+const KEY: &[u8] = b"org-signing-key";
+
+fn query() -> TreeSitterQuery {
+    TreeSitterQuery {
+        question_id: "q1".to_string(),
+        file_type: "rust".to_string(),
+        query: "(function_item) @fn".to_string(),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_lists_files_with_hashes() {
+        let dir = tempfile::tempdir().unwrap();
+        let lib = dir.path().join("lib.rs");
+        let notes = dir.path().join("notes.txt");
+        fs::write(&lib, "fn main() {}\n").unwrap();
+        fs::write(&notes, "hello").unwrap();
+        let files = vec![notes.display().to_string(), lib.display().to_string()];
+
+        let engine = Engine::new("org".to_string(), "abc123".to_string());
+        let manifest = ScanManifest::build(&files, &engine, &[query()], Some("r1".to_string()));
+
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(manifest.files[0].path, lib.display().to_string());
+        assert_eq!(manifest.files[0].language.as_deref(), Some("rust"));
+        assert_eq!(manifest.files[0].bytes, 13);
+        assert_eq!(manifest.files[1].sha256, "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+        assert_eq!(manifest.code_base_version, "abc123");
+        assert!(manifest.changed_files().is_empty());
+
+        fs::write(&notes, "changed").unwrap();
+        assert_eq!(manifest.changed_files(), vec![notes.display().to_string()]);
+    }
+
+    #[test]
+    fn test_signature_detects_tampering() {
+        let dir = tempfile::tempdir().unwrap();
+        let lib = dir.path().join("lib.rs");
+        fs::write(&lib, "fn main() {}\n").unwrap();
+
+        let engine = Engine::new("org".to_string(), "abc123".to_string());
+        let mut manifest = ScanManifest::build(&[lib.display().to_string()], &engine, &[query()], None);
+        assert!(manifest.verify(KEY).is_err());
+
+        manifest.sign(KEY).unwrap();
+        let path = dir.path().join("manifest.json");
+        manifest.write(&path).unwrap();
+        let read = ScanManifest::read(&path).unwrap();
+        assert!(read.verify(KEY).is_ok());
+        assert!(read.verify(b"another-key").is_err());
+
+        let mut tampered = read.clone();
+        tampered.files.clear();
+        assert!(tampered.verify(KEY).is_err());
+    }
+}
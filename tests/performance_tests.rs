@@ -39,10 +39,12 @@ fn create_many_queries(count: usize) -> Vec<TreeSitterQuery> {
         queries.push(TreeSitterQuery {
             question_id: format!("perf_query_{}", i),
             file_type: ".rs".to_string(),
-            query: base_queries[query_index].to_string(),
+            // Distinct text, so repeated patterns aren't served from the compiled query cache
+            query: format!("{} ; query {}", base_queries[query_index], i),
             object_id: format!("perf_object_{}", i),
             prompt: format!("Performance test query {}", i),
             reasoning: "Testing query performance".to_string(),
+            ..Default::default()
         });
     }
 
@@ -110,7 +112,7 @@ mod tests {
 
         // Just print the results - don't assert on timing as it can be unpredictable
         // The first parse might be slower due to JIT compilation, caching, etc.
-        println!("Small file size: {} bytes", RUST_SAMPLE.repeat(1).len());
+        println!("Small file size: {} bytes", RUST_SAMPLE.len());
         println!("Medium file size: {} bytes", RUST_SAMPLE.repeat(10).len());
         println!("Large file size: {} bytes", RUST_SAMPLE.repeat(50).len());
     }
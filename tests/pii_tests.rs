@@ -0,0 +1,47 @@
+use magma_scanner::engine::Engine;
+use magma_scanner::pii::{detect, luhn_valid, PiiKind};
+use magma_scanner::types::Severity;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_and_redacts_pii() {
+        let found = detect("mail jane.doe@example.com, ssn 123-45-6789, card 4111 1111 1111 1111, call (555) 123-4567");
+        let kinds: Vec<PiiKind> = found.iter().map(|m| m.kind).collect();
+
+        assert_eq!(kinds, vec![PiiKind::Email, PiiKind::Ssn, PiiKind::CreditCard, PiiKind::Phone]);
+        assert_eq!(found[0].redacted, "j***@example.com");
+        assert_eq!(found[1].redacted, "***-**-6789");
+        assert_eq!(found[2].redacted, "**** **** **** 1111");
+        assert_eq!(found[3].redacted, "(***) ***-4567");
+    }
+
+    #[test]
+    fn test_validators_reject_lookalikes() {
+        assert!(luhn_valid("4111111111111111"));
+        assert!(!luhn_valid("4111111111111112"));
+
+        // Failing Luhn, never-issued SSN areas, and all-zero numbers are not PII
+        assert!(detect("order 4111 1111 1111 1112").is_empty());
+        assert!(detect("id 666-12-3456 and 900-12-3456").is_empty());
+        assert!(detect("000-000-0000").is_empty());
+    }
+
+    #[test]
+    fn test_engine_reports_pii_in_string_literals() {
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string()).with_pii_detection(true);
+        let source = "// owner: bob@example.com\nfn main() {\n    let contact = \"to: alice@example.com\";\n}\n".to_string();
+
+        let results = engine.scan_source_as("fixtures.rs", source, "rust", &[]).unwrap();
+
+        // Only string literals are searched, so the comment is ignored
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].question_id, "pii/email");
+        assert_eq!(results[0].category.as_deref(), Some("pii"));
+        assert_eq!(results[0].severity, Severity::Medium);
+        assert_eq!(results[0].text, "a***@example.com");
+        assert_eq!((results[0].line, results[0].column), (3, 24));
+    }
+}
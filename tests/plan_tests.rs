@@ -0,0 +1,43 @@
+mod test_utils;
+
+use test_utils::create_test_query;
+use magma_scanner::engine::Engine;
+use magma_scanner::plan::ScanPlan;
+use std::fs;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_lists_files_and_applicable_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+        fs::write(path("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(path("big.rs"), "// padding\n".repeat(100)).unwrap();
+        fs::write(path("notes.txt"), "not code\n").unwrap();
+        let files = vec![path("main.rs"), path("big.rs"), path("notes.txt")];
+
+        let mut rust = create_test_query("rs", "(function_item) @function");
+        rust.question_id = "rust/functions".to_string();
+        let mut python = create_test_query("py", "(function_definition) @function");
+        python.question_id = "python/functions".to_string();
+
+        let engine = Engine::new("org".to_string(), "commit".to_string()).with_max_file_size(Some(512));
+        let plan = ScanPlan::new(&engine, &files, Some(&[rust, python]));
+
+        let main = &plan.files[0];
+        assert_eq!((main.language, main.bytes, main.too_large), (Some("rust"), 13, false));
+        assert!(plan.files[1].too_large);
+        assert_eq!(plan.files[2].language, None);
+
+        let rs = plan.file_types.iter().find(|file_type| file_type.file_type == ".rs").unwrap();
+        assert_eq!(rs.files, 2);
+        assert_eq!(rs.queries, Some(vec!["rust/functions".to_string()]));
+        let txt = plan.file_types.iter().find(|file_type| file_type.file_type == ".txt").unwrap();
+        assert_eq!(txt.queries, Some(Vec::new()));
+
+        let unknown = ScanPlan::new(&engine, &files, None);
+        assert!(unknown.file_types.iter().all(|file_type| file_type.queries.is_none()));
+    }
+}
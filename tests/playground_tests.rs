@@ -0,0 +1,53 @@
+use magma_scanner::playground::Playground;
+use magma_scanner::rules::load_queries_file;
+use magma_scanner::types::Severity;
+
+const SOURCE: &str = "fn main() {\n    let token = \"abc\";\n    helper(token);\n}\n";
+
+fn playground() -> Playground {
+    Playground::from_source("main.rs", "rust", SOURCE.to_string()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_and_highlight_matches() {
+        let mut playground = playground();
+
+        let captures = playground.run("(call_expression function: (identifier) @fn)").unwrap();
+        assert_eq!(captures.len(), 1);
+        assert_eq!(playground.highlight(&captures), "    3 |     helper(token);\n      |     ^^^^^^ @fn\n");
+
+        // Invalid queries report the compiler's error and are not added to the history
+        assert!(playground.run("(no_such_node) @x").is_err());
+        assert_eq!(playground.history().len(), 1);
+        assert!(playground.ast().contains("  name: identifier [1:4-1:8] \"main\""));
+    }
+
+    #[test]
+    fn test_repl_runs_multiline_queries_and_saves_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let rules = dir.path().join("rules.json");
+        let input = format!(
+            "(let_declaration\n  pattern: (identifier) @name)\n:history\n:save {} secrets/token-var high\n:quit\n(ignored) @x\n",
+            rules.display()
+        );
+
+        let mut output = Vec::new();
+        playground().repl(input.as_bytes(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains("1 captures"));
+        assert!(output.contains("  1: (let_declaration   pattern: (identifier) @name)"));
+        assert!(!output.contains("ignored"));
+
+        let saved = load_queries_file(&rules).unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].question_id, "secrets/token-var");
+        assert_eq!(saved[0].file_type, "rust");
+        assert_eq!(saved[0].severity, Severity::High);
+        assert!(saved[0].query.starts_with("(let_declaration\n"));
+    }
+}
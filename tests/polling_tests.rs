@@ -0,0 +1,51 @@
+use magma_scanner::polling::{PollHint, PollSchedule, MAX_POLL_INTERVAL, MIN_POLL_INTERVAL};
+use serde_json::json;
+use std::time::Duration;
+
+const BASE: Duration = Duration::from_secs(5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hint_from_body_takes_precedence_over_header() {
+        let hint = PollHint::from_response(&json!({ "nextPollSeconds": 2.5 }), Some("30"));
+        assert_eq!(hint.next_poll, Some(Duration::from_millis(2500)));
+
+        let hint = PollHint::from_response(&json!({ "moreQueriesPending": true }), Some(" 30 "));
+        assert_eq!(hint, PollHint { next_poll: Some(Duration::from_secs(30)), more_pending: true });
+
+        assert_eq!(PollHint::from_response(&json!({}), Some("soon")), PollHint::default());
+    }
+
+    #[test]
+    fn test_idle_polls_back_off_until_new_queries_arrive() {
+        let mut schedule = PollSchedule::new(BASE);
+        let idle = PollHint::default();
+
+        assert_eq!(schedule.next_delay(&idle, true), BASE);
+        assert_eq!(schedule.next_delay(&idle, false), BASE * 2);
+        assert_eq!(schedule.next_delay(&idle, false), BASE * 4);
+        assert_eq!(schedule.next_delay(&idle, false), BASE * 8);
+        assert_eq!(schedule.next_delay(&idle, false), BASE * 8);
+        assert_eq!(schedule.next_delay(&idle, true), BASE);
+    }
+
+    #[test]
+    fn test_server_hints_override_backoff() {
+        let mut schedule = PollSchedule::new(BASE);
+
+        let pending = PollHint { next_poll: None, more_pending: true };
+        assert_eq!(schedule.next_delay(&pending, false), MIN_POLL_INTERVAL);
+
+        let suggested = PollHint { next_poll: Some(Duration::from_secs(12)), more_pending: false };
+        assert_eq!(schedule.next_delay(&suggested, false), Duration::from_secs(12));
+
+        let too_long = PollHint { next_poll: Some(Duration::from_secs(3600)), more_pending: false };
+        assert_eq!(schedule.next_delay(&too_long, false), MAX_POLL_INTERVAL);
+
+        let too_short = PollHint { next_poll: Some(Duration::ZERO), more_pending: false };
+        assert_eq!(schedule.next_delay(&too_short, false), MIN_POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,70 @@
+mod test_utils;
+
+use test_utils::create_test_query;
+use magma_scanner::gate::FailOn;
+use magma_scanner::project::{ProjectConfig, PROJECT_FILE};
+use magma_scanner::types::Severity;
+use std::fs;
+
+const PROJECT: &str = r#"
+[api]
+organization_id = "org_1"
+
+[scan]
+exclude = ["**/generated/**"]
+fail_on = "high:3"
+
+[languages]
+php = false
+rs = true
+
+[rules."style/todo"]
+enabled = false
+
+[rules."secrets/hardcoded-token"]
+severity = "critical"
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_project_settings() {
+        let project = ProjectConfig::parse(PROJECT).unwrap();
+
+        assert_eq!(project.api.organization_id.as_deref(), Some("org_1"));
+        assert_eq!(project.scan.exclude, vec!["**/generated/**"]);
+        assert_eq!(project.scan.fail_on, Some(FailOn { severity: Severity::High, count: 3 }));
+        assert_eq!(project.disabled_languages().unwrap(), vec!["php"]);
+
+        let mut todo = create_test_query("rs", "(line_comment) @comment");
+        todo.question_id = "style/todo".to_string();
+        let mut token = create_test_query("py", "(string) @value");
+        token.question_id = "secrets/hardcoded-token".to_string();
+        let other = create_test_query("rs", "(identifier) @name");
+
+        let queries = project.rules.apply(vec![todo, token, other]);
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].severity, Severity::Critical);
+        assert_eq!(queries[1].severity, Severity::Medium);
+
+        // Mistakes are reported instead of silently ignored
+        assert!(ProjectConfig::parse("[languages]\ncobol = false\n").is_err());
+        assert!(ProjectConfig::parse("[scan]\nexclude_dirs = [\"x\"]\n").is_err());
+        assert!(ProjectConfig::parse("[scan]\nfail_on = \"severe\"\n").is_err());
+    }
+
+    #[test]
+    fn test_find_looks_in_parent_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("services/api");
+        fs::create_dir_all(&nested).unwrap();
+        assert!(ProjectConfig::find(&nested).unwrap().is_none());
+
+        fs::write(dir.path().join(PROJECT_FILE), PROJECT).unwrap();
+        let (path, project) = ProjectConfig::find(&nested).unwrap().unwrap();
+        assert_eq!(path, dir.path().canonicalize().unwrap().join(PROJECT_FILE));
+        assert_eq!(project.api.organization_id.as_deref(), Some("org_1"));
+    }
+}
@@ -0,0 +1,36 @@
+use magma_scanner::redact::{self, REDACTED};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registered_secret_is_redacted() {
+        redact::register("sk_live_0123456789abcdef");
+        let text = redact::redact("Request with sk_live_0123456789abcdef failed");
+        assert_eq!(text, format!("Request with {} failed", REDACTED));
+    }
+
+    #[test]
+    fn test_short_secrets_are_not_registered() {
+        redact::register("abc");
+        assert_eq!(redact::redact("abcdef abc"), "abcdef abc");
+    }
+
+    #[test]
+    fn test_credential_patterns_are_redacted() {
+        let text = redact::redact("Authorization: APIKey unregistered-key-value");
+        assert_eq!(text, format!("Authorization: APIKey {}", REDACTED));
+
+        let text = redact::redact("GET /scan?api_key=abc123&org=acme");
+        assert_eq!(text, format!("GET /scan?api_key={}&org=acme", REDACTED));
+
+        assert_eq!(redact::redact("Error posting evidence: 500"), "Error posting evidence: 500");
+    }
+
+    #[test]
+    fn test_mask_shows_only_tail_of_long_secrets() {
+        assert_eq!(redact::mask("0123456789abcdefWXYZ"), "****WXYZ");
+        assert_eq!(redact::mask("short-key"), "****");
+    }
+}
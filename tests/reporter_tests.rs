@@ -0,0 +1,87 @@
+mod test_utils;
+
+use test_utils::finding;
+use magma_scanner::bundle::BundleSummary;
+use magma_scanner::reporter::{write_atomic, ReportFormat, ReportOutput};
+use magma_scanner::results::{ScanResults, RESULTS_FORMAT_VERSION};
+use magma_scanner::types::{MatchResult, Severity};
+use std::{fs, io, path::PathBuf};
+
+fn results(findings: Vec<MatchResult>) -> ScanResults {
+    ScanResults {
+        format_version: RESULTS_FORMAT_VERSION,
+        scanner_version: "0.1.0".to_string(),
+        organization_id: "test_org_id".to_string(),
+        code_base_version: "abc123".to_string(),
+        branch_name: "main".to_string(),
+        repo_url: "unknown".to_string(),
+        target: ".".to_string(),
+        started_at: 0,
+        finished_at: 0,
+        queries: vec!["secrets".to_string()],
+        summary: BundleSummary::new(2, &findings),
+        findings,
+    }
+}
+
+fn render(format: ReportFormat, results: &ScanResults) -> String {
+    let mut out = Vec::new();
+    format.reporter().report(results, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_console_and_json_reporters() {
+        let results = results(vec![
+            MatchResult { column: 5, severity: Severity::High, ..finding("src/main.rs", 3, "password", "secrets") },
+            MatchResult { column: 5, severity: Severity::Low, ..finding("src/lib.rs", 3, "password", "secrets") },
+        ]);
+
+        let console = render(ReportFormat::Console, &results);
+        assert_eq!(
+            console,
+            "  [high] src/main.rs:3:5 secrets — \"password\"\n  [low] src/lib.rs:3:5 secrets — \"password\"\n📊 2 findings in 2 files\n      2  secrets\n"
+        );
+
+        let json: ScanResults = serde_json::from_str(&render(ReportFormat::Json, &results)).unwrap();
+        assert_eq!(json.findings.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_report_format() {
+        assert_eq!("console".parse::<ReportFormat>().unwrap(), ReportFormat::Console);
+        assert_eq!("JSON".parse::<ReportFormat>().unwrap(), ReportFormat::Json);
+        assert!("xml".parse::<ReportFormat>().is_err());
+    }
+
+    #[test]
+    fn test_parse_report_output() {
+        assert_eq!("-".parse::<ReportOutput>().unwrap(), ReportOutput::Stdout);
+        assert_eq!("out/report.json".parse::<ReportOutput>().unwrap(), ReportOutput::File(PathBuf::from("out/report.json")));
+        assert!("".parse::<ReportOutput>().is_err());
+    }
+
+    #[test]
+    fn test_report_output_replaces_files_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("artifacts/report.json");
+        let results = results(vec![MatchResult { column: 5, severity: Severity::High, ..finding("src/main.rs", 3, "password", "secrets") }]);
+
+        ReportOutput::File(path.clone()).write(ReportFormat::Json, &results).unwrap();
+        let written: ScanResults = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written.findings.len(), 1);
+
+        // A failed write keeps the previous file and cleans up after itself
+        let failed = write_atomic(&path, |out| {
+            out.write_all(b"{\"partial\"")?;
+            Err(io::Error::other("scan failed"))
+        });
+        assert!(failed.is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), render(ReportFormat::Json, &results));
+        assert_eq!(fs::read_dir(path.parent().unwrap()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,89 @@
+use magma_scanner::bundle::BundleSummary;
+use magma_scanner::results::{ScanResults, StoredReport, RESULTS_FORMAT_VERSION};
+use magma_scanner::types::{MatchResult, Severity};
+
+fn sample_results() -> ScanResults {
+    let findings = vec![MatchResult {
+        file: "src/main.rs".to_string(),
+        line: 3,
+        column: 5,
+        text: "password".to_string(),
+        question_id: "secrets".to_string(),
+        organization_id: "test_org_id".to_string(),
+        code_base_version: "abc123".to_string(),
+        severity: Severity::High,
+        sampled: false,
+        category: None,
+        covered: None,
+        tags: Vec::new(),
+    }];
+
+    ScanResults {
+        format_version: RESULTS_FORMAT_VERSION,
+        scanner_version: "0.1.0".to_string(),
+        organization_id: "test_org_id".to_string(),
+        code_base_version: "abc123".to_string(),
+        branch_name: "main".to_string(),
+        repo_url: "git@example.com:repo.git".to_string(),
+        target: ".".to_string(),
+        started_at: 100,
+        finished_at: 102,
+        queries: vec!["secrets".to_string()],
+        summary: BundleSummary::new(4, &findings),
+        findings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_results_document_layout() {
+        let json = sample_results().to_json().unwrap();
+        assert!(json.ends_with("}\n"));
+
+        // Other tools read these fields, so their names are part of the output format
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["format_version"], 1);
+        assert_eq!(value["code_base_version"], "abc123");
+        assert_eq!(value["summary"]["files_scanned"], 4);
+        assert_eq!(value["summary"]["by_rule"]["secrets"], 1);
+        assert_eq!(value["findings"][0]["file"], "src/main.rs");
+        assert_eq!(value["findings"][0]["severity"], "high");
+
+        let parsed: ScanResults = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.findings.len(), 1);
+        assert_eq!(parsed.queries, vec!["secrets"]);
+    }
+
+    #[test]
+    fn test_results_from_report_evidence() {
+        let report: StoredReport = serde_json::from_str(r#"{
+            "report_id": "rpt_1",
+            "code_base_version": "abc123",
+            "branch_name": "main",
+            "repo_url": "git@example.com:repo.git",
+            "evidence": [
+                {"question_id": "secrets", "severity": "high", "evidence": [
+                    {"name": "match", "value": "password", "position": [3, 5], "node_type": "unknown", "file": "src/main.rs"},
+                    {"name": "match", "value": "token", "position": [8, 1], "node_type": "unknown"}
+                ]},
+                {"question_id": "style", "evidence": [
+                    {"name": "no_match", "value": "No matches found", "position": [0, 0], "node_type": "none"}
+                ]}
+            ]
+        }"#).unwrap();
+
+        let results = ScanResults::from_report("test_org_id", report);
+
+        assert_eq!(results.queries, vec!["secrets", "style"]);
+        assert_eq!(results.findings.len(), 2);
+        let expected = &sample_results().findings[0];
+        let finding = &results.findings[0];
+        assert_eq!((&finding.file, finding.line, finding.column, &finding.text), (&expected.file, 3, 5, &expected.text));
+        assert_eq!((finding.severity, &finding.code_base_version), (Severity::High, &expected.code_base_version));
+        assert_eq!(results.findings[1].file, "");
+        assert_eq!(results.summary.findings, 2);
+    }
+}
@@ -0,0 +1,69 @@
+mod test_utils;
+
+use test_utils::{create_test_query, RUST_SAMPLE};
+use magma_scanner::rpc::RpcServer;
+use serde_json::{json, Value};
+use std::io::Write;
+use tokio::sync::mpsc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: u64, method: &str, params: Value) -> String {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_scan_sources_and_get_results() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let server = RpcServer::new(tx);
+
+        let mut rules = tempfile::NamedTempFile::new().unwrap();
+        let query = create_test_query("rs", "(function_item) @function");
+        let rules_json = json!([{
+            "question_id": query.question_id,
+            "file_type": query.file_type,
+            "query": query.query,
+        }]);
+        write!(rules, "{}", rules_json).unwrap();
+
+        let response = server.handle_line(&request(1, "reloadRules", json!({ "path": rules.path() }))).unwrap();
+        assert_eq!(response["result"]["rules"], 1);
+
+        let sources = json!({ "sources": [{ "path": "buffer.rs", "content": RUST_SAMPLE }] });
+        let response = server.handle_line(&request(2, "scan", sources)).unwrap();
+        let scan_id = response["result"]["scanId"].as_u64().unwrap();
+
+        let finished = loop {
+            let message = rx.recv().await.unwrap();
+            if message["method"] == "scan/finished" {
+                break message;
+            }
+        };
+        assert_eq!(finished["params"]["status"], "completed");
+
+        let response = server.handle_line(&request(3, "getResults", json!({ "scanId": scan_id }))).unwrap();
+        let results = response["result"]["results"].as_array().unwrap();
+        assert!(!results.is_empty());
+        assert_eq!(results[0]["file"], "buffer.rs");
+    }
+
+    #[tokio::test]
+    async fn test_protocol_errors() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let server = RpcServer::new(tx);
+
+        let response = server.handle_line("not json").unwrap();
+        assert_eq!(response["error"]["code"], -32700);
+
+        let response = server.handle_line(&request(1, "explode", json!({}))).unwrap();
+        assert_eq!(response["error"]["code"], -32601);
+
+        let response = server.handle_line(&request(2, "getResults", json!({ "scanId": 42 }))).unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+
+        // Notifications get no response, even when they fail
+        assert!(server.handle_line(r#"{"jsonrpc":"2.0","method":"cancel"}"#).is_none());
+    }
+}
@@ -0,0 +1,93 @@
+use magma_scanner::engine::Engine;
+use magma_scanner::rules::{lint_rules, load_queries_file, load_rules, FixtureExpectations};
+use magma_scanner::types::Severity;
+use std::fs;
+
+const YAML_QUERIES: &str = "TreeSitterQueries:
+  - question_id: secrets/hardcoded-token
+    file_type: .py
+    query: '(assignment left: (identifier) @name right: (string) @value)'
+    severity: high
+  - question_id: style/todo
+    file_type: .rs
+    query: (line_comment) @comment
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_yaml_and_json_queries() {
+        let dir = tempfile::tempdir().unwrap();
+        let yaml = dir.path().join("policies.yaml");
+        fs::write(&yaml, YAML_QUERIES).unwrap();
+
+        let queries = load_queries_file(&yaml).unwrap();
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0].question_id, "secrets/hardcoded-token");
+        assert_eq!(queries[0].file_type, ".py");
+        assert_eq!(queries[0].severity, Severity::High);
+        assert_eq!(queries[1].query, "(line_comment) @comment");
+        assert_eq!(queries[1].severity, Severity::Medium);
+
+        // JSON stays the format of any other extension, as a bare array too
+        let json = dir.path().join("policies.json");
+        fs::write(&json, r#"[{"question_id": "style/todo", "file_type": ".rs", "query": "(line_comment) @comment"}]"#).unwrap();
+        assert_eq!(load_queries_file(&json).unwrap()[0].question_id, "style/todo");
+
+        let invalid = dir.path().join("invalid.yml");
+        fs::write(&invalid, "- question_id: [unclosed").unwrap();
+        assert!(load_queries_file(&invalid).is_err());
+    }
+
+    #[test]
+    fn test_lint_rules_across_packs() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("policies.yaml"), YAML_QUERIES).unwrap();
+        fs::write(dir.path().join("pack.json"), r#"{"TreeSitterQueries": [
+            {"question_id": "style/todo", "file_type": "python", "query": "(comment) @comment"},
+            {"question_id": "broken", "file_type": "rust", "query": "(function_item"},
+            {"question_id": "silent", "file_type": "rust", "query": "(function_item)"},
+            {"question_id": "config", "file_type": ".ini", "query": "(section) @section"}
+        ]}"#).unwrap();
+        fs::write(dir.path().join("notes.txt"), "not rules").unwrap();
+
+        let rules = load_rules(&[dir.path().to_path_buf()]).unwrap();
+        assert_eq!(rules.len(), 6);
+
+        let problems: Vec<(String, String)> = lint_rules(&rules).into_iter()
+            .map(|problem| (problem.question_id, problem.message))
+            .collect();
+        assert_eq!(problems.len(), 4);
+        assert!(problems[0].0 == "broken" && problems[0].1.starts_with("does not compile for rust"));
+        assert!(problems[1].0 == "silent" && problems[1].1.contains("captures nothing"));
+        assert!(problems[2].0 == "config" && problems[2].1.contains("no grammar for .ini"));
+        assert!(problems[3].0 == "style/todo" && problems[3].1.contains("pack.json"));
+
+        assert!(load_rules(&[dir.path().join("missing.json")]).is_err());
+    }
+
+    #[test]
+    fn test_fixture_annotations_are_checked_against_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = dir.path().join("fixture.py");
+        fs::write(&fixture, "# ruleid: secrets/hardcoded-token\ntoken = 'abc'\n# ok: secrets/hardcoded-token\nuser = input()\ncount = 'x'\n").unwrap();
+        let source = fs::read_to_string(&fixture).unwrap();
+
+        let expectations = FixtureExpectations::parse(&source);
+        assert_eq!(expectations.matches["secrets/hardcoded-token"].iter().copied().collect::<Vec<_>>(), vec![2]);
+        assert_eq!(expectations.non_matches["secrets/hardcoded-token"].iter().copied().collect::<Vec<_>>(), vec![4]);
+
+        let queries = load_queries_file(&{
+            let path = dir.path().join("policies.yaml");
+            fs::write(&path, YAML_QUERIES).unwrap();
+            path
+        }).unwrap();
+        let engine = Engine::new("org".to_string(), "commit".to_string());
+        let results = engine.scan_files(&[fixture.to_string_lossy().to_string()], &queries);
+
+        // The unannotated assignment on line 5 matches too, so the fixture fails
+        assert_eq!(expectations.check(&results), vec!["line 5: secrets/hardcoded-token matched unexpectedly".to_string()]);
+    }
+}
@@ -1,8 +1,35 @@
 mod test_utils;
 
-use test_utils::{create_test_scanner, ensure_test_repo, test_repo_path, RUST_SAMPLE};
-use magma_scanner::types::TreeSitterQuery;
+use test_utils::{create_test_query, create_test_scanner, ensure_test_repo, test_repo_path, RUST_SAMPLE};
+use magma_scanner::engine::{Engine, ScanProgress};
+use magma_scanner::scanner::CacheMode;
+use magma_scanner::types::{FileTarget, MatchResult, Severity, TreeSitterQuery};
 use std::fs;
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+
+/// Counts the progress notifications it receives
+#[derive(Default)]
+struct CountingProgress {
+    total: AtomicUsize,
+    scanned: AtomicUsize,
+    matches: AtomicUsize,
+    finished: AtomicUsize,
+}
+
+impl ScanProgress for CountingProgress {
+    fn started(&self, files: usize) {
+        self.total.store(files, Ordering::SeqCst);
+    }
+
+    fn file_scanned(&self, matches: usize) {
+        self.scanned.fetch_add(1, Ordering::SeqCst);
+        self.matches.fetch_add(matches, Ordering::SeqCst);
+    }
+
+    fn finished(&self) {
+        self.finished.fetch_add(1, Ordering::SeqCst);
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -29,8 +56,39 @@ mod tests {
         assert_eq!(scanner.get_language_for_file("test.java"), Some("java"));
         assert_eq!(scanner.get_language_for_file("test.cpp"), Some("cpp"));
         assert_eq!(scanner.get_language_for_file("test.h"), Some("cpp"));
+        assert_eq!(scanner.get_language_for_file("test.c"), Some("c"));
         assert_eq!(scanner.get_language_for_file("test.rb"), Some("ruby"));
         assert_eq!(scanner.get_language_for_file("test.php"), Some("php"));
+        assert_eq!(scanner.get_language_for_file("Test.cs"), Some("csharp"));
+        assert_eq!(scanner.get_language_for_file("Main.kt"), Some("kotlin"));
+        assert_eq!(scanner.get_language_for_file("build.gradle.kts"), Some("kotlin"));
+        assert_eq!(scanner.get_language_for_file("AppDelegate.swift"), Some("swift"));
+        assert_eq!(scanner.get_language_for_file("Service.scala"), Some("scala"));
+        assert_eq!(scanner.get_language_for_file("build.sc"), Some("scala"));
+        // HCL, SQL, Zig, PowerShell and Groovy files need a grammar loaded from a grammar directory
+        assert_eq!(scanner.get_language_for_file("main.tf"), None);
+        assert_eq!(scanner.get_language_for_file("prod.tfvars"), None);
+        assert_eq!(scanner.get_language_for_file(".github/workflows/ci.yml"), Some("yaml"));
+        assert_eq!(scanner.get_language_for_file("values.yaml"), Some("yaml"));
+        assert_eq!(scanner.get_language_for_file("migrations/0001_init.sql"), None);
+        assert_eq!(scanner.get_language_for_file("templates/index.html"), Some("html"));
+        assert_eq!(scanner.get_language_for_file("legacy.htm"), Some("html"));
+        assert_eq!(scanner.get_language_for_file("static/site.css"), Some("css"));
+        assert_eq!(scanner.get_language_for_file("theme.scss"), Some("css"));
+        assert_eq!(scanner.get_language_for_file("src/App.tsx"), Some("tsx"));
+        assert_eq!(scanner.get_language_for_file("src/Button.jsx"), Some("javascript"));
+        assert_eq!(scanner.get_language_for_file("src/routes/+page.svelte"), Some("svelte"));
+        assert_eq!(scanner.get_language_for_file("contracts/Vault.sol"), Some("solidity"));
+        assert_eq!(scanner.get_language_for_file("plugins/init.lua"), Some("lua"));
+        assert_eq!(scanner.get_language_for_file("lib/main.dart"), Some("dart"));
+        assert_eq!(scanner.get_language_for_file("firmware/src/main.zig"), None);
+        assert_eq!(scanner.get_language_for_file("scripts/Deploy.ps1"), None);
+        assert_eq!(scanner.get_language_for_file("modules/Audit.psm1"), None);
+        assert_eq!(scanner.get_language_for_file("app/build.gradle"), None);
+        assert_eq!(scanner.get_language_for_file("Jenkinsfile"), None);
+        assert_eq!(scanner.get_language_for_file("ci/Jenkinsfile.release"), None);
+        assert_eq!(scanner.get_language_for_file("Makefile"), None);
+        assert_eq!(scanner.get_language_for_file("scripts/setup.sh"), Some("bash"));
 
         // Test with unsupported extension
         assert_eq!(scanner.get_language_for_file("test_repo/test_python.py"), Some("python"));
@@ -39,6 +97,41 @@ mod tests {
         assert_eq!(scanner.get_language_for_file("test.xyz"), None);
     }
 
+    #[test]
+    fn test_get_language_for_extensionless_script() {
+        let scanner = create_test_scanner();
+
+        let deploy = create_temp_file("test_deploy", "#!/usr/bin/env python3\nprint('deploying')\n");
+        let hook = create_temp_file("test_pre_push", "#!/bin/bash\nset -e\n");
+        let server = create_temp_file("test_server", "#!/usr/bin/env -S node --no-warnings\nconsole.log(1)\n");
+        let notes = create_temp_file("TEST_NOTES", "no shebang here\n");
+
+        assert_eq!(scanner.get_language_for_file(&deploy), Some("python"));
+        assert_eq!(scanner.get_language_for_file(&hook), Some("bash"));
+        assert_eq!(scanner.get_language_for_file(&server), Some("javascript"));
+        assert_eq!(scanner.get_language_for_file(&notes), None);
+        assert!(magma_scanner::discovery::find_files(&test_repo_path().to_string_lossy()).unwrap().contains(&deploy));
+    }
+
+    #[test]
+    fn test_in_memory_sources_are_told_apart_by_their_content() {
+        let scanner = create_test_scanner();
+        let query = create_test_query("python", "(function_definition name: (identifier) @name)");
+        // Neither path exists on disk; a header of the same name on disk must not be consulted
+        let sources = vec![
+            ("hooks/deploy".to_string(), "#!/usr/bin/env python3\ndef deploy(): pass\n".to_string()),
+            ("include/util.h".to_string(), "int add(int a, int b);\n".to_string()),
+        ];
+
+        assert_eq!(scanner.engine().get_language_for_source("hooks/deploy", &sources[0].1), Some("python"));
+        assert_eq!(scanner.engine().get_language_for_source("include/util.h", &sources[1].1), Some("c"));
+        assert_eq!(scanner.engine().get_language_for_source("include/widget.h", "class Widget {};"), Some("cpp"));
+
+        let results = scanner.scan_sources(sources, &[query]);
+        assert_eq!(results.len(), 1);
+        assert_eq!((results[0].file.as_str(), results[0].text.as_str()), ("hooks/deploy", "deploy"));
+    }
+
     #[test]
     fn test_parse_file() {
         let scanner = create_test_scanner();
@@ -83,6 +176,7 @@ mod tests {
             object_id: "test_object".to_string(),
             prompt: "Find struct definitions".to_string(),
             reasoning: "Testing struct detection".to_string(),
+            ..Default::default()
         };
 
         // Run the scan
@@ -120,4 +214,221 @@ mod tests {
         assert!(captures.iter().any(|c| c.value == "add"));
         assert!(captures.iter().any(|c| c.value == "subtract"));
     }
+
+    #[tokio::test]
+    async fn test_fail_fast_stops_after_blocking_finding() {
+        let scanner = create_test_scanner().with_fail_fast(Severity::High);
+        let first = create_temp_file("fail_fast_first.rs", "fn first() {}");
+        let second = create_temp_file("fail_fast_second.rs", "fn second() {}");
+
+        let mut query = create_test_query("rs", "(function_item name: (identifier) @function_name)");
+        query.severity = Severity::Critical;
+
+        let results = scanner.scan_files(vec![first, second], vec![query]).await;
+
+        // The scan aborts after the first file produces a blocking finding
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].text, "first");
+    }
+
+    #[test]
+    fn test_query_content_hash_detects_changes() {
+        let query = create_test_query("rs", "(function_item) @function");
+        let same = create_test_query("rs", "(function_item) @function");
+        let mut changed = create_test_query("rs", "(function_item) @function");
+        changed.query = "(struct_item) @struct".to_string();
+
+        assert_eq!(query.content_hash(), same.content_hash());
+        assert_ne!(query.content_hash(), changed.content_hash());
+    }
+
+    #[tokio::test]
+    async fn test_warm_cache_parses_supported_files() {
+        let scanner = create_test_scanner();
+        let rust_file = create_temp_file("warm_up.rs", RUST_SAMPLE);
+        let unsupported = create_temp_file("warm_up.txt", "plain text");
+
+        let warmed = scanner.warm_cache(&[rust_file, unsupported]).await;
+
+        assert_eq!(warmed, 1);
+    }
+
+    #[tokio::test]
+    async fn test_cache_modes() {
+        let file_path = create_temp_file("cache_mode.rs", RUST_SAMPLE);
+        let query = create_test_query("rs", "(struct_item name: (type_identifier) @struct_name)");
+
+        let persistent = create_test_scanner();
+        persistent.scan_files(vec![file_path.clone()], vec![query.clone()]).await;
+        assert_eq!(persistent.cached_files(), 1);
+
+        let run_scoped = create_test_scanner().with_cache_mode(CacheMode::Run);
+        let results = run_scoped.scan_files(vec![file_path.clone()], vec![query.clone()]).await;
+        assert!(!results.is_empty());
+        assert_eq!(run_scoped.cached_files(), 0);
+
+        let disabled = create_test_scanner().with_cache_mode(CacheMode::Disabled);
+        let results = disabled.scan_files(vec![file_path], vec![query]).await;
+        assert!(!results.is_empty());
+        assert_eq!(disabled.cached_files(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cached_asts_follow_file_changes() {
+        let file_path = create_temp_file("cache_changes.rs", "struct Alpha {}\n");
+        let query = create_test_query("rs", "(struct_item name: (type_identifier) @struct_name)");
+        let names = |results: Vec<MatchResult>| results.into_iter().map(|r| r.text).collect::<Vec<_>>();
+
+        let scanner = create_test_scanner();
+        assert_eq!(names(scanner.scan_files(vec![file_path.clone()], vec![query.clone()]).await), vec!["Alpha"]);
+
+        // Same size and, on coarse clocks, the same modification time: only the contents differ
+        fs::write(&file_path, "struct Omega {}\n").unwrap();
+        assert_eq!(names(scanner.scan_files(vec![file_path.clone()], vec![query.clone()]).await), vec!["Omega"]);
+        assert_eq!(scanner.cached_files(), 1);
+
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string());
+        assert_eq!(names(engine.scan_files(std::slice::from_ref(&file_path), std::slice::from_ref(&query))), vec!["Omega"]);
+        fs::write(&file_path, "struct Gamma {}\nstruct Delta {}\n").unwrap();
+        assert_eq!(names(engine.scan_files(&[file_path], &[query])), vec!["Gamma", "Delta"]);
+    }
+
+    #[test]
+    fn test_file_type_accepts_languages_and_extension_lists() {
+        let mut query = create_test_query("rs", "(struct_item) @struct");
+        query.file_type = "rust".to_string();
+        assert_eq!(query.file_targets(), vec![FileTarget::Language("rust")]);
+
+        query.file_type = ".cpp, .h, .xyz".to_string();
+        assert_eq!(query.file_targets(), vec![FileTarget::Language("cpp"), FileTarget::Extension(".xyz".to_string())]);
+    }
+
+    #[test]
+    fn test_queries_routed_by_language() {
+        let scanner = create_test_scanner();
+        let mut query = create_test_query("cpp", "(class_specifier name: (type_identifier) @class_name)");
+        query.file_type = ".cpp".to_string();
+        let sources = vec![
+            ("widget.h".to_string(), "class Widget {};".to_string()),
+            ("widget.py".to_string(), "class Widget: pass".to_string()),
+        ];
+
+        let results = scanner.scan_sources(sources, &[query]);
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file, "widget.h");
+        assert_eq!(results[0].text, "Widget");
+    }
+
+    #[test]
+    fn test_result_paths_use_forward_slashes() {
+        let scanner = create_test_scanner();
+        let query = create_test_query("rs", "(struct_item name: (type_identifier) @struct_name)");
+        let sources = vec![(r"src\models\user.rs".to_string(), RUST_SAMPLE.to_string())];
+
+        let results = scanner.scan_sources(sources, &[query]);
+
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|r| r.file == "src/models/user.rs"));
+    }
+
+    #[test]
+    fn test_engine_scans_without_runtime() {
+        let file_path = create_temp_file("engine_scan.rs", RUST_SAMPLE);
+        let query = create_test_query("rs", "(struct_item name: (type_identifier) @struct_name)");
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string());
+
+        let results = engine.scan_files(&[file_path], &[query]);
+
+        assert!(results.iter().any(|r| r.text == "User"));
+        assert_eq!(engine.cached_files(), 1);
+    }
+
+    #[test]
+    fn test_engine_threads_keep_file_order() {
+        let files: Vec<String> = (0..8)
+            .map(|i| create_temp_file(&format!("threads_{}.rs", i), &format!("struct Item{} {{}}\n", i)))
+            .collect();
+        let query = create_test_query("rs", "(struct_item name: (type_identifier) @struct_name)");
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string()).with_threads(2);
+
+        let results = engine.scan_files(&files, &[query]);
+
+        let names: Vec<&str> = results.iter().map(|r| r.text.as_str()).collect();
+        assert_eq!(names, (0..8).map(|i| format!("Item{}", i)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_engine_reports_progress() {
+        let mut files: Vec<String> = (0..3)
+            .map(|i| create_temp_file(&format!("progress_{}.rs", i), &format!("struct Tracked{} {{}}\n", i)))
+            .collect();
+        files.push(create_temp_file("progress_notes.txt", "not code"));
+        let query = create_test_query("rs", "(struct_item name: (type_identifier) @struct_name)");
+        let progress = Arc::new(CountingProgress::default());
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string())
+            .with_threads(2)
+            .with_progress(progress.clone());
+
+        let results = engine.scan_files(&files, &[query]);
+
+        // Files that can't be scanned still count towards the total
+        assert_eq!(results.len(), 3);
+        assert_eq!(progress.total.load(Ordering::SeqCst), 4);
+        assert_eq!(progress.scanned.load(Ordering::SeqCst), 4);
+        assert_eq!(progress.matches.load(Ordering::SeqCst), 3);
+        assert_eq!(progress.finished.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_engine_skips_files_over_size_limit() {
+        let small = create_temp_file("size_small.rs", "struct Small {}\n");
+        let large = create_temp_file("size_large.rs", &format!("struct Large {{}}\n{}", "// padding\n".repeat(100)));
+        let files = vec![small.clone(), large];
+        let query = create_test_query("rs", "(struct_item name: (type_identifier) @struct_name)");
+
+        let limited = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string())
+            .with_max_file_size(Some(512));
+        let results = limited.scan_files(&files, std::slice::from_ref(&query));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file, small);
+
+        let unlimited = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string())
+            .with_max_file_size(None);
+        assert_eq!(unlimited.scan_files(&files, &[query]).len(), 2);
+    }
+
+    #[test]
+    fn test_scan_source_as_overrides_language() {
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string());
+        let mut query = create_test_query("py", "(class_definition name: (identifier) @class_name)");
+        query.file_type = "python".to_string();
+
+        let results = engine.scan_source_as("generated.txt", "class Widget:\n    pass\n".to_string(), "py", &[query]).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file, "generated.txt");
+        assert_eq!(results[0].text, "Widget");
+        assert!(engine.scan_source_as("x.txt", String::new(), "cobol", &[]).is_err());
+    }
+
+    #[test]
+    fn test_sample_limits_matches_per_file() {
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string());
+        let source: String = (1..=20).map(|i| format!("const C{}: &str = \"s{}\";\n", i, i)).collect();
+        let mut query = create_test_query("rs", "(string_literal) @string");
+        query.sample = Some(4);
+
+        let results = engine.scan_source_as("strings.rs", source.clone(), "rust", &[query.clone()]).unwrap();
+        let lines: Vec<usize> = results.iter().map(|r| r.line).collect();
+
+        assert_eq!(results.len(), 4);
+        assert!(results.iter().all(|r| r.sampled));
+        assert_eq!(&lines[..2], &[1, 2]);
+        assert!(lines.windows(2).all(|w| w[0] < w[1]));
+
+        // The sample is the same on every scan
+        let again = engine.scan_source_as("strings.rs", source, "rust", &[query]).unwrap();
+        assert_eq!(again.iter().map(|r| r.line).collect::<Vec<_>>(), lines);
+    }
 }
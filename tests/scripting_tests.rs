@@ -0,0 +1,63 @@
+use magma_scanner::engine::Engine;
+use magma_scanner::scripting::ResultTransform;
+use magma_scanner::types::{Severity, TreeSitterQuery};
+
+const SOURCE: &str = "fn main() {\n    let a = \"vendor/key\";\n    let b = \"prod/key\";\n    let c = \"test/key\";\n}\n";
+
+fn string_query() -> Vec<TreeSitterQuery> {
+    vec![TreeSitterQuery {
+        question_id: "secrets".to_string(),
+        file_type: ".rs".to_string(),
+        query: "(string_literal) @s".to_string(),
+        severity: Severity::Medium,
+        ..Default::default()
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transform_drops_rerates_and_tags() {
+        let transform = ResultTransform::from_script(r#"
+            fn transform(finding) {
+                if finding.text.contains("vendor") { return (); }
+                if finding.text.contains("prod") {
+                    finding.severity = "critical";
+                    finding.tags.push("production");
+                }
+                finding.text = finding.text.to_upper();
+                finding
+            }
+        "#).unwrap();
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string())
+            .with_quiet(true)
+            .with_transform(transform);
+
+        let results = engine.scan_source_as("main.rs", SOURCE.to_string(), "rust", &string_query()).unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].text, "\"PROD/KEY\"");
+        assert_eq!(results[0].severity, Severity::Critical);
+        assert_eq!(results[0].tags, vec!["production"]);
+        assert_eq!(results[1].severity, Severity::Medium);
+        assert!(results[1].tags.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_scripts_are_rejected_and_failures_keep_findings() {
+        assert!(ResultTransform::from_script("fn other(x) { x }").is_err());
+        assert!(ResultTransform::from_script("fn transform(finding) {").is_err());
+
+        // A script returning an invalid severity fails for that finding, which is kept as is
+        let transform = ResultTransform::from_script(r#"fn transform(finding) { finding.severity = "urgent"; finding }"#).unwrap();
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string())
+            .with_quiet(true)
+            .with_transform(transform);
+
+        let results = engine.scan_source_as("main.rs", SOURCE.to_string(), "rust", &string_query()).unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.severity == Severity::Medium));
+    }
+}
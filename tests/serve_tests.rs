@@ -0,0 +1,77 @@
+mod test_utils;
+
+use test_utils::create_test_query;
+use magma_scanner::discovery::PathFilter;
+use magma_scanner::engine::Engine;
+use magma_scanner::serve::{changed_lines, ScanDaemon};
+use serde_json::Value;
+use std::fs;
+
+const DIFF: &str = "diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -1,2 +1,3 @@
+ fn keep() {}
+-fn old() {}
++fn new() {}
++fn added() {}
+diff --git a/src/gone.rs b/src/gone.rs
+--- a/src/gone.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-fn gone() {}
+";
+
+fn lines(results: &Value) -> Vec<u64> {
+    results["results"].as_array().unwrap().iter().map(|result| result["line"].as_u64().unwrap()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_lines_of_a_diff() {
+        let changed = changed_lines(DIFF);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed["src/lib.rs"].iter().copied().collect::<Vec<_>>(), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_daemon_scans_paths_and_diffs_from_a_warm_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/lib.rs"), "fn keep() {}\nfn new() {}\nfn added() {}\n").unwrap();
+        fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        let target = dir.path().to_string_lossy().to_string();
+
+        let query = create_test_query("rs", "(function_item) @function");
+        let engine = Engine::new("org".to_string(), "commit".to_string());
+        let daemon = ScanDaemon::new(engine, &target, PathFilter::default(), vec![query]);
+        assert_eq!(daemon.warm().unwrap(), 2);
+        assert_eq!(daemon.cached_files(), 2);
+
+        let (status, health) = daemon.handle("GET", "/health", b"");
+        assert_eq!((status, health["cachedFiles"].as_u64()), (200, Some(2)));
+
+        let (status, whole) = daemon.handle("POST", "/scan", b"");
+        assert_eq!((status, whole["files"].as_u64()), (200, Some(2)));
+
+        let (_, paths) = daemon.handle("POST", "/scan", br#"{"paths": ["src"]}"#);
+        assert_eq!(lines(&paths), vec![1, 2, 3]);
+
+        // Only findings on the lines the diff adds are reported
+        let body = serde_json::json!({ "diff": DIFF }).to_string();
+        let (_, diff) = daemon.handle("POST", "/scan", body.as_bytes());
+        assert_eq!(lines(&diff), vec![2, 3]);
+
+        // Files edited since they were parsed are parsed again
+        fs::write(dir.path().join("src/lib.rs"), "fn keep() {}\n").unwrap();
+        let (_, edited) = daemon.handle("POST", "/scan", br#"{"paths": ["src/lib.rs"]}"#);
+        assert_eq!(lines(&edited), vec![1]);
+
+        assert_eq!(daemon.handle("POST", "/scan", b"{").0, 400);
+        assert_eq!(daemon.handle("GET", "/scan", b"").0, 405);
+        assert_eq!(daemon.handle("GET", "/missing", b"").0, 404);
+    }
+}
@@ -0,0 +1,37 @@
+use magma_scanner::engine::Engine;
+use magma_scanner::stats::{count_lines, LineCounts};
+use std::fs;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_code_comment_and_blank_lines() {
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string());
+        let source = "// header\n\nfn main() { // trailing comments still count as code\n    /* block\n       comment */\n    let x = 1;\n\n}\n";
+        let (tree, source) = engine.parse_source(source.to_string(), "rust").unwrap();
+
+        let counts = count_lines(&tree, &source);
+
+        assert_eq!(counts, LineCounts { files: 1, code: 3, comment: 3, blank: 2 });
+        assert_eq!(counts.lines(), 8);
+    }
+
+    #[test]
+    fn test_collect_stats_by_language() {
+        let dir = tempfile::tempdir().unwrap();
+        let rust = dir.path().join("main.rs");
+        let python = dir.path().join("app.py");
+        fs::write(&rust, "fn main() {}\n").unwrap();
+        fs::write(&python, "# entry point\nprint('hi')\n").unwrap();
+        let files = vec![rust.to_string_lossy().to_string(), python.to_string_lossy().to_string()];
+
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string()).with_quiet(true);
+        let stats = engine.collect_stats(&files);
+
+        assert_eq!(stats.languages["rust"].code, 1);
+        assert_eq!(stats.languages["python"].comment, 1);
+        assert_eq!(stats.total, LineCounts { files: 2, code: 2, comment: 1, blank: 0 });
+    }
+}
@@ -0,0 +1,45 @@
+mod test_utils;
+
+use test_utils::finding;
+use magma_scanner::store::ResultsStore;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_query_scans() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("results.db");
+        let mut store = ResultsStore::open(&path).unwrap();
+
+        let results = vec![
+            finding("repo/src/a.rs", 1, "match at 1", "secrets"),
+            finding("repo/src/a.rs", 9, "match at 9", "secrets"),
+            finding("repo/src/b.rs", 4, "match at 4", "sql"),
+        ];
+        let first = store.record_scan("repo", "abc123", Some("report_1"), &results).unwrap();
+        let second = store.record_scan("repo", "def456", None, &results[..1]).unwrap();
+
+        let scans = store.scans().unwrap();
+        assert_eq!(scans.iter().map(|s| s.id).collect::<Vec<_>>(), vec![second, first]);
+        assert_eq!(scans[1].findings, 3);
+        assert_eq!(scans[1].report_id.as_deref(), Some("report_1"));
+
+        let summary = store.summary(first).unwrap();
+        assert_eq!(summary["secrets"], 2);
+        assert_eq!(summary["sql"], 1);
+
+        // Fingerprints match those used in baselines, so unchanged findings line up across scans
+        let fixed: Vec<String> = store.fingerprints(first).unwrap()
+            .difference(&store.fingerprints(second).unwrap())
+            .cloned()
+            .collect();
+        assert_eq!(fixed.len(), 2);
+
+        let rows = store.query("SELECT question_id, COUNT(*) AS n FROM findings GROUP BY question_id ORDER BY question_id").unwrap();
+        assert_eq!(rows.columns, vec!["question_id", "n"]);
+        assert_eq!(rows.rows, vec![vec!["secrets", "3"], vec!["sql", "1"]]);
+        assert!(store.query("SELECT * FROM nope").is_err());
+    }
+}
@@ -0,0 +1,26 @@
+use magma_scanner::svelte::script_ranges;
+
+const COMPONENT: &str = "<script lang=\"ts\">\n  export let name: string;\n</script>\n\n<h1>Hello {name}</h1>\n\n<SCRIPT context=\"module\">const x = 1;</SCRIPT>\n";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script_ranges_cover_each_script_body() {
+        let ranges = script_ranges(COMPONENT);
+        assert_eq!(ranges.len(), 2);
+
+        assert_eq!(&COMPONENT[ranges[0].start_byte..ranges[0].end_byte], "\n  export let name: string;\n");
+        assert_eq!((ranges[0].start_point.row, ranges[0].start_point.column), (0, 18));
+        assert_eq!(ranges[0].end_point.row, 2);
+
+        assert_eq!(&COMPONENT[ranges[1].start_byte..ranges[1].end_byte], "const x = 1;");
+        assert_eq!(ranges[1].start_point.row, 6);
+    }
+
+    #[test]
+    fn test_markup_only_component_has_no_scripts() {
+        assert!(script_ranges("<p>no scripts here</p>\n<scripts-list />").is_empty());
+    }
+}
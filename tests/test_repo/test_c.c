@@ -0,0 +1,25 @@
+
+// Sample C file for testing TreeSitter queries
+#include <stdio.h>
+
+struct user {
+    int id;
+    const char *name;
+    const char *email;
+};
+
+static void display(const struct user *u) {
+    printf("User %d (%s): %s\n", u->id, u->name, u->email);
+}
+
+int main(void) {
+    struct user users[] = {
+        { 1, "Alice", "alice@example.com" },
+        { 2, "Bob", "bob@example.com" },
+    };
+
+    for (int i = 0; i < 2; i++) {
+        display(&users[i]);
+    }
+    return 0;
+}
@@ -0,0 +1,17 @@
+
+// Sample React component for testing TreeSitter queries
+import React from "react";
+
+interface ProfileProps {
+  name: string;
+  bio: string;
+}
+
+export function Profile({ name, bio }: ProfileProps) {
+  return (
+    <div className="profile">
+      <h1>{name}</h1>
+      <div dangerouslySetInnerHTML={{ __html: bio }} />
+    </div>
+  );
+}
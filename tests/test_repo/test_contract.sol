@@ -0,0 +1,21 @@
+
+// Sample contract for testing TreeSitter queries
+pragma solidity ^0.8.0;
+
+contract Vault {
+    address public owner;
+    mapping(address => uint256) public balances;
+
+    constructor() {
+        owner = msg.sender;
+    }
+
+    function deposit() external payable {
+        balances[msg.sender] += msg.value;
+    }
+
+    function close() external {
+        require(tx.origin == owner, "not owner");
+        selfdestruct(payable(owner));
+    }
+}
@@ -0,0 +1,48 @@
+
+// Sample C# file for testing TreeSitter queries
+using System;
+using System.Collections.Generic;
+
+namespace Sample
+{
+    public class User
+    {
+        public int Id { get; }
+        private readonly string name;
+        private readonly string email;
+
+        public User(int id, string name, string email)
+        {
+            Id = id;
+            this.name = name;
+            this.email = email;
+        }
+
+        public string Display()
+        {
+            return $"User {Id} ({name}): {email}";
+        }
+    }
+
+    public static class Program
+    {
+        public static void Main()
+        {
+            // Create a dictionary of users
+            var users = new Dictionary<int, User>();
+
+            // Add some users
+            var user1 = new User(1, "Alice", "alice@example.com");
+            var user2 = new User(2, "Bob", "bob@example.com");
+
+            users[user1.Id] = user1;
+            users[user2.Id] = user2;
+
+            // Print users
+            foreach (var user in users.Values)
+            {
+                Console.WriteLine(user.Display());
+            }
+        }
+    }
+}
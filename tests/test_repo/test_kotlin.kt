@@ -0,0 +1,20 @@
+
+// Sample Kotlin file for testing TreeSitter queries
+data class User(val id: Int, val name: String, val email: String) {
+    fun display(): String = "User $id ($name): $email"
+}
+
+fun main() {
+    // Create a map of users
+    val users = mutableMapOf<Int, User>()
+
+    // Add some users
+    val user1 = User(1, "Alice", "alice@example.com")
+    val user2 = User(2, "Bob", "bob@example.com")
+
+    users[user1.id] = user1
+    users[user2.id] = user2
+
+    // Print users
+    users.values.forEach { println(it.display()) }
+}
@@ -0,0 +1,18 @@
+
+// Sample Flutter code for testing TreeSitter queries
+import 'package:flutter/material.dart';
+import 'package:shared_preferences/shared_preferences.dart';
+
+class LoginPage extends StatelessWidget {
+  const LoginPage({super.key});
+
+  Future<void> saveToken(String token) async {
+    final prefs = await SharedPreferences.getInstance();
+    await prefs.setString('auth_token', token);
+  }
+
+  @override
+  Widget build(BuildContext context) {
+    return const Text('Sign in');
+  }
+}
@@ -0,0 +1,18 @@
+
+-- Sample plugin for testing TreeSitter queries
+local M = {}
+
+local config = {
+  endpoint = "https://telemetry.example.com/collect",
+}
+
+function M.run(input)
+  local chunk = loadstring(input)
+  return chunk()
+end
+
+function M.report(event)
+  os.execute("curl -s " .. config.endpoint .. " -d " .. event)
+end
+
+return M
@@ -0,0 +1,24 @@
+
+// Sample Scala file for testing TreeSitter queries
+import scala.collection.mutable
+
+case class User(id: Int, name: String, email: String) {
+  def display(): String = s"User $id ($name): $email"
+}
+
+object Main {
+  def main(args: Array[String]): Unit = {
+    // Create a map of users
+    val users = mutable.Map[Int, User]()
+
+    // Add some users
+    val user1 = User(1, "Alice", "alice@example.com")
+    val user2 = User(2, "Bob", "bob@example.com")
+
+    users(user1.id) = user1
+    users(user2.id) = user2
+
+    // Print users
+    users.values.foreach(user => println(user.display()))
+  }
+}
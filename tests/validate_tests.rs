@@ -0,0 +1,32 @@
+use magma_scanner::validate::check_query;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_query_lists_captures() {
+        let check = check_query("rs", "(function_item name: (identifier) @name body: (block) @body)").unwrap();
+        assert_eq!(check.language, "rust");
+        assert_eq!(check.captures, vec!["name", "body"]);
+        assert!(check.error.is_none());
+    }
+
+    #[test]
+    fn test_errors_name_the_problem_and_location() {
+        let query = "(function_item\n  name: (identifer) @name)";
+        let problem = check_query("rust", query).unwrap().error.unwrap();
+        assert_eq!((problem.line, problem.column), (2, 10));
+        assert_eq!(problem.message, "unknown node kind `identifer` in rust");
+        assert_eq!(problem.render(query), "2:10: unknown node kind `identifer` in rust\n    name: (identifer) @name)\n           ^");
+
+        let field = check_query("rust", "(function_item title: (identifier))").unwrap().error.unwrap();
+        assert_eq!(field.message, "unknown field `title` in rust");
+
+        let capture = check_query("rust", "((identifier) @name (#eq? @nmae \"main\"))").unwrap().error.unwrap();
+        assert!(capture.message.starts_with("unknown capture `@nmae`"));
+
+        assert_eq!(check_query("rust", "(identifier").unwrap().error.unwrap().message, "syntax error");
+        assert!(check_query("cobol", "(identifier)").is_err());
+    }
+}
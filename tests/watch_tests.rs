@@ -0,0 +1,56 @@
+mod test_utils;
+
+use test_utils::create_test_query;
+use magma_scanner::discovery::{is_scannable, MagmaIgnore, PathFilter};
+use magma_scanner::engine::Engine;
+use magma_scanner::watch::WatchSession;
+use std::{fs, path::Path};
+
+const STRUCT_QUERY: &str = "(struct_item name: (type_identifier) @struct_name)";
+
+fn texts(session: &WatchSession) -> Vec<String> {
+    session.findings().map(|finding| finding.text.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rescan_updates_only_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first.rs").to_string_lossy().to_string();
+        let second = dir.path().join("second.rs").to_string_lossy().to_string();
+        fs::write(&first, "struct First {}\n").unwrap();
+        fs::write(&second, "struct Second {}\n").unwrap();
+
+        let engine = Engine::new("test_org_id".to_string(), "test_commit_hash".to_string());
+        let mut session = WatchSession::new(engine, vec![create_test_query("rs", STRUCT_QUERY)]);
+        assert_eq!(session.scan_all(&[first.clone(), second.clone()]).len(), 2);
+
+        fs::write(&first, "struct Renamed {}\nstruct Added {}\n").unwrap();
+        let rescanned = session.rescan(std::slice::from_ref(&first));
+        assert_eq!(rescanned.len(), 2);
+        assert_eq!(texts(&session), vec!["Renamed", "Added", "Second"]);
+
+        // Deleted files are forgotten along with their findings
+        fs::remove_file(&second).unwrap();
+        assert!(session.rescan(std::slice::from_ref(&second)).is_empty());
+        assert!(!session.knows(&second));
+        assert_eq!(session.files(), 1);
+        assert_eq!(texts(&session), vec!["Renamed", "Added"]);
+    }
+
+    #[test]
+    fn test_is_scannable_matches_discovery() {
+        let filter = PathFilter::new(&[], &["**/generated/**".to_string()]).unwrap();
+        let ignore = MagmaIgnore::parse("vendor/\n").unwrap();
+        let scannable = |relative: &str| is_scannable(Path::new(relative), relative, &filter, Some(&ignore));
+
+        assert!(scannable("src/main.rs"));
+        assert!(!scannable("src/generated/schema.rs"));
+        assert!(!scannable("vendor/lib.js"));
+        assert!(!scannable("node_modules/pkg/index.js"));
+        assert!(!scannable("README.md"));
+    }
+}